@@ -0,0 +1,270 @@
+use regex::Regex;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use walkdir::WalkDir;
+
+use crate::llm_client::FlaggedPattern;
+use crate::report::CrateFinding;
+
+/// Text files larger than this are not searched for scripts and blobs.
+const MAX_TEXT_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Shortest unbroken run of base64 characters reported as an embedded blob.
+pub const BASE64_BLOB_MIN_CHARS: usize = 2048;
+
+/// Script lines at least this long with almost no whitespace look minified or packed.
+const PACKED_LINE_MIN_CHARS: usize = 1000;
+
+/// Extensions of native code that a Rust package has no reason to ship.
+const BINARY_EXTENSIONS: &[&str] = &["so", "dll", "dylib", "exe", "a", "lib", "o", "obj", "node"];
+
+const SCRIPT_EXTENSIONS: &[&str] = &[
+    "sh", "bash", "ps1", "psm1", "bat", "cmd", "vbs", "py", "js", "pl", "rb",
+];
+
+/// Ways scripts decode and run a hidden payload.
+const DECODE_AND_RUN_PATTERN: &str = r"(?i)(eval|exec|iex|invoke-expression)\b.{0,80}(base64|b64decode|atob|fromcharcode|frombase64string|unescape)|(base64\s+(-d|--decode)|xxd\s+-r)[^|\n]*\|\s*(ba)?sh\b|-enc(odedcommand)?\s+[A-Za-z0-9+/=]{40,}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    PrecompiledBinary,
+    ObfuscatedScript,
+    Base64Blob,
+}
+
+impl ArtifactKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ArtifactKind::PrecompiledBinary => "precompiled binary",
+            ArtifactKind::ObfuscatedScript => "obfuscated script",
+            ArtifactKind::Base64Blob => "large base64 blob",
+        }
+    }
+}
+
+/// A file that is not Rust source and hides what it does from a source review.
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub path: PathBuf,
+    pub kind: ArtifactKind,
+    /// Line of the script or blob; 1 for binaries
+    pub line: usize,
+    /// What gave it away, e.g. `ELF` or `3072 base64 characters`
+    pub detail: String,
+}
+
+impl Artifact {
+    /// A High finding for an artifact in the scanned crate itself.
+    pub fn into_finding(self) -> CrateFinding {
+        CrateFinding {
+            llm_analysis: format!("Not Rust source: {} ({}).", self.kind.label(), self.detail),
+            flagged_patterns: vec![self.pattern()],
+            file_path: self.path,
+            analysis_version: None,
+            identical_files: Vec::new(),
+            truncation: None,
+        }
+    }
+
+    /// The High pattern reporting the artifact, also used for scripts the
+    /// scanner analyzes as source.
+    pub fn pattern(&self) -> FlaggedPattern {
+        FlaggedPattern {
+            line: self.line,
+            severity: "High".to_string(),
+            description: format!("[static] Bundled {}: {}", self.kind.label(), self.detail),
+            code_snippet: String::new(),
+            confidence: 1.0,
+            cwe: None,
+            owasp: None,
+            id: None,
+            triage: None,
+            blame: None,
+            rule: None,
+        }
+    }
+}
+
+fn base64_blob_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    // Long counted repetitions make huge automata, so runs are measured afterwards
+    REGEX.get_or_init(|| Regex::new("[A-Za-z0-9+/]{64,}={0,2}").expect("valid base64 pattern"))
+}
+
+/// First large base64 run in `text` that is not an inline image or font
+/// (`data:image/png;base64,...`), which documentation and web assets embed.
+pub fn find_base64_blob(text: &str) -> Option<regex::Match<'_>> {
+    base64_blob_regex().find_iter(text).find(|blob| {
+        if blob.len() < BASE64_BLOB_MIN_CHARS {
+            return false;
+        }
+        let before = &text[..blob.start()];
+        let media = before.strip_suffix(";base64,").and_then(|before| {
+            let uri = &before[before.rfind("data:")?..];
+            Some(uri.starts_with("data:image/") || uri.starts_with("data:font/"))
+        });
+        media != Some(true)
+    })
+}
+
+fn decode_and_run_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(DECODE_AND_RUN_PATTERN).expect("valid script pattern"))
+}
+
+/// Every artifact under `dir`, skipping build output and version control.
+pub fn scan_dir(dir: &Path) -> Vec<Artifact> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || (e.file_name() != "target" && e.file_name() != ".git"))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| inspect(e.path()))
+        .collect()
+}
+
+/// Checks one file: native code by extension or magic number, then scripts
+/// that decode and run a payload or are packed, then base64 blobs in any text.
+pub fn inspect(path: &Path) -> Option<Artifact> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; 4];
+    let header_len = file.read(&mut header).ok()?;
+    let format = executable_format(&header[..header_len]);
+    if format.is_some() || BINARY_EXTENSIONS.contains(&extension.as_str()) {
+        return Some(Artifact {
+            path: path.to_path_buf(),
+            kind: ArtifactKind::PrecompiledBinary,
+            line: 1,
+            detail: format.map_or_else(|| format!(".{} file", extension), str::to_string),
+        });
+    }
+
+    if file.metadata().ok()?.len() > MAX_TEXT_BYTES {
+        return None;
+    }
+    let content = std::fs::read_to_string(path).ok()?;
+    inspect_text(path, &content)
+}
+
+/// The text checks of `inspect`: an obfuscated script, then a base64 blob.
+pub fn inspect_text(path: &Path, content: &str) -> Option<Artifact> {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if SCRIPT_EXTENSIONS.contains(&extension.as_str()) {
+        if let Some((line, detail)) = obfuscation(content) {
+            return Some(Artifact {
+                path: path.to_path_buf(),
+                kind: ArtifactKind::ObfuscatedScript,
+                line,
+                detail,
+            });
+        }
+    }
+    let blob = find_base64_blob(content)?;
+    Some(Artifact {
+        path: path.to_path_buf(),
+        kind: ArtifactKind::Base64Blob,
+        line: content[..blob.start()].matches('\n').count() + 1,
+        detail: format!("{} base64 characters", blob.len()),
+    })
+}
+
+fn executable_format(header: &[u8]) -> Option<&'static str> {
+    match header {
+        [0x7f, b'E', b'L', b'F', ..] => Some("ELF"),
+        [b'M', b'Z', ..] => Some("Windows PE"),
+        [0xfe, 0xed, 0xfa, 0xce | 0xcf, ..]
+        | [0xce | 0xcf, 0xfa, 0xed, 0xfe, ..]
+        | [0xca, 0xfe, 0xba, 0xbe, ..] => Some("Mach-O"),
+        _ => None,
+    }
+}
+
+/// Line and reason if a script decodes and runs a payload or is packed onto long lines.
+fn obfuscation(content: &str) -> Option<(usize, String)> {
+    for (index, line) in content.lines().enumerate() {
+        if decode_and_run_regex().is_match(line) {
+            return Some((index + 1, "decodes and runs a payload".to_string()));
+        }
+        let length = line.chars().count();
+        let whitespace = line.chars().filter(|c| c.is_whitespace()).count();
+        if length >= PACKED_LINE_MIN_CHARS && whitespace * 50 < length {
+            return Some((index + 1, format!("packed line of {} characters", length)));
+        }
+    }
+    None
+}
+
+/// Crates whose purpose is to ship prebuilt Windows import libraries, such as
+/// `windows_x86_64_msvc`; their `.lib` and `.a` files are expected.
+pub fn ships_import_libraries(package: &str) -> bool {
+    package.starts_with("windows_")
+        || (package.starts_with("winapi-") && package.ends_with("-pc-windows-gnu"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_binaries_scripts_and_blobs() {
+        let dir =
+            std::env::temp_dir().join(format!("rustrecon-test-{}-artifacts", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        std::fs::write(dir.join("payload"), b"\x7fELF\x02\x01\x01").unwrap();
+        std::fs::write(dir.join("helper.dll"), b"not really").unwrap();
+        std::fs::write(
+            dir.join("install.sh"),
+            "#!/bin/sh\necho aGVsbG8= | base64 -d | sh\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("build.sh"), "#!/bin/sh\ncargo build --release\n").unwrap();
+        std::fs::write(
+            dir.join("data.rs"),
+            format!("const DATA: &str = \"{}\";\n", "QUJD".repeat(600)),
+        )
+        .unwrap();
+        std::fs::write(dir.join("target/libdep.so"), b"\x7fELF").unwrap();
+        std::fs::write(
+            dir.join("logo.svg"),
+            format!(
+                "<image href=\"data:image/png;base64,{}\"/>",
+                "QUJD".repeat(600)
+            ),
+        )
+        .unwrap();
+
+        let mut artifacts = scan_dir(&dir);
+        artifacts.sort_by(|a, b| a.path.cmp(&b.path));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let found: Vec<(String, ArtifactKind, usize)> = artifacts
+            .iter()
+            .map(|a| {
+                let name = a.path.file_name().unwrap().to_string_lossy().to_string();
+                (name, a.kind, a.line)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("data.rs".to_string(), ArtifactKind::Base64Blob, 1),
+                ("helper.dll".to_string(), ArtifactKind::PrecompiledBinary, 1),
+                ("install.sh".to_string(), ArtifactKind::ObfuscatedScript, 2),
+                ("payload".to_string(), ArtifactKind::PrecompiledBinary, 1),
+            ]
+        );
+        assert_eq!(artifacts[3].detail, "ELF");
+        assert!(ships_import_libraries("windows_x86_64_msvc"));
+        assert!(!ships_import_libraries("windows-sys"));
+    }
+}
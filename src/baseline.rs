@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::llm_client::FlaggedPattern;
+
+pub const DEFAULT_BASELINE_FILE: &str = "rustrecon-baseline.json";
+
+/// A reviewer's verdict on a finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Disposition {
+    Accepted,
+    FalsePositive,
+    NeedsFix,
+}
+
+impl Disposition {
+    pub fn label(self) -> &'static str {
+        match self {
+            Disposition::Accepted => "accepted",
+            Disposition::FalsePositive => "false-positive",
+            Disposition::NeedsFix => "needs-fix",
+        }
+    }
+}
+
+/// One reviewed finding. Findings are matched by file, description and code
+/// snippet rather than line number, so a disposition survives edits that only
+/// move the code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub file: PathBuf,
+    pub line: usize,
+    pub severity: String,
+    pub description: String,
+    pub code_snippet: String,
+    pub disposition: Disposition,
+    pub reviewed_at: DateTime<Utc>,
+}
+
+impl BaselineEntry {
+    fn matches(&self, file: &Path, pattern: &FlaggedPattern) -> bool {
+        self.file == file
+            && self.description == pattern.description
+            && self.code_snippet == pattern.code_snippet
+    }
+}
+
+/// Reviewed findings of a project, written by `rustrecon review`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    pub entries: Vec<BaselineEntry>,
+}
+
+impl Baseline {
+    /// Loads the baseline at `path`; a missing file is an empty baseline.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Baseline::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse baseline {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write baseline {}", path.display()))
+    }
+
+    pub fn disposition(&self, file: &Path, pattern: &FlaggedPattern) -> Option<Disposition> {
+        self.entries
+            .iter()
+            .find(|entry| entry.matches(file, pattern))
+            .map(|entry| entry.disposition)
+    }
+
+    /// Records `disposition` for the finding, or forgets it when `None`.
+    pub fn set(&mut self, file: &Path, pattern: &FlaggedPattern, disposition: Option<Disposition>) {
+        self.entries.retain(|entry| !entry.matches(file, pattern));
+        if let Some(disposition) = disposition {
+            self.entries.push(BaselineEntry {
+                file: file.to_path_buf(),
+                line: pattern.line,
+                severity: pattern.severity.clone(),
+                description: pattern.description.clone(),
+                code_snippet: pattern.code_snippet.clone(),
+                disposition,
+                reviewed_at: Utc::now(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disposition_survives_line_changes() {
+        let mut baseline = Baseline::default();
+        let file = Path::new("src/main.rs");
+        let mut pattern = FlaggedPattern {
+            line: 10,
+            severity: "High".to_string(),
+            description: "Reads environment variables".to_string(),
+            code_snippet: "env::var(\"TOKEN\")".to_string(),
+            confidence: 0.8,
+            cwe: None,
+            owasp: None,
+            id: None,
+            triage: None,
+            blame: None,
+            rule: None,
+        };
+        baseline.set(file, &pattern, Some(Disposition::NeedsFix));
+        baseline.set(file, &pattern, Some(Disposition::FalsePositive));
+        assert_eq!(baseline.entries.len(), 1);
+
+        pattern.line = 42;
+        assert_eq!(
+            baseline.disposition(file, &pattern),
+            Some(Disposition::FalsePositive)
+        );
+        assert_eq!(
+            baseline.disposition(Path::new("src/lib.rs"), &pattern),
+            None
+        );
+
+        baseline.set(file, &pattern, None);
+        assert!(baseline.entries.is_empty());
+    }
+}
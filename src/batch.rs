@@ -0,0 +1,421 @@
+use anyhow::{Context, Result};
+use globset::GlobBuilder;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tracing::{error, info};
+use walkdir::WalkDir;
+
+use crate::config::Config;
+use crate::report::RiskReport;
+use crate::severity;
+use crate::{run_scan, ScanOptions};
+
+/// Directories never searched for projects when expanding a glob.
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+/// One project's line in the aggregate report.
+#[derive(Debug, Serialize)]
+pub struct ProjectResult {
+    pub name: String,
+    pub path: PathBuf,
+    /// Sum of the severity weights of its findings and risky dependencies
+    pub risk_score: i32,
+    pub files_scanned: usize,
+    pub findings: usize,
+    pub severity_counts: BTreeMap<String, usize>,
+    pub dependencies: usize,
+    pub high_risk_dependencies: usize,
+    /// Dependencies rated above `Low`, riskiest first
+    pub risky_dependencies: Vec<RiskyDependency>,
+    pub elapsed_secs: u64,
+    /// Why the scan failed; the counts are zero then
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A dependency rated above `Low` in one project's scan.
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskyDependency {
+    pub package: String,
+    pub version: String,
+    pub risk: String,
+}
+
+impl ProjectResult {
+    fn from_report(path: &Path, report: &RiskReport, elapsed_secs: u64) -> Self {
+        let patterns = report
+            .findings
+            .iter()
+            .flat_map(|finding| &finding.flagged_patterns)
+            .map(|pattern| severity::weight(&pattern.severity));
+        let dependencies = report
+            .dependency_findings
+            .iter()
+            .filter(|dep| dep.risk_score.rank() > 0)
+            .map(|dep| severity::weight(&format!("{:?}", dep.risk_score)));
+        let mut risky_dependencies: Vec<_> = report
+            .dependency_findings
+            .iter()
+            .filter(|dep| dep.risk_score.rank() > 1)
+            .map(|dep| RiskyDependency {
+                package: dep.package_name.clone(),
+                version: dep.version.clone(),
+                risk: format!("{:?}", dep.risk_score),
+            })
+            .collect();
+        risky_dependencies.sort_by_key(|dep| std::cmp::Reverse(severity::weight(&dep.risk)));
+        Self {
+            name: report.crate_name.clone(),
+            path: path.to_path_buf(),
+            risk_score: patterns.chain(dependencies).sum(),
+            files_scanned: report.summary.total_files_scanned,
+            findings: report.summary.total_flagged_patterns,
+            severity_counts: report.summary.severity_counts.clone(),
+            dependencies: report.summary.total_dependencies_scanned,
+            high_risk_dependencies: report.summary.high_risk_dependencies,
+            risky_dependencies,
+            elapsed_secs,
+            error: None,
+        }
+    }
+
+    pub fn failed(path: &Path, error: &anyhow::Error, elapsed_secs: u64) -> Self {
+        Self {
+            name: crate::utils::get_crate_name_from_path(path),
+            path: path.to_path_buf(),
+            risk_score: 0,
+            files_scanned: 0,
+            findings: 0,
+            severity_counts: BTreeMap::new(),
+            dependencies: 0,
+            high_risk_dependencies: 0,
+            risky_dependencies: Vec::new(),
+            elapsed_secs,
+            error: Some(format!("{:#}", error)),
+        }
+    }
+}
+
+/// Results of a `scan-many` run, riskiest project first.
+#[derive(Debug, Serialize)]
+pub struct AggregateReport {
+    pub timestamp: String,
+    pub projects: Vec<ProjectResult>,
+}
+
+impl AggregateReport {
+    pub fn failed(&self) -> usize {
+        self.projects.iter().filter(|p| p.error.is_some()).count()
+    }
+
+    /// Adds projects, e.g. ones that could not be fetched, keeping the ranking.
+    pub fn extend(&mut self, projects: impl IntoIterator<Item = ProjectResult>) {
+        self.projects.extend(projects);
+        self.projects.sort_by(|a, b| {
+            b.risk_score
+                .cmp(&a.risk_score)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+    }
+
+    /// Each risky `(package, version, risk)` with the projects depending on it,
+    /// riskiest and most widely used first.
+    fn risky_dependencies(&self) -> Vec<(&RiskyDependency, Vec<&str>)> {
+        let mut by_package: BTreeMap<(&str, &str), (&RiskyDependency, Vec<&str>)> = BTreeMap::new();
+        for project in &self.projects {
+            for dep in &project.risky_dependencies {
+                by_package
+                    .entry((&dep.package, &dep.version))
+                    .or_insert_with(|| (dep, Vec::new()))
+                    .1
+                    .push(&project.name);
+            }
+        }
+        let mut deps: Vec<_> = by_package.into_values().collect();
+        deps.sort_by_key(|(dep, projects)| {
+            std::cmp::Reverse((severity::weight(&dep.risk), projects.len()))
+        });
+        deps
+    }
+
+    pub fn render(&self, format: &str) -> Result<String> {
+        Ok(match format {
+            "json" => serde_json::to_string_pretty(self)?,
+            "markdown" => self.to_markdown(),
+            "summary" => self.to_summary(),
+            _ => anyhow::bail!("Unsupported aggregate report format: {}", format),
+        })
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut md = String::from("# RustRecon Aggregate Report\n\n");
+        md.push_str(&format!(
+            "**Generated:** {} | **Projects:** {} | **Failed:** {}\n\n",
+            self.timestamp,
+            self.projects.len(),
+            self.failed()
+        ));
+        md.push_str("| Rank | Project | Risk score | Critical | High | Medium | Low | Dependencies | High-risk deps | Path |\n");
+        md.push_str("|------|---------|------------|----------|------|--------|-----|--------------|----------------|------|\n");
+        let count = |project: &ProjectResult, level: &str| {
+            project.severity_counts.get(level).copied().unwrap_or(0)
+        };
+        for (rank, project) in self
+            .projects
+            .iter()
+            .filter(|p| p.error.is_none())
+            .enumerate()
+        {
+            md.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} | {} | {} | {} | `{}` |\n",
+                rank + 1,
+                project.name,
+                project.risk_score,
+                count(project, "Critical"),
+                count(project, "High"),
+                count(project, "Medium"),
+                count(project, "Low"),
+                project.dependencies,
+                project.high_risk_dependencies,
+                project.path.display()
+            ));
+        }
+        let risky = self.risky_dependencies();
+        if !risky.is_empty() {
+            md.push_str("\n## Riskiest Dependencies\n\n");
+            md.push_str("| Package | Version | Risk | Used by |\n");
+            md.push_str("|---------|---------|------|---------|\n");
+            for (dep, projects) in risky {
+                md.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    dep.package,
+                    dep.version,
+                    dep.risk,
+                    projects.join(", ")
+                ));
+            }
+        }
+        if self.failed() > 0 {
+            md.push_str("\n## Failed Scans\n\n");
+            for project in self.projects.iter().filter(|p| p.error.is_some()) {
+                md.push_str(&format!(
+                    "- `{}`: {}\n",
+                    project.path.display(),
+                    project.error.as_deref().unwrap_or_default()
+                ));
+            }
+        }
+        md.push_str("\n*Risk score: 80 per Critical, 30 per High, 15 per Medium and 5 per Low finding or risky dependency.*\n");
+        md
+    }
+
+    fn to_summary(&self) -> String {
+        let mut lines: Vec<String> = self
+            .projects
+            .iter()
+            .map(|project| match &project.error {
+                Some(error) => format!("❌ {} | failed: {}", project.name, error),
+                None => format!(
+                    "📊 {} | Risk: {} | Findings: {} | High-Risk Deps: {}",
+                    project.name,
+                    project.risk_score,
+                    project.findings,
+                    project.high_risk_dependencies
+                ),
+            })
+            .collect();
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+/// Projects named by `target`: the paths listed in a file (one per line, `#`
+/// starts a comment), or else the directories matching a glob that contain a
+/// `Cargo.toml`.
+pub fn resolve_targets(target: &str) -> Result<Vec<PathBuf>> {
+    let path = Path::new(target);
+    if path.is_file() {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        return Ok(content
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect());
+    }
+
+    let glob = GlobBuilder::new(target)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("'{}' is neither a file nor a valid glob", target))?
+        .compile_matcher();
+    // Walk from the longest leading part without wildcards
+    let base: PathBuf = path
+        .components()
+        .take_while(|c| {
+            !c.as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '[', '{'])
+        })
+        .collect();
+    let base = if base.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        base
+    };
+    let mut projects: Vec<PathBuf> = WalkDir::new(&base)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()))
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_dir())
+        .map(|entry| entry.into_path())
+        // Walking "." yields "./app", which should match "*" as well as "./*"
+        .filter(|dir| glob.is_match(dir) || glob.is_match(dir.strip_prefix(".").unwrap_or(dir)))
+        .filter(|dir| dir.join("Cargo.toml").is_file())
+        .collect();
+    projects.sort();
+    Ok(projects)
+}
+
+/// Scans every project, `jobs` at a time, and ranks them by risk score.
+/// A project that fails to scan is listed with its error. With `reports_dir`,
+/// each project's full JSON report is written there as `<name>.json`.
+pub fn scan_all(
+    projects: &[PathBuf],
+    jobs: usize,
+    options: &ScanOptions,
+    reports_dir: Option<&Path>,
+) -> Result<AggregateReport> {
+    if let Some(dir) = reports_dir {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::new());
+    let report_names = Mutex::new(HashSet::new());
+
+    // Each worker has its own runtime, as in `serve`, since scans are not `Send`
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, projects.len().max(1)) {
+            scope.spawn(|| {
+                let runtime = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        error!("Failed to start a scan worker: {}", e);
+                        return;
+                    }
+                };
+                loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(path) = projects.get(index) else {
+                        break;
+                    };
+                    info!(
+                        "📦 [{}/{}] Scanning {}",
+                        index + 1,
+                        projects.len(),
+                        path.display()
+                    );
+                    let started = std::time::Instant::now();
+                    let result = runtime.block_on(async {
+                        if !path.is_dir() {
+                            anyhow::bail!("Not a directory");
+                        }
+                        let config = Config::load_for_project(path)?;
+                        run_scan(path, &config, options).await
+                    });
+                    let elapsed = started.elapsed().as_secs();
+                    let project = match result {
+                        Ok(report) => {
+                            if let Some(dir) = reports_dir {
+                                let name = unique_name(&report_names, &report.crate_name);
+                                let file = dir.join(format!("{}.json", name));
+                                if let Err(e) = report.generate_report("json", Some(&file)) {
+                                    error!("Failed to write {}: {:#}", file.display(), e);
+                                }
+                            }
+                            ProjectResult::from_report(path, &report, elapsed)
+                        }
+                        Err(e) => {
+                            error!("❌ {}: {:#}", path.display(), e);
+                            ProjectResult::failed(path, &e, elapsed)
+                        }
+                    };
+                    results.lock().unwrap().push(project);
+                }
+            });
+        }
+    });
+
+    let mut aggregate = AggregateReport {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        projects: Vec::new(),
+    };
+    aggregate.extend(results.into_inner().unwrap());
+    Ok(aggregate)
+}
+
+/// `name`, or `name-2`, `name-3`... if an earlier project took it.
+fn unique_name(taken: &Mutex<HashSet<String>>, name: &str) -> String {
+    let mut taken = taken.lock().unwrap();
+    let mut candidate = name.to_string();
+    let mut n = 1;
+    while !taken.insert(candidate.clone()) {
+        n += 1;
+        candidate = format!("{}-{}", name, n);
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::FlaggedPattern;
+
+    #[test]
+    fn test_risk_score_and_ranking_output() {
+        let mut report = RiskReport::new("api".to_string());
+        let pattern = |severity: &str| FlaggedPattern {
+            line: 1,
+            severity: severity.to_string(),
+            description: "Spawns an external process".to_string(),
+            code_snippet: String::new(),
+            confidence: 1.0,
+            cwe: None,
+            owasp: None,
+            id: None,
+            triage: None,
+            blame: None,
+            rule: None,
+        };
+        report.add_file_finding(
+            PathBuf::from("src/main.rs"),
+            String::new(),
+            vec![pattern("Critical"), pattern("High"), pattern("Low")],
+        );
+        let api = ProjectResult::from_report(Path::new("repos/api"), &report, 1);
+        assert_eq!(api.risk_score, 80 + 30 + 5);
+        assert_eq!(api.severity_counts["High"], 1);
+
+        let failed =
+            ProjectResult::failed(Path::new("repos/web"), &anyhow::anyhow!("no Cargo.toml"), 0);
+        let aggregate = AggregateReport {
+            timestamp: "now".to_string(),
+            projects: vec![api, failed],
+        };
+        let markdown = aggregate.render("markdown").unwrap();
+        assert!(markdown.contains("| 1 | api | 115 | 1 | 1 | 0 | 1 | 0 | 0 | `repos/api` |"));
+        assert!(markdown.contains("- `repos/web`: no Cargo.toml"));
+        assert_eq!(aggregate.failed(), 1);
+    }
+}
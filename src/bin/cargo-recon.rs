@@ -0,0 +1,58 @@
+//! `cargo recon`: runs `rustrecon` as a cargo subcommand.
+//!
+//! Cargo runs `cargo-recon recon <args>`. This drops the `recon` argument and
+//! runs the `rustrecon` binary installed next to this one, with
+//! `CARGO_MANIFEST_DIR` set to the current workspace root (unless already set)
+//! so `cargo recon scan` needs no path.
+
+use std::path::PathBuf;
+use std::process::{exit, Command};
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "recon") {
+        args.remove(0);
+    }
+
+    let mut command = Command::new(rustrecon_path());
+    command.args(&args);
+    if std::env::var_os("CARGO_MANIFEST_DIR").is_none() {
+        if let Some(root) = workspace_root() {
+            command.env("CARGO_MANIFEST_DIR", root);
+        }
+    }
+
+    match command.status() {
+        Ok(status) => exit(status.code().unwrap_or(1)),
+        Err(e) => {
+            eprintln!("error: failed to run rustrecon: {}", e);
+            exit(1);
+        }
+    }
+}
+
+/// `rustrecon` next to this executable (both are installed by `cargo install`),
+/// otherwise whichever one is on the PATH.
+fn rustrecon_path() -> PathBuf {
+    let name = format!("rustrecon{}", std::env::consts::EXE_SUFFIX);
+    std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(&name))
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+/// Root directory of the workspace containing the current directory.
+fn workspace_root() -> Option<PathBuf> {
+    // Cargo tells subcommands which cargo invoked them
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = Command::new(cargo)
+        .args(["locate-project", "--workspace", "--message-format", "plain"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let manifest = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    manifest.parent().map(PathBuf::from)
+}
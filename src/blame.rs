@@ -0,0 +1,222 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::debug;
+
+use crate::report::RiskReport;
+
+/// Commit id git blame gives lines that are not committed yet.
+const UNCOMMITTED: &str = "0000000000000000000000000000000000000000";
+
+/// Who last changed a flagged line, from `git blame`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlameInfo {
+    pub author: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub email: String,
+    pub commit: String,
+    /// Author date, RFC 3339
+    pub date: String,
+}
+
+impl BlameInfo {
+    /// `Name <email>`, or the name alone.
+    pub fn owner(&self) -> String {
+        if self.email.is_empty() {
+            self.author.clone()
+        } else {
+            format!("{} <{}>", self.author, self.email)
+        }
+    }
+
+    pub fn short_commit(&self) -> &str {
+        &self.commit[..self.commit.len().min(8)]
+    }
+}
+
+/// Adds `git blame` ownership to findings, one `git blame` run per file.
+pub struct Blamer {
+    project: PathBuf,
+    /// Blame by line number, per file already looked up
+    files: HashMap<PathBuf, HashMap<usize, BlameInfo>>,
+}
+
+impl Blamer {
+    /// Fails when `project` is not in a git work tree or git is missing.
+    pub fn new(project: &Path) -> Result<Self> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(project)
+            .args(["rev-parse", "--is-inside-work-tree"])
+            .output()
+            .context("Failed to run git, which --blame needs")?;
+        if !output.status.success() {
+            bail!(
+                "{} is not in a git repository, so --blame has no history to read",
+                project.display()
+            );
+        }
+        Ok(Blamer {
+            project: project.to_path_buf(),
+            files: HashMap::new(),
+        })
+    }
+
+    /// Sets `blame` on the report's flagged patterns that do not have it yet.
+    /// Lines that are not committed, and files git does not track, stay
+    /// without an owner.
+    pub fn annotate(&mut self, report: &mut RiskReport) {
+        for finding in &mut report.findings {
+            let lines: Vec<usize> = finding
+                .flagged_patterns
+                .iter()
+                .filter(|pattern| pattern.blame.is_none() && pattern.line > 0)
+                .map(|pattern| pattern.line)
+                .collect();
+            if lines.is_empty() {
+                continue;
+            }
+            let blame = self.blame_file(&finding.file_path, &lines);
+            for pattern in &mut finding.flagged_patterns {
+                if pattern.blame.is_none() {
+                    pattern.blame = blame.get(&pattern.line).cloned();
+                }
+            }
+        }
+    }
+
+    fn blame_file(&mut self, file: &Path, lines: &[usize]) -> &HashMap<usize, BlameInfo> {
+        // Scanner paths are relative to the working directory or, for some
+        // callers, to the crate root
+        let path = if file.is_file() {
+            file.to_path_buf()
+        } else {
+            self.project.join(file)
+        };
+        let blame = self.files.entry(file.to_path_buf()).or_default();
+        let missing: Vec<usize> = lines
+            .iter()
+            .copied()
+            .filter(|line| !blame.contains_key(line))
+            .collect();
+        if !missing.is_empty() {
+            match blame_lines(&path, &missing) {
+                Ok(found) => blame.extend(found),
+                Err(e) => debug!("No blame for {}: {}", path.display(), e),
+            }
+        }
+        blame
+    }
+}
+
+/// Runs `git blame` on just `lines` of `path`. Lines past the end of the file
+/// (a model's wrong guess) are left out.
+fn blame_lines(path: &Path, lines: &[usize]) -> Result<HashMap<usize, BlameInfo>> {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = path.file_name().context("Not a file")?;
+    let length = std::fs::read_to_string(path)?.lines().count();
+    let mut command = Command::new("git");
+    command
+        .arg("-C")
+        .arg(dir)
+        .args(["blame", "--line-porcelain"]);
+    let mut any = false;
+    for &line in lines.iter().filter(|&&line| line <= length) {
+        command.arg("-L").arg(format!("{},{}", line, line));
+        any = true;
+    }
+    if !any {
+        return Ok(HashMap::new());
+    }
+    let output = command.arg("--").arg(name).output()?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(parse_line_porcelain(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Reads `git blame --line-porcelain` output into blame per final line number.
+fn parse_line_porcelain(output: &str) -> HashMap<usize, BlameInfo> {
+    let mut blame = HashMap::new();
+    let mut current: Option<(usize, BlameInfo)> = None;
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            // The line's content ends each entry
+            if let Some((number, info)) = current.take() {
+                if info.commit != UNCOMMITTED {
+                    blame.insert(number, info);
+                }
+            }
+            continue;
+        }
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match (&mut current, key) {
+            (None, commit) if commit.len() == 40 => {
+                let number = value.split(' ').nth(1).and_then(|n| n.parse().ok());
+                current = number.map(|number| {
+                    (
+                        number,
+                        BlameInfo {
+                            author: String::new(),
+                            email: String::new(),
+                            commit: commit.to_string(),
+                            date: String::new(),
+                        },
+                    )
+                });
+            }
+            (Some((_, info)), "author") => info.author = value.to_string(),
+            (Some((_, info)), "author-mail") => {
+                info.email = value.trim_matches(['<', '>']).to_string();
+            }
+            (Some((_, info)), "author-time") => {
+                info.date = value
+                    .parse()
+                    .ok()
+                    .and_then(|seconds| chrono::DateTime::from_timestamp(seconds, 0))
+                    .map(|date| date.to_rfc3339())
+                    .unwrap_or_default();
+            }
+            _ => {}
+        }
+    }
+    blame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_porcelain() {
+        let output = "\
+3f2a9c1d5e7b8a9c0d1e2f3a4b5c6d7e8f9a0b1c 4 12 1
+author Jane Doe
+author-mail <jane@example.com>
+author-time 1700000000
+author-tz +0000
+summary Add the fetcher
+filename src/net.rs
+\tCommand::new(\"sh\")
+0000000000000000000000000000000000000000 20 30 1
+author Not Committed Yet
+author-mail <not.committed.yet>
+author-time 1800000000
+filename src/net.rs
+\tlet x = 1;
+";
+        let blame = parse_line_porcelain(output);
+        assert_eq!(blame.len(), 1);
+        let info = &blame[&12];
+        assert_eq!(info.owner(), "Jane Doe <jane@example.com>");
+        assert_eq!(info.short_commit(), "3f2a9c1d");
+        assert!(info.date.starts_with("2023-11-14T"));
+    }
+}
@@ -0,0 +1,203 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::llm_client::{LlmClientError, LlmClientTrait, LlmRequest, LlmResponse};
+use crate::metrics;
+use crate::progress::{self, ProgressEvent};
+
+/// Identifies one cached LLM analysis. For dependencies `package`/`version` are
+/// the crate and its version; for local source files they are the scanned crate
+/// and the file's path within it. The content hash covers the exact prompt, so
+/// any change to the analyzed code misses the cache, and the model and prompt
+/// fingerprint keep analyses from other analyzers apart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheKey {
+    pub package: String,
+    pub version: String,
+    pub content_hash: String,
+    pub model: String,
+    pub prompt_fingerprint: String,
+}
+
+impl CacheKey {
+    /// Model and prompt fingerprint are filled in by `CachedLlmClient`.
+    pub fn new(package: &str, version: &str, prompt: &str) -> Self {
+        CacheKey {
+            package: package.to_string(),
+            version: version.to_string(),
+            content_hash: format!("{:x}", Sha256::digest(prompt.as_bytes())),
+            model: String::new(),
+            prompt_fingerprint: String::new(),
+        }
+    }
+}
+
+/// One stored analysis, as written by `cache --export`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    #[serde(flatten)]
+    pub key: CacheKey,
+    pub scan_date: DateTime<Utc>,
+    pub analysis: LlmResponse,
+}
+
+/// A cached analysis together with its id in the local store.
+#[derive(Debug, Clone)]
+pub struct StoredAnalysis {
+    pub id: i64,
+    pub entry: CacheEntry,
+}
+
+/// File format shared by `cache --export` and `cache import`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CacheExport {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub entries: Vec<CacheEntry>,
+}
+
+pub const CACHE_EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// Outcome of merging exported entries into the local cache.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub added: usize,
+    /// Entries that replaced an older local analysis
+    pub updated: usize,
+    /// Entries skipped because the local analysis is as new or newer
+    pub kept_local: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64 * 100.0
+        }
+    }
+}
+
+/// Storage for LLM analyses, so unchanged dependencies and files are not sent
+/// to the API again.
+pub trait CacheStore {
+    /// Returns the stored analysis for `key` and records a hit or miss.
+    fn get_analysis(&self, key: &CacheKey) -> Result<Option<LlmResponse>>;
+    fn put_analysis(&self, key: &CacheKey, response: &LlmResponse) -> Result<()>;
+    fn cache_stats(&self) -> Result<CacheStats>;
+    /// Removes every stored analysis and resets the statistics; returns the
+    /// number of entries removed.
+    fn clear_analyses(&self) -> Result<usize>;
+    /// Removes the analyses produced by `model`; returns the number removed.
+    fn invalidate_model(&self, model: &str) -> Result<usize>;
+    fn export_analyses(&self) -> Result<Vec<CacheEntry>>;
+    /// Lists stored analyses, optionally only those for one package.
+    fn list_analyses(&self, package: Option<&str>) -> Result<Vec<StoredAnalysis>>;
+    fn show_analysis(&self, id: i64) -> Result<Option<StoredAnalysis>>;
+    /// Removes every analysis of `package`; returns the number removed.
+    fn delete_package(&self, package: &str) -> Result<usize>;
+    /// Merges `entries`; when both sides have an analysis for the same key the
+    /// one with the later scan date wins.
+    fn import_analyses(&self, entries: &[CacheEntry]) -> Result<ImportSummary>;
+}
+
+/// Answers requests that carry a cache key from the store and forwards the rest
+/// to the wrapped client, storing successful responses.
+pub struct CachedLlmClient<C> {
+    inner: C,
+    store: Option<Mutex<Box<dyn CacheStore + Send>>>,
+    hits: AtomicUsize,
+}
+
+impl<C> CachedLlmClient<C> {
+    /// `store` is `None` when caching is disabled or the database is unavailable.
+    pub fn new(inner: C, store: Option<Box<dyn CacheStore + Send>>) -> Self {
+        CachedLlmClient {
+            inner,
+            store: store.map(Mutex::new),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    /// The request's cache key, completed with the model and prompt version.
+    fn full_key(&self, request: &LlmRequest) -> Option<CacheKey>
+    where
+        C: LlmClientTrait,
+    {
+        request.cache_key.clone().map(|key| CacheKey {
+            model: self.inner.model_name().to_string(),
+            prompt_fingerprint: self.inner.prompt_fingerprint(),
+            ..key
+        })
+    }
+
+    fn with_store<R>(&self, f: impl FnOnce(&dyn CacheStore) -> Result<R>) -> Option<R> {
+        let store = self.store.as_ref()?.lock().ok()?;
+        f(store.as_ref())
+            .map_err(|e| warn!("⚠️  Analysis cache error: {}", e))
+            .ok()
+    }
+}
+
+#[async_trait]
+impl<C: LlmClientTrait + Send + Sync> LlmClientTrait for CachedLlmClient<C> {
+    async fn analyze_code(&self, request: LlmRequest) -> Result<LlmResponse, LlmClientError> {
+        let key = self.full_key(&request);
+        if let Some(key) = &key {
+            if let Some(Some(cached)) = self.with_store(|store| store.get_analysis(key)) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                metrics::record_cache_lookup(true);
+                progress::emit(ProgressEvent::CacheHit {
+                    package: &key.package,
+                    version: &key.version,
+                });
+                return Ok(cached);
+            }
+        }
+
+        if key.is_some() && self.store.is_some() {
+            metrics::record_cache_lookup(false);
+        }
+        let response = self.inner.analyze_code(request).await?;
+        if let Some(key) = &key {
+            self.with_store(|store| store.put_analysis(key, &response));
+        }
+        Ok(response)
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn prompt_fingerprint(&self) -> String {
+        self.inner.prompt_fingerprint()
+    }
+
+    fn cache_hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    fn is_cached(&self, request: &LlmRequest) -> bool {
+        self.full_key(request).is_some_and(|key| {
+            self.with_store(|store| store.get_analysis(&key))
+                .flatten()
+                .is_some()
+        })
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::warn;
+
+use crate::dependency_graph::package_key;
+use crate::lockfile;
+
+/// Refs tried, in order, as the default branch a change is compared with.
+const DEFAULT_BRANCHES: &[&str] = &[
+    "refs/remotes/origin/HEAD",
+    "origin/main",
+    "origin/master",
+    "main",
+    "master",
+];
+
+/// What changed in a crate since a git ref, for `scan --changed`.
+#[derive(Debug, Clone)]
+pub struct ChangeSet {
+    /// Commit the working tree was compared with
+    pub base: String,
+    /// Added, modified and untracked files, relative to the crate root
+    pub files: Vec<String>,
+    /// `name@version` of dependencies the lockfile adds or bumps
+    pub packages: HashSet<String>,
+}
+
+/// Files and dependencies of the crate at `project` that changed since
+/// `since`, or since the merge base with the default branch. The working
+/// tree counts, uncommitted and untracked files included.
+pub fn since(project: &Path, since: Option<&str>) -> Result<ChangeSet> {
+    let base = match since {
+        Some(reference) => git(
+            project,
+            &[
+                "rev-parse",
+                "--verify",
+                &format!("{}^{{commit}}", reference),
+            ],
+        )
+        .with_context(|| format!("Unknown git ref '{}'", reference))?,
+        None => DEFAULT_BRANCHES
+            .iter()
+            .find_map(|branch| git(project, &["merge-base", "HEAD", branch]).ok())
+            .context("Could not find the default branch to compare with; pass --since <ref>")?,
+    };
+
+    let mut files: Vec<String> = git(
+        project,
+        &[
+            "diff",
+            "-z",
+            "--name-only",
+            "--relative",
+            "--diff-filter=ACMR",
+            &base,
+        ],
+    )?
+    .split('\0')
+    .chain(
+        git(
+            project,
+            &["ls-files", "-z", "--others", "--exclude-standard"],
+        )?
+        .split('\0'),
+    )
+    .filter(|path| !path.is_empty())
+    .map(str::to_string)
+    .collect();
+    files.sort();
+    files.dedup();
+
+    let packages = changed_packages(project, &base)?;
+    Ok(ChangeSet {
+        base,
+        files,
+        packages,
+    })
+}
+
+/// Dependencies added or bumped in the crate's lockfile since `base`.
+fn changed_packages(project: &Path, base: &str) -> Result<HashSet<String>> {
+    let toplevel = PathBuf::from(git(project, &["rev-parse", "--show-toplevel"])?);
+    let toplevel = toplevel.canonicalize().unwrap_or(toplevel);
+    let project = project.canonicalize()?;
+    // The nearest lockfile at or above the crate, as cargo finds it
+    let Some(lockfile) = project
+        .ancestors()
+        .take_while(|dir| dir.starts_with(&toplevel))
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file())
+    else {
+        return Ok(HashSet::new());
+    };
+    let relative = lockfile
+        .strip_prefix(&toplevel)?
+        .to_string_lossy()
+        .replace('\\', "/");
+    let Ok(base_content) = git(&project, &["show", &format!("{}:{}", base, relative)]) else {
+        warn!(
+            "⚠️  {} is not tracked at {}; dependency changes are unknown, so no dependencies are scanned",
+            relative,
+            short(base)
+        );
+        return Ok(HashSet::new());
+    };
+    let base_packages = lockfile::parse_lockfile_str(&base_content)?;
+    let head_packages = lockfile::parse_lockfile(&lockfile)?;
+    Ok(lockfile::diff_lockfiles(&base_packages, &head_packages)
+        .changed_packages()
+        .iter()
+        .map(|package| package_key(&package.name, &package.version))
+        .collect())
+}
+
+/// Abbreviated commit id for messages.
+pub fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(12)]
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git, which --changed needs")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_since_ref() {
+        let dir =
+            std::env::temp_dir().join(format!("rustrecon-test-{}-changes", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let lock = |packages: &[(&str, &str)]| -> String {
+            packages
+                .iter()
+                .map(|(name, version)| {
+                    format!(
+                        "[[package]]\nname = \"{}\"\nversion = \"{}\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n\n",
+                        name, version
+                    )
+                })
+                .collect()
+        };
+        let run = |args: &[&str]| {
+            git(
+                &dir,
+                &[
+                    &["-c", "user.name=test", "-c", "user.email=test@example.com"],
+                    args,
+                ]
+                .concat(),
+            )
+            .unwrap()
+        };
+        run(&["init", "-q"]);
+        std::fs::write(dir.join("src/lib.rs"), "mod net;\n").unwrap();
+        std::fs::write(dir.join("src/net.rs"), "fn f() {}\n").unwrap();
+        std::fs::write(dir.join("Cargo.lock"), lock(&[("anyhow", "1.0.0")])).unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-q", "-m", "base"]);
+
+        std::fs::write(dir.join("src/net.rs"), "fn g() {}\n").unwrap();
+        std::fs::write(dir.join("src/new.rs"), "fn h() {}\n").unwrap();
+        std::fs::write(
+            dir.join("Cargo.lock"),
+            lock(&[("anyhow", "1.0.1"), ("base64", "0.22.1")]),
+        )
+        .unwrap();
+        let changes = since(&dir, Some("HEAD"));
+        let unknown = since(&dir, Some("no-such-ref"));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let changes = changes.unwrap();
+        assert_eq!(
+            changes.files,
+            vec!["Cargo.lock", "src/net.rs", "src/new.rs"]
+        );
+        let mut packages: Vec<_> = changes.packages.into_iter().collect();
+        packages.sort();
+        assert_eq!(packages, vec!["anyhow@1.0.1", "base64@0.22.1"]);
+        assert!(unknown.is_err());
+    }
+}
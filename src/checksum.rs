@@ -0,0 +1,120 @@
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Cargo's names for its crates.io index directories under `registry/cache`:
+/// the sparse index and the older git index.
+const CRATES_IO_CACHE_PREFIXES: &[&str] = &["index.crates.io-", "github.com-1ecc6299db9ec823"];
+
+/// A package whose downloaded contents do not hash to the `Cargo.lock` checksum.
+#[derive(Debug)]
+pub struct Mismatch {
+    /// The `.crate` (or vendored checksum file) that was checked
+    pub file: PathBuf,
+    pub actual: String,
+}
+
+/// Checks a package unpacked at `package_dir` against the `Cargo.lock`
+/// checksum. Registry packages are checked through the `.crate` Cargo keeps in
+/// `registry/cache`, vendored ones through the hash `cargo vendor` recorded.
+/// Returns `None` when the contents match or there is nothing to check.
+pub fn verify_unpacked(package_dir: &Path, expected: &str) -> Option<Mismatch> {
+    if let Some(tarball) = cached_tarball(package_dir) {
+        return verify_file(tarball, expected);
+    }
+    let vendored = package_dir.join(".cargo-checksum.json");
+    let recorded: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&vendored).ok()?).ok()?;
+    let actual = recorded["package"].as_str()?;
+    (!actual.eq_ignore_ascii_case(expected)).then(|| Mismatch {
+        file: vendored,
+        actual: actual.to_string(),
+    })
+}
+
+/// Checks the `.crate` Cargo downloaded for `name@version` from crates.io,
+/// for scans that only have the lockfile.
+pub fn verify_cached(name: &str, version: &str, expected: &str) -> Option<Mismatch> {
+    let cache = cargo_home()?.join("registry").join("cache");
+    let file_name = format!("{}-{}.crate", name, version);
+    let tarball = std::fs::read_dir(cache)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let index = entry.file_name().to_string_lossy().to_string();
+            CRATES_IO_CACHE_PREFIXES
+                .iter()
+                .any(|prefix| index.starts_with(prefix))
+        })
+        .map(|entry| entry.path().join(&file_name))
+        .find(|tarball| tarball.is_file())?;
+    verify_file(tarball, expected)
+}
+
+/// `$CARGO_HOME/registry/cache/<index>/<name>-<version>.crate` for a package
+/// unpacked at `$CARGO_HOME/registry/src/<index>/<name>-<version>`.
+fn cached_tarball(package_dir: &Path) -> Option<PathBuf> {
+    let index_dir = package_dir.parent()?;
+    let src_dir = index_dir.parent()?;
+    if src_dir.file_name()? != "src" {
+        return None;
+    }
+    let tarball = src_dir
+        .parent()?
+        .join("cache")
+        .join(index_dir.file_name()?)
+        .join(format!(
+            "{}.crate",
+            package_dir.file_name()?.to_string_lossy()
+        ));
+    tarball.is_file().then_some(tarball)
+}
+
+fn verify_file(file: PathBuf, expected: &str) -> Option<Mismatch> {
+    let actual = format!("{:x}", Sha256::digest(std::fs::read(&file).ok()?));
+    (!actual.eq_ignore_ascii_case(expected)).then_some(Mismatch { file, actual })
+}
+
+fn cargo_home() -> Option<PathBuf> {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_cached_and_vendored_packages() {
+        let root =
+            std::env::temp_dir().join(format!("rustrecon-test-{}-checksum", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let unpacked = root.join("registry/src/index.crates.io-abc/demo-1.0.0");
+        std::fs::create_dir_all(&unpacked).unwrap();
+        std::fs::create_dir_all(root.join("registry/cache/index.crates.io-abc")).unwrap();
+        std::fs::write(
+            root.join("registry/cache/index.crates.io-abc/demo-1.0.0.crate"),
+            b"tarball",
+        )
+        .unwrap();
+        let vendored = root.join("vendor/demo");
+        std::fs::create_dir_all(&vendored).unwrap();
+        std::fs::write(
+            vendored.join(".cargo-checksum.json"),
+            r#"{"files":{},"package":"abc123"}"#,
+        )
+        .unwrap();
+
+        let sha = format!("{:x}", Sha256::digest(b"tarball"));
+        let matching = verify_unpacked(&unpacked, &sha);
+        let tampered = verify_unpacked(&unpacked, &"0".repeat(64));
+        let vendored_matching = verify_unpacked(&vendored, "abc123");
+        let vendored_tampered = verify_unpacked(&vendored, "def456");
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(matching.is_none());
+        assert_eq!(tampered.unwrap().actual, sha);
+        assert!(vendored_matching.is_none());
+        assert_eq!(vendored_tampered.unwrap().actual, "abc123");
+    }
+}
@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::{error, warn};
+
+use crate::llm_client::{LlmClientError, LlmClientTrait, LlmRequest, LlmResponse};
+use crate::network::{self, RetryPolicy};
+use crate::scan_budget::ScanBudget;
+
+/// Stops sending requests once the LLM service keeps failing. Quota, 5xx and
+/// network failures are retried from a budget shared by the whole scan, and
+/// `threshold` of them in a row open the breaker: every later request fails at
+/// once with `CircuitOpen`, so the remaining files fall back to static analysis
+/// instead of each waiting out its own retries. Every request sent, retries
+/// included, is also counted against the scan's [`ScanBudget`].
+pub struct CircuitBreaker<C> {
+    inner: C,
+    threshold: usize,
+    /// Retries of one request and the wait between them, from `[network]`
+    retry_policy: RetryPolicy,
+    retries_left: AtomicUsize,
+    consecutive_failures: AtomicUsize,
+    open: AtomicBool,
+    budget: Arc<ScanBudget>,
+}
+
+impl<C> CircuitBreaker<C> {
+    pub fn new(inner: C, threshold: usize, retry_budget: usize) -> Self {
+        CircuitBreaker {
+            inner,
+            threshold,
+            retry_policy: network::retry_policy(),
+            retries_left: AtomicUsize::new(retry_budget),
+            consecutive_failures: AtomicUsize::new(0),
+            open: AtomicBool::new(false),
+            budget: Arc::default(),
+        }
+    }
+
+    pub fn with_budget(mut self, budget: Arc<ScanBudget>) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    fn take_retry(&self) -> bool {
+        self.retries_left
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                left.checked_sub(1)
+            })
+            .is_ok()
+    }
+}
+
+#[async_trait]
+impl<C: LlmClientTrait + Send + Sync> LlmClientTrait for CircuitBreaker<C> {
+    async fn analyze_code(&self, request: LlmRequest) -> Result<LlmResponse, LlmClientError> {
+        let mut attempt = 0;
+        loop {
+            if self.open.load(Ordering::Relaxed) {
+                return Err(LlmClientError::CircuitOpen(self.threshold));
+            }
+            self.budget.take().map_err(LlmClientError::BudgetSpent)?;
+            let e = match self.inner.analyze_code(request.clone()).await {
+                Ok(response) => {
+                    self.consecutive_failures.store(0, Ordering::Relaxed);
+                    return Ok(response);
+                }
+                Err(e) if e.is_service_failure() => e,
+                Err(e) => return Err(e),
+            };
+
+            let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+            if failures >= self.threshold {
+                if !self.open.swap(true, Ordering::Relaxed) {
+                    error!(
+                        "⛔ LLM failed {} times in a row ({}); analyzing the remaining files with static rules only",
+                        failures, e
+                    );
+                }
+                return Err(LlmClientError::CircuitOpen(failures));
+            }
+            if attempt == self.retry_policy.max_retries || !self.take_retry() {
+                return Err(e);
+            }
+            let delay = self.retry_policy.delay(attempt);
+            warn!("⚠️  {}; retrying in {:.1}s", e, delay.as_secs_f64());
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn prompt_fingerprint(&self) -> String {
+        self.inner.prompt_fingerprint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::Language;
+    use std::time::Duration;
+
+    /// Fails with a quota error for the first `failures` requests.
+    struct Flaky {
+        failures: usize,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LlmClientTrait for Flaky {
+        async fn analyze_code(&self, _: LlmRequest) -> Result<LlmResponse, LlmClientError> {
+            if self.calls.fetch_add(1, Ordering::Relaxed) < self.failures {
+                return Err(LlmClientError::QuotaExhausted("429".to_string()));
+            }
+            Ok(LlmResponse {
+                analysis: "ok".to_string(),
+                flagged_patterns: Vec::new(),
+                version: None,
+            })
+        }
+
+        fn model_name(&self) -> &str {
+            "flaky"
+        }
+
+        fn prompt_fingerprint(&self) -> String {
+            String::new()
+        }
+    }
+
+    fn breaker(failures: usize, threshold: usize, retry_budget: usize) -> CircuitBreaker<Flaky> {
+        CircuitBreaker {
+            retry_policy: RetryPolicy {
+                max_retries: 2,
+                backoff: Duration::ZERO,
+            },
+            ..CircuitBreaker::new(
+                Flaky {
+                    failures,
+                    calls: AtomicUsize::new(0),
+                },
+                threshold,
+                retry_budget,
+            )
+        }
+    }
+
+    fn request() -> LlmRequest {
+        LlmRequest {
+            prompt: String::new(),
+            cache_key: None,
+            language: Language::Rust,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_then_opens() {
+        // A transient failure is retried from the budget
+        let recovering = breaker(1, 5, 10);
+        assert!(recovering.analyze_code(request()).await.is_ok());
+        assert_eq!(recovering.retries_left.load(Ordering::Relaxed), 9);
+
+        // Without budget the failure is returned as is
+        let no_budget = breaker(1, 5, 0);
+        assert!(matches!(
+            no_budget.analyze_code(request()).await,
+            Err(LlmClientError::QuotaExhausted(_))
+        ));
+
+        // Persistent failures open the breaker, and later requests are not sent
+        let failing = breaker(usize::MAX, 3, 10);
+        assert!(failing.analyze_code(request()).await.is_err());
+        assert!(matches!(
+            failing.analyze_code(request()).await,
+            Err(LlmClientError::CircuitOpen(3))
+        ));
+        assert_eq!(failing.inner.calls.load(Ordering::Relaxed), 3);
+    }
+}
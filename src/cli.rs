@@ -1,12 +1,39 @@
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Parser, Subcommand};
+use std::time::Duration;
+
+use crate::scan_budget;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 pub struct Cli {
     #[clap(subcommand)]
     pub command: Option<Commands>,
+    /// Show more detail (-v debug, -vv trace)
+    #[clap(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+    /// Print only the report or command output: no logs, warnings or progress. Failures still print one error line, and the exit status tells what happened
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Log level, overriding -v/-q and RUST_LOG
+    #[clap(long, global = true, value_parser = ["trace", "debug", "info", "warn", "error"])]
+    pub log_level: Option<String>,
+    /// Write log lines to stderr as JSON
+    #[clap(long, global = true)]
+    pub log_json: bool,
+    /// Never prompt or open interactive views; fail instead (for containers and CI)
+    #[clap(long, global = true)]
+    pub non_interactive: bool,
+    /// Refuse every network request except to the LLM endpoint, crates.io and
+    /// `[network] allowed_hosts`; anything else fails the command
+    #[clap(long, global = true)]
+    pub no_network_except_llm: bool,
+    /// Write man pages for rustrecon and its subcommands into DIR and exit
+    #[clap(long, value_name = "DIR")]
+    pub generate_man: Option<String>,
 }
 
+// Parsed once per run, so the size of `Scan` does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Initializes configuration files
@@ -14,17 +41,76 @@ pub enum Commands {
         /// Path to create the config file
         #[clap(short, long, default_value = "./rustrecon_config.toml")]
         config_path: String,
+        /// Store the API key in the OS keychain instead of the config file
+        #[clap(long)]
+        keychain: bool,
+        /// API key to write to the config (or the keychain with --keychain) instead of a placeholder
+        #[clap(long, value_name = "KEY")]
+        api_key: Option<String>,
+        /// LLM provider; Gemini is the only one supported so far
+        #[clap(long, default_value = "gemini", value_parser = ["gemini"])]
+        provider: String,
+        /// Model to analyze code with [default: gemini-1.5-flash]
+        #[clap(long)]
+        model: Option<String>,
+        /// API endpoint, for proxies and other Gemini-compatible services
+        #[clap(long, value_name = "URL")]
+        endpoint: Option<String>,
+        /// Replace an existing config file without asking, and take the default model without offering a choice
+        #[clap(short, long)]
+        yes: bool,
+        /// Write the config without checking the API key and model with the provider
+        #[clap(long)]
+        no_verify: bool,
     },
     /// Tests the LLM API connection
     Test,
+    /// Validates, displays or upgrades the configuration
+    Config {
+        #[clap(subcommand)]
+        action: ConfigAction,
+    },
+    /// Vets a crate name for typosquatting and registry red flags before adding it
+    CheckName {
+        /// Name of the crate to check
+        #[clap(value_parser)]
+        crate_name: String,
+    },
+    /// Scans only the dependencies listed in a Cargo.lock and prints JSON results
+    Deps {
+        /// Path to the Cargo.lock file
+        #[clap(short, long, default_value = "Cargo.lock")]
+        lockfile: String,
+        /// Lockfile-only mode: no source tree or LLM, registry checks run concurrently
+        #[clap(long)]
+        fast: bool,
+        /// Output file for the JSON results
+        #[clap(short, long)]
+        output: Option<String>,
+    },
+    /// Analyzes only dependencies added or bumped between two lockfiles (for PR gating)
+    DepsDiff {
+        /// Lockfile of the base branch
+        #[clap(long)]
+        base: String,
+        /// Lockfile of the pull request
+        #[clap(long)]
+        head: String,
+        /// Output file for the JSON results
+        #[clap(short, long)]
+        output: Option<String>,
+    },
     /// Scans a specified crate
     Scan {
-        /// Path to the crate to scan
+        /// Path to the crate to scan [default: CARGO_MANIFEST_DIR (set by `cargo recon`), or the current directory]
         #[clap(value_parser)]
-        crate_path: String,
-        /// Output format for the report (json, markdown, condensed, summary)
+        crate_path: Option<String>,
+        /// Output format for the report (json, markdown, condensed, summary, summary-json, gitlab-codequality, junit, sarif, html)
         #[clap(short, long, default_value = "markdown")]
         format: String,
+        /// Render the report with this Handlebars template instead of a built-in format
+        #[clap(long, value_name = "FILE", conflicts_with = "format")]
+        template: Option<String>,
         /// Output file for the report
         #[clap(short, long)]
         output: Option<String>,
@@ -34,5 +120,464 @@ pub enum Commands {
         /// Skip dependency scanning (code only)
         #[clap(long)]
         skip_dependencies: bool,
+        /// Dependencies to scan: direct, or transitive (the whole graph) [default: dependencies.depth from the config, else transitive]
+        #[clap(long, value_name = "DEPTH", value_parser = ["direct", "transitive"])]
+        dependency_depth: Option<String>,
+        /// Scan at most this many dependencies, nearest first [default: dependencies.max_packages]
+        #[clap(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        max_dependencies: Option<u64>,
+        /// Make at most this many uncached LLM requests for dependencies [default: dependencies.max_llm_calls]
+        #[clap(long, value_name = "N")]
+        max_llm_calls: Option<usize>,
+        /// Send no new LLM requests after this long (e.g. 90s, 15m, 2h); the rest gets static checks and the report is marked partial
+        #[clap(long, value_name = "DURATION", value_parser = scan_budget::parse_duration)]
+        max_duration: Option<Duration>,
+        /// Send at most this many LLM requests, retries included, for code and dependencies; the report is marked partial when reached
+        #[clap(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        max_api_calls: Option<u64>,
+        /// Leave the timestamp and scan-history trend out of the report, so scans of the same code give identical reports
+        #[clap(long)]
+        reproducible: bool,
+        /// Show absolute file paths in the report instead of paths relative to the crate
+        #[clap(long)]
+        absolute_paths: bool,
+        /// Resume a previously interrupted scan of this crate from its checkpoint
+        #[clap(long)]
+        resume: bool,
+        /// Scan profile: quick (static rules only, no LLM), standard, deep, or one defined under [profiles]
+        #[clap(long, default_value = "standard")]
+        profile: String,
+        /// Make no network calls: static rules, secrets, typosquatting and local metadata only
+        #[clap(long, visible_alias = "no-llm")]
+        offline: bool,
+        /// Diff each dependency's crates.io tarball against its repository (requires git)
+        #[clap(long)]
+        verify_sources: bool,
+        /// Also analyze the crate after macro expansion (requires cargo-expand)
+        #[clap(long)]
+        expand_macros: bool,
+        /// Ignore cached LLM analyses and analyze everything again
+        #[clap(long)]
+        no_cache: bool,
+        /// Progress reporting: "text" (log lines) or "json" (newline-delimited events on stderr)
+        #[clap(long, default_value = "text", value_parser = ["text", "json"])]
+        progress_format: String,
+        /// Skip files matching this glob (relative to the crate root); repeatable, adds to [scanner] exclude
+        #[clap(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+        /// Only scan files matching this glob; repeatable, adds to [scanner] include
+        #[clap(long, value_name = "GLOB")]
+        include: Vec<String>,
+        /// Scan only this directory or the files matching this glob, in place of [scanner] include; repeatable. Skips the dependency scan
+        #[clap(long, value_name = "DIR|GLOB")]
+        only: Vec<String>,
+        /// Scan only this file, even if excluded or ignored; repeatable, combines with --only. Skips the dependency scan
+        #[clap(long = "file", value_name = "PATH")]
+        files: Vec<String>,
+        /// Scan only files changed since --since (uncommitted and untracked ones too) and dependencies added or updated in Cargo.lock
+        #[clap(long)]
+        changed: bool,
+        /// Git ref --changed compares with [default: the merge base with the default branch]
+        #[clap(long, value_name = "REF", requires = "changed")]
+        since: Option<String>,
+        /// Add the last author and commit of each flagged line (git blame) and a findings-by-author section to the report
+        #[clap(long)]
+        blame: bool,
+        /// Also scan files excluded by .gitignore, .ignore or .rustreconignore
+        #[clap(long)]
+        no_ignore: bool,
+        /// Leave findings with a confidence below this value (0.0-1.0) out of the report
+        #[clap(long, value_name = "CONFIDENCE")]
+        min_confidence: Option<f32>,
+        /// Check the results against the rules in this policy file and fail if any is broken
+        #[clap(long, value_name = "FILE")]
+        policy: Option<String>,
+        /// Previous JSON report that policy rules with `new = true` compare against
+        #[clap(long, value_name = "REPORT", requires = "policy")]
+        policy_baseline: Option<String>,
+        /// Exit with status 1 if a code finding or dependency has this severity or above
+        #[clap(long, value_name = "SEVERITY")]
+        fail_on: Option<String>,
+        /// Do not send the [notifications] webhook alerts for this scan
+        #[clap(long)]
+        no_notify: bool,
+        /// Append results to --output as they complete (JSON Lines for json, markdown otherwise), for very large workspaces
+        #[clap(long, requires = "output", conflicts_with_all = ["template", "policy"])]
+        stream: bool,
+        /// Write a detached SSH signature of the report to <OUTPUT>.sig
+        #[clap(long, requires = "output")]
+        sign: bool,
+        /// Write an in-toto provenance attestation to <OUTPUT>.intoto.json (signed too with --sign)
+        #[clap(long, requires = "output")]
+        attest: bool,
+        /// SSH private key for --sign [default: signing.key from the config]
+        #[clap(long, value_name = "KEY", requires = "sign")]
+        signing_key: Option<String>,
+    },
+    /// Scans many projects and writes one report ranking them by risk
+    ScanMany {
+        /// File listing one project path per line, or a glob matching project directories (e.g. "repos/*")
+        #[clap(value_parser)]
+        targets: String,
+        /// Number of projects to scan at the same time
+        #[clap(short, long, default_value_t = 1)]
+        jobs: usize,
+        /// Output format for the aggregate report (markdown, json, summary)
+        #[clap(short, long, default_value = "markdown", value_parser = ["markdown", "json", "summary"])]
+        format: String,
+        /// Output file for the aggregate report
+        #[clap(short, long)]
+        output: Option<String>,
+        /// Also write each project's full JSON report into this directory
+        #[clap(long, value_name = "DIR")]
+        reports_dir: Option<String>,
+        /// Scan profile: quick (static rules only, no LLM), standard, deep, or one defined under [profiles]
+        #[clap(long, default_value = "standard")]
+        profile: String,
+        /// Make no network calls: static rules, secrets, typosquatting and local metadata only
+        #[clap(long, visible_alias = "no-llm")]
+        offline: bool,
+        /// Skip dependency scanning (code only)
+        #[clap(long)]
+        skip_dependencies: bool,
+        /// Leave findings with a confidence below this value (0.0-1.0) out of the reports
+        #[clap(long, value_name = "CONFIDENCE")]
+        min_confidence: Option<f32>,
+    },
+    /// Audits the dependencies of every Rust repository of a GitHub organization
+    AuditOrg {
+        /// Organization (or user) whose repositories to audit
+        #[clap(long, value_name = "ORG")]
+        github: String,
+        /// GitHub API token, needed for private repositories [default: GITHUB_TOKEN]
+        #[clap(long)]
+        token: Option<String>,
+        /// GitHub API URL (for GitHub Enterprise Server)
+        #[clap(long, default_value = "https://api.github.com")]
+        api_url: String,
+        /// Also audit forks
+        #[clap(long)]
+        include_forks: bool,
+        /// Also audit archived repositories
+        #[clap(long)]
+        include_archived: bool,
+        /// Keep the shallow clones in this directory (and update them on later runs) instead of a temporary one
+        #[clap(long, value_name = "DIR")]
+        clone_dir: Option<String>,
+        /// Number of repositories to scan at the same time
+        #[clap(short, long, default_value_t = 1)]
+        jobs: usize,
+        /// Output format for the dashboard (markdown, json, summary)
+        #[clap(short, long, default_value = "markdown", value_parser = ["markdown", "json", "summary"])]
+        format: String,
+        /// Output file for the dashboard
+        #[clap(short, long)]
+        output: Option<String>,
+        /// Also write each repository's full JSON report into this directory
+        #[clap(long, value_name = "DIR")]
+        reports_dir: Option<String>,
+        /// Scan profile: quick (static rules only, no LLM), standard, deep, or one defined under [profiles]
+        #[clap(long, default_value = "standard")]
+        profile: String,
+        /// Make no network calls for analysis: static rules, typosquatting and local metadata only
+        #[clap(long, visible_alias = "no-llm")]
+        offline: bool,
+    },
+    /// Shows findings and dependency risk across earlier scans of a crate
+    History {
+        /// Crate path or name
+        #[clap(value_parser, default_value = ".")]
+        crate_ref: String,
+        /// Number of most recent scans to show
+        #[clap(short, long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Regenerates a report in another format from a saved JSON report, without re-scanning
+    Report {
+        /// Report written by `scan --format json`
+        #[clap(value_parser)]
+        report: String,
+        /// Output format (json, markdown, condensed, summary, summary-json, gitlab-codequality, junit, sarif, html)
+        #[clap(short, long, default_value = "markdown")]
+        format: String,
+        /// Render the report with this Handlebars template instead of a built-in format
+        #[clap(long, value_name = "FILE", conflicts_with = "format")]
+        template: Option<String>,
+        /// Output file for the report
+        #[clap(short, long)]
+        output: Option<String>,
+        /// Leave findings with a confidence below this value (0.0-1.0) out of the report
+        #[clap(long, value_name = "CONFIDENCE")]
+        min_confidence: Option<f32>,
+    },
+    /// Checks a report's signature and, when present, its attestation
+    Verify {
+        /// Report written by `scan --sign`
+        #[clap(value_parser)]
+        report: String,
+        /// ssh-keygen allowed_signers file listing the trusted signing keys
+        #[clap(long, value_name = "FILE")]
+        allowed_signers: String,
+        /// Signer identity the key is listed under in the allowed_signers file
+        #[clap(long)]
+        identity: String,
+    },
+    /// Steps through the findings of a JSON report and records dispositions
+    Review {
+        /// Report written by `scan --format json`
+        #[clap(value_parser)]
+        report: String,
+        /// Baseline file that stores the dispositions
+        #[clap(long, default_value = crate::baseline::DEFAULT_BASELINE_FILE)]
+        baseline: String,
+    },
+    /// Comments on a GitHub pull request with the findings on lines it changes
+    PrComment {
+        /// Repository as owner/name
+        #[clap(long)]
+        repo: String,
+        /// Pull request number
+        #[clap(long)]
+        pr: u64,
+        /// Report written by `scan --format json` for the pull request's head
+        #[clap(long)]
+        report: String,
+        /// Repository root that GitHub paths are relative to [default: the current git repository]
+        #[clap(long, value_name = "DIR")]
+        root: Option<String>,
+        /// Findings reviewed as accepted or false positive in this baseline are left out
+        #[clap(long, default_value = crate::baseline::DEFAULT_BASELINE_FILE)]
+        baseline: String,
+        /// GitHub API token [default: GITHUB_TOKEN]
+        #[clap(long)]
+        token: Option<String>,
+        /// GitHub API URL (for GitHub Enterprise Server)
+        #[clap(long, default_value = "https://api.github.com")]
+        api_url: String,
+        /// Exit with status 1 if a new finding has this severity or above
+        #[clap(long, value_name = "SEVERITY")]
+        fail_on: Option<String>,
+    },
+    /// Runs a scanning service with a REST API for submitting scans and fetching reports
+    Serve {
+        /// Address to listen on
+        #[clap(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+        /// Number of scans to run at the same time
+        #[clap(long, default_value_t = 1)]
+        workers: usize,
+        /// Scan profile for jobs that do not ask for one
+        #[clap(long, default_value = "standard")]
+        profile: String,
+        /// Also accept directories and git repositories on the server's own file system
+        #[clap(long)]
+        allow_local_paths: bool,
+        /// Bearer token every request must carry; required to listen on a
+        /// non-loopback address. RUSTRECON_SERVE_TOKEN also sets it
+        #[clap(long)]
+        token: Option<String>,
+    },
+    /// Runs a language server on stdin/stdout that shows findings in the editor
+    Lsp {
+        /// Scan profile for LLM analyses; `quick` uses static rules only
+        #[clap(long, default_value = "standard")]
+        profile: String,
+    },
+    /// Runs a Model Context Protocol server on stdin/stdout with scan tools for AI agents
+    Mcp {
+        /// Scan profile for tool calls that do not name one
+        #[clap(long, default_value = "standard")]
+        profile: String,
+    },
+    /// Installs or runs fast static-only checks as git hooks
+    Hook {
+        #[clap(subcommand)]
+        action: HookAction,
+    },
+    /// Reports configuration, database and helper tool status for troubleshooting
+    Diagnose,
+    /// Prints a shell completion script to stdout
+    Completions {
+        /// Shell to generate completions for
+        #[clap(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Exports the scan, cache and LLM metrics recorded with `[metrics] enabled = true`
+    Metrics {
+        /// Output format (prometheus, json)
+        #[clap(short, long, default_value = "prometheus")]
+        format: String,
+        /// Write to this file instead of stdout, replacing it atomically
+        /// (for the node exporter's textfile collector)
+        #[clap(short, long)]
+        output: Option<String>,
+        /// Delete the recorded metrics
+        #[clap(long, conflicts_with_all = ["format", "output"])]
+        reset: bool,
+    },
+    /// Lists the models the configured LLM provider offers
+    Models {
+        #[clap(subcommand)]
+        action: ModelsAction,
+    },
+    /// Lists findings recorded by earlier scans and updates their triage state
+    Triage {
+        #[clap(subcommand)]
+        action: TriageAction,
+    },
+    /// Shows or clears the local LLM analysis cache
+    Cache {
+        /// Remove all cached analyses
+        #[clap(long)]
+        clear: bool,
+        /// Remove cached analyses produced by the named model
+        #[clap(long, value_name = "MODEL")]
+        invalidate_model: Option<String>,
+        /// Write all cached analyses to a JSON file for sharing
+        #[clap(long, value_name = "FILE")]
+        export: Option<String>,
+        /// List cached analyses with their ids
+        #[clap(long)]
+        list: bool,
+        /// With --list, only show analyses of this package
+        #[clap(long, requires = "list")]
+        package: Option<String>,
+        /// Print the cached analysis with this id
+        #[clap(long, value_name = "ID")]
+        show: Option<i64>,
+        /// Remove all cached analyses of a package
+        #[clap(long, value_name = "PACKAGE")]
+        delete: Option<String>,
+        #[clap(subcommand)]
+        action: Option<CacheAction>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Reports unknown keys, placeholder API keys and other setup problems
+    Validate {
+        /// Config file to check (defaults to the usual lookup order)
+        #[clap(short, long)]
+        config_path: Option<String>,
+    },
+    /// Prints the effective configuration with secrets redacted
+    Show {
+        /// Config file to show (defaults to the usual lookup order)
+        #[clap(short, long)]
+        config_path: Option<String>,
+        /// Crate whose .rustrecon.toml is merged into the output
+        #[clap(long, default_value = ".")]
+        project: String,
+    },
+    /// Adds the sections and settings of newer releases to a config file,
+    /// keeping its values and comments
+    Upgrade {
+        /// Config file to upgrade (defaults to the usual lookup order)
+        #[clap(short, long)]
+        config_path: Option<String>,
+        /// Print the upgraded file instead of writing it
+        #[clap(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HookAction {
+    /// Installs a pre-push hook in the current git repository
+    Install {
+        /// Also install a pre-commit hook that checks staged changes
+        #[clap(long)]
+        pre_commit: bool,
+        /// Replace existing hooks not installed by rustrecon
+        #[clap(long)]
+        force: bool,
+    },
+    /// Checks changed files with the static rules and changed lockfiles offline
+    Run {
+        /// Check staged changes (pre-commit) instead of commits being pushed
+        #[clap(long)]
+        staged: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ModelsAction {
+    /// Lists available models, marking deprecated ones and the configured one
+    List {
+        /// Print the models as JSON
+        #[clap(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TriageAction {
+    /// Lists recorded findings with their ids and triage states
+    List {
+        /// Only findings of this crate
+        #[clap(long = "crate", value_name = "NAME")]
+        crate_name: Option<String>,
+        /// Only findings in this state (new, confirmed, false-positive, fixed)
+        #[clap(long)]
+        state: Option<String>,
+        /// Print the findings as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Sets the triage state of one or more findings
+    Set {
+        /// new, confirmed, false-positive or fixed
+        #[clap(value_parser)]
+        state: String,
+        /// Finding ids from reports or `triage list`; a unique prefix is enough
+        #[clap(value_parser, required = true)]
+        ids: Vec<String>,
+        /// Reviewer's note, shown next to the finding in reports
+        #[clap(long)]
+        note: Option<String>,
+        /// Name recorded with the decision (defaults to git's user.name)
+        #[clap(long)]
+        reviewer: Option<String>,
+    },
+    /// Adds a review comment to a finding
+    Comment {
+        /// Finding id; a unique prefix is enough
+        #[clap(value_parser)]
+        id: String,
+        /// The comment
+        #[clap(value_parser)]
+        text: String,
+        /// Name recorded with the comment (defaults to git's user.name)
+        #[clap(long)]
+        reviewer: Option<String>,
+    },
+    /// Writes findings, triage decisions and comments to a bundle for another reviewer
+    Export {
+        /// Bundle file to write
+        #[clap(short, long)]
+        output: String,
+        /// Only findings of this crate
+        #[clap(long = "crate", value_name = "NAME")]
+        crate_name: Option<String>,
+        /// Name recorded as the bundle's author (defaults to git's user.name)
+        #[clap(long)]
+        reviewer: Option<String>,
+    },
+    /// Merges another reviewer's bundle; the later triage decision wins and comments are combined
+    Import {
+        /// File written by `triage export`
+        #[clap(value_parser)]
+        file: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheAction {
+    /// Merges analyses exported with `cache --export`, keeping the newer of any duplicates
+    Import {
+        /// File written by `cache --export`
+        #[clap(value_parser)]
+        file: String,
     },
 }
@@ -0,0 +1,174 @@
+use anyhow::{Context, Result};
+use toml_edit::{DocumentMut, Item};
+
+use crate::config::Config;
+use crate::exit_code::config_error;
+
+/// The comment written above each section an upgrade adds.
+const SECTION_COMMENTS: &[(&str, &str)] = &[
+    (
+        "llm",
+        "LLM provider; set gemini_api_key, or keychain_entry to read the key from the OS keychain",
+    ),
+    (
+        "scanner",
+        "Which files are scanned, and how long a file's LLM analysis may take",
+    ),
+    (
+        "dependencies",
+        "Dependency analysis and where package metadata comes from",
+    ),
+    (
+        "severity",
+        "Severity overrides by dependency flag type or CWE, e.g. NetworkingCapabilities = \"Low\"",
+    ),
+    (
+        "rate_limiting",
+        "Pacing of LLM requests; set requests_per_minute or delay_ms, and the slower one wins",
+    ),
+    (
+        "profiles",
+        "Custom scan profiles, as [profiles.<name>] tables",
+    ),
+    (
+        "cache",
+        "Analysis cache shared by a team or CI; set remote_url to use one",
+    ),
+    (
+        "notifications",
+        "Chat and webhook alerts, as [[notifications.webhook]] entries",
+    ),
+    (
+        "network",
+        "Proxy, TLS, timeouts and retries of every HTTP request",
+    ),
+    (
+        "metrics",
+        "Operational metrics for `rustrecon metrics`, kept in the local database",
+    ),
+    (
+        "signing",
+        "SSH private key that signs reports with `scan --sign`: key = \"...\"",
+    ),
+    (
+        "threat_intel",
+        "Indicator lists checked against findings: feeds = [\"https://...\"]",
+    ),
+];
+
+/// `content` of a config file with the sections and defaulted settings of
+/// [`Config::with_defaults`] it lacks added, and what was added, e.g.
+/// `[network]` or `rate_limiting.retry_budget`. The file's own values,
+/// comments and layout are kept.
+pub fn upgrade(content: &str) -> Result<(String, Vec<String>)> {
+    toml::from_str::<Config>(content)
+        .context("Failed to parse")
+        .map_err(config_error)?;
+    let mut document: DocumentMut = content
+        .parse()
+        .context("Failed to parse")
+        .map_err(config_error)?;
+    let template: DocumentMut = toml::to_string(&Config::with_defaults())
+        .expect("defaults serialize")
+        .parse()
+        .expect("defaults are valid TOML");
+
+    let mut added = Vec::new();
+    for (name, section) in template.iter() {
+        let Some(defaults) = section.as_table() else {
+            continue;
+        };
+        let Some(existing) = document.get_mut(name) else {
+            let mut table = defaults.clone();
+            // After the file's own tables, in the order of `Config`
+            table.set_position(usize::MAX);
+            table.set_implicit(false);
+            if let Some((_, comment)) = SECTION_COMMENTS
+                .iter()
+                .find(|(section, _)| *section == name)
+            {
+                table.decor_mut().set_prefix(format!("\n# {}\n", comment));
+            }
+            document.insert(name, Item::Table(table));
+            added.push(format!("[{}]", name));
+            continue;
+        };
+        let Some(existing) = existing.as_table_like_mut() else {
+            continue;
+        };
+        for (key, value) in defaults.iter() {
+            if existing.get(key).is_none() {
+                existing.insert(key, value.clone());
+                added.push(format!("{}.{}", name, key));
+            }
+        }
+    }
+
+    let upgraded = document.to_string();
+    toml::from_str::<Config>(&upgraded).context("Upgraded configuration does not parse")?;
+    Ok((upgraded, added))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upgrade_config() {
+        let old = "# My settings\n[llm]\ngemini_api_key = \"key\" # from the console\ngemini_api_endpoint = \"https://example.com\"\n\n[rate_limiting]\ndelay_ms = 6000\n";
+        let (upgraded, added) = upgrade(old).unwrap();
+        assert!(upgraded.starts_with(
+            "# My settings\n[llm]\ngemini_api_key = \"key\" # from the console\ngemini_api_endpoint = \"https://example.com\"\n"
+        ));
+        assert!(added.contains(&"llm.model".to_string()));
+        assert!(added.contains(&"rate_limiting.retry_budget".to_string()));
+        assert!(!added.contains(&"llm.gemini_api_key".to_string()));
+        assert!(!added.contains(&"rate_limiting.delay_ms".to_string()));
+        let template: DocumentMut = toml::to_string(&Config::with_defaults())
+            .unwrap()
+            .parse()
+            .unwrap();
+        for (section, _) in template.iter() {
+            assert!(
+                SECTION_COMMENTS.iter().any(|(s, _)| *s == section),
+                "{}",
+                section
+            );
+            if section == "llm" || section == "rate_limiting" {
+                continue;
+            }
+            assert!(added.contains(&format!("[{}]", section)), "{}", section);
+            assert!(
+                upgraded.contains(&format!("\n[{}]\n", section)),
+                "{}",
+                section
+            );
+        }
+        assert!(upgraded.contains(
+            "\n# Analysis cache shared by a team or CI; set remote_url to use one\n[cache]\n"
+        ));
+
+        let config: Config = toml::from_str(&upgraded).unwrap();
+        let defaults = Config::with_defaults();
+        assert_eq!(config.llm.as_ref().unwrap().gemini_api_key, "key");
+        assert_eq!(config.rate_limiting.delay_ms, Some(6000));
+        assert_eq!(
+            config.rate_limiting.retry_budget,
+            defaults.rate_limiting.retry_budget
+        );
+        assert_eq!(
+            config.network.llm_timeout_secs,
+            defaults.network.llm_timeout_secs
+        );
+        assert_eq!(
+            config.threat_intel.refresh_hours,
+            defaults.threat_intel.refresh_hours
+        );
+        assert_eq!(config.cache.remote_url, None);
+
+        let (again, added) = upgrade(&upgraded).unwrap();
+        assert_eq!(again, upgraded);
+        assert!(added.is_empty());
+        assert!(upgrade("[llm]\ngemini_api_key = 1\n").is_err());
+    }
+}
@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::CodeChunk;
+
+/// Context window assumed for models not in `model_context_window`.
+const DEFAULT_CONTEXT_WINDOW: usize = 32_768;
+
+/// Tokens kept for the prompt instructions, file path and package details
+/// that go around the code.
+const PROMPT_OVERHEAD_TOKENS: usize = 1_000;
+
+/// Most code sent in one request, even to models with far larger windows:
+/// past this, answers get vaguer and reported line numbers less accurate.
+const MAX_INPUT_TOKENS: usize = 8_000;
+
+/// Least code sent in one request, however small the configured window.
+const MIN_INPUT_TOKENS: usize = 500;
+
+/// Context window of a model, in tokens.
+pub fn model_context_window(model: &str) -> usize {
+    match model {
+        m if m.starts_with("gemini-1.5-pro") => 2_097_152,
+        m if m.starts_with("gemini-1.5-flash") || m.starts_with("gemini-2") => 1_048_576,
+        m if m.starts_with("gemini-1.0-pro") => 30_720,
+        _ => DEFAULT_CONTEXT_WINDOW,
+    }
+}
+
+/// Estimated token count of source code, following how BPE tokenizers split
+/// it: words and numbers cost a token per four characters, every other
+/// visible character a token of its own, and a line break with its
+/// indentation one token.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut word: usize = 0;
+    let mut whitespace = 0;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            tokens += word.div_ceil(4);
+            word = 0;
+            whitespace += 1;
+            continue;
+        }
+        tokens += usize::from(whitespace > 1);
+        whitespace = 0;
+        if c.is_ascii_alphanumeric() || c == '_' {
+            word += 1;
+        } else {
+            tokens += word.div_ceil(4) + 1;
+            word = 0;
+        }
+    }
+    tokens + word.div_ceil(4) + usize::from(whitespace > 1)
+}
+
+/// How much of some code the LLM saw, when it could not see all of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Truncation {
+    pub analyzed_tokens: usize,
+    pub total_tokens: usize,
+}
+
+impl Truncation {
+    pub fn percent_analyzed(&self) -> usize {
+        self.analyzed_tokens * 100 / self.total_tokens.max(1)
+    }
+}
+
+/// Tokens of code that fit in one request: the model's context window less
+/// the answer (`max_output_tokens`) and the prompt around the code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextBudget {
+    input_tokens: usize,
+}
+
+impl Default for ContextBudget {
+    fn default() -> Self {
+        ContextBudget {
+            input_tokens: MAX_INPUT_TOKENS,
+        }
+    }
+}
+
+impl ContextBudget {
+    pub fn new(context_window: usize, max_output_tokens: u32) -> Self {
+        let available = context_window
+            .saturating_sub(max_output_tokens as usize)
+            .saturating_sub(PROMPT_OVERHEAD_TOKENS);
+        ContextBudget {
+            input_tokens: available.clamp(MIN_INPUT_TOKENS, MAX_INPUT_TOKENS),
+        }
+    }
+
+    pub fn input_tokens(&self) -> usize {
+        self.input_tokens
+    }
+
+    /// The chunk size, in characters, that keeps chunks of `text` within the
+    /// budget, going by how densely `text` packs its tokens.
+    pub fn chunk_chars(&self, text: &str) -> usize {
+        let tokens = estimate_tokens(text).max(1);
+        (self.input_tokens * text.len() / tokens).max(1)
+    }
+
+    /// Cuts chunks that are still over the budget, which happens only for
+    /// single lines too long to split (minified code, embedded blobs).
+    pub fn fit_chunks(&self, chunks: &mut [CodeChunk]) -> Option<Truncation> {
+        let mut total = Truncation {
+            analyzed_tokens: 0,
+            total_tokens: 0,
+        };
+        for chunk in chunks.iter_mut() {
+            let (text, truncation) = self.truncate(&chunk.text);
+            if let Some(truncation) = truncation {
+                chunk.text = text.to_string();
+                total.analyzed_tokens += truncation.analyzed_tokens;
+                total.total_tokens += truncation.total_tokens;
+            } else {
+                let tokens = estimate_tokens(&chunk.text);
+                total.analyzed_tokens += tokens;
+                total.total_tokens += tokens;
+            }
+        }
+        (total.analyzed_tokens < total.total_tokens).then_some(total)
+    }
+
+    /// The start of `text` that fits in the budget, ending at a line break
+    /// where possible, and the truncation if anything was left out.
+    pub fn truncate<'a>(&self, text: &'a str) -> (&'a str, Option<Truncation>) {
+        let total_tokens = estimate_tokens(text);
+        if total_tokens <= self.input_tokens {
+            return (text, None);
+        }
+        let mut end = self.chunk_chars(text).min(text.len());
+        loop {
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            // The cut goes by the average density, so a dense start can
+            // still be over: shrink by how far over it is
+            let tokens = estimate_tokens(&text[..end]);
+            if tokens <= self.input_tokens || end == 0 {
+                break;
+            }
+            end = (end * self.input_tokens / tokens).min(end - 1);
+        }
+        // Back off to a line break unless that drops more than half
+        if let Some(line_end) = text[..end].rfind('\n').filter(|i| *i > end / 2) {
+            end = line_end + 1;
+        }
+        let analyzed = &text[..end];
+        let truncation = Truncation {
+            analyzed_tokens: estimate_tokens(analyzed),
+            total_tokens,
+        };
+        (analyzed, Some(truncation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_and_truncation() {
+        assert_eq!(estimate_tokens("let x = 1;"), 5);
+        assert_eq!(estimate_tokens("fn main() {\n    run();\n}"), 11);
+
+        // A small window leaves room for the answer and the prompt
+        let small = ContextBudget::new(4_096, 2_048);
+        assert_eq!(small.input_tokens(), 1_048);
+        assert_eq!(
+            ContextBudget::new(model_context_window("gemini-1.5-flash"), 8_192).input_tokens(),
+            MAX_INPUT_TOKENS
+        );
+
+        let code = "let value = compute(42);\n".repeat(200);
+        let (analyzed, truncation) = small.truncate(&code);
+        let truncation = truncation.unwrap();
+        assert!(analyzed.ends_with('\n'));
+        assert!(truncation.analyzed_tokens <= small.input_tokens());
+        assert_eq!(truncation.total_tokens, estimate_tokens(&code));
+        assert!(small.truncate("fn f() {}").1.is_none());
+
+        // A start denser than the rest is still cut to fit
+        let skewed = format!("{}{}", "+".repeat(2_000), "a".repeat(40_000));
+        let (analyzed, truncation) = small.truncate(&skewed);
+        assert!(estimate_tokens(analyzed) <= small.input_tokens());
+        assert_eq!(
+            truncation.unwrap().analyzed_tokens,
+            estimate_tokens(analyzed)
+        );
+
+        // Chunks within the budget are left alone; an overlong line is cut
+        let mut chunks = vec![
+            CodeChunk {
+                start_line: 1,
+                text: "fn f() {}\n".to_string(),
+            },
+            CodeChunk {
+                start_line: 2,
+                text: format!("const BLOB: &str = \"{}\";", "ab+/".repeat(2_000)),
+            },
+        ];
+        let truncation = small.fit_chunks(&mut chunks).unwrap();
+        assert_eq!(chunks[0].text, "fn f() {}\n");
+        assert!(estimate_tokens(&chunks[1].text) <= small.input_tokens());
+        assert!(truncation.percent_analyzed() < 50);
+    }
+}
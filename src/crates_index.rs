@@ -0,0 +1,104 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// One line of an index file: a published version of the crate.
+#[derive(Deserialize)]
+struct IndexEntry {
+    name: String,
+    vers: String,
+    #[serde(default)]
+    cksum: Option<String>,
+    #[serde(default)]
+    yanked: bool,
+}
+
+/// Package metadata from a local copy of the crates.io index (a checkout of
+/// `crates.io-index`, or a mirror such as a `cargo local-registry`), shaped
+/// like the `crates/<name>` response of the crates.io API. The index only
+/// knows versions, checksums and yanks; download counts, publish dates and
+/// publishers are absent. `None` when the crate is not in the index.
+pub fn metadata(index_dir: &Path, name: &str) -> Option<serde_json::Value> {
+    let content = std::fs::read_to_string(index_dir.join(entry_path(name)?)).ok()?;
+    let entries: Vec<IndexEntry> = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let first = entries.first()?;
+    let max_version = entries
+        .iter()
+        .rev()
+        .find(|entry| !entry.yanked)
+        .unwrap_or(entries.last()?);
+    let versions: Vec<serde_json::Value> = entries
+        .iter()
+        .rev()
+        .map(|entry| {
+            serde_json::json!({
+                "num": entry.vers,
+                "yanked": entry.yanked,
+                "checksum": entry.cksum,
+            })
+        })
+        .collect();
+    Some(serde_json::json!({
+        "crate": {
+            "name": first.name,
+            "max_version": max_version.vers,
+        },
+        "versions": versions,
+    }))
+}
+
+/// Where the index keeps a crate: `1/a`, `2/ab`, `3/a/abc`, then `se/rd/serde`.
+fn entry_path(name: &str) -> Option<PathBuf> {
+    let name = name.to_lowercase();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return None;
+    }
+    Some(match name.len() {
+        1 => Path::new("1").join(&name),
+        2 => Path::new("2").join(&name),
+        3 => Path::new("3").join(&name[..1]).join(&name),
+        _ => Path::new(&name[..2]).join(&name[2..4]).join(&name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_metadata() {
+        assert_eq!(entry_path("Serde").unwrap(), Path::new("se/rd/serde"));
+        assert_eq!(entry_path("syn").unwrap(), Path::new("3/s/syn"));
+        assert!(entry_path("../etc").is_none());
+
+        let index =
+            std::env::temp_dir().join(format!("rustrecon-test-{}-index", std::process::id()));
+        std::fs::create_dir_all(index.join("de/mo")).unwrap();
+        std::fs::write(
+            index.join("de/mo/demo"),
+            concat!(
+                r#"{"name":"demo","vers":"1.0.0","deps":[],"cksum":"aa","features":{},"yanked":false}"#,
+                "\n",
+                r#"{"name":"demo","vers":"1.1.0","deps":[],"cksum":"bb","features":{},"yanked":true}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+        let demo = metadata(&index, "demo");
+        let missing = metadata(&index, "absent");
+        let _ = std::fs::remove_dir_all(&index);
+
+        let demo = demo.unwrap();
+        assert_eq!(demo["crate"]["max_version"], "1.0.0");
+        assert_eq!(demo["versions"][0]["num"], "1.1.0");
+        assert_eq!(demo["versions"][0]["yanked"], true);
+        assert!(missing.is_none());
+    }
+}
@@ -0,0 +1,1517 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cache::{CacheEntry, CacheKey, CacheStats, CacheStore, ImportSummary, StoredAnalysis};
+use crate::dependency_scanner::{DependencyAnalysisResult, DependencyScan};
+use crate::llm_client::{AnalysisVersion, LlmResponse, ModelInfo};
+use crate::metrics::MetricValue;
+use crate::network::Validators;
+use crate::osv::Vulnerability;
+use crate::report::{CrateFinding, HistoryEntry};
+use crate::triage::{
+    AnnotatedFinding, Comment, FindingRecord, MergeSummary, TriageState, TriageStatus,
+};
+
+const DEFAULT_DATABASE_FILE_NAME: &str = "rustrecon.db";
+
+/// Ordered schema migrations: entry `i` upgrades a database from version `i` to
+/// `i + 1`. Append new steps; never edit one that has shipped. The first steps
+/// use `IF NOT EXISTS` because databases from before versioning already have
+/// their tables.
+const MIGRATIONS: &[&str] = &[
+    // 1: scan checkpoints and the popular-crates list
+    "CREATE TABLE IF NOT EXISTS scan_checkpoints (
+        scan_key TEXT PRIMARY KEY,
+        started_at TEXT NOT NULL,
+        dependency_findings TEXT
+    );
+    CREATE TABLE IF NOT EXISTS checkpoint_files (
+        scan_key TEXT NOT NULL,
+        file_path TEXT NOT NULL,
+        finding TEXT NOT NULL,
+        PRIMARY KEY (scan_key, file_path)
+    );
+    CREATE TABLE IF NOT EXISTS popular_crates (
+        name TEXT PRIMARY KEY,
+        downloads INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS refresh_log (
+        resource TEXT PRIMARY KEY,
+        refreshed_at TEXT NOT NULL
+    );",
+    // 2: OSV lookups
+    "CREATE TABLE IF NOT EXISTS osv_results (
+        package TEXT NOT NULL,
+        version TEXT NOT NULL,
+        fetched_at TEXT NOT NULL,
+        vulnerabilities TEXT NOT NULL,
+        PRIMARY KEY (package, version)
+    );",
+    // 3: LLM analysis cache
+    "CREATE TABLE IF NOT EXISTS scan_results (
+        package TEXT NOT NULL,
+        version TEXT NOT NULL,
+        content_hash TEXT NOT NULL,
+        analysis TEXT NOT NULL,
+        scan_date TEXT NOT NULL,
+        PRIMARY KEY (package, version, content_hash)
+    );
+    CREATE TABLE IF NOT EXISTS cache_stats (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        hits INTEGER NOT NULL,
+        misses INTEGER NOT NULL
+    );
+    INSERT OR IGNORE INTO cache_stats (id, hits, misses) VALUES (1, 0, 0);",
+    // 4: key cached analyses by model and prompt fingerprint. Existing entries
+    // came from the only model used so far; their unknown fingerprint means
+    // they are listed and purged but never served again.
+    "CREATE TABLE scan_results_v4 (
+        package TEXT NOT NULL,
+        version TEXT NOT NULL,
+        content_hash TEXT NOT NULL,
+        model TEXT NOT NULL,
+        prompt_fingerprint TEXT NOT NULL,
+        analysis TEXT NOT NULL,
+        scan_date TEXT NOT NULL,
+        PRIMARY KEY (package, version, content_hash, model, prompt_fingerprint)
+    );
+    INSERT INTO scan_results_v4
+        (package, version, content_hash, model, prompt_fingerprint, analysis, scan_date)
+        SELECT package, version, content_hash, 'gemini-1.5-flash', '', analysis, scan_date
+        FROM scan_results;
+    DROP TABLE scan_results;
+    ALTER TABLE scan_results_v4 RENAME TO scan_results;",
+    // 5: stable ids for `cache --show`
+    "CREATE TABLE scan_results_v5 (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        package TEXT NOT NULL,
+        version TEXT NOT NULL,
+        content_hash TEXT NOT NULL,
+        model TEXT NOT NULL,
+        prompt_fingerprint TEXT NOT NULL,
+        analysis TEXT NOT NULL,
+        scan_date TEXT NOT NULL,
+        UNIQUE (package, version, content_hash, model, prompt_fingerprint)
+    );
+    INSERT INTO scan_results_v5
+        (package, version, content_hash, model, prompt_fingerprint, analysis, scan_date)
+        SELECT package, version, content_hash, model, prompt_fingerprint, analysis, scan_date
+        FROM scan_results ORDER BY scan_date;
+    DROP TABLE scan_results;
+    ALTER TABLE scan_results_v5 RENAME TO scan_results;
+    CREATE INDEX scan_results_package ON scan_results (package);",
+    // 6: summaries of completed scans
+    "CREATE TABLE scan_history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        scan_key TEXT NOT NULL,
+        crate_name TEXT NOT NULL,
+        scanned_at TEXT NOT NULL,
+        summary TEXT NOT NULL
+    );
+    CREATE INDEX scan_history_scan_key ON scan_history (scan_key);
+    CREATE INDEX scan_history_crate_name ON scan_history (crate_name);",
+    // 7: prompt and analyzer version of each analysis; every analysis so far
+    // came from the first prompt
+    "ALTER TABLE scan_results ADD COLUMN prompt_version INTEGER;
+    ALTER TABLE scan_results ADD COLUMN analyzer_version TEXT;
+    UPDATE scan_results SET prompt_version = 1;",
+    // 8: opt-in operational metrics; labels are a JSON object
+    "CREATE TABLE metrics (
+        name TEXT NOT NULL,
+        labels TEXT NOT NULL,
+        value REAL NOT NULL,
+        PRIMARY KEY (name, labels)
+    );",
+    // 9: findings and their triage state
+    "CREATE TABLE findings (
+        id TEXT PRIMARY KEY,
+        crate_name TEXT NOT NULL,
+        file_path TEXT NOT NULL,
+        line INTEGER NOT NULL,
+        severity TEXT NOT NULL,
+        description TEXT NOT NULL,
+        code_snippet TEXT NOT NULL,
+        state TEXT NOT NULL,
+        note TEXT,
+        first_seen TEXT NOT NULL,
+        last_seen TEXT NOT NULL,
+        updated_at TEXT
+    );
+    CREATE INDEX findings_crate_name ON findings (crate_name);",
+    // 10: who made each triage decision, and review comments
+    "ALTER TABLE findings ADD COLUMN updated_by TEXT;
+    CREATE TABLE finding_comments (
+        finding_id TEXT NOT NULL,
+        author TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        body TEXT NOT NULL,
+        PRIMARY KEY (finding_id, author, created_at)
+    );",
+    // 11: models offered by each LLM endpoint
+    "CREATE TABLE model_catalog (
+        endpoint TEXT PRIMARY KEY,
+        fetched_at TEXT NOT NULL,
+        models TEXT NOT NULL
+    );",
+    // 12: downloaded threat-intel feeds
+    "CREATE TABLE threat_intel_feeds (
+        url TEXT PRIMARY KEY,
+        fetched_at TEXT NOT NULL,
+        content TEXT NOT NULL
+    );",
+    // 13: whole dependency scans, keyed by lockfile, advisories and settings
+    "CREATE TABLE dependency_scans (
+        fingerprint TEXT PRIMARY KEY,
+        model TEXT,
+        scanned_at TEXT NOT NULL,
+        scan TEXT NOT NULL
+    );",
+    // 14: crates.io API responses
+    "CREATE TABLE registry_responses (
+        url TEXT PRIMARY KEY,
+        fetched_at TEXT NOT NULL,
+        body TEXT NOT NULL
+    );",
+    // 15: HTTP validators for conditional refreshes, and OSV advisory details
+    "ALTER TABLE registry_responses ADD COLUMN etag TEXT;
+    ALTER TABLE registry_responses ADD COLUMN last_modified TEXT;
+    ALTER TABLE threat_intel_feeds RENAME COLUMN content TO body;
+    ALTER TABLE threat_intel_feeds ADD COLUMN etag TEXT;
+    ALTER TABLE threat_intel_feeds ADD COLUMN last_modified TEXT;
+    CREATE TABLE osv_advisories (
+        id TEXT PRIMARY KEY,
+        fetched_at TEXT NOT NULL,
+        etag TEXT,
+        last_modified TEXT,
+        body TEXT NOT NULL
+    );",
+    // 16: content hashes of checkpointed files
+    "ALTER TABLE checkpoint_files ADD COLUMN content_hash TEXT NOT NULL DEFAULT '';",
+];
+
+/// Local SQLite store for state that must survive between runs.
+pub struct Database {
+    conn: Connection,
+}
+
+/// Progress saved by an interrupted scan.
+#[derive(Debug, Default)]
+pub struct ScanCheckpoint {
+    /// Findings of analyzed files, with the hash of the content analyzed
+    pub completed_files: Vec<(CrateFinding, String)>,
+    pub dependency_findings: Option<Vec<DependencyAnalysisResult>>,
+}
+
+/// A saved HTTP response: a crates.io lookup, an OSV advisory or a feed.
+#[derive(Debug)]
+pub struct SavedResponse {
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+    pub body: String,
+    pub validators: Validators,
+}
+
+impl SavedResponse {
+    /// Whether the response is less than `hours` old.
+    pub fn is_fresh(&self, hours: i64) -> bool {
+        chrono::Utc::now()
+            .signed_duration_since(self.fetched_at)
+            .num_hours()
+            < hours
+    }
+}
+
+/// Cached top-crates list used for typosquatting comparison.
+#[derive(Debug)]
+pub struct PopularCrates {
+    pub refreshed_at: chrono::DateTime<chrono::Utc>,
+    pub crates: HashMap<String, u64>, // crate name -> download count
+}
+
+impl Database {
+    /// Default location: the user cache directory (e.g., ~/.cache/rustrecon/rustrecon.db).
+    pub fn default_path() -> Result<PathBuf> {
+        let mut path = dirs::cache_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine the user cache directory"))?;
+        path.push("rustrecon");
+        path.push(DEFAULT_DATABASE_FILE_NAME);
+        Ok(path)
+    }
+
+    pub fn open_default() -> Result<Self> {
+        Self::open(&Self::default_path()?)
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open database at {}", path.display()))?;
+        let mut db = Database { conn };
+        db.migrate()?;
+        Ok(db)
+    }
+
+    /// Applies every pending migration in order, each in its own transaction.
+    fn migrate(&mut self) -> Result<()> {
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL);",
+        )?;
+        let current = self.schema_version()?;
+        if current > MIGRATIONS.len() {
+            bail!(
+                "Database schema version {} is newer than this rustrecon supports ({}); please upgrade",
+                current,
+                MIGRATIONS.len()
+            );
+        }
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(migration)
+                .with_context(|| format!("Database migration {} failed", index + 1))?;
+            tx.execute("DELETE FROM schema_version", [])?;
+            tx.execute(
+                "INSERT INTO schema_version (version) VALUES (?1)",
+                params![(index + 1) as i64],
+            )?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Number of migrations applied; 0 for databases created before versioning.
+    pub fn schema_version(&self) -> Result<usize> {
+        let version: Option<i64> = self
+            .conn
+            .query_row("SELECT version FROM schema_version", [], |row| row.get(0))
+            .optional()?;
+        Ok(version.unwrap_or(0) as usize)
+    }
+
+    /// Starts a fresh checkpoint for `scan_key`, discarding any previous progress.
+    pub fn start_checkpoint(&self, scan_key: &str) -> Result<()> {
+        self.clear_checkpoint(scan_key)?;
+        self.conn.execute(
+            "INSERT INTO scan_checkpoints (scan_key, started_at) VALUES (?1, ?2)",
+            params![scan_key, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Records `finding` of a file whose content hashed to `content_hash`.
+    pub fn save_checkpoint_file(
+        &self,
+        scan_key: &str,
+        finding: &CrateFinding,
+        content_hash: &str,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO checkpoint_files (scan_key, file_path, finding, content_hash)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                scan_key,
+                finding.file_path.to_string_lossy(),
+                serde_json::to_string(finding)?,
+                content_hash
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn save_checkpoint_dependencies(
+        &self,
+        scan_key: &str,
+        dependency_findings: &[DependencyAnalysisResult],
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE scan_checkpoints SET dependency_findings = ?2 WHERE scan_key = ?1",
+            params![scan_key, serde_json::to_string(dependency_findings)?],
+        )?;
+        Ok(())
+    }
+
+    pub fn load_checkpoint(&self, scan_key: &str) -> Result<Option<ScanCheckpoint>> {
+        let dependency_json: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "SELECT dependency_findings FROM scan_checkpoints WHERE scan_key = ?1",
+                params![scan_key],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let Some(dependency_json) = dependency_json else {
+            return Ok(None);
+        };
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT finding, content_hash FROM checkpoint_files WHERE scan_key = ?1")?;
+        let completed_files = stmt
+            .query_map(params![scan_key], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .map(|row| {
+                let (json, content_hash) = row?;
+                Ok((serde_json::from_str(&json)?, content_hash))
+            })
+            .collect::<Result<Vec<(CrateFinding, String)>>>()?;
+
+        let dependency_findings = dependency_json
+            .map(|json| serde_json::from_str(&json))
+            .transpose()?;
+
+        Ok(Some(ScanCheckpoint {
+            completed_files,
+            dependency_findings,
+        }))
+    }
+
+    /// Returns the cached popular-crates list and when it was fetched, if any.
+    pub fn load_popular_crates(&self) -> Result<Option<PopularCrates>> {
+        let Some(refreshed_at) = self.refreshed_at("popular_crates")? else {
+            return Ok(None);
+        };
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, downloads FROM popular_crates")?;
+        let crates = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+
+        Ok(Some(PopularCrates {
+            refreshed_at,
+            crates,
+        }))
+    }
+
+    pub fn save_popular_crates(&mut self, crates: &HashMap<String, u64>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM popular_crates", [])?;
+        for (name, downloads) in crates {
+            tx.execute(
+                "INSERT INTO popular_crates (name, downloads) VALUES (?1, ?2)",
+                params![name, *downloads as i64],
+            )?;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO refresh_log (resource, refreshed_at) VALUES (?1, ?2)",
+            params!["popular_crates", chrono::Utc::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Returns the cached OSV lookup for a package version and when it was made.
+    pub fn load_osv_result(
+        &self,
+        package: &str,
+        version: &str,
+    ) -> Result<Option<(chrono::DateTime<chrono::Utc>, Vec<Vulnerability>)>> {
+        let row: Option<(String, String)> = self
+            .conn
+            .query_row(
+                "SELECT fetched_at, vulnerabilities FROM osv_results WHERE package = ?1 AND version = ?2",
+                params![package, version],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        let Some((fetched_at, vulnerabilities)) = row else {
+            return Ok(None);
+        };
+        let fetched_at =
+            chrono::DateTime::parse_from_rfc3339(&fetched_at)?.with_timezone(&chrono::Utc);
+        Ok(Some((fetched_at, serde_json::from_str(&vulnerabilities)?)))
+    }
+
+    /// Returns the cached model list of an LLM endpoint and when it was fetched.
+    pub fn load_model_catalog(
+        &self,
+        endpoint: &str,
+    ) -> Result<Option<(chrono::DateTime<chrono::Utc>, Vec<ModelInfo>)>> {
+        let row: Option<(String, String)> = self
+            .conn
+            .query_row(
+                "SELECT fetched_at, models FROM model_catalog WHERE endpoint = ?1",
+                params![endpoint],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        let Some((fetched_at, models)) = row else {
+            return Ok(None);
+        };
+        let fetched_at =
+            chrono::DateTime::parse_from_rfc3339(&fetched_at)?.with_timezone(&chrono::Utc);
+        Ok(Some((fetched_at, serde_json::from_str(&models)?)))
+    }
+
+    pub fn save_model_catalog(&self, endpoint: &str, models: &[ModelInfo]) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO model_catalog (endpoint, fetched_at, models)
+             VALUES (?1, ?2, ?3)",
+            params![
+                endpoint,
+                chrono::Utc::now().to_rfc3339(),
+                serde_json::to_string(models)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the dependency scan stored under `fingerprint` and when it ran.
+    pub fn load_dependency_scan(
+        &self,
+        fingerprint: &str,
+    ) -> Result<Option<(chrono::DateTime<chrono::Utc>, DependencyScan)>> {
+        let row: Option<(String, String)> = self
+            .conn
+            .query_row(
+                "SELECT scanned_at, scan FROM dependency_scans WHERE fingerprint = ?1",
+                params![fingerprint],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        let Some((scanned_at, scan)) = row else {
+            return Ok(None);
+        };
+        let scanned_at =
+            chrono::DateTime::parse_from_rfc3339(&scanned_at)?.with_timezone(&chrono::Utc);
+        Ok(Some((scanned_at, serde_json::from_str(&scan)?)))
+    }
+
+    /// Stores a dependency scan and drops those that ran before `expired`.
+    pub fn save_dependency_scan(
+        &self,
+        fingerprint: &str,
+        model: Option<&str>,
+        scan: &DependencyScan,
+        expired: chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM dependency_scans WHERE scanned_at < ?1",
+            params![expired.to_rfc3339()],
+        )?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO dependency_scans (fingerprint, model, scanned_at, scan)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                fingerprint,
+                model,
+                chrono::Utc::now().to_rfc3339(),
+                serde_json::to_string(scan)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Returns a saved crates.io API response.
+    pub fn load_registry_response(&self, url: &str) -> Result<Option<SavedResponse>> {
+        self.load_saved_response("registry_responses", "url", url)
+    }
+
+    pub fn save_registry_response(
+        &self,
+        url: &str,
+        body: &str,
+        validators: &Validators,
+    ) -> Result<()> {
+        self.save_response("registry_responses", "url", url, body, validators)
+    }
+
+    /// Returns a saved OSV advisory (`/vulns/{id}` response).
+    pub fn load_osv_advisory(&self, id: &str) -> Result<Option<SavedResponse>> {
+        self.load_saved_response("osv_advisories", "id", id)
+    }
+
+    pub fn save_osv_advisory(&self, id: &str, body: &str, validators: &Validators) -> Result<()> {
+        self.save_response("osv_advisories", "id", id, body, validators)
+    }
+
+    /// Returns a downloaded threat-intel feed.
+    pub fn load_threat_feed(&self, url: &str) -> Result<Option<SavedResponse>> {
+        self.load_saved_response("threat_intel_feeds", "url", url)
+    }
+
+    pub fn save_threat_feed(
+        &self,
+        url: &str,
+        content: &str,
+        validators: &Validators,
+    ) -> Result<()> {
+        self.save_response("threat_intel_feeds", "url", url, content, validators)
+    }
+
+    /// Reads a row of one of the tables of saved HTTP responses, which share
+    /// their columns apart from the key.
+    fn load_saved_response(
+        &self,
+        table: &str,
+        key_column: &str,
+        key: &str,
+    ) -> Result<Option<SavedResponse>> {
+        let row: Option<(String, String, Option<String>, Option<String>)> = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT fetched_at, body, etag, last_modified FROM {} WHERE {} = ?1",
+                    table, key_column
+                ),
+                params![key],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()?;
+
+        let Some((fetched_at, body, etag, last_modified)) = row else {
+            return Ok(None);
+        };
+        Ok(Some(SavedResponse {
+            fetched_at: chrono::DateTime::parse_from_rfc3339(&fetched_at)?
+                .with_timezone(&chrono::Utc),
+            body,
+            validators: Validators {
+                etag,
+                last_modified,
+            },
+        }))
+    }
+
+    /// Saves a response as fetched now; also used when a 304 confirms the
+    /// saved copy.
+    fn save_response(
+        &self,
+        table: &str,
+        key_column: &str,
+        key: &str,
+        body: &str,
+        validators: &Validators,
+    ) -> Result<()> {
+        self.conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO {} ({}, fetched_at, body, etag, last_modified)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                table, key_column
+            ),
+            params![
+                key,
+                chrono::Utc::now().to_rfc3339(),
+                body,
+                validators.etag,
+                validators.last_modified
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn save_osv_result(
+        &self,
+        package: &str,
+        version: &str,
+        vulnerabilities: &[Vulnerability],
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO osv_results (package, version, fetched_at, vulnerabilities)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                package,
+                version,
+                chrono::Utc::now().to_rfc3339(),
+                serde_json::to_string(vulnerabilities)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Records a completed scan so later reports and `history` can show trends.
+    pub fn record_scan(
+        &self,
+        scan_key: &str,
+        crate_name: &str,
+        entry: &HistoryEntry,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO scan_history (scan_key, crate_name, scanned_at, summary)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                scan_key,
+                crate_name,
+                entry.timestamp,
+                serde_json::to_string(&entry.summary)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The most recent `limit` scans whose key or crate name is `crate_ref`,
+    /// oldest first.
+    pub fn scan_history(&self, crate_ref: &str, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT scanned_at, summary FROM scan_history
+             WHERE scan_key = ?1 OR crate_name = ?1
+             ORDER BY id DESC LIMIT ?2",
+        )?;
+        let rows = stmt
+            .query_map(params![crate_ref, limit as i64], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut history = rows
+            .into_iter()
+            .map(|(timestamp, summary)| {
+                Ok(HistoryEntry {
+                    timestamp,
+                    summary: serde_json::from_str(&summary)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        history.reverse();
+        Ok(history)
+    }
+
+    /// Adds `values` to the stored metric totals.
+    pub fn add_metrics(&self, values: &[MetricValue]) -> Result<()> {
+        for value in values {
+            self.conn.execute(
+                "INSERT INTO metrics (name, labels, value) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (name, labels) DO UPDATE SET value = value + excluded.value",
+                params![
+                    value.name,
+                    serde_json::to_string(&value.labels)?,
+                    value.value
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn load_metrics(&self) -> Result<Vec<MetricValue>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, labels, value FROM metrics ORDER BY name, labels")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, f64>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter()
+            .map(|(name, labels, value)| {
+                Ok(MetricValue {
+                    name,
+                    labels: serde_json::from_str(&labels)?,
+                    value,
+                })
+            })
+            .collect()
+    }
+
+    /// Deletes the metric totals; returns how many were removed.
+    pub fn reset_metrics(&self) -> Result<usize> {
+        Ok(self.conn.execute("DELETE FROM metrics", [])?)
+    }
+
+    /// Adds a finding seen in a scan, or updates its location and last sighting
+    /// if it is already known, and returns its triage status. A `fixed`
+    /// finding seen again is reopened as `new`.
+    pub fn record_finding(&self, finding: &FindingRecord) -> Result<TriageStatus> {
+        self.conn.execute(
+            "INSERT INTO findings (id, crate_name, file_path, line, severity, description,
+                 code_snippet, state, first_seen, last_seen)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)
+             ON CONFLICT (id) DO UPDATE SET
+                 line = excluded.line,
+                 severity = excluded.severity,
+                 last_seen = excluded.last_seen,
+                 state = CASE state WHEN ?10 THEN ?8 ELSE state END",
+            params![
+                finding.id,
+                finding.crate_name,
+                finding.file_path.to_string_lossy(),
+                finding.line as i64,
+                finding.severity,
+                finding.description,
+                finding.code_snippet,
+                TriageState::New.label(),
+                finding.last_seen,
+                TriageState::Fixed.label(),
+            ],
+        )?;
+        let (state, note) = self.conn.query_row(
+            "SELECT state, note FROM findings WHERE id = ?1",
+            params![finding.id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+        )?;
+        Ok(TriageStatus {
+            state: state.parse().map_err(anyhow::Error::msg)?,
+            note,
+        })
+    }
+
+    /// Sets the triage state of the finding whose id is or starts with
+    /// `id_prefix`. `note` replaces the reviewer's note when given. Returns the
+    /// full id.
+    pub fn set_triage(
+        &self,
+        id_prefix: &str,
+        state: TriageState,
+        note: Option<&str>,
+        reviewer: &str,
+    ) -> Result<String> {
+        let id = self.resolve_finding_id(id_prefix)?;
+        self.conn.execute(
+            "UPDATE findings SET state = ?2, note = COALESCE(?3, note), updated_at = ?4,
+                 updated_by = ?5
+             WHERE id = ?1",
+            params![
+                id,
+                state.label(),
+                note,
+                chrono::Utc::now().to_rfc3339(),
+                reviewer
+            ],
+        )?;
+        Ok(id)
+    }
+
+    /// Adds a review comment to the finding whose id is or starts with
+    /// `id_prefix`; returns the full id.
+    pub fn add_comment(&self, id_prefix: &str, comment: &Comment) -> Result<String> {
+        let id = self.resolve_finding_id(id_prefix)?;
+        insert_comment(&self.conn, &id, comment)?;
+        Ok(id)
+    }
+
+    /// Review comments on a finding, oldest first.
+    pub fn finding_comments(&self, id: &str) -> Result<Vec<Comment>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT author, created_at, body FROM finding_comments
+             WHERE finding_id = ?1 ORDER BY created_at",
+        )?;
+        let comments = stmt
+            .query_map(params![id], |row| {
+                Ok(Comment {
+                    author: row.get(0)?,
+                    created_at: row.get(1)?,
+                    body: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(comments)
+    }
+
+    /// Recorded findings with their comments, for `triage export`.
+    pub fn export_annotations(&self, crate_name: Option<&str>) -> Result<Vec<AnnotatedFinding>> {
+        self.list_findings(crate_name, None)?
+            .into_iter()
+            .map(|finding| {
+                let comments = self.finding_comments(&finding.id)?;
+                Ok(AnnotatedFinding { finding, comments })
+            })
+            .collect()
+    }
+
+    /// Merges another reviewer's annotations. Unknown findings are added. For
+    /// known ones the later triage decision wins, the sightings widen to
+    /// cover both databases, and comments are combined.
+    pub fn import_annotations(&self, findings: &[AnnotatedFinding]) -> Result<MergeSummary> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut summary = MergeSummary::default();
+        for annotated in findings {
+            let theirs = &annotated.finding;
+            let local = tx
+                .query_row(
+                    "SELECT state, updated_at, first_seen, last_seen FROM findings WHERE id = ?1",
+                    params![theirs.id],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, Option<String>>(1)?,
+                            row.get::<_, String>(2)?,
+                            row.get::<_, String>(3)?,
+                        ))
+                    },
+                )
+                .optional()?;
+            match local {
+                None => {
+                    tx.execute(
+                        "INSERT INTO findings (id, crate_name, file_path, line, severity,
+                             description, code_snippet, state, note, first_seen, last_seen,
+                             updated_at, updated_by)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                        params![
+                            theirs.id,
+                            theirs.crate_name,
+                            theirs.file_path.to_string_lossy(),
+                            theirs.line as i64,
+                            theirs.severity,
+                            theirs.description,
+                            theirs.code_snippet,
+                            theirs.status.state.label(),
+                            theirs.status.note,
+                            theirs.first_seen,
+                            theirs.last_seen,
+                            theirs.updated_at,
+                            theirs.updated_by,
+                        ],
+                    )?;
+                    summary.added += 1;
+                }
+                Some((state, local_updated, first_seen, last_seen)) => {
+                    tx.execute(
+                        "UPDATE findings SET first_seen = ?2, last_seen = ?3 WHERE id = ?1",
+                        params![
+                            theirs.id,
+                            if is_later(&first_seen, &theirs.first_seen) {
+                                &theirs.first_seen
+                            } else {
+                                &first_seen
+                            },
+                            if is_later(&theirs.last_seen, &last_seen) {
+                                &theirs.last_seen
+                            } else {
+                                &last_seen
+                            },
+                        ],
+                    )?;
+                    let theirs_newer = match (&local_updated, &theirs.updated_at) {
+                        (_, None) => false,
+                        (None, Some(_)) => true,
+                        (Some(ours), Some(their_update)) => {
+                            if state != theirs.status.state.label() {
+                                summary.conflicts.push(theirs.id.clone());
+                            }
+                            is_later(their_update, ours)
+                        }
+                    };
+                    if theirs_newer {
+                        tx.execute(
+                            "UPDATE findings SET state = ?2, note = ?3, updated_at = ?4,
+                                 updated_by = ?5
+                             WHERE id = ?1",
+                            params![
+                                theirs.id,
+                                theirs.status.state.label(),
+                                theirs.status.note,
+                                theirs.updated_at,
+                                theirs.updated_by,
+                            ],
+                        )?;
+                        summary.updated += 1;
+                    } else {
+                        summary.kept_local += 1;
+                    }
+                }
+            }
+            for comment in &annotated.comments {
+                summary.comments_added += insert_comment(&tx, &theirs.id, comment)?;
+            }
+        }
+        tx.commit()?;
+        Ok(summary)
+    }
+
+    fn resolve_finding_id(&self, id_prefix: &str) -> Result<String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id FROM findings WHERE id LIKE ?1 || '%' LIMIT 2")?;
+        let ids = stmt
+            .query_map(params![id_prefix.to_ascii_lowercase()], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        match ids.as_slice() {
+            [id] => Ok(id.clone()),
+            [] => bail!("No finding with id {}", id_prefix),
+            _ => bail!("Finding id {} is ambiguous; give more of it", id_prefix),
+        }
+    }
+
+    /// Recorded findings, optionally of one crate and in one state, by crate,
+    /// file and line.
+    pub fn list_findings(
+        &self,
+        crate_name: Option<&str>,
+        state: Option<TriageState>,
+    ) -> Result<Vec<FindingRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, crate_name, file_path, line, severity, description, code_snippet,
+                 state, note, first_seen, last_seen, updated_at, updated_by
+             FROM findings
+             WHERE (?1 IS NULL OR crate_name = ?1) AND (?2 IS NULL OR state = ?2)
+             ORDER BY crate_name, file_path, line",
+        )?;
+        let rows = stmt
+            .query_map(params![crate_name, state.map(TriageState::label)], |row| {
+                Ok((
+                    FindingRecord {
+                        id: row.get(0)?,
+                        crate_name: row.get(1)?,
+                        file_path: PathBuf::from(row.get::<_, String>(2)?),
+                        line: row.get::<_, i64>(3)? as usize,
+                        severity: row.get(4)?,
+                        description: row.get(5)?,
+                        code_snippet: row.get(6)?,
+                        status: TriageStatus {
+                            state: TriageState::New,
+                            note: row.get(8)?,
+                        },
+                        first_seen: row.get(9)?,
+                        last_seen: row.get(10)?,
+                        updated_at: row.get(11)?,
+                        updated_by: row.get(12)?,
+                    },
+                    row.get::<_, String>(7)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter()
+            .map(|(mut record, state)| {
+                record.status.state = state.parse().map_err(anyhow::Error::msg)?;
+                Ok(record)
+            })
+            .collect()
+    }
+
+    /// Reads cached analyses; `clause` filters and orders the rows.
+    fn stored_analyses(
+        &self,
+        clause: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<StoredAnalysis>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, package, version, content_hash, model, prompt_fingerprint, scan_date, analysis,
+                    prompt_version, analyzer_version
+             FROM scan_results {}",
+            clause
+        ))?;
+        let rows = stmt
+            .query_map(params, |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    CacheKey {
+                        package: row.get(1)?,
+                        version: row.get(2)?,
+                        content_hash: row.get(3)?,
+                        model: row.get(4)?,
+                        prompt_fingerprint: row.get(5)?,
+                    },
+                    row.get::<_, String>(6)?,
+                    row.get::<_, String>(7)?,
+                    (row.get(8)?, row.get(9)?),
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        rows.into_iter()
+            .map(|(id, key, scan_date, analysis, stored_version)| {
+                let analysis = parse_analysis(&analysis, stored_version, &key.model)?;
+                Ok(StoredAnalysis {
+                    id,
+                    entry: CacheEntry {
+                        key,
+                        scan_date: chrono::DateTime::parse_from_rfc3339(&scan_date)?
+                            .with_timezone(&chrono::Utc),
+                        analysis,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    fn refreshed_at(&self, resource: &str) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+        let refreshed_at: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT refreshed_at FROM refresh_log WHERE resource = ?1",
+                params![resource],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(refreshed_at
+            .and_then(|ts| chrono::DateTime::parse_from_rfc3339(&ts).ok())
+            .map(|ts| ts.with_timezone(&chrono::Utc)))
+    }
+
+    pub fn clear_checkpoint(&self, scan_key: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM checkpoint_files WHERE scan_key = ?1",
+            params![scan_key],
+        )?;
+        self.conn.execute(
+            "DELETE FROM scan_checkpoints WHERE scan_key = ?1",
+            params![scan_key],
+        )?;
+        Ok(())
+    }
+}
+
+impl CacheStore for Database {
+    fn get_analysis(&self, key: &CacheKey) -> Result<Option<LlmResponse>> {
+        let row: Option<(String, StoredVersion)> = self
+            .conn
+            .query_row(
+                "SELECT analysis, prompt_version, analyzer_version FROM scan_results
+                 WHERE package = ?1 AND version = ?2 AND content_hash = ?3
+                   AND model = ?4 AND prompt_fingerprint = ?5",
+                params![
+                    key.package,
+                    key.version,
+                    key.content_hash,
+                    key.model,
+                    key.prompt_fingerprint
+                ],
+                |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))),
+            )
+            .optional()?;
+
+        let counter = if row.is_some() { "hits" } else { "misses" };
+        self.conn.execute(
+            &format!("UPDATE cache_stats SET {0} = {0} + 1 WHERE id = 1", counter),
+            [],
+        )?;
+
+        row.map(|(analysis, stored_version)| parse_analysis(&analysis, stored_version, &key.model))
+            .transpose()
+    }
+
+    fn put_analysis(&self, key: &CacheKey, response: &LlmResponse) -> Result<()> {
+        insert_analysis(&self.conn, key, response, chrono::Utc::now())
+    }
+
+    fn cache_stats(&self) -> Result<CacheStats> {
+        let (entries, oldest, newest): (i64, Option<String>, Option<String>) =
+            self.conn.query_row(
+                "SELECT COUNT(*), MIN(scan_date), MAX(scan_date) FROM scan_results",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )?;
+        let (hits, misses): (i64, i64) = self.conn.query_row(
+            "SELECT hits, misses FROM cache_stats WHERE id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let parse = |ts: Option<String>| {
+            ts.and_then(|ts| chrono::DateTime::parse_from_rfc3339(&ts).ok())
+                .map(|ts| ts.with_timezone(&chrono::Utc))
+        };
+        Ok(CacheStats {
+            entries: entries as usize,
+            hits: hits as u64,
+            misses: misses as u64,
+            oldest: parse(oldest),
+            newest: parse(newest),
+        })
+    }
+
+    fn clear_analyses(&self) -> Result<usize> {
+        let removed = self.conn.execute("DELETE FROM scan_results", [])?;
+        self.conn.execute("DELETE FROM dependency_scans", [])?;
+        self.conn.execute(
+            "UPDATE cache_stats SET hits = 0, misses = 0 WHERE id = 1",
+            [],
+        )?;
+        Ok(removed)
+    }
+
+    fn invalidate_model(&self, model: &str) -> Result<usize> {
+        self.conn.execute(
+            "DELETE FROM dependency_scans WHERE model = ?1",
+            params![model],
+        )?;
+        Ok(self
+            .conn
+            .execute("DELETE FROM scan_results WHERE model = ?1", params![model])?)
+    }
+
+    fn export_analyses(&self) -> Result<Vec<CacheEntry>> {
+        Ok(self
+            .stored_analyses("ORDER BY package, version", [])?
+            .into_iter()
+            .map(|stored| stored.entry)
+            .collect())
+    }
+
+    fn list_analyses(&self, package: Option<&str>) -> Result<Vec<StoredAnalysis>> {
+        match package {
+            Some(package) => self.stored_analyses(
+                "WHERE package = ?1 ORDER BY version, scan_date",
+                params![package],
+            ),
+            None => self.stored_analyses("ORDER BY package, version, scan_date", []),
+        }
+    }
+
+    fn show_analysis(&self, id: i64) -> Result<Option<StoredAnalysis>> {
+        Ok(self
+            .stored_analyses("WHERE id = ?1", params![id])?
+            .into_iter()
+            .next())
+    }
+
+    fn delete_package(&self, package: &str) -> Result<usize> {
+        // Any stored dependency scan may include the package's analysis
+        self.conn.execute("DELETE FROM dependency_scans", [])?;
+        Ok(self.conn.execute(
+            "DELETE FROM scan_results WHERE package = ?1",
+            params![package],
+        )?)
+    }
+
+    fn import_analyses(&self, entries: &[CacheEntry]) -> Result<ImportSummary> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut summary = ImportSummary::default();
+        for entry in entries {
+            let key = &entry.key;
+            let local_date: Option<String> = tx
+                .query_row(
+                    "SELECT scan_date FROM scan_results
+                     WHERE package = ?1 AND version = ?2 AND content_hash = ?3
+                       AND model = ?4 AND prompt_fingerprint = ?5",
+                    params![
+                        key.package,
+                        key.version,
+                        key.content_hash,
+                        key.model,
+                        key.prompt_fingerprint
+                    ],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let local_date = local_date
+                .map(|ts| chrono::DateTime::parse_from_rfc3339(&ts))
+                .transpose()?;
+
+            match local_date {
+                Some(local) if local >= entry.scan_date => {
+                    summary.kept_local += 1;
+                    continue;
+                }
+                Some(_) => summary.updated += 1,
+                None => summary.added += 1,
+            }
+            insert_analysis(&tx, key, &entry.analysis, entry.scan_date)?;
+        }
+        tx.commit()?;
+        Ok(summary)
+    }
+}
+
+/// Stores a comment unless the same author already left one at that time;
+/// returns how many rows were added.
+fn insert_comment(conn: &Connection, finding_id: &str, comment: &Comment) -> Result<usize> {
+    Ok(conn.execute(
+        "INSERT OR IGNORE INTO finding_comments (finding_id, author, created_at, body)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![finding_id, comment.author, comment.created_at, comment.body],
+    )?)
+}
+
+/// Whether RFC 3339 timestamp `a` is after `b`, comparing the text when
+/// either does not parse.
+fn is_later(a: &str, b: &str) -> bool {
+    match (
+        chrono::DateTime::parse_from_rfc3339(a),
+        chrono::DateTime::parse_from_rfc3339(b),
+    ) {
+        (Ok(a_time), Ok(b_time)) => a_time > b_time,
+        _ => a > b,
+    }
+}
+
+/// `prompt_version` and `analyzer_version` columns of `scan_results`.
+type StoredVersion = (Option<u32>, Option<String>);
+
+fn insert_analysis(
+    conn: &Connection,
+    key: &CacheKey,
+    response: &LlmResponse,
+    scan_date: chrono::DateTime<chrono::Utc>,
+) -> Result<()> {
+    let version = response.version.as_ref();
+    conn.execute(
+        "INSERT OR REPLACE INTO scan_results
+            (package, version, content_hash, model, prompt_fingerprint, analysis, scan_date,
+             prompt_version, analyzer_version)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            key.package,
+            key.version,
+            key.content_hash,
+            key.model,
+            key.prompt_fingerprint,
+            serde_json::to_string(response)?,
+            scan_date.to_rfc3339(),
+            version.map(|v| v.prompt_version),
+            version.map(|v| v.analyzer_version.as_str())
+        ],
+    )?;
+    Ok(())
+}
+
+/// Analyses cached before versions were recorded get theirs from the columns.
+fn parse_analysis(analysis: &str, stored: StoredVersion, model: &str) -> Result<LlmResponse> {
+    let mut response: LlmResponse = serde_json::from_str(analysis)?;
+    if let (None, (Some(prompt_version), analyzer_version)) = (&response.version, stored) {
+        response.version = Some(AnalysisVersion {
+            prompt_version,
+            analyzer_version: analyzer_version.unwrap_or_default(),
+            model: model.to_string(),
+        });
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let path = std::env::temp_dir().join(format!(
+            "rustrecon-test-{}-checkpoint.db",
+            std::process::id()
+        ));
+        let db = Database::open(&path).unwrap();
+
+        assert!(db.load_checkpoint("crate").unwrap().is_none());
+
+        db.start_checkpoint("crate").unwrap();
+        db.save_checkpoint_file(
+            "crate",
+            &CrateFinding {
+                file_path: PathBuf::from("src/lib.rs"),
+                llm_analysis: "No significant security issues detected.".to_string(),
+                flagged_patterns: Vec::new(),
+                analysis_version: None,
+                identical_files: Vec::new(),
+                truncation: None,
+            },
+            "abc123",
+        )
+        .unwrap();
+
+        let checkpoint = db.load_checkpoint("crate").unwrap().unwrap();
+        assert_eq!(checkpoint.completed_files.len(), 1);
+        assert_eq!(checkpoint.completed_files[0].1, "abc123");
+        assert!(checkpoint.dependency_findings.is_none());
+
+        db.clear_checkpoint("crate").unwrap();
+        assert!(db.load_checkpoint("crate").unwrap().is_none());
+
+        drop(db);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_migrations_upgrade_unversioned_database() {
+        let path = std::env::temp_dir().join(format!(
+            "rustrecon-test-{}-migrations.db",
+            std::process::id()
+        ));
+        // A database from before versioning: tables exist, no schema_version
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(MIGRATIONS[0])
+            .unwrap();
+
+        let db = Database::open(&path).unwrap();
+        assert_eq!(db.schema_version().unwrap(), MIGRATIONS.len());
+        drop(db);
+
+        // Reopening applies nothing and keeps the version
+        let db = Database::open(&path).unwrap();
+        assert_eq!(db.schema_version().unwrap(), MIGRATIONS.len());
+
+        drop(db);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_analysis_cache_roundtrip() {
+        let path =
+            std::env::temp_dir().join(format!("rustrecon-test-{}-cache.db", std::process::id()));
+        let db = Database::open(&path).unwrap();
+        let key = CacheKey {
+            model: "gemini-1.5-flash".to_string(),
+            prompt_fingerprint: "v1".to_string(),
+            ..CacheKey::new("serde", "1.0.0", "prompt")
+        };
+
+        assert!(db.get_analysis(&key).unwrap().is_none());
+        db.put_analysis(
+            &key,
+            &LlmResponse {
+                analysis: "No significant security issues detected.".to_string(),
+                flagged_patterns: Vec::new(),
+                version: Some(AnalysisVersion::current("gemini-1.5-flash")),
+            },
+        )
+        .unwrap();
+        let cached = db.get_analysis(&key).unwrap().unwrap();
+        assert!(!cached.version.unwrap().is_outdated());
+        assert!(db
+            .get_analysis(&CacheKey {
+                content_hash: CacheKey::new("serde", "1.0.0", "changed prompt").content_hash,
+                ..key.clone()
+            })
+            .unwrap()
+            .is_none());
+        assert!(db
+            .get_analysis(&CacheKey {
+                prompt_fingerprint: "v2".to_string(),
+                ..key.clone()
+            })
+            .unwrap()
+            .is_none());
+
+        let stats = db.cache_stats().unwrap();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (1, 1, 3));
+
+        assert_eq!(db.invalidate_model("gemini-1.0-pro").unwrap(), 0);
+        assert_eq!(db.invalidate_model("gemini-1.5-flash").unwrap(), 1);
+        db.put_analysis(
+            &key,
+            &LlmResponse {
+                analysis: "No significant security issues detected.".to_string(),
+                flagged_patterns: Vec::new(),
+                version: None,
+            },
+        )
+        .unwrap();
+
+        // Importing keeps whichever side analyzed more recently
+        let mut exported = db.export_analyses().unwrap();
+        assert_eq!(exported.len(), 1);
+        exported[0].scan_date -= chrono::Duration::days(1);
+        let mut newer = exported[0].clone();
+        newer.key.version = "1.0.1".to_string();
+        exported.push(newer);
+        assert_eq!(
+            db.import_analyses(&exported).unwrap(),
+            ImportSummary {
+                added: 1,
+                updated: 0,
+                kept_local: 1,
+            }
+        );
+        exported[0].scan_date += chrono::Duration::days(2);
+        assert_eq!(db.import_analyses(&exported[..1]).unwrap().updated, 1);
+
+        let listed = db.list_analyses(Some("serde")).unwrap();
+        assert_eq!(listed.len(), 2);
+        let shown = db.show_analysis(listed[1].id).unwrap().unwrap();
+        assert_eq!(shown.entry.key.version, "1.0.1");
+        assert!(db.list_analyses(Some("tokio")).unwrap().is_empty());
+
+        assert_eq!(db.clear_analyses().unwrap(), 2);
+        assert_eq!(db.cache_stats().unwrap().hits, 0);
+
+        drop(db);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_finding_triage() {
+        let path =
+            std::env::temp_dir().join(format!("rustrecon-test-{}-triage.db", std::process::id()));
+        let db = Database::open(&path).unwrap();
+        let mut finding = FindingRecord {
+            id: "abc123def456".to_string(),
+            crate_name: "demo".to_string(),
+            file_path: PathBuf::from("src/main.rs"),
+            line: 3,
+            severity: "High".to_string(),
+            description: "Spawns a shell".to_string(),
+            code_snippet: "Command::new(\"sh\")".to_string(),
+            status: TriageStatus {
+                state: TriageState::New,
+                note: None,
+            },
+            first_seen: "2026-01-01T00:00:00Z".to_string(),
+            last_seen: "2026-01-01T00:00:00Z".to_string(),
+            updated_at: None,
+            updated_by: None,
+        };
+        assert_eq!(db.record_finding(&finding).unwrap().state, TriageState::New);
+
+        let id = db
+            .set_triage(
+                "ABC1",
+                TriageState::FalsePositive,
+                Some("Test helper"),
+                "ana",
+            )
+            .unwrap();
+        assert_eq!(id, finding.id);
+        finding.line = 7;
+        let status = db.record_finding(&finding).unwrap();
+        assert_eq!(status.state, TriageState::FalsePositive);
+        assert_eq!(status.note.as_deref(), Some("Test helper"));
+
+        // A fixed finding that comes back is reopened
+        db.set_triage(&id, TriageState::Fixed, None, "ana").unwrap();
+        assert_eq!(db.record_finding(&finding).unwrap().state, TriageState::New);
+        assert!(db
+            .set_triage("ffff", TriageState::Fixed, None, "ana")
+            .is_err());
+
+        let listed = db.list_findings(Some("demo"), None).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].line, 7);
+        assert!(db
+            .list_findings(None, Some(TriageState::Confirmed))
+            .unwrap()
+            .is_empty());
+
+        // A second reviewer's later decision wins; comments are combined
+        let comment = Comment {
+            author: "ana".to_string(),
+            created_at: "2026-01-02T00:00:00Z".to_string(),
+            body: "Only reachable from tests".to_string(),
+        };
+        db.add_comment(&id, &comment).unwrap();
+        let mut bundle = db.export_annotations(Some("demo")).unwrap();
+        assert_eq!(bundle[0].comments, vec![comment.clone()]);
+        bundle[0].finding.status.state = TriageState::Confirmed;
+        bundle[0].finding.updated_at = Some("2099-01-01T00:00:00Z".to_string());
+        bundle[0].finding.updated_by = Some("ben".to_string());
+        bundle[0].comments.push(Comment {
+            author: "ben".to_string(),
+            body: "Reachable from the CLI too".to_string(),
+            ..comment
+        });
+        let mut unknown = bundle[0].clone();
+        unknown.finding.id = "fedcba987654".to_string();
+        bundle.push(unknown);
+        let summary = db.import_annotations(&bundle).unwrap();
+        assert_eq!(
+            (summary.added, summary.updated, summary.kept_local),
+            (1, 1, 0)
+        );
+        assert_eq!(summary.comments_added, 3);
+        assert_eq!(summary.conflicts, vec![id.clone()]);
+        let merged = db
+            .list_findings(None, Some(TriageState::Confirmed))
+            .unwrap();
+        assert_eq!(merged[0].updated_by.as_deref(), Some("ben"));
+        assert_eq!(db.finding_comments(&id).unwrap().len(), 2);
+        assert_eq!(db.import_annotations(&bundle).unwrap().comments_added, 0);
+
+        drop(db);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,193 @@
+use cargo_metadata::{Metadata, PackageId};
+use std::collections::{HashMap, VecDeque};
+
+use crate::dependency_scanner::{DependencyAnalysisResult, RiskScore};
+
+/// Identifies one resolved package; a graph can hold several versions of a crate.
+pub fn package_key(name: &str, version: &str) -> String {
+    format!("{}@{}", name, version)
+}
+
+#[derive(Debug, Default)]
+struct GraphNode {
+    depth: usize,
+    dependents: Vec<String>,
+    dependencies: Vec<String>,
+}
+
+/// The resolved dependency graph reported by `cargo metadata`.
+pub struct DependencyGraph {
+    nodes: HashMap<String, GraphNode>,
+}
+
+impl DependencyGraph {
+    /// Walks the resolve graph breadth-first from the workspace members, so each
+    /// package's depth is its shortest distance from the workspace (1 = direct).
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let keys: HashMap<&PackageId, String> = metadata
+            .packages
+            .iter()
+            .map(|p| (&p.id, package_key(&p.name, &p.version.to_string())))
+            .collect();
+        let mut nodes: HashMap<String, GraphNode> = HashMap::new();
+
+        let Some(resolve) = &metadata.resolve else {
+            return DependencyGraph { nodes };
+        };
+
+        let mut edges: HashMap<&PackageId, Vec<&PackageId>> = HashMap::new();
+        for node in &resolve.nodes {
+            let entry = edges.entry(&node.id).or_default();
+            for dep in &node.deps {
+                entry.push(&dep.pkg);
+                if let (Some(parent), Some(child)) = (keys.get(&node.id), keys.get(&dep.pkg)) {
+                    nodes
+                        .entry(parent.clone())
+                        .or_default()
+                        .dependencies
+                        .push(child.clone());
+                    nodes
+                        .entry(child.clone())
+                        .or_default()
+                        .dependents
+                        .push(parent.clone());
+                }
+            }
+        }
+
+        let mut visited: HashMap<&PackageId, usize> = HashMap::new();
+        let mut queue: VecDeque<(&PackageId, usize)> = metadata
+            .workspace_members
+            .iter()
+            .map(|id| (id, 0))
+            .collect();
+        while let Some((id, depth)) = queue.pop_front() {
+            if visited.contains_key(id) {
+                continue;
+            }
+            visited.insert(id, depth);
+            if let Some(key) = keys.get(id) {
+                nodes.entry(key.clone()).or_default().depth = depth;
+            }
+            for child in edges.get(id).into_iter().flatten() {
+                if !visited.contains_key(child) {
+                    queue.push_back((child, depth + 1));
+                }
+            }
+        }
+
+        DependencyGraph { nodes }
+    }
+
+    /// Shortest distance of the package with this key from the workspace.
+    pub fn depth(&self, key: &str) -> Option<usize> {
+        self.nodes.get(key).map(|node| node.depth)
+    }
+
+    /// Copies depth, dependents and dependencies onto the matching scan results.
+    pub fn annotate(&self, results: &mut [DependencyAnalysisResult]) {
+        for result in results {
+            if let Some(node) = self.nodes.get(&result.key()) {
+                result.depth = node.depth;
+                result.dependents = node.dependents.clone();
+                result.dependencies = node.dependencies.clone();
+            }
+        }
+    }
+}
+
+/// Raises a package's risk when one of its dependencies is High or Critical:
+/// a Critical dependency makes its parent at least High, a High one at least
+/// Medium. Repeats until stable, so risk decays as it travels up the tree.
+pub fn propagate_risk(results: &mut [DependencyAnalysisResult]) {
+    let index: HashMap<String, usize> = results
+        .iter()
+        .enumerate()
+        .map(|(i, r)| (r.key(), i))
+        .collect();
+
+    loop {
+        let mut changed = false;
+        for i in 0..results.len() {
+            let mut strongest: Option<(RiskScore, String)> = None;
+            for dep_key in &results[i].dependencies {
+                let Some(&dep) = index.get(dep_key) else {
+                    continue;
+                };
+                let inherited = match results[dep].risk_score {
+                    RiskScore::Critical => RiskScore::High,
+                    RiskScore::High => RiskScore::Medium,
+                    _ => continue,
+                };
+                if strongest
+                    .as_ref()
+                    .is_none_or(|(risk, _)| inherited.rank() > risk.rank())
+                {
+                    strongest = Some((inherited, dep_key.clone()));
+                }
+            }
+
+            if let Some((risk, dep_key)) = strongest {
+                if risk.rank() > results[i].risk_score.rank() {
+                    results[i].risk_score = risk;
+                    results[i].elevated_by = Some(dep_key);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency_scanner::DependencySource;
+
+    fn result(
+        name: &str,
+        risk_score: RiskScore,
+        dependencies: &[&str],
+    ) -> DependencyAnalysisResult {
+        DependencyAnalysisResult {
+            package_name: name.to_string(),
+            version: "1.0.0".to_string(),
+            source: DependencySource::Unknown,
+            risk_score,
+            suspicious_patterns: Vec::new(),
+            metadata_flags: Vec::new(),
+            code_analysis: None,
+            analysis_version: None,
+            depth: 0,
+            dependents: Vec::new(),
+            dependencies: dependencies
+                .iter()
+                .map(|d| package_key(d, "1.0.0"))
+                .collect(),
+            elevated_by: None,
+            vulnerabilities: Vec::new(),
+            unsafe_metrics: None,
+            network_indicators: Vec::new(),
+            source_truncation: None,
+            native_footprint: None,
+        }
+    }
+
+    #[test]
+    fn test_propagate_risk_decays_up_the_tree() {
+        let mut results = vec![
+            result("app-dep", RiskScore::Clean, &["middle"]),
+            result("middle", RiskScore::Low, &["evil"]),
+            result("evil", RiskScore::Critical, &[]),
+        ];
+        propagate_risk(&mut results);
+
+        assert!(matches!(results[1].risk_score, RiskScore::High));
+        assert_eq!(results[1].elevated_by.as_deref(), Some("evil@1.0.0"));
+        assert!(matches!(results[0].risk_score, RiskScore::Medium));
+        assert_eq!(results[0].elevated_by.as_deref(), Some("middle@1.0.0"));
+        assert!(results[2].elevated_by.is_none());
+    }
+}
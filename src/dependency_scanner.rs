@@ -1,12 +1,84 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use cargo_metadata::{Metadata, MetadataCommand, Package};
-use reqwest::Client;
+use futures::stream::{self, StreamExt};
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use std::path::Path;
-use tokio::time::{sleep, timeout, Duration};
-
-use crate::llm_client::{FlaggedPattern, LlmClientTrait, LlmRequest};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::time::{timeout, Duration, Instant};
+use tracing::{debug, info, warn};
+
+use crate::artifacts::{self, ArtifactKind};
+use crate::cache::CacheKey;
+use crate::checksum;
+use crate::config::{DependencyConfig, DependencyDepth};
+use crate::context_budget::{ContextBudget, Truncation};
+use crate::crates_index;
+use crate::database::{Database, SavedResponse};
+use crate::dependency_graph::{self, package_key, DependencyGraph};
+use crate::indicators::{self, NetworkIndicator};
+use crate::languages::Language;
+use crate::llm_client::{AnalysisVersion, FlaggedPattern, LlmClientTrait, LlmRequest};
+use crate::lockfile::LockedPackage;
+use crate::native_code::{self, NativeFootprint};
+use crate::network::{self, Revalidated, Validators};
+use crate::osv::{self, Vulnerability};
+use crate::progress::{self, EtaTracker, ProgressEvent};
+use crate::rate_limit::RateLimiter;
+use crate::scan_budget::ScanBudget;
+use crate::severity;
+use crate::source_verify;
+use crate::typosquat::{self, TyposquatMatch};
+use crate::unsafe_metrics::UnsafeMetrics;
+
+/// The crates.io API, the default source of package metadata.
+const CRATES_IO_API: &str = "https://crates.io/api/v1";
+
+/// Number of dependencies whose crates.io lookups and downloads are in flight
+/// at once. LLM requests among them are still paced by the rate limiter, and
+/// crates.io and GitHub API requests by `metadata_limiter`.
+const DEPENDENCY_CONCURRENCY: usize = 8;
+/// crates.io crawler policy: at most one request per second.
+const METADATA_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many of the most-downloaded crates are used for typosquatting comparison.
+const TOP_CRATES_COUNT: usize = 1000;
+/// crates.io caps `per_page` at 100.
+const TOP_CRATES_PAGE_SIZE: usize = 100;
+/// Cached crates.io responses are reused for a day unless `metadata_cache_hours` says otherwise.
+pub const METADATA_CACHE_HOURS: u64 = 24;
+/// The cached top-crates list is refreshed once a week.
+const TOP_CRATES_MAX_AGE_DAYS: i64 = 7;
+/// A crate with no release for this long is checked for signs of abandonment.
+const UNMAINTAINED_AFTER_DAYS: i64 = 730;
+/// Repository activity more recent than this means the crate is simply stable.
+const REPOSITORY_ACTIVE_DAYS: i64 = 365;
+/// A new publisher's first release this close before the scanned version looks like a transfer.
+const TRANSFER_WINDOW_DAYS: i64 = 30;
+/// Owners who have not published for this long before are considered new.
+const OWNER_CHANGE_DAYS: i64 = 90;
+/// Packages with at least this many `unsafe` usages get a Medium rather than Low flag.
+const UNSAFE_HEAVY_THRESHOLD: usize = 50;
+
+/// Index URLs of crates.io, always an approved source: the git index and the sparse one.
+const CRATES_IO_INDEXES: &[&str] = &[
+    "https://github.com/rust-lang/crates.io-index",
+    "https://index.crates.io/",
+];
+
+/// Artifact paths named in a `BundledArtifact` flag; the rest are counted.
+const MAX_LISTED_ARTIFACTS: usize = 3;
+
+/// Results of a dependency scan.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DependencyScan {
+    pub results: Vec<DependencyAnalysisResult>,
+    /// `name@version` of packages left out by `max_packages`
+    pub skipped: Vec<String>,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DependencyAnalysisResult {
@@ -17,6 +89,42 @@ pub struct DependencyAnalysisResult {
     pub suspicious_patterns: Vec<FlaggedPattern>,
     pub metadata_flags: Vec<MetadataFlag>,
     pub code_analysis: Option<String>,
+    /// Shortest distance from the workspace (1 = direct dependency, 0 = unknown)
+    #[serde(default)]
+    pub depth: usize,
+    /// `name@version` of the packages that depend on this one
+    #[serde(default)]
+    pub dependents: Vec<String>,
+    /// `name@version` of this package's own dependencies
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    /// Dependency whose risk raised `risk_score` above this package's own findings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevated_by: Option<String>,
+    /// Advisories from OSV affecting this exact version
+    #[serde(default)]
+    pub vulnerabilities: Vec<Vulnerability>,
+    /// `unsafe` usage in the package source; `None` when the source was not available
+    #[serde(default)]
+    pub unsafe_metrics: Option<UnsafeMetrics>,
+    /// Prompt and analyzer behind `code_analysis`; `None` without LLM analysis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub analysis_version: Option<AnalysisVersion>,
+    /// URLs, IPs and domains in the package source, with paths relative to it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network_indicators: Vec<NetworkIndicator>,
+    /// Set when deep analysis sent only part of the source to the LLM
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_truncation: Option<Truncation>,
+    /// Bundled C/C++ and native build steps of a `-sys` crate; `None` for other packages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub native_footprint: Option<NativeFootprint>,
+}
+
+impl DependencyAnalysisResult {
+    pub fn key(&self) -> String {
+        dependency_graph::package_key(&self.package_name, &self.version)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +132,10 @@ pub enum DependencySource {
     CratesIo {
         registry_url: String,
     },
+    /// A registry other than crates.io
+    Registry {
+        registry_url: String,
+    },
     Git {
         repository: String,
         rev: Option<String>,
@@ -43,6 +155,19 @@ pub enum RiskScore {
     Clean,    // No issues detected
 }
 
+impl RiskScore {
+    /// Higher is riskier.
+    pub fn rank(&self) -> u8 {
+        match self {
+            RiskScore::Critical => 4,
+            RiskScore::High => 3,
+            RiskScore::Medium => 2,
+            RiskScore::Low => 1,
+            RiskScore::Clean => 0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetadataFlag {
     pub flag_type: MetadataFlagType,
@@ -61,6 +186,68 @@ pub enum MetadataFlagType {
     FileSystemAccess,
     ProcessExecution,
     CryptoOperations,
+    KnownMalicious,
+    SuspiciousName,
+    YankedVersion,
+    Unmaintained,
+    SourceMismatch,
+    KnownVulnerability,
+    UnsafeCode,
+    BundledArtifact,
+    UnapprovedSource,
+    ChecksumMismatch,
+    NativeCode,
+    LookupFailed,
+}
+
+impl MetadataFlagType {
+    /// Every flag type, for checking `[severity]` keys.
+    pub const ALL: &'static [MetadataFlagType] = &[
+        MetadataFlagType::Typosquatting,
+        MetadataFlagType::RecentPublication,
+        MetadataFlagType::LowDownloads,
+        MetadataFlagType::SuspiciousAuthor,
+        MetadataFlagType::UnusualDependencies,
+        MetadataFlagType::NetworkingCapabilities,
+        MetadataFlagType::FileSystemAccess,
+        MetadataFlagType::ProcessExecution,
+        MetadataFlagType::CryptoOperations,
+        MetadataFlagType::KnownMalicious,
+        MetadataFlagType::SuspiciousName,
+        MetadataFlagType::YankedVersion,
+        MetadataFlagType::Unmaintained,
+        MetadataFlagType::SourceMismatch,
+        MetadataFlagType::KnownVulnerability,
+        MetadataFlagType::UnsafeCode,
+        MetadataFlagType::BundledArtifact,
+        MetadataFlagType::UnapprovedSource,
+        MetadataFlagType::ChecksumMismatch,
+        MetadataFlagType::NativeCode,
+        MetadataFlagType::LookupFailed,
+    ];
+}
+
+/// The flag type as words, e.g. `Known Malicious`.
+impl std::fmt::Display for MetadataFlagType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, c) in format!("{:?}", self).chars().enumerate() {
+            if i > 0 && c.is_ascii_uppercase() {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of vetting a single crate name before it is added as a dependency.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameCheckResult {
+    pub crate_name: String,
+    pub exists_on_crates_io: bool,
+    pub risk_score: RiskScore,
+    pub metadata_flags: Vec<MetadataFlag>,
+    pub closest_popular: Vec<(String, usize)>, // (popular package, edit distance)
 }
 
 pub struct DependencyScanner {
@@ -68,12 +255,35 @@ pub struct DependencyScanner {
     known_malicious: HashSet<String>,
     popular_packages: HashMap<String, u64>, // package_name -> download_count
     trusted_packages: HashSet<String>,
+    severity_overrides: HashMap<String, String>, // flag type name -> severity
+    rate_limiter: RateLimiter,                   // paces LLM requests across concurrent analyses
+    deep_analysis: bool,                         // LLM-review every dependency's source
+    native_review: bool,  // LLM-review the build script and C/C++ of -sys crates
+    offline: bool,        // no crates.io or registry access
+    verify_sources: bool, // diff published tarballs against their repos
+    vulnerabilities: HashMap<String, Vec<Vulnerability>>, // name@version -> OSV advisories
+    approved_sources: Vec<String>, // registry index and git URLs allowed besides crates.io
+    checksums: HashMap<String, String>, // name@version -> Cargo.lock checksum
+    context_budget: ContextBudget, // bounds the source sent in deep analysis
+    metadata_api: String, // crates.io-compatible API for package metadata
+    metadata_index: Option<PathBuf>, // local crates.io index used instead of the API
+    metadata_cache: Option<Mutex<Database>>, // crates.io responses saved between scans
+    metadata_cache_hours: u64, // how long a saved response is used without a request
+    depth: DependencyDepth, // direct dependencies only, or the whole graph
+    max_packages: Option<usize>, // packages analyzed per scan, nearest first
+    max_llm_calls: Option<usize>, // uncached LLM requests per scan
+    llm_calls: AtomicUsize, // uncached LLM requests made or refused so far
+    incomplete: AtomicUsize, // LLM analyses that failed, timed out or hit max_llm_calls
+    budget: Arc<ScanBudget>, // --max-duration and --max-api-calls of the whole scan
+    only_packages: Option<HashSet<String>>, // name@version of the packages to scan, if not all
 }
 
 impl DependencyScanner {
     pub fn new() -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
+        // crates.io rejects API requests without a User-Agent
+        let client = network::client_builder()
+            .timeout(network::timeouts().request)
+            .user_agent(concat!("rustrecon/", env!("CARGO_PKG_VERSION")))
             .build()
             .expect("Failed to create HTTP client");
 
@@ -111,89 +321,572 @@ impl DependencyScanner {
             known_malicious,
             popular_packages,
             trusted_packages,
+            severity_overrides: HashMap::new(),
+            rate_limiter: RateLimiter::new(Duration::from_millis(4000)),
+            deep_analysis: false,
+            native_review: false,
+            offline: false,
+            verify_sources: false,
+            vulnerabilities: HashMap::new(),
+            approved_sources: Vec::new(),
+            checksums: HashMap::new(),
+            context_budget: ContextBudget::default(),
+            metadata_api: CRATES_IO_API.to_string(),
+            metadata_index: None,
+            metadata_cache: None,
+            metadata_cache_hours: METADATA_CACHE_HOURS,
+            depth: DependencyDepth::default(),
+            max_packages: None,
+            max_llm_calls: None,
+            llm_calls: AtomicUsize::new(0),
+            incomplete: AtomicUsize::new(0),
+            budget: Arc::default(),
+            only_packages: None,
+        }
+    }
+
+    pub fn set_request_interval(&mut self, request_interval: Duration) {
+        self.rate_limiter = RateLimiter::new(request_interval);
+    }
+
+    /// Sends every dependency (trusted ones included) to the LLM along with its source.
+    pub fn set_deep_analysis(&mut self, deep_analysis: bool) {
+        self.deep_analysis = deep_analysis;
+    }
+
+    /// Sends `-sys` crates to the LLM with their build script and a sample
+    /// of their C/C++ sources, in place of their Rust source.
+    pub fn set_native_review(&mut self, native_review: bool) {
+        self.native_review = native_review;
+    }
+
+    /// Sizes the source excerpt sent in deep analysis to the LLM's context window.
+    pub fn set_context_budget(&mut self, budget: ContextBudget) {
+        self.context_budget = budget;
+    }
+
+    /// Reads package metadata from a mirror of the crates.io API or from a
+    /// local copy of the index. A local index works offline too, but has no
+    /// download counts, publish dates or owners, so the checks that need them
+    /// are skipped. API responses are saved in the local database and reused
+    /// for `metadata_cache_hours`.
+    pub fn set_metadata_source(&mut self, config: &DependencyConfig) {
+        if let Some(url) = &config.metadata_url {
+            self.metadata_api = url.trim_end_matches('/').to_string();
         }
+        self.metadata_index = config.metadata_index.clone();
+        self.metadata_cache_hours = config.metadata_cache_hours.unwrap_or(METADATA_CACHE_HOURS);
+        self.metadata_cache = match Database::open_default() {
+            Ok(db) => Some(Mutex::new(db)),
+            Err(e) => {
+                debug!("crates.io responses will not be cached: {}", e);
+                None
+            }
+        };
+    }
+
+    /// Applies the `depth`, `max_packages` and `max_llm_calls` limits of
+    /// `[dependencies]`.
+    pub fn set_limits(&mut self, config: &DependencyConfig) {
+        self.depth = config.depth.unwrap_or_default();
+        self.max_packages = config.max_packages;
+        self.max_llm_calls = config.max_llm_calls;
+    }
+
+    /// Identifies the results a scan of `project_path` would give: its
+    /// `Cargo.lock` and `Cargo.toml`, the OSV advisories and top-crates list
+    /// loaded so far, the LLM model and every setting that changes results.
+    /// `None` when the crate has no lockfile to key on.
+    pub fn fingerprint(&self, project_path: &Path, model: Option<&str>) -> Option<String> {
+        let lockfile = std::fs::read(project_path.join("Cargo.lock")).ok()?;
+        let manifest = std::fs::read(project_path.join("Cargo.toml")).unwrap_or_default();
+        fn sorted(set: &HashSet<String>) -> BTreeSet<&String> {
+            set.iter().collect()
+        }
+        let vulnerabilities: BTreeMap<_, _> = self.vulnerabilities.iter().collect();
+        let settings = serde_json::json!({
+            "rustrecon": env!("CARGO_PKG_VERSION"),
+            "model": model,
+            "vulnerabilities": vulnerabilities,
+            "popular_packages": self.popular_packages.iter().collect::<BTreeMap<_, _>>(),
+            "known_malicious": sorted(&self.known_malicious),
+            "trusted_packages": sorted(&self.trusted_packages),
+            "severity_overrides": self.severity_overrides.iter().collect::<BTreeMap<_, _>>(),
+            "approved_sources": self.approved_sources,
+            "deep_analysis": self.deep_analysis,
+            "native_review": self.native_review,
+            "offline": self.offline,
+            "verify_sources": self.verify_sources,
+            "context_budget": format!("{:?}", self.context_budget),
+            "metadata_api": self.metadata_api,
+            "metadata_index": self.metadata_index,
+            "depth": self.depth,
+            "max_packages": self.max_packages,
+            "max_llm_calls": self.max_llm_calls,
+            "only_packages": self.only_packages.as_ref().map(sorted),
+        });
+        let mut hasher = Sha256::new();
+        hasher.update(Sha256::digest(&lockfile));
+        hasher.update(Sha256::digest(&manifest));
+        hasher.update(settings.to_string().as_bytes());
+        Some(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Deep analyses that failed, timed out or were refused by
+    /// `max_llm_calls`, which a later scan would redo.
+    pub fn incomplete_analyses(&self) -> usize {
+        self.incomplete.load(Ordering::Relaxed)
+    }
+
+    /// Scans only the packages with these `name@version` keys, e.g. those a
+    /// change adds to the lockfile.
+    pub fn set_only_packages(&mut self, packages: HashSet<String>) {
+        self.only_packages = Some(packages);
+    }
+
+    /// Disables every network call: crates.io lookups are skipped, the cached
+    /// top-crates list is used regardless of age, and `cargo metadata` runs `--offline`.
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    /// Shares the scan's budget, so packages reached after it is spent are not
+    /// queued for the LLM at all.
+    pub fn set_budget(&mut self, budget: Arc<ScanBudget>) {
+        self.budget = budget;
+    }
+
+    /// Compares each crates.io dependency's published tarball with its repository.
+    pub fn set_source_verification(&mut self, verify_sources: bool) {
+        self.verify_sources = verify_sources;
+    }
+
+    /// Allows dependencies from these registry index URLs and git repositories
+    /// (or URL prefixes ending in `/`) besides crates.io; others are flagged.
+    pub fn add_approved_sources(&mut self, sources: &[String]) {
+        self.approved_sources.extend(sources.iter().cloned());
+    }
+
+    /// Marks additional packages (e.g., from project config) as trusted.
+    pub fn add_trusted_packages(&mut self, packages: &[String]) {
+        self.trusted_packages.extend(packages.iter().cloned());
+    }
+
+    pub fn set_severity_overrides(&mut self, overrides: HashMap<String, String>) {
+        self.severity_overrides = overrides;
+    }
+
+    /// `[severity]` override for a flag type, in canonical spelling.
+    fn severity_override(&self, flag_type: &MetadataFlagType) -> Option<&'static str> {
+        self.severity_overrides
+            .get(&format!("{:?}", flag_type))
+            .and_then(|value| severity::canonical(value))
+    }
+
+    fn apply_severity_overrides(&self, flags: &mut Vec<MetadataFlag>) {
+        flags.retain_mut(|flag| match self.severity_override(&flag.flag_type) {
+            Some(severity::IGNORE) => false,
+            Some(severity) => {
+                flag.severity = severity.to_string();
+                true
+            }
+            None => true,
+        });
+    }
+
+    /// Extends the built-in popular-package list with the top crates by downloads.
+    /// Uses the copy cached in the database while it is less than a week old, and
+    /// falls back to a stale copy (or the built-in list) if crates.io is unreachable.
+    pub async fn load_popular_packages(&mut self, db: Option<&mut Database>) {
+        let cached = match &db {
+            Some(db) => db.load_popular_crates().unwrap_or_else(|e| {
+                warn!("⚠️  Could not read cached top crates: {}", e);
+                None
+            }),
+            None => None,
+        };
+
+        if let Some(cached) = &cached {
+            let age = chrono::Utc::now().signed_duration_since(cached.refreshed_at);
+            if self.offline
+                || self.metadata_index.is_some()
+                || age.num_days() < TOP_CRATES_MAX_AGE_DAYS
+            {
+                self.popular_packages.extend(cached.crates.clone());
+                return;
+            }
+        }
+        // A local index has no download counts to rank crates by
+        if self.offline || self.metadata_index.is_some() {
+            return;
+        }
+
+        match self.fetch_top_crates().await {
+            Ok(crates) => {
+                if let Some(db) = db {
+                    if let Err(e) = db.save_popular_crates(&crates) {
+                        warn!("⚠️  Could not cache top crates: {}", e);
+                    }
+                }
+                self.popular_packages.extend(crates);
+            }
+            Err(e) => {
+                warn!("⚠️  Could not refresh top crates from crates.io: {}", e);
+                if let Some(cached) = cached {
+                    self.popular_packages.extend(cached.crates);
+                }
+            }
+        }
+    }
+
+    /// Fetches known vulnerabilities for every locked package from OSV (or the
+    /// local cache) so they can be attached to the analysis results.
+    pub async fn load_vulnerabilities(
+        &mut self,
+        packages: &[LockedPackage],
+        db: Option<&Database>,
+    ) {
+        self.vulnerabilities = osv::lookup(&self.client, packages, db, self.offline).await;
+        let count: usize = self.vulnerabilities.values().map(Vec::len).sum();
+        if count > 0 {
+            info!(
+                "🛡️  OSV: {} known vulnerabilities across {} packages",
+                count,
+                self.vulnerabilities.len()
+            );
+        }
+    }
+
+    /// Remembers the `Cargo.lock` checksums that downloaded packages are verified against.
+    pub fn load_checksums(&mut self, packages: &[LockedPackage]) {
+        self.checksums = packages
+            .iter()
+            .filter_map(|package| {
+                let key = dependency_graph::package_key(&package.name, &package.version);
+                Some((key, package.checksum.clone()?))
+            })
+            .collect();
+    }
+
+    fn vulnerabilities_for(&self, name: &str, version: &str) -> Vec<Vulnerability> {
+        self.vulnerabilities
+            .get(&dependency_graph::package_key(name, version))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn vulnerability_flags(&self, name: &str, version: &str) -> Vec<MetadataFlag> {
+        self.vulnerabilities_for(name, version)
+            .into_iter()
+            .map(|vulnerability| {
+                let fix = if vulnerability.fixed_versions.is_empty() {
+                    "no fixed version available".to_string()
+                } else {
+                    format!("fixed in {}", vulnerability.fixed_versions.join(", "))
+                };
+                MetadataFlag {
+                    flag_type: MetadataFlagType::KnownVulnerability,
+                    description: format!(
+                        "{}: {} ({})",
+                        vulnerability.id, vulnerability.summary, fix
+                    ),
+                    severity: vulnerability.severity,
+                }
+            })
+            .collect()
+    }
+
+    async fn fetch_top_crates(&self) -> Result<HashMap<String, u64>> {
+        let mut crates = HashMap::new();
+        let pages = TOP_CRATES_COUNT.div_ceil(TOP_CRATES_PAGE_SIZE);
+
+        for page in 1..=pages {
+            let url = format!(
+                "{}/crates?sort=downloads&per_page={}&page={}",
+                self.metadata_api, TOP_CRATES_PAGE_SIZE, page
+            );
+            self.pace_metadata_api().await;
+            let response = network::send(self.client.get(&url))
+                .await?
+                .error_for_status()?;
+            let body: serde_json::Value = response.json().await?;
+
+            for entry in body["crates"].as_array().into_iter().flatten() {
+                if let (Some(name), Some(downloads)) =
+                    (entry["name"].as_str(), entry["downloads"].as_u64())
+                {
+                    crates.insert(name.to_string(), downloads);
+                }
+            }
+        }
+
+        if crates.is_empty() {
+            bail!("crates.io returned no crates");
+        }
+        Ok(crates)
     }
 
     pub async fn scan_dependencies<T: LlmClientTrait>(
         &self,
         project_path: &Path,
-        llm_client: &T,
-    ) -> Result<Vec<DependencyAnalysisResult>> {
-        println!("🔍 Scanning dependencies for supply chain security...");
+        llm_client: Option<&T>,
+    ) -> Result<DependencyScan> {
+        info!("🔍 Scanning dependencies for supply chain security...");
 
         // Get cargo metadata
         let metadata = self.get_cargo_metadata(project_path)?;
+        let graph = DependencyGraph::from_metadata(&metadata);
         let mut results = Vec::new();
 
+        // Skip the workspace packages (focus on external dependencies)
+        let workspace_package_ids: Vec<_> = metadata
+            .workspace_packages()
+            .into_iter()
+            .map(|wp| &wp.id)
+            .collect();
+        let depth_of = |package: &Package| {
+            graph.depth(&package_key(&package.name, &package.version.to_string()))
+        };
+        let mut packages: Vec<&Package> = metadata
+            .packages
+            .iter()
+            .filter(|package| !workspace_package_ids.contains(&&package.id))
+            .filter(|package| {
+                self.only_packages.as_ref().is_none_or(|only| {
+                    only.contains(&package_key(&package.name, &package.version.to_string()))
+                })
+            })
+            .filter(|package| {
+                self.depth == DependencyDepth::Transitive || depth_of(package) == Some(1)
+            })
+            .collect();
+        // Suspicious packages, then the nearest ones, come first, so limits
+        // cut the deepest dependencies
+        packages.sort_by_cached_key(|package| {
+            (
+                !self.wants_llm_analysis(package),
+                depth_of(package).unwrap_or(usize::MAX),
+                package.name.clone(),
+                package.version.clone(),
+            )
+        });
+        let mut skipped = Vec::new();
+        if let Some(max_packages) = self.max_packages {
+            if packages.len() > max_packages {
+                skipped = packages
+                    .split_off(max_packages)
+                    .into_iter()
+                    .map(|package| package_key(&package.name, &package.version.to_string()))
+                    .collect();
+                warn!(
+                    "⚠️  Scanning {} of {} dependencies (max_packages = {})",
+                    max_packages,
+                    max_packages + skipped.len(),
+                    max_packages
+                );
+            }
+        }
+
         // Filter and prioritize dependencies for analysis
         let mut dependencies_to_analyze = Vec::new();
         let mut low_priority_deps = Vec::new();
 
-        for package in &metadata.packages {
-            // Skip the workspace packages (focus on external dependencies)
-            let workspace_package_ids: Vec<_> = metadata
-                .workspace_packages()
-                .into_iter()
-                .map(|wp| &wp.id)
-                .collect();
-            if workspace_package_ids.contains(&&package.id) {
-                continue;
-            }
-
+        for package in packages {
             // Prioritize suspicious packages for LLM analysis
-            if self.should_analyze_with_llm(&package.name) {
+            if llm_client.is_some() && self.wants_llm_analysis(package) {
                 dependencies_to_analyze.push(package);
             } else {
                 low_priority_deps.push(package);
             }
         }
 
-        println!(
+        info!(
             "📊 Found {} dependencies ({} high-priority for deep analysis)",
             dependencies_to_analyze.len() + low_priority_deps.len(),
             dependencies_to_analyze.len()
         );
 
-        // Analyze high-priority dependencies with LLM (with rate limiting)
-        for (i, package) in dependencies_to_analyze.iter().enumerate() {
-            println!(
-                "   🔍 Deep analysis [{}/{}]: {} v{}",
-                i + 1,
-                dependencies_to_analyze.len(),
-                package.name,
-                package.version
-            );
-
-            let analysis = self.analyze_dependency(package, llm_client).await?;
-            results.push(analysis);
-
-            // Rate limiting: sleep between requests to avoid quota issues
-            if i < dependencies_to_analyze.len() - 1 {
-                tokio::time::sleep(Duration::from_millis(4000)).await; // 4 second delay
+        let total = dependencies_to_analyze.len() + low_priority_deps.len();
+        let bar = progress::dependency_bar(total);
+        let mut eta = EtaTracker::new(dependencies_to_analyze.len())
+            .with_request_interval(self.rate_limiter.interval());
+
+        // Metadata lookups and downloads run concurrently; the LLM requests of
+        // high-priority dependencies wait for the rate limiter
+        let deep_count = dependencies_to_analyze.len();
+        let deep = dependencies_to_analyze
+            .into_iter()
+            .enumerate()
+            .map(|(i, package)| (package, llm_client.map(|llm_client| (i, llm_client))));
+        let quick = low_priority_deps.into_iter().map(|package| (package, None));
+        let mut analyses = stream::iter(deep.chain(quick))
+            .map(|(package, llm)| async move {
+                match llm {
+                    Some((i, llm_client)) => {
+                        info!(
+                            "   🔍 Deep analysis [{}/{}]: {} v{}",
+                            i + 1,
+                            deep_count,
+                            package.name,
+                            package.version
+                        );
+                        self.analyze_dependency(package, llm_client)
+                            .await
+                            .map(|(analysis, cached)| (analysis, Some(cached)))
+                    }
+                    None => {
+                        info!("   📦 Quick scan: {} v{}", package.name, package.version);
+                        self.analyze_dependency_light(package)
+                            .await
+                            .map(|analysis| (analysis, None))
+                    }
+                }
+            })
+            .buffer_unordered(DEPENDENCY_CONCURRENCY);
+
+        // Time between completions, since analyses overlap
+        let mut last_deep = Instant::now();
+        while let Some(analysis) = analyses.next().await {
+            let (analysis, cached) = analysis?;
+            if let Some(cached) = cached {
+                eta.record(cached, last_deep.elapsed());
+                last_deep = Instant::now();
+                progress::show_eta(&bar, &eta);
             }
-        }
-
-        // Analyze low-priority dependencies without LLM (metadata only)
-        for package in low_priority_deps {
-            println!("   📦 Quick scan: {} v{}", package.name, package.version);
-            let analysis = self.analyze_dependency_light(package).await?;
+            emit_analyzed(&analysis, results.len() + 1, total);
             results.push(analysis);
+            bar.inc(1);
         }
+        bar.finish_and_clear();
+
+        // Place each package in the dependency tree and let risky transitive
+        // dependencies raise the score of the packages that pull them in
+        graph.annotate(&mut results);
+        dependency_graph::propagate_risk(&mut results);
 
         // Sort by risk score for reporting
         results.sort_by(|a, b| self.compare_risk_scores(&a.risk_score, &b.risk_score));
 
+        Ok(DependencyScan { results, skipped })
+    }
+
+    /// Counts an uncached LLM request against `max_llm_calls`; false once the
+    /// limit or the scan's budget is reached.
+    fn take_llm_call(&self) -> bool {
+        if self.budget.is_spent() {
+            return false;
+        }
+        let Some(max_llm_calls) = self.max_llm_calls else {
+            return true;
+        };
+        let calls = self.llm_calls.fetch_add(1, Ordering::Relaxed);
+        if calls == max_llm_calls {
+            warn!(
+                "⚠️  Reached max_llm_calls ({}); remaining dependencies get metadata checks only",
+                max_llm_calls
+            );
+        }
+        calls < max_llm_calls
+    }
+
+    /// Lockfile-only scan: no source tree, no LLM. Registry, typosquatting and
+    /// known-malicious checks run concurrently for every external package.
+    pub async fn scan_lockfile_fast(
+        &self,
+        packages: &[LockedPackage],
+    ) -> Result<Vec<DependencyAnalysisResult>> {
+        let external: Vec<&LockedPackage> = packages.iter().filter(|p| p.is_external()).collect();
+
+        let mut results: Vec<DependencyAnalysisResult> = stream::iter(external)
+            .map(|package| self.analyze_locked_package(package))
+            .buffer_unordered(DEPENDENCY_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_>>()?;
+
+        results.sort_by(|a, b| {
+            self.compare_risk_scores(&a.risk_score, &b.risk_score)
+                .then_with(|| a.package_name.cmp(&b.package_name))
+        });
+
         Ok(results)
     }
 
+    async fn analyze_locked_package(
+        &self,
+        package: &LockedPackage,
+    ) -> Result<DependencyAnalysisResult> {
+        let mut metadata_flags = Vec::new();
+
+        if self.known_malicious.contains(&package.name) {
+            metadata_flags.push(MetadataFlag {
+                flag_type: MetadataFlagType::KnownMalicious,
+                description: format!("'{}' is on the known malicious package list", package.name),
+                severity: "Critical".to_string(),
+            });
+        }
+
+        metadata_flags.extend(self.typosquatting_flag(&package.name));
+        metadata_flags.extend(self.vulnerability_flags(&package.name, &package.version));
+
+        let source = self.source_from_str(package.source.clone());
+        metadata_flags.extend(
+            self.crates_io_flags(&source, &package.name, &package.version)
+                .await,
+        );
+        metadata_flags.extend(self.source_flag(&source));
+        if let (DependencySource::CratesIo { .. }, Some(expected)) = (&source, &package.checksum) {
+            let mismatch = checksum::verify_cached(&package.name, &package.version, expected);
+            metadata_flags.extend(mismatch.map(|mismatch| checksum_flag(&mismatch, expected)));
+        }
+
+        self.apply_severity_overrides(&mut metadata_flags);
+        let risk_score = self.calculate_risk_score(&metadata_flags, &[]);
+
+        Ok(DependencyAnalysisResult {
+            package_name: package.name.clone(),
+            version: package.version.clone(),
+            source,
+            risk_score,
+            suspicious_patterns: Vec::new(),
+            metadata_flags,
+            code_analysis: None,
+            analysis_version: None,
+            depth: 0,
+            dependents: Vec::new(),
+            dependencies: Vec::new(),
+            elevated_by: None,
+            vulnerabilities: self.vulnerabilities_for(&package.name, &package.version),
+            unsafe_metrics: None,
+            network_indicators: Vec::new(),
+            source_truncation: None,
+            native_footprint: None,
+        })
+    }
+
     fn get_cargo_metadata(&self, project_path: &Path) -> Result<Metadata> {
         let mut cmd = MetadataCommand::new();
         cmd.manifest_path(project_path.join("Cargo.toml"));
+        if self.offline {
+            cmd.other_options(vec!["--offline".to_string()]);
+        }
         cmd.exec()
             .map_err(|e| anyhow::anyhow!("Failed to get cargo metadata: {}", e))
     }
 
+    /// Whether `package` is analyzed with the LLM: by name, or as a `-sys`
+    /// crate under `native_review`.
+    fn wants_llm_analysis(&self, package: &Package) -> bool {
+        self.should_analyze_with_llm(&package.name)
+            || (self.native_review && native_code::is_sys_crate(package))
+    }
+
     fn should_analyze_with_llm(&self, package_name: &str) -> bool {
+        if self.deep_analysis {
+            return true;
+        }
+
         // Skip trusted packages to save API calls
         if self.trusted_packages.contains(package_name) {
             return false;
@@ -210,22 +903,82 @@ impl DependencyScanner {
         }
 
         // Analyze packages with suspicious patterns in name
+        if self.suspicious_name_pattern(package_name).is_some() {
+            return true;
+        }
+
+        // For now, limit deep analysis to reduce API calls
+        // In production, you might want more sophisticated filtering
+        false
+    }
+
+    fn suspicious_name_pattern(&self, package_name: &str) -> Option<&'static str> {
         let suspicious_patterns = [
             "steal", "hack", "backdoor", "malware", "virus", "trojan", "keylog", "password",
             "credit", "bank", "wallet", "bitcoin", "mining", "miner", "crypto", "shell", "reverse",
             "payload",
         ];
 
-        if suspicious_patterns
-            .iter()
-            .any(|&pattern| package_name.contains(pattern))
-        {
-            return true;
+        suspicious_patterns
+            .into_iter()
+            .find(|&pattern| package_name.contains(pattern))
+    }
+
+    /// Runs the name and registry checks for a crate that is not yet a dependency.
+    pub async fn check_crate_name(&self, crate_name: &str) -> Result<NameCheckResult> {
+        let mut flags = Vec::new();
+
+        if self.known_malicious.contains(crate_name) {
+            flags.push(MetadataFlag {
+                flag_type: MetadataFlagType::KnownMalicious,
+                description: format!("'{}' is on the known malicious package list", crate_name),
+                severity: "Critical".to_string(),
+            });
         }
 
-        // For now, limit deep analysis to reduce API calls
-        // In production, you might want more sophisticated filtering
-        false
+        flags.extend(self.typosquatting_flag(crate_name));
+
+        if let Some(pattern) = self.suspicious_name_pattern(crate_name) {
+            flags.push(MetadataFlag {
+                flag_type: MetadataFlagType::SuspiciousName,
+                description: format!("Package name contains suspicious term '{}'", pattern),
+                severity: "Medium".to_string(),
+            });
+        }
+
+        let metadata = match self.fetch_crates_io_metadata(crate_name).await {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                flags.push(lookup_failed_flag("the crate on crates.io", &e));
+                None
+            }
+        };
+        if let Some(metadata) = &metadata {
+            flags.extend(self.registry_metadata_flags(metadata));
+            flags.extend(self.maintenance_flags(metadata).await);
+        }
+
+        self.apply_severity_overrides(&mut flags);
+        let risk_score = self.calculate_risk_score(&flags, &[]);
+
+        Ok(NameCheckResult {
+            crate_name: crate_name.to_string(),
+            exists_on_crates_io: metadata.is_some(),
+            risk_score,
+            metadata_flags: flags,
+            closest_popular: self.closest_popular_packages(crate_name, 3),
+        })
+    }
+
+    fn closest_popular_packages(&self, package_name: &str, limit: usize) -> Vec<(String, usize)> {
+        let mut candidates: Vec<(String, usize)> = self
+            .popular_packages
+            .keys()
+            .map(|name| (name.clone(), levenshtein_distance(package_name, name)))
+            .collect();
+        candidates.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        candidates.truncate(limit);
+        candidates
     }
 
     async fn analyze_dependency_light(
@@ -234,7 +987,8 @@ impl DependencyScanner {
     ) -> Result<DependencyAnalysisResult> {
         // Quick analysis without LLM - just metadata checks
         let source = self.determine_dependency_source(package);
-        let metadata_flags = self.analyze_package_metadata(package).await?;
+        let (metadata_flags, unsafe_metrics, native_footprint) =
+            self.analyze_package_metadata(package).await?;
         let risk_score = self.calculate_risk_score(&metadata_flags, &[]);
 
         Ok(DependencyAnalysisResult {
@@ -244,57 +998,100 @@ impl DependencyScanner {
             risk_score,
             suspicious_patterns: Vec::new(),
             metadata_flags,
-            code_analysis: Some(
-                "Quick scan - no deep code analysis performed for trusted package".to_string(),
-            ),
+            code_analysis: Some("Quick scan - no deep code analysis performed".to_string()),
+            analysis_version: None,
+            depth: 0,
+            dependents: Vec::new(),
+            dependencies: Vec::new(),
+            elevated_by: None,
+            vulnerabilities: self.vulnerabilities_for(&package.name, &package.version.to_string()),
+            unsafe_metrics,
+            network_indicators: package_indicators(package),
+            source_truncation: None,
+            native_footprint,
         })
     }
 
+    /// Full analysis with the LLM; also returns whether the LLM answer came from the cache.
     async fn analyze_dependency<T: LlmClientTrait>(
         &self,
         package: &Package,
         llm_client: &T,
-    ) -> Result<DependencyAnalysisResult> {
+    ) -> Result<(DependencyAnalysisResult, bool)> {
         // Determine dependency source
         let source = self.determine_dependency_source(package);
 
         // Check metadata for red flags
-        let metadata_flags = self.analyze_package_metadata(package).await?;
+        let (metadata_flags, unsafe_metrics, native_footprint) =
+            self.analyze_package_metadata(package).await?;
 
         // Download and analyze source code (with size limits)
-        let (code_analysis, suspicious_patterns) = if self.trusted_packages.contains(&package.name)
+        let mut cached = true;
+        let mut source_truncation = None;
+        let (code_analysis, suspicious_patterns, analysis_version) = if !self.deep_analysis
+            && self.trusted_packages.contains(&package.name)
         {
             // Skip LLM analysis for trusted packages to save API calls
             (
                 Some("Trusted package - skipped deep analysis".to_string()),
                 Vec::new(),
+                None,
             )
         } else {
-            match timeout(
-                Duration::from_secs(60),
-                self.download_and_analyze_source(package, llm_client),
-            )
-            .await
-            {
-                Ok(Ok(result)) => result,
-                Ok(Err(e)) => {
-                    println!(
-                        "   ⚠️  Could not analyze source for {}: {}",
-                        package.name, e
-                    );
-                    (Some(format!("Failed to analyze source: {}", e)), Vec::new())
+            let (request, truncation) = native_footprint
+                .as_ref()
+                .and_then(|footprint| self.native_analysis_request(package, footprint))
+                .unwrap_or_else(|| self.source_analysis_request(package));
+            // Cached answers do not reach the API, so they skip the queue
+            // and the call limit; the wait for a slot does not count
+            // towards the timeout
+            cached = llm_client.is_cached(&request);
+            if !cached && !self.take_llm_call() {
+                // Nothing was sent, so nothing to wait for either
+                cached = true;
+                self.incomplete.fetch_add(1, Ordering::Relaxed);
+                (
+                    Some("LLM call limit reached - no deep code analysis performed".to_string()),
+                    Vec::new(),
+                    None,
+                )
+            } else {
+                source_truncation = truncation;
+                if !cached {
+                    self.rate_limiter.acquire().await;
                 }
-                Err(_) => {
-                    println!("   ⏰ Analysis timeout for {}", package.name);
-                    (Some("Analysis timed out".to_string()), Vec::new())
+                // Room for the LLM client's retries of a failed request
+                let deadline = network::retry_policy().deadline(network::timeouts().llm);
+                match timeout(deadline, self.analyze_source(request, llm_client)).await {
+                    Ok(Ok(result)) => result,
+                    Ok(Err(e)) => {
+                        warn!(
+                            "   ⚠️  Could not analyze source for {}: {}",
+                            package.name, e
+                        );
+                        self.incomplete.fetch_add(1, Ordering::Relaxed);
+                        (
+                            Some(format!("Failed to analyze source: {}", e)),
+                            Vec::new(),
+                            None,
+                        )
+                    }
+                    Err(_) => {
+                        warn!("   ⏰ Analysis timeout for {}", package.name);
+                        self.incomplete.fetch_add(1, Ordering::Relaxed);
+                        (Some("Analysis timed out".to_string()), Vec::new(), None)
+                    }
                 }
             }
         };
 
+        let mut suspicious_patterns = suspicious_patterns;
+        severity::apply_to_patterns(&self.severity_overrides, &mut suspicious_patterns);
+
         // Calculate overall risk score
         let risk_score = self.calculate_risk_score(&metadata_flags, &suspicious_patterns);
 
-        Ok(DependencyAnalysisResult {
+        let analysis = DependencyAnalysisResult {
             package_name: package.name.clone(),
             version: package.version.to_string(),
             source,
@@ -302,20 +1099,48 @@ impl DependencyScanner {
             suspicious_patterns,
             metadata_flags,
             code_analysis,
-        })
+            analysis_version,
+            depth: 0,
+            dependents: Vec::new(),
+            dependencies: Vec::new(),
+            elevated_by: None,
+            vulnerabilities: self.vulnerabilities_for(&package.name, &package.version.to_string()),
+            unsafe_metrics,
+            network_indicators: package_indicators(package),
+            source_truncation,
+            native_footprint,
+        };
+        Ok((analysis, cached))
     }
 
     fn determine_dependency_source(&self, package: &Package) -> DependencySource {
-        if let Some(source) = &package.source {
-            let source_str = source.to_string();
-            if source_str.contains("registry+") {
-                DependencySource::CratesIo {
-                    registry_url: source_str,
+        self.source_from_str(package.source.as_ref().map(|s| s.to_string()))
+    }
+
+    /// Classifies a Cargo source ID such as `registry+https://...`,
+    /// `sparse+https://...` or `git+https://...?branch=main#<commit>`.
+    fn source_from_str(&self, source: Option<String>) -> DependencySource {
+        if let Some(source_str) = source {
+            if let Some(index) = source_str
+                .strip_prefix("registry+")
+                .or_else(|| source_str.strip_prefix("sparse+"))
+            {
+                if CRATES_IO_INDEXES.contains(&index) {
+                    DependencySource::CratesIo {
+                        registry_url: source_str,
+                    }
+                } else {
+                    DependencySource::Registry {
+                        registry_url: source_str,
+                    }
                 }
-            } else if source_str.contains("git+") {
+            } else if source_str.starts_with("git+") {
+                let rev = source_str
+                    .split_once('#')
+                    .map(|(_, commit)| commit.to_string());
                 DependencySource::Git {
                     repository: source_str,
-                    rev: None, // Could be extracted from source string
+                    rev,
                 }
             } else {
                 DependencySource::Unknown
@@ -328,81 +1153,447 @@ impl DependencyScanner {
         }
     }
 
-    async fn analyze_package_metadata(&self, package: &Package) -> Result<Vec<MetadataFlag>> {
+    /// Returns the metadata flags and, when the package source is on disk, its `unsafe` counts.
+    async fn analyze_package_metadata(
+        &self,
+        package: &Package,
+    ) -> Result<(
+        Vec<MetadataFlag>,
+        Option<UnsafeMetrics>,
+        Option<NativeFootprint>,
+    )> {
         let mut flags = Vec::new();
 
         // Check for typosquatting
-        if let Some(similar_package) = self.check_typosquatting(&package.name) {
-            flags.push(MetadataFlag {
+        flags.extend(self.typosquatting_flag(&package.name));
+
+        // Known vulnerabilities loaded from OSV
+        flags.extend(self.vulnerability_flags(&package.name, &package.version.to_string()));
+
+        // Recent publication (potential 0-day), yanks, maintenance and owners
+        let source = self.determine_dependency_source(package);
+        let version = package.version.to_string();
+        flags.extend(self.crates_io_flags(&source, &package.name, &version).await);
+
+        if self.verify_sources && !self.offline {
+            if let DependencySource::CratesIo { .. } = source {
+                flags.extend(self.source_mismatch_flag(package).await);
+            }
+        }
+
+        flags.extend(self.source_flag(&source));
+
+        // Analyze dependencies for suspicious patterns
+        self.analyze_dependency_tree(package, &mut flags);
+
+        let unsafe_metrics = package
+            .manifest_path
+            .parent()
+            .and_then(|dir| UnsafeMetrics::scan_dir(dir.as_std_path()).ok());
+        flags.extend(unsafe_metrics.as_ref().and_then(unsafe_flag));
+
+        let native_footprint = NativeFootprint::of(package);
+        flags.extend(native_footprint.as_ref().and_then(native_flag));
+
+        // Binaries and packed scripts in the downloaded package that its .rs files do not show
+        if let Some(dir) = package.manifest_path.parent() {
+            flags.extend(artifact_flags(&package.name, dir.as_std_path()));
+        }
+
+        let key = dependency_graph::package_key(&package.name, &package.version.to_string());
+        if let (Some(expected), Some(dir)) =
+            (self.checksums.get(&key), package.manifest_path.parent())
+        {
+            let mismatch = checksum::verify_unpacked(dir.as_std_path(), expected);
+            flags.extend(mismatch.map(|mismatch| checksum_flag(&mismatch, expected)));
+        }
+
+        self.apply_severity_overrides(&mut flags);
+        Ok((flags, unsafe_metrics, native_footprint))
+    }
+
+    /// Flags a package from a registry other than crates.io, or from a git
+    /// repository, that is not among the approved sources.
+    fn source_flag(&self, source: &DependencySource) -> Option<MetadataFlag> {
+        let (url, severity, what) = match source {
+            DependencySource::Registry { registry_url } => (
+                registry_url
+                    .split_once('+')
+                    .map_or(registry_url.as_str(), |(_, url)| url),
+                "High",
+                "an alternate registry",
+            ),
+            DependencySource::Git { repository, .. } => (
+                repository
+                    .trim_start_matches("git+")
+                    .split(['?', '#'])
+                    .next()
+                    .unwrap_or_default(),
+                "Medium",
+                "a git repository",
+            ),
+            _ => return None,
+        };
+        if self
+            .approved_sources
+            .iter()
+            .any(|approved| source_matches(url, approved))
+        {
+            return None;
+        }
+        Some(MetadataFlag {
+            flag_type: MetadataFlagType::UnapprovedSource,
+            description: format!(
+                "Resolved from {} that is not an approved source: {}",
+                what, url
+            ),
+            severity: severity.to_string(),
+        })
+    }
+
+    fn typosquatting_flag(&self, package_name: &str) -> Option<MetadataFlag> {
+        self.check_typosquatting(package_name)
+            .map(|typosquat_match| MetadataFlag {
                 flag_type: MetadataFlagType::Typosquatting,
                 description: format!(
-                    "Package name '{}' is similar to popular package '{}'",
-                    package.name, similar_package
+                    "Package name '{}' is similar to popular package '{}' ({})",
+                    package_name,
+                    typosquat_match.popular_name,
+                    typosquat_match.technique.describe()
                 ),
                 severity: "High".to_string(),
+            })
+    }
+
+    fn registry_metadata_flags(&self, metadata: &serde_json::Value) -> Vec<MetadataFlag> {
+        let mut flags = Vec::new();
+
+        // Check publication date
+        if self.is_recently_published(metadata) {
+            flags.push(MetadataFlag {
+                flag_type: MetadataFlagType::RecentPublication,
+                description: "Package was published very recently, could be a 0-day injection"
+                    .to_string(),
+                severity: "Medium".to_string(),
             });
         }
 
-        // Check if package was published recently (potential 0-day)
-        if let Some(metadata) = self.fetch_crates_io_metadata(&package.name).await? {
-            // Check publication date
-            if self.is_recently_published(&metadata) {
-                flags.push(MetadataFlag {
-                    flag_type: MetadataFlagType::RecentPublication,
-                    description: "Package was published very recently, could be a 0-day injection"
-                        .to_string(),
-                    severity: "Medium".to_string(),
-                });
+        // Check download count, which a local index does not have
+        if self.metadata_index.is_none() && self.has_low_downloads(metadata) {
+            flags.push(MetadataFlag {
+                flag_type: MetadataFlagType::LowDownloads,
+                description: "Package has unusually low download count for its age".to_string(),
+                severity: "Low".to_string(),
+            });
+        }
+
+        flags
+    }
+
+    /// The checks of a package's crates.io metadata. Git, path and
+    /// alternate-registry packages are skipped: a crates.io crate of the same
+    /// name is a different crate.
+    async fn crates_io_flags(
+        &self,
+        source: &DependencySource,
+        name: &str,
+        version: &str,
+    ) -> Vec<MetadataFlag> {
+        if !matches!(source, DependencySource::CratesIo { .. }) {
+            return Vec::new();
+        }
+        let metadata = match self.fetch_crates_io_metadata(name).await {
+            Ok(Some(metadata)) => metadata,
+            Ok(None) => return Vec::new(),
+            Err(e) => return vec![lookup_failed_flag("the crate on crates.io", &e)],
+        };
+        let mut flags = self.registry_metadata_flags(&metadata);
+        flags.extend(self.yanked_flag(&metadata, version));
+        flags.extend(self.maintenance_flags(&metadata).await);
+        flags.extend(self.author_flags(&metadata, version).await);
+        flags
+    }
+
+    /// Flags a locked version that has since been yanked from crates.io.
+    fn yanked_flag(&self, metadata: &serde_json::Value, version: &str) -> Option<MetadataFlag> {
+        let yanked = metadata["versions"]
+            .as_array()?
+            .iter()
+            .find(|v| v["num"].as_str() == Some(version))?["yanked"]
+            .as_bool()?;
+        yanked.then(|| MetadataFlag {
+            flag_type: MetadataFlagType::YankedVersion,
+            description: format!(
+                "Version {} has been yanked from crates.io; it may contain a serious bug or a compromise",
+                version
+            ),
+            severity: "High".to_string(),
+        })
+    }
+
+    /// Flags crates that look abandoned: no release for two years and either an
+    /// archived repository or no repository activity in the last year.
+    async fn maintenance_flags(&self, metadata: &serde_json::Value) -> Vec<MetadataFlag> {
+        let Some(release_age) = days_since_last_release(metadata) else {
+            return Vec::new();
+        };
+        if release_age < UNMAINTAINED_AFTER_DAYS {
+            return Vec::new();
+        }
+
+        let activity = match metadata["crate"]["repository"].as_str() {
+            Some(repository) => self.fetch_repository_activity(repository).await,
+            None => None,
+        };
+        let (severity, detail) = match activity {
+            Some(activity) if activity.archived => {
+                ("Medium", "its repository is archived".to_string())
             }
+            Some(activity) if activity.days_since_push < REPOSITORY_ACTIVE_DAYS => {
+                return Vec::new();
+            }
+            Some(activity) => (
+                "Low",
+                format!(
+                    "its repository was last pushed {} days ago with {} open issues",
+                    activity.days_since_push, activity.open_issues
+                ),
+            ),
+            None => ("Low", "repository activity is unknown".to_string()),
+        };
 
-            // Check download count
-            if self.has_low_downloads(&metadata) {
+        vec![MetadataFlag {
+            flag_type: MetadataFlagType::Unmaintained,
+            description: format!(
+                "No release in {} days and {}; the package may be abandoned",
+                release_age, detail
+            ),
+            severity: severity.to_string(),
+        }]
+    }
+
+    /// Flags a version published right after the crate changed hands, a crate
+    /// whose current owners are all new, and a publisher with no other crates.
+    async fn author_flags(&self, metadata: &serde_json::Value, version: &str) -> Vec<MetadataFlag> {
+        let Some(name) = metadata["crate"]["name"].as_str() else {
+            return Vec::new();
+        };
+        let mut failed = Vec::new();
+        let owners = self
+            .fetch_crates_io_json(&format!("crates/{}/owners", name))
+            .await
+            .unwrap_or_else(|e| {
+                failed.push(lookup_failed_flag("the crate's owners", &e));
+                None
+            });
+        let owners: Option<Vec<&str>> = owners.as_ref().and_then(|owners| {
+            let owners = owners["users"].as_array()?;
+            // Team membership is not visible, so a team owner could be anyone
+            if owners.iter().any(|owner| owner["kind"] != "user") {
+                return None;
+            }
+            owners.iter().map(|owner| owner["login"].as_str()).collect()
+        });
+        let mut flags = publisher_flags(metadata, version, owners.as_deref(), chrono::Utc::now());
+
+        let publisher = metadata["versions"]
+            .as_array()
+            .and_then(|versions| versions.iter().find(|v| v["num"] == version))
+            .map(|v| &v["published_by"]);
+        if let Some((id, login)) =
+            publisher.and_then(|p| Some((p["id"].as_u64()?, p["login"].as_str()?)))
+        {
+            let crates = self
+                .fetch_crates_io_json(&format!("crates?user_id={}&per_page=1", id))
+                .await
+                .unwrap_or_else(|e| {
+                    failed.push(lookup_failed_flag(
+                        &format!("the crates of `{}`", login),
+                        &e,
+                    ));
+                    None
+                })
+                .and_then(|crates| crates["meta"]["total"].as_u64());
+            if crates.is_some_and(|total| total <= 1) {
                 flags.push(MetadataFlag {
-                    flag_type: MetadataFlagType::LowDownloads,
-                    description: "Package has unusually low download count for its age".to_string(),
+                    flag_type: MetadataFlagType::SuspiciousAuthor,
+                    description: format!(
+                        "Version {} was published by `{}`, who owns no other crates",
+                        version, login
+                    ),
                     severity: "Low".to_string(),
                 });
             }
         }
+        flags.extend(failed);
+        flags
+    }
 
-        // Analyze dependencies for suspicious patterns
-        self.analyze_dependency_tree(package, &mut flags);
+    /// GETs `{metadata_api}/{path}`, or reuses the response saved by an earlier
+    /// scan while it is younger than `metadata_cache_hours`. Offline, or when
+    /// the request fails, a saved response of any age is used. `None` with a
+    /// local index, offline without a saved response, and for a 404; an error
+    /// when the request fails and nothing is saved.
+    async fn fetch_crates_io_json(&self, path: &str) -> Result<Option<serde_json::Value>> {
+        if self.metadata_index.is_some() {
+            return Ok(None);
+        }
+        let url = format!("{}/{}", self.metadata_api, path);
+        let saved = self.saved_response(&url);
+        let body = match saved {
+            Some(saved) if self.offline || saved.is_fresh(self.metadata_cache_hours as i64) => {
+                saved.body
+            }
+            _ if self.offline => return Ok(None),
+            saved => {
+                // A saved copy past its age is revalidated: crates.io answers
+                // an unchanged resource with an empty 304
+                let validators = saved.as_ref().map(|saved| &saved.validators);
+                self.pace_metadata_api().await;
+                let request = network::conditional_get(&self.client, &url, validators);
+                match (request.await, saved) {
+                    (Ok(Revalidated::Modified { body, validators }), _) => {
+                        self.save_response(&url, &body, &validators);
+                        body
+                    }
+                    (Ok(Revalidated::NotModified), saved) => {
+                        let saved = saved.context("Got 304 Not Modified with nothing saved")?;
+                        self.save_response(&url, &saved.body, &saved.validators);
+                        saved.body
+                    }
+                    (Err(_), Some(saved)) => saved.body,
+                    (Err(e), None) if e.status() == Some(StatusCode::NOT_FOUND) => return Ok(None),
+                    (Err(e), None) => return Err(e).context(format!("GET {} failed", url)),
+                }
+            }
+        };
+        serde_json::from_str(&body)
+            .map(Some)
+            .with_context(|| format!("Invalid response from {}", url))
+    }
+
+    /// Waits for the next crates.io API slot shared by every scan in the
+    /// process. A `metadata_url` mirror is not paced.
+    async fn pace_metadata_api(&self) {
+        if self.metadata_api == CRATES_IO_API {
+            metadata_limiter().acquire().await;
+        }
+    }
+
+    fn saved_response(&self, url: &str) -> Option<SavedResponse> {
+        if self.metadata_cache_hours == 0 {
+            return None;
+        }
+        let db = self.metadata_cache.as_ref()?.lock().ok()?;
+        db.load_registry_response(url).unwrap_or_else(|e| {
+            debug!("Could not read the saved response for {}: {}", url, e);
+            None
+        })
+    }
 
-        Ok(flags)
+    fn save_response(&self, url: &str, body: &str, validators: &Validators) {
+        if self.metadata_cache_hours == 0 {
+            return;
+        }
+        let Some(Ok(db)) = self.metadata_cache.as_ref().map(|db| db.lock()) else {
+            return;
+        };
+        if let Err(e) = db.save_registry_response(url, body, validators) {
+            debug!("Could not save the response for {}: {}", url, e);
+        }
     }
 
-    fn check_typosquatting(&self, package_name: &str) -> Option<String> {
-        for popular_name in self.popular_packages.keys() {
-            if self.is_similar_name(package_name, popular_name) && package_name != popular_name {
-                return Some(popular_name.clone());
+    /// Looks up a GitHub repository's archive status and last push. Other hosts
+    /// and failed lookups return `None`.
+    async fn fetch_repository_activity(&self, repository: &str) -> Option<RepositoryActivity> {
+        if self.offline {
+            return None;
+        }
+        let path = repository
+            .trim_end_matches('/')
+            .strip_prefix("https://github.com/")?;
+        let mut parts = path.split('/');
+        let owner = parts.next()?;
+        let repo = parts.next()?.trim_end_matches(".git");
+        let url = format!("https://api.github.com/repos/{}/{}", owner, repo);
+
+        metadata_limiter().acquire().await;
+        let response = network::send(self.client.get(&url)).await.ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let body: serde_json::Value = response.json().await.ok()?;
+        let pushed_at = chrono::DateTime::parse_from_rfc3339(body["pushed_at"].as_str()?).ok()?;
+
+        Some(RepositoryActivity {
+            archived: body["archived"].as_bool().unwrap_or(false),
+            days_since_push: chrono::Utc::now()
+                .signed_duration_since(pushed_at)
+                .num_days(),
+            open_issues: body["open_issues_count"].as_u64().unwrap_or(0),
+        })
+    }
+
+    /// Flags a package whose published code differs from its claimed repository,
+    /// a common way to ship a backdoor that never appears in the public source.
+    async fn source_mismatch_flag(&self, package: &Package) -> Option<MetadataFlag> {
+        let Some(repository) = &package.repository else {
+            return Some(MetadataFlag {
+                flag_type: MetadataFlagType::SourceMismatch,
+                description: "No repository is declared, so the published code cannot be verified"
+                    .to_string(),
+                severity: "Low".to_string(),
+            });
+        };
+
+        let version = package.version.to_string();
+        let diff = match timeout(
+            Duration::from_secs(120),
+            source_verify::verify_package(&self.client, &package.name, &version, repository),
+        )
+        .await
+        {
+            Ok(Ok(diff)) => diff,
+            Ok(Err(e)) => {
+                warn!(
+                    "   ⚠️  Could not verify source of {} v{}: {:#}",
+                    package.name, version, e
+                );
+                return None;
+            }
+            Err(_) => {
+                warn!("   ⏰ Source verification timeout for {}", package.name);
+                return None;
             }
+        };
+
+        if diff.is_clean() {
+            return None;
         }
-        None
+        Some(MetadataFlag {
+            flag_type: MetadataFlagType::SourceMismatch,
+            description: format!(
+                "Published crate differs from {}: {}",
+                repository,
+                diff.summary()
+            ),
+            severity: if diff.touches_code() { "High" } else { "Low" }.to_string(),
+        })
     }
 
-    fn is_similar_name(&self, name1: &str, name2: &str) -> bool {
-        // Simple similarity check - could be enhanced with more sophisticated algorithms
-        let distance = levenshtein_distance(name1, name2);
-        distance <= 2 && distance > 0
+    fn check_typosquatting(&self, package_name: &str) -> Option<TyposquatMatch> {
+        typosquat::find_best_match(package_name, self.popular_packages.keys())
     }
 
     async fn fetch_crates_io_metadata(
         &self,
         package_name: &str,
     ) -> Result<Option<serde_json::Value>> {
-        let url = format!("https://crates.io/api/v1/crates/{}", package_name);
-
-        match timeout(Duration::from_secs(10), self.client.get(&url).send()).await {
-            Ok(Ok(response)) => {
-                if response.status().is_success() {
-                    let metadata = response.json::<serde_json::Value>().await?;
-                    Ok(Some(metadata))
-                } else {
-                    Ok(None)
-                }
-            }
-            _ => Ok(None), // Timeout or error - don't fail the entire scan
+        if let Some(index) = &self.metadata_index {
+            return Ok(crates_index::metadata(index, package_name));
         }
+        self.fetch_crates_io_json(&format!("crates/{}", package_name))
+            .await
     }
 
     fn is_recently_published(&self, metadata: &serde_json::Value) -> bool {
@@ -465,11 +1656,10 @@ impl DependencyScanner {
         }
     }
 
-    async fn download_and_analyze_source<T: LlmClientTrait>(
-        &self,
-        package: &Package,
-        llm_client: &T,
-    ) -> Result<(Option<String>, Vec<FlaggedPattern>)> {
+    /// The LLM request for a package, and how much of its source was left out
+    /// to fit the context budget. A package gets a single request, so its
+    /// source is truncated rather than chunked.
+    fn source_analysis_request(&self, package: &Package) -> (LlmRequest, Option<Truncation>) {
         // For now, we'll analyze the package's lib.rs or main.rs if accessible
         // In a full implementation, we'd download the crate source from crates.io
 
@@ -492,26 +1682,101 @@ Provide analysis and flag any suspicious patterns with line numbers if possible.
             package.name,
             package.version,
             package.dependencies.iter()
-                .map(|d| format!("{}", d.name))
+                .map(|d| d.name.clone())
                 .collect::<Vec<_>>()
                 .join(", ")
         );
+        let (analysis_prompt, truncation) = if self.deep_analysis {
+            let source = self.read_package_source(package);
+            let (excerpt, truncation) = self.context_budget.truncate(&source);
+            (
+                format!("{}\n\nSource excerpts:\n{}", analysis_prompt, excerpt),
+                truncation,
+            )
+        } else {
+            (analysis_prompt, None)
+        };
 
         let request = LlmRequest {
+            cache_key: Some(CacheKey::new(
+                &package.name,
+                &package.version.to_string(),
+                &analysis_prompt,
+            )),
             prompt: analysis_prompt,
+            language: Language::Rust,
+        };
+        (request, truncation)
+    }
+
+    /// The LLM request for the build script and a sample of the C/C++ of a
+    /// `-sys` crate under `native_review`; `None` when it has neither.
+    fn native_analysis_request(
+        &self,
+        package: &Package,
+        footprint: &NativeFootprint,
+    ) -> Option<(LlmRequest, Option<Truncation>)> {
+        if !self.native_review {
+            return None;
+        }
+        let source = native_code::review_source(package.manifest_path.parent()?.as_std_path());
+        if source.is_empty() {
+            return None;
+        }
+        let (excerpt, truncation) = self.context_budget.truncate(&source);
+        let prompt = format!(
+            "Package: {} v{}\nNative footprint: {}\n\n{}",
+            package.name,
+            package.version,
+            footprint.describe(),
+            excerpt
+        );
+        let request = LlmRequest {
+            cache_key: Some(CacheKey::new(
+                &package.name,
+                &package.version.to_string(),
+                &prompt,
+            )),
+            prompt,
+            language: Language::Native,
         };
+        Some((request, truncation))
+    }
 
-        match timeout(Duration::from_secs(45), llm_client.analyze_code(request)).await {
-            Ok(Ok(response)) => Ok((Some(response.analysis), response.flagged_patterns)),
-            Ok(Err(e)) => {
+    async fn analyze_source<T: LlmClientTrait>(
+        &self,
+        request: LlmRequest,
+        llm_client: &T,
+    ) -> Result<(Option<String>, Vec<FlaggedPattern>, Option<AnalysisVersion>)> {
+        match llm_client.analyze_code(request).await {
+            Ok(response) => Ok((
+                Some(response.analysis),
+                response.flagged_patterns,
+                response.version,
+            )),
+            Err(e) => {
                 bail!("LLM analysis failed: {}", e)
             }
-            Err(_) => {
-                bail!("LLM analysis timed out")
-            }
         }
     }
 
+    /// Reads the entry points of an already-downloaded package, build script
+    /// first since it runs at compile time.
+    fn read_package_source(&self, package: &Package) -> String {
+        let Some(package_dir) = package.manifest_path.parent() else {
+            return String::new();
+        };
+
+        let mut source = String::new();
+        for file in ["build.rs", "src/lib.rs", "src/main.rs"] {
+            let Ok(content) = std::fs::read_to_string(package_dir.join(file)) else {
+                continue;
+            };
+            source.push_str(&format!("// file: {}\n{}\n", file, content));
+        }
+        source
+    }
+
     fn calculate_risk_score(
         &self,
         metadata_flags: &[MetadataFlag],
@@ -519,27 +1784,41 @@ Provide analysis and flag any suspicious patterns with line numbers if possible.
     ) -> RiskScore {
         let mut score = 0i32;
 
-        // Weight metadata flags
+        // Weight metadata flags; overridden ones count by their configured severity
         for flag in metadata_flags {
+            if self.severity_override(&flag.flag_type).is_some() {
+                score += severity::weight(&flag.severity);
+                continue;
+            }
             match flag.flag_type {
+                MetadataFlagType::KnownMalicious => score += 100,
+                MetadataFlagType::ChecksumMismatch => score += 100,
                 MetadataFlagType::Typosquatting => score += 50,
                 MetadataFlagType::ProcessExecution => score += 30,
                 MetadataFlagType::NetworkingCapabilities => score += 20,
+                MetadataFlagType::SuspiciousAuthor if flag.severity == "Low" => score += 10,
                 MetadataFlagType::SuspiciousAuthor => score += 40,
                 MetadataFlagType::RecentPublication => score += 15,
+                MetadataFlagType::SuspiciousName => score += 25,
                 MetadataFlagType::LowDownloads => score += 10,
+                MetadataFlagType::YankedVersion => score += 30,
+                MetadataFlagType::Unmaintained => score += 10,
+                MetadataFlagType::SourceMismatch => score += 40,
+                MetadataFlagType::KnownVulnerability => score += 35,
+                MetadataFlagType::BundledArtifact => score += 50,
+                MetadataFlagType::UnapprovedSource if flag.severity == "High" => score += 50,
+                MetadataFlagType::UnapprovedSource => score += 25,
+                MetadataFlagType::UnsafeCode if flag.severity == "Low" => score += 5,
+                MetadataFlagType::UnsafeCode => score += 15,
+                // Enough for Low, so a package is not called clean unchecked
+                MetadataFlagType::LookupFailed => score += 10,
                 _ => score += 5,
             }
         }
 
         // Weight flagged patterns
         for pattern in patterns {
-            match pattern.severity.as_str() {
-                "High" => score += 30,
-                "Medium" => score += 15,
-                "Low" => score += 5,
-                _ => score += 5,
-            }
+            score += severity::weight(&pattern.severity);
         }
 
         // Convert score to risk level
@@ -553,22 +1832,236 @@ Provide analysis and flag any suspicious patterns with line numbers if possible.
     }
 
     fn compare_risk_scores(&self, a: &RiskScore, b: &RiskScore) -> std::cmp::Ordering {
-        let a_val = match a {
-            RiskScore::Critical => 4,
-            RiskScore::High => 3,
-            RiskScore::Medium => 2,
-            RiskScore::Low => 1,
-            RiskScore::Clean => 0,
-        };
-        let b_val = match b {
-            RiskScore::Critical => 4,
-            RiskScore::High => 3,
-            RiskScore::Medium => 2,
-            RiskScore::Low => 1,
-            RiskScore::Clean => 0,
-        };
-        b_val.cmp(&a_val) // Reverse order (highest risk first)
+        b.rank().cmp(&a.rank()) // Reverse order (highest risk first)
+    }
+}
+
+fn unsafe_flag(metrics: &UnsafeMetrics) -> Option<MetadataFlag> {
+    if metrics.total() == 0 {
+        return None;
     }
+    let severity = if metrics.total() >= UNSAFE_HEAVY_THRESHOLD {
+        "Medium"
+    } else {
+        "Low"
+    };
+    Some(MetadataFlag {
+        flag_type: MetadataFlagType::UnsafeCode,
+        description: format!("Uses unsafe code: {}", metrics.describe()),
+        severity: severity.to_string(),
+    })
+}
+
+/// A Low flag for a `-sys` crate that compiles bundled C/C++ or runs native
+/// build tools; one that only links a system library is not flagged.
+fn native_flag(footprint: &NativeFootprint) -> Option<MetadataFlag> {
+    footprint.builds_native_code().then(|| MetadataFlag {
+        flag_type: MetadataFlagType::NativeCode,
+        description: format!("Builds native code: {}", footprint.describe()),
+        severity: "Low".to_string(),
+    })
+}
+
+/// Network indicators in the downloaded source of `package`, if it is on disk.
+fn package_indicators(package: &Package) -> Vec<NetworkIndicator> {
+    package
+        .manifest_path
+        .parent()
+        .map(|dir| indicators::scan_dir(dir.as_std_path()))
+        .unwrap_or_default()
+}
+
+/// One High flag per kind of artifact found in the package directory `dir`.
+fn artifact_flags(package_name: &str, dir: &Path) -> Vec<MetadataFlag> {
+    let mut by_kind: Vec<(ArtifactKind, Vec<String>)> = Vec::new();
+    for artifact in artifacts::scan_dir(dir) {
+        let expected_import_library = artifact.kind == ArtifactKind::PrecompiledBinary
+            && artifacts::ships_import_libraries(package_name)
+            && artifact
+                .path
+                .extension()
+                .is_some_and(|ext| ext == "lib" || ext == "a");
+        if expected_import_library {
+            continue;
+        }
+        let relative = artifact.path.strip_prefix(dir).unwrap_or(&artifact.path);
+        let entry = format!("{} ({})", relative.display(), artifact.detail);
+        match by_kind.iter_mut().find(|(kind, _)| *kind == artifact.kind) {
+            Some((_, entries)) => entries.push(entry),
+            None => by_kind.push((artifact.kind, vec![entry])),
+        }
+    }
+    by_kind
+        .into_iter()
+        .map(|(kind, mut entries)| {
+            let count = entries.len();
+            entries.sort();
+            let mut listed = entries[..count.min(MAX_LISTED_ARTIFACTS)].join(", ");
+            if count > MAX_LISTED_ARTIFACTS {
+                listed.push_str(&format!(" and {} more", count - MAX_LISTED_ARTIFACTS));
+            }
+            MetadataFlag {
+                flag_type: MetadataFlagType::BundledArtifact,
+                description: format!("Ships {} {}(s): {}", count, kind.label(), listed),
+                severity: "High".to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Whether `url` is the approved source `approved`, or under it when
+/// `approved` ends with `/` (e.g. `https://github.com/my-org/`).
+fn source_matches(url: &str, approved: &str) -> bool {
+    // Approved entries may be written as Cargo source IDs
+    let approved = ["registry+", "sparse+", "git+"]
+        .iter()
+        .find_map(|kind| approved.strip_prefix(kind))
+        .unwrap_or(approved);
+    let trim = |url: &str| {
+        url.trim_end_matches(".git")
+            .trim_end_matches('/')
+            .to_string()
+    };
+    if approved.ends_with('/') {
+        url.starts_with(approved) || trim(url) == trim(approved)
+    } else {
+        trim(url) == trim(approved)
+    }
+}
+
+/// Flags checks that did not run because `what` could not be looked up.
+fn lookup_failed_flag(what: &str, error: &anyhow::Error) -> MetadataFlag {
+    MetadataFlag {
+        flag_type: MetadataFlagType::LookupFailed,
+        description: format!(
+            "Could not look up {}, so its checks did not run: {:#}",
+            what, error
+        ),
+        severity: "Low".to_string(),
+    }
+}
+
+/// Paces crates.io API and GitHub requests across the dependency lookups of
+/// every scan in the process.
+fn metadata_limiter() -> &'static RateLimiter {
+    static LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+    LIMITER.get_or_init(|| RateLimiter::new(METADATA_REQUEST_INTERVAL))
+}
+
+fn checksum_flag(mismatch: &checksum::Mismatch, expected: &str) -> MetadataFlag {
+    MetadataFlag {
+        flag_type: MetadataFlagType::ChecksumMismatch,
+        description: format!(
+            "{} hashes to {}, but Cargo.lock records {}; the downloaded crate is not the one that was locked",
+            mismatch.file.display(),
+            mismatch.actual,
+            expected
+        ),
+        severity: "Critical".to_string(),
+    }
+}
+
+struct RepositoryActivity {
+    archived: bool,
+    days_since_push: i64,
+    open_issues: u64,
+}
+
+fn emit_analyzed(result: &DependencyAnalysisResult, index: usize, total: usize) {
+    progress::emit(ProgressEvent::DependencyAnalyzed {
+        package: &result.package_name,
+        version: &result.version,
+        risk: format!("{:?}", result.risk_score),
+        index,
+        total,
+    });
+}
+
+/// Days since the newest non-yanked version was published.
+fn days_since_last_release(metadata: &serde_json::Value) -> Option<i64> {
+    let latest = metadata["versions"]
+        .as_array()?
+        .iter()
+        .filter(|v| !v["yanked"].as_bool().unwrap_or(false))
+        .filter_map(|v| chrono::DateTime::parse_from_rfc3339(v["created_at"].as_str()?).ok())
+        .max()?;
+    Some(chrono::Utc::now().signed_duration_since(latest).num_days())
+}
+
+/// Ownership flags judged from who published each version (`published_by`)
+/// and the current user `owners` (`None` when unknown or a team is an owner).
+fn publisher_flags(
+    metadata: &serde_json::Value,
+    version: &str,
+    owners: Option<&[&str]>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Vec<MetadataFlag> {
+    let Some(versions) = metadata["versions"].as_array() else {
+        return Vec::new();
+    };
+    // (published at, publisher), oldest first; crates.io only knows publishers since 2019
+    let mut releases: Vec<(chrono::DateTime<chrono::Utc>, &str, &str)> = versions
+        .iter()
+        .filter_map(|v| {
+            let created = chrono::DateTime::parse_from_rfc3339(v["created_at"].as_str()?).ok()?;
+            Some((
+                created.with_timezone(&chrono::Utc),
+                v["num"].as_str()?,
+                v["published_by"]["login"].as_str()?,
+            ))
+        })
+        .collect();
+    releases.sort_by_key(|(created, _, _)| *created);
+    let publishers_before = |cutoff: chrono::DateTime<chrono::Utc>| {
+        let mut logins: Vec<&str> = releases
+            .iter()
+            .filter(|(created, _, _)| *created < cutoff)
+            .map(|(_, _, login)| *login)
+            .collect();
+        logins.sort();
+        logins.dedup();
+        logins
+    };
+    let mut flags = Vec::new();
+
+    if let Some(&(published, _, publisher)) = releases.iter().find(|(_, num, _)| *num == version) {
+        let first_release = releases
+            .iter()
+            .find(|(_, _, login)| *login == publisher)
+            .map_or(published, |(created, _, _)| *created);
+        let earlier = publishers_before(first_release);
+        let days = published.signed_duration_since(first_release).num_days();
+        if !earlier.is_empty() && days <= TRANSFER_WINDOW_DAYS {
+            flags.push(MetadataFlag {
+                flag_type: MetadataFlagType::SuspiciousAuthor,
+                description: format!(
+                    "Version {} was published by `{}`, whose first release of this crate came {} days before; earlier releases came from {}",
+                    version,
+                    publisher,
+                    days,
+                    earlier.join(", ")
+                ),
+                severity: "High".to_string(),
+            });
+        }
+    }
+
+    if let Some(owners) = owners.filter(|owners| !owners.is_empty()) {
+        let established = publishers_before(now - chrono::Duration::days(OWNER_CHANGE_DAYS));
+        if !established.is_empty() && !owners.iter().any(|owner| established.contains(owner)) {
+            flags.push(MetadataFlag {
+                flag_type: MetadataFlagType::SuspiciousAuthor,
+                description: format!(
+                    "Ownership changed: none of the current owners ({}) published a release more than {} days ago; earlier releases came from {}",
+                    owners.join(", "),
+                    OWNER_CHANGE_DAYS,
+                    established.join(", ")
+                ),
+                severity: "Medium".to_string(),
+            });
+        }
+    }
+    flags
 }
 
 // Simple Levenshtein distance implementation
@@ -577,11 +2070,11 @@ fn levenshtein_distance(s1: &str, s2: &str) -> usize {
     let len2 = s2.chars().count();
     let mut matrix = vec![vec![0; len2 + 1]; len1 + 1];
 
-    for i in 0..=len1 {
-        matrix[i][0] = i;
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[0] = i;
     }
-    for j in 0..=len2 {
-        matrix[0][j] = j;
+    for (j, cell) in matrix[0].iter_mut().enumerate() {
+        *cell = j;
     }
 
     let s1_chars: Vec<char> = s1.chars().collect();
@@ -612,7 +2105,7 @@ mod tests {
         assert_eq!(levenshtein_distance("serde", "sede"), 1);
         assert_eq!(levenshtein_distance("tokio", "tokyio"), 1);
         assert_eq!(levenshtein_distance("clap", "clep"), 1);
-        assert_eq!(levenshtein_distance("completely", "different"), 9);
+        assert_eq!(levenshtein_distance("completely", "different"), 8);
     }
 
     #[test]
@@ -621,4 +2114,125 @@ mod tests {
         assert!(scanner.check_typosquatting("serde-json").is_none()); // This is legitimate
         assert!(scanner.check_typosquatting("sede").is_some()); // This would be flagged
     }
+
+    #[test]
+    fn test_yanked_version_and_release_age() {
+        let scanner = DependencyScanner::new();
+        let metadata = serde_json::json!({
+            "versions": [
+                {"num": "1.0.1", "yanked": true, "created_at": "2030-01-01T00:00:00Z"},
+                {"num": "1.0.0", "yanked": false, "created_at": "2020-01-01T00:00:00Z"},
+            ]
+        });
+        assert!(scanner.yanked_flag(&metadata, "1.0.1").is_some());
+        assert!(scanner.yanked_flag(&metadata, "1.0.0").is_none());
+        // The yanked release does not count as recent activity
+        assert!(days_since_last_release(&metadata).unwrap() > UNMAINTAINED_AFTER_DAYS);
+    }
+
+    #[test]
+    fn test_publisher_change_and_new_owners() {
+        let release = |num: &str, date: &str, login: &str| {
+            serde_json::json!({
+                "num": num,
+                "created_at": format!("{}T00:00:00Z", date),
+                "published_by": { "id": 1, "login": login },
+            })
+        };
+        let metadata = serde_json::json!({
+            "versions": [
+                release("1.0.0", "2022-01-01", "alice"),
+                release("1.1.0", "2023-01-01", "alice"),
+                release("1.1.1", "2024-03-01", "mallory"),
+                release("1.1.2", "2024-03-20", "mallory"),
+            ]
+        });
+        let now = "2024-04-01T00:00:00Z".parse().unwrap();
+        let flags = |version: &str, owners: &[&str]| {
+            publisher_flags(&metadata, version, Some(owners), now)
+                .into_iter()
+                .map(|flag| flag.severity)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(flags("1.1.2", &["mallory"]), vec!["High", "Medium"]);
+        assert_eq!(flags("1.1.0", &["alice", "mallory"]), Vec::<String>::new());
+        assert_eq!(flags("1.0.0", &["mallory"]), vec!["Medium"]);
+    }
+
+    #[test]
+    fn test_unapproved_registry_and_git_sources() {
+        let mut scanner = DependencyScanner::new();
+        scanner.add_approved_sources(&[
+            "sparse+https://registry.example.com/index".to_string(),
+            "https://github.com/acme/".to_string(),
+        ]);
+        let flag = |source: &str| {
+            let source = scanner.source_from_str(Some(source.to_string()));
+            scanner.source_flag(&source).map(|flag| flag.severity)
+        };
+        assert_eq!(
+            flag("registry+https://github.com/rust-lang/crates.io-index"),
+            None
+        );
+        assert_eq!(flag("sparse+https://index.crates.io/"), None);
+        assert_eq!(flag("sparse+https://registry.example.com/index/"), None);
+        assert_eq!(
+            flag("sparse+https://mirror.example.net/index/").as_deref(),
+            Some("High")
+        );
+        assert_eq!(
+            flag("git+https://github.com/acme/tool.git?branch=main#1a2b3c"),
+            None
+        );
+        assert_eq!(
+            flag("git+https://github.com/acme-evil/tool#1a2b3c").as_deref(),
+            Some("Medium")
+        );
+        assert!(matches!(
+            scanner.source_from_str(Some("git+https://example.com/x#1a2b3c".to_string())),
+            DependencySource::Git { rev: Some(rev), .. } if rev == "1a2b3c"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_lookups_are_flagged() {
+        use std::io::{Read, Write};
+
+        // A registry that refuses every request
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let _ = stream.read(&mut [0u8; 4096]);
+                let _ = stream.write_all(
+                    b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                );
+            }
+        });
+        let mut scanner = DependencyScanner::new();
+        scanner.metadata_api = format!("http://{}/api/v1", address);
+
+        let crates_io =
+            scanner.source_from_str(Some("sparse+https://index.crates.io/".to_string()));
+        let flags = scanner.crates_io_flags(&crates_io, "demo", "1.0.0").await;
+        assert!(matches!(
+            flags.as_slice(),
+            [MetadataFlag {
+                flag_type: MetadataFlagType::LookupFailed,
+                ..
+            }]
+        ));
+        assert!(matches!(
+            scanner.calculate_risk_score(&flags, &[]),
+            RiskScore::Low
+        ));
+        assert_eq!(flags[0].flag_type.to_string(), "Lookup Failed");
+
+        let git = scanner.source_from_str(Some("git+https://example.com/demo#1a2b3c".to_string()));
+        assert!(scanner
+            .crates_io_flags(&git, "demo", "1.0.0")
+            .await
+            .is_empty());
+    }
 }
@@ -0,0 +1,53 @@
+//! Exit statuses of `rustrecon`, so wrappers can rely on them instead of
+//! parsing output.
+
+/// Nothing at or above the failure threshold was found.
+pub const CLEAN: i32 = 0;
+/// Findings at or above `--fail-on`, a broken policy, or a blocking change
+/// found by `deps-diff` or the git hook.
+pub const FINDINGS: i32 = 1;
+/// The scan or command could not complete.
+pub const SCAN_ERROR: i32 = 2;
+/// The configuration or the command line is invalid.
+pub const CONFIG_ERROR: i32 = 3;
+
+/// Marks an error as a configuration problem: a config file that is missing
+/// or invalid, or an option with a bad value. Displays as the inner error.
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct ConfigError(anyhow::Error);
+
+/// Wraps `error` so the command exits with [`CONFIG_ERROR`], even after
+/// callers add context to it.
+pub fn config_error(error: impl Into<anyhow::Error>) -> anyhow::Error {
+    ConfigError(error.into()).into()
+}
+
+/// The status for a command that failed with `error`.
+pub fn for_error(error: &anyhow::Error) -> i32 {
+    if error.downcast_ref::<ConfigError>().is_some() {
+        CONFIG_ERROR
+    } else {
+        SCAN_ERROR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_code_for_error() {
+        let error = config_error(anyhow::anyhow!("Unknown scan profile 'fast'"));
+        assert_eq!(error.to_string(), "Unknown scan profile 'fast'");
+        assert_eq!(for_error(&error), CONFIG_ERROR);
+
+        let wrapped = Err::<(), _>(error).context("Scanning demo").unwrap_err();
+        assert_eq!(for_error(&wrapped), CONFIG_ERROR);
+        assert_eq!(
+            for_error(&anyhow::anyhow!("Connection refused")),
+            SCAN_ERROR
+        );
+    }
+}
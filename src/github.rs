@@ -0,0 +1,418 @@
+use anyhow::{bail, Context, Result};
+use reqwest::{Client, Method};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::baseline::{Baseline, Disposition};
+use crate::network;
+use crate::report::RiskReport;
+use crate::severity;
+use crate::taxonomy;
+
+/// Marks the summary comment so later runs update it instead of adding another.
+const COMMENT_MARKER: &str = "<!-- rustrecon:summary -->";
+
+/// Rows in the summary comment's table; the rest are counted.
+const MAX_TABLE_ROWS: usize = 50;
+
+/// GitHub returns at most this many items per page.
+const PER_PAGE: usize = 100;
+
+pub struct PrTarget<'a> {
+    pub api_url: &'a str,
+    /// `owner/name`
+    pub repo: &'a str,
+    pub number: u64,
+    pub token: &'a str,
+}
+
+/// A finding on a line the pull request adds.
+#[derive(Debug)]
+pub struct NewFinding {
+    /// Path relative to the repository root, with forward slashes
+    pub path: String,
+    pub line: usize,
+    pub severity: String,
+    pub description: String,
+    pub category: Option<String>,
+    /// Identifies the finding across runs, so annotations are not repeated
+    pub fingerprint: String,
+}
+
+/// Updates (or creates) the RustRecon comment on the pull request and adds
+/// review annotations for new findings that were not annotated before.
+/// Returns the findings on changed lines.
+pub async fn comment_on_pr(
+    target: &PrTarget<'_>,
+    report: &RiskReport,
+    root: &Path,
+    baseline: &Baseline,
+) -> Result<Vec<NewFinding>> {
+    let github = GitHub::new(target.api_url, Some(target.token))?;
+    let pull_path = format!("/repos/{}/pulls/{}", target.repo, target.number);
+    let pull: Value = github.request(Method::GET, &pull_path, None).await?;
+    let head_sha = pull["head"]["sha"]
+        .as_str()
+        .context("Pull request has no head commit")?
+        .to_string();
+
+    let mut changed_lines = HashMap::new();
+    for file in github.get_all(&format!("{}/files", pull_path)).await? {
+        if let (Some(name), Some(patch)) = (file["filename"].as_str(), file["patch"].as_str()) {
+            changed_lines.insert(name.to_string(), added_lines(patch));
+        }
+    }
+    let findings = new_findings(report, root, baseline, &changed_lines);
+    info!(
+        "🔍 {} findings on lines changed by {}#{}",
+        findings.len(),
+        target.repo,
+        target.number
+    );
+
+    // Summary comment
+    let lockfile_changed = changed_lines
+        .keys()
+        .any(|path| path.ends_with("Cargo.lock"));
+    let body = summary_comment(report, &findings, lockfile_changed);
+    let issue = format!("/repos/{}/issues/{}", target.repo, target.number);
+    let existing = github
+        .get_all(&format!("{}/comments", issue))
+        .await?
+        .into_iter()
+        .find(|comment| {
+            comment["body"]
+                .as_str()
+                .is_some_and(|body| body.contains(COMMENT_MARKER))
+        });
+    match existing.and_then(|comment| comment["id"].as_u64()) {
+        Some(id) => {
+            github
+                .request(
+                    Method::PATCH,
+                    &format!("/repos/{}/issues/comments/{}", target.repo, id),
+                    Some(json!({ "body": body })),
+                )
+                .await?;
+            info!("💬 Updated the RustRecon comment");
+        }
+        None => {
+            github
+                .request(
+                    Method::POST,
+                    &format!("{}/comments", issue),
+                    Some(json!({ "body": body })),
+                )
+                .await?;
+            info!("💬 Posted the RustRecon comment");
+        }
+    }
+
+    // Line annotations, skipping findings annotated by earlier runs
+    let annotated: HashSet<String> = github
+        .get_all(&format!("{}/comments", pull_path))
+        .await?
+        .iter()
+        .filter_map(|comment| comment["body"].as_str())
+        .filter_map(annotation_fingerprint)
+        .collect();
+    let comments: Vec<Value> = findings
+        .iter()
+        .filter(|finding| !annotated.contains(&finding.fingerprint))
+        .map(|finding| {
+            json!({
+                "path": finding.path,
+                "line": finding.line,
+                "side": "RIGHT",
+                "body": annotation(finding),
+            })
+        })
+        .collect();
+    if !comments.is_empty() {
+        let count = comments.len();
+        github
+            .request(
+                Method::POST,
+                &format!("{}/reviews", pull_path),
+                Some(json!({
+                    "commit_id": head_sha,
+                    "event": "COMMENT",
+                    "body": format!("RustRecon found {} new issues on changed lines.", count),
+                    "comments": comments,
+                })),
+            )
+            .await?;
+        info!("📝 Added {} review annotations", count);
+    }
+
+    Ok(findings)
+}
+
+/// Whether a finding is at least `min` severity, for `--fail-on`.
+pub fn is_blocking(finding: &NewFinding, min: &str) -> bool {
+    severity::is_at_least(&finding.severity, min)
+}
+
+/// Minimal GitHub REST API client.
+pub struct GitHub {
+    client: Client,
+    api_url: String,
+    token: Option<String>,
+}
+
+impl GitHub {
+    pub fn new(api_url: &str, token: Option<&str>) -> Result<Self> {
+        let client = network::client_builder()
+            .timeout(network::timeouts().request)
+            .user_agent(concat!("rustrecon/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self {
+            client,
+            api_url: api_url.trim_end_matches('/').to_string(),
+            token: token.map(str::to_string),
+        })
+    }
+
+    /// Sends a request to `path`, e.g. `/repos/owner/name`, and returns the JSON response.
+    pub async fn request(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value> {
+        let url = format!("{}{}", self.api_url, path);
+        let mut request = self
+            .client
+            .request(method.clone(), &url)
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("{} {} failed", method, url))?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            bail!(
+                "GitHub API {} {} returned {}: {}",
+                method,
+                path,
+                status,
+                text
+            );
+        }
+        Ok(serde_json::from_str(&text).unwrap_or(Value::Null))
+    }
+
+    /// Every item of a paginated list.
+    pub async fn get_all(&self, path: &str) -> Result<Vec<Value>> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut items = Vec::new();
+        for page in 1.. {
+            let url = format!("{}{}per_page={}&page={}", path, separator, PER_PAGE, page);
+            let Value::Array(batch) = self.request(Method::GET, &url, None).await? else {
+                bail!("GitHub API {} did not return a list", path);
+            };
+            let done = batch.len() < PER_PAGE;
+            items.extend(batch);
+            if done {
+                break;
+            }
+        }
+        Ok(items)
+    }
+}
+
+/// Line numbers in the new version of a file that a unified diff adds.
+fn added_lines(patch: &str) -> HashSet<usize> {
+    let mut lines = HashSet::new();
+    let mut line = 0;
+    for diff_line in patch.lines() {
+        if let Some(header) = diff_line.strip_prefix("@@ ") {
+            // @@ -a,b +c,d @@
+            line = header
+                .split_whitespace()
+                .find_map(|range| range.strip_prefix('+'))
+                .and_then(|range| range.split(',').next())
+                .and_then(|start| start.parse().ok())
+                .unwrap_or(0);
+        } else if diff_line.starts_with('+') {
+            lines.insert(line);
+            line += 1;
+        } else if diff_line.starts_with(' ') {
+            line += 1;
+        }
+    }
+    lines
+}
+
+/// Findings on added lines, leaving out those reviewed as accepted or false
+/// positives in the baseline.
+fn new_findings(
+    report: &RiskReport,
+    root: &Path,
+    baseline: &Baseline,
+    changed_lines: &HashMap<String, HashSet<usize>>,
+) -> Vec<NewFinding> {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let mut findings = Vec::new();
+    // Identical files share a finding, but each copy is annotated where it changed
+    let files = report
+        .findings
+        .iter()
+        .flat_map(|finding| finding.paths().map(move |file| (file, finding)));
+    for (file, finding) in files {
+        let path = repository_path(file, &root);
+        let Some(lines) = changed_lines.get(&path) else {
+            continue;
+        };
+        for pattern in &finding.flagged_patterns {
+            if !lines.contains(&pattern.line) {
+                continue;
+            }
+            if matches!(
+                baseline.disposition(file, pattern),
+                Some(Disposition::Accepted | Disposition::FalsePositive)
+            ) {
+                continue;
+            }
+            let fingerprint = format!(
+                "{:x}",
+                Sha256::digest(
+                    [path.as_str(), &pattern.description, &pattern.code_snippet]
+                        .join("\0")
+                        .as_bytes()
+                )
+            );
+            findings.push(NewFinding {
+                path: path.clone(),
+                line: pattern.line,
+                severity: pattern.severity.clone(),
+                description: pattern.description.clone(),
+                category: taxonomy::label(pattern),
+                fingerprint: fingerprint[..16].to_string(),
+            });
+        }
+    }
+    findings
+}
+
+/// `file` relative to the repository root, as GitHub names it.
+fn repository_path(file: &Path, root: &Path) -> String {
+    let absolute = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+    let relative: PathBuf = match absolute.strip_prefix(root) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => file.strip_prefix(".").unwrap_or(file).to_path_buf(),
+    };
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+fn summary_comment(report: &RiskReport, findings: &[NewFinding], lockfile_changed: bool) -> String {
+    let mut body = format!("{}\n## 🔍 RustRecon\n\n", COMMENT_MARKER);
+    if findings.is_empty() {
+        body.push_str("✅ No new findings on the lines this pull request changes.\n");
+    } else {
+        body.push_str(&format!(
+            "⚠️ **{} new findings** on the lines this pull request changes.\n\n",
+            findings.len()
+        ));
+        body.push_str("| Severity | Location | Finding |\n|----------|----------|---------|\n");
+        for finding in findings.iter().take(MAX_TABLE_ROWS) {
+            body.push_str(&format!(
+                "| {} | `{}:{}` | {} |\n",
+                finding.severity,
+                finding.path,
+                finding.line,
+                finding.description.replace('|', "\\|")
+            ));
+        }
+        if findings.len() > MAX_TABLE_ROWS {
+            body.push_str(&format!(
+                "\n…and {} more.\n",
+                findings.len() - MAX_TABLE_ROWS
+            ));
+        }
+    }
+    if lockfile_changed {
+        body.push_str(&format!(
+            "\n📦 Cargo.lock changed: {} dependencies scanned, {} high risk.\n",
+            report.summary.total_dependencies_scanned, report.summary.high_risk_dependencies
+        ));
+    }
+    body.push_str(&format!(
+        "\n<sub>{} files scanned, {} findings in total · rustrecon {}</sub>\n",
+        report.summary.total_files_scanned,
+        report.summary.total_flagged_patterns,
+        env!("CARGO_PKG_VERSION")
+    ));
+    body
+}
+
+fn annotation(finding: &NewFinding) -> String {
+    let mut body = format!(
+        "<!-- rustrecon:{} -->\n**RustRecon [{}]** {}",
+        finding.fingerprint, finding.severity, finding.description
+    );
+    if let Some(category) = &finding.category {
+        body.push_str(&format!("\n\n_{}_", category));
+    }
+    body
+}
+
+fn annotation_fingerprint(body: &str) -> Option<String> {
+    let rest = body.strip_prefix("<!-- rustrecon:")?;
+    let (fingerprint, _) = rest.split_once(" -->")?;
+    Some(fingerprint.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::FlaggedPattern;
+
+    #[test]
+    fn test_findings_on_added_lines_only() {
+        let patch = "@@ -1,3 +1,4 @@\n fn main() {\n-    old();\n+    new();\n+    spawn();\n }\n@@ -20,2 +21,2 @@\n-x\n+y\n";
+        let lines = added_lines(patch);
+        assert_eq!(lines, HashSet::from([2, 3, 21]));
+
+        let mut report = RiskReport::new("demo".to_string());
+        let pattern = |line: usize| FlaggedPattern {
+            line,
+            severity: "High".to_string(),
+            description: "Spawns an external process".to_string(),
+            code_snippet: format!("line {}", line),
+            confidence: 1.0,
+            cwe: None,
+            owasp: None,
+            id: None,
+            triage: None,
+            blame: None,
+            rule: None,
+        };
+        report.add_file_finding(
+            PathBuf::from("/repo/src/main.rs"),
+            String::new(),
+            vec![pattern(1), pattern(3)],
+        );
+        let changed = HashMap::from([("src/main.rs".to_string(), lines)]);
+        let findings = new_findings(&report, Path::new("/repo"), &Baseline::default(), &changed);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].line, 3);
+        assert_eq!(
+            findings[0].category.as_deref(),
+            Some("CWE-78 OS Command Injection (A03:2021-Injection)")
+        );
+
+        let body = annotation(&findings[0]);
+        assert_eq!(
+            annotation_fingerprint(&body).as_deref(),
+            Some(findings[0].fingerprint.as_str())
+        );
+        assert!(is_blocking(&findings[0], "High"));
+    }
+}
@@ -0,0 +1,241 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use tree_sitter::{Query, QueryCursor, Tree};
+
+use crate::config::{GrammarConfig, GrammarRuleConfig};
+use crate::llm_client::FlaggedPattern;
+use crate::severity;
+use crate::static_rules::flag;
+
+/// A tree-sitter grammar loaded from a shared library under
+/// `[[scanner.grammars]]`, with the static rules for its files. Grammars are
+/// loaded once per process and never unloaded, since trees borrow from them.
+pub struct Grammar {
+    config: GrammarConfig,
+    pub language: tree_sitter::Language,
+    /// What the LLM is asked to analyze, e.g. "C code"
+    pub subject: String,
+    pub fence: String,
+    pub focus: String,
+    rules: Vec<(GrammarRuleConfig, Query)>,
+}
+
+impl std::fmt::Debug for Grammar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Grammar")
+            .field("name", &self.config.name)
+            .field("library", &self.config.library)
+            .finish()
+    }
+}
+
+/// Each configuration is loaded once, so a grammar is its own identity.
+impl PartialEq for Grammar {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for Grammar {}
+
+impl Grammar {
+    /// `config` with its rules compiled against `language`.
+    pub fn new(config: GrammarConfig, language: tree_sitter::Language) -> Result<Self> {
+        let rules = config
+            .rules
+            .iter()
+            .map(|rule| {
+                let query = Query::new(language, &rule.query).with_context(|| {
+                    format!("scanner.grammars '{}': invalid query", config.name)
+                })?;
+                if query.capture_names().is_empty() {
+                    bail!(
+                        "scanner.grammars '{}': the query of \"{}\" captures no node to report",
+                        config.name,
+                        rule.description
+                    );
+                }
+                let Some(severity) = severity::canonical(&rule.severity) else {
+                    bail!(
+                        "scanner.grammars '{}': unknown severity \"{}\"",
+                        config.name,
+                        rule.severity
+                    );
+                };
+                let rule = GrammarRuleConfig {
+                    severity: severity.to_string(),
+                    ..rule.clone()
+                };
+                Ok((rule, query))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Grammar {
+            subject: format!("{} code", config.name),
+            fence: config.name.to_lowercase(),
+            focus: format!(
+                " This {} code ships inside a Rust crate, for example in a -sys crate: look for code that runs at build or load time, network and process access, and hidden or obfuscated payloads.",
+                config.name
+            ),
+            config,
+            language,
+            rules,
+        })
+    }
+
+    /// Whether the grammar parses the file at `path`.
+    pub fn parses(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|ext| {
+            let ext = ext.to_string_lossy();
+            self.config
+                .extensions
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(&ext))
+        })
+    }
+
+    /// Findings of the grammar's rules, one per rule and line.
+    pub fn scan_tree(&self, tree: &Tree, source: &str) -> Vec<FlaggedPattern> {
+        let mut patterns = Vec::new();
+        let mut seen = HashSet::new();
+        let mut cursor = QueryCursor::new();
+        for (index, (rule, query)) in self.rules.iter().enumerate() {
+            for found in cursor.matches(query, tree.root_node(), source.as_bytes()) {
+                let Some(capture) = found.captures.first() else {
+                    continue;
+                };
+                if !seen.insert((index, capture.node.start_position().row)) {
+                    continue;
+                }
+                let mut pattern = flag(capture.node, source, &rule.severity, &rule.description);
+                pattern.rule = rule.id.clone();
+                patterns.push(pattern);
+            }
+        }
+        patterns.sort_by_key(|pattern| pattern.line);
+        patterns
+    }
+}
+
+/// The grammar for `config`, loading its library the first time.
+pub fn load(config: &GrammarConfig) -> Result<&'static Grammar> {
+    static LOADED: Mutex<Vec<&'static Grammar>> = Mutex::new(Vec::new());
+    let mut loaded = LOADED
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Grammar registry unavailable"))?;
+    if let Some(grammar) = loaded.iter().find(|grammar| grammar.config == *config) {
+        return Ok(grammar);
+    }
+    let language = load_language(&config.library, &config.symbol())
+        .with_context(|| format!("Failed to load grammar '{}'", config.name))?;
+    let version = language.version();
+    if !(tree_sitter::MIN_COMPATIBLE_LANGUAGE_VERSION..=tree_sitter::LANGUAGE_VERSION)
+        .contains(&version)
+    {
+        bail!(
+            "Grammar '{}' was generated for tree-sitter language version {}; this build supports {} to {}",
+            config.name,
+            version,
+            tree_sitter::MIN_COMPATIBLE_LANGUAGE_VERSION,
+            tree_sitter::LANGUAGE_VERSION
+        );
+    }
+    let grammar: &'static Grammar = Box::leak(Box::new(Grammar::new(config.clone(), language)?));
+    loaded.push(grammar);
+    Ok(grammar)
+}
+
+#[cfg(unix)]
+fn load_language(library: &Path, symbol: &str) -> Result<tree_sitter::Language> {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+
+    let last_error = || {
+        // SAFETY: dlerror returns null or a NUL-terminated message
+        let message = unsafe { libc::dlerror() };
+        if message.is_null() {
+            "unknown error".to_string()
+        } else {
+            unsafe { CStr::from_ptr(message) }
+                .to_string_lossy()
+                .to_string()
+        }
+    };
+    let path = CString::new(library.as_os_str().as_bytes())?;
+    // SAFETY: the path is NUL-terminated; the handle is never closed
+    let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if handle.is_null() {
+        bail!("{}", last_error());
+    }
+    let name = CString::new(symbol)?;
+    // SAFETY: `handle` is a library opened above
+    let function = unsafe { libc::dlsym(handle, name.as_ptr()) };
+    if function.is_null() {
+        bail!(
+            "{} has no function {}: {}",
+            library.display(),
+            symbol,
+            last_error()
+        );
+    }
+    // SAFETY: grammars export `const TSLanguage *tree_sitter_<name>(void)`,
+    // which grammar crates declare as returning `Language` in the same way
+    let function: unsafe extern "C" fn() -> tree_sitter::Language =
+        unsafe { std::mem::transmute(function) };
+    Ok(unsafe { function() })
+}
+
+#[cfg(not(unix))]
+fn load_language(_library: &Path, _symbol: &str) -> Result<tree_sitter::Language> {
+    bail!("Grammar plugins are only supported on Unix")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grammar_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = GrammarConfig {
+            name: "C".to_string(),
+            library: dir.path().join("libtree-sitter-c.so"),
+            symbol: None,
+            extensions: vec!["c".to_string(), "h".to_string()],
+            rules: vec![GrammarRuleConfig {
+                id: Some("ORG-001".to_string()),
+                query: r#"((call_expression function: (identifier) @name) (#eq? @name "system"))"#
+                    .to_string(),
+                severity: "high".to_string(),
+                description: "Runs a shell command".to_string(),
+            }],
+        };
+        assert_eq!(config.symbol(), "tree_sitter_c");
+        assert!(load(&config).is_err());
+
+        // The Rust grammar stands in for a loaded one
+        let language = tree_sitter_rust::language();
+        let grammar = Grammar::new(config.clone(), language).unwrap();
+        assert!(grammar.parses(Path::new("src/shim.H")));
+        assert!(!grammar.parses(Path::new("src/lib.rs")));
+        let source = "fn main() {\n    system(cmd);\n    other(system);\n}\n";
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(language).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let patterns = grammar.scan_tree(&tree, source);
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].line, 2);
+        assert_eq!(patterns[0].severity, "High");
+        assert_eq!(patterns[0].rule.as_deref(), Some("ORG-001"));
+
+        let uncaptured = GrammarConfig {
+            rules: vec![GrammarRuleConfig {
+                query: "(call_expression)".to_string(),
+                ..config.rules[0].clone()
+            }],
+            ..config
+        };
+        assert!(Grammar::new(uncaptured, language).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io::{BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+use crate::dependency_scanner::{DependencyScanner, RiskScore};
+use crate::llm_client::FlaggedPattern;
+use crate::lockfile;
+use crate::scanner::Scanner;
+use crate::severity;
+
+/// First line after the shebang of hooks written by `hook install`, so they can
+/// be told apart from hooks written by hand or by other tools.
+const HOOK_MARKER: &str = "# Installed by `rustrecon hook install`";
+
+/// Object name git uses for "no commit", e.g. for a branch new to the remote.
+const NULL_SHA: &str = "0000000000000000000000000000000000000000";
+
+/// Where the content to check comes from.
+enum Revision {
+    /// The index (`git commit` is about to record it)
+    Staged,
+    Commit(String),
+}
+
+impl Revision {
+    /// Argument for `git show` that names `path` at this revision.
+    fn object(&self, path: &str) -> String {
+        match self {
+            Revision::Staged => format!(":{}", path),
+            Revision::Commit(sha) => format!("{}:{}", sha, path),
+        }
+    }
+}
+
+/// Writes a pre-push hook (and optionally a pre-commit hook) that runs
+/// `rustrecon hook run`. Existing hooks from elsewhere are kept unless `force`.
+pub fn install(pre_commit: bool, force: bool) -> Result<()> {
+    let hooks_dir = PathBuf::from(git(&["rev-parse", "--git-path", "hooks"])?);
+    fs::create_dir_all(&hooks_dir)
+        .with_context(|| format!("Failed to create {}", hooks_dir.display()))?;
+    let executable = std::env::current_exe()?;
+
+    let mut hooks = vec![("pre-push", "")];
+    if pre_commit {
+        hooks.push(("pre-commit", " --staged"));
+    }
+    for (name, args) in hooks {
+        let path = hooks_dir.join(name);
+        if let Ok(existing) = fs::read_to_string(&path) {
+            if !existing.contains(HOOK_MARKER) && !force {
+                bail!(
+                    "{} already exists and was not installed by rustrecon; use --force to replace it",
+                    path.display()
+                );
+            }
+        }
+        let script = format!(
+            "#!/bin/sh\n{}; skip with --no-verify\nexec {} hook run{}\n",
+            HOOK_MARKER,
+            shell_quote(&executable.to_string_lossy()),
+            args
+        );
+        fs::write(&path, script).with_context(|| format!("Failed to write {}", path.display()))?;
+        make_executable(&path)?;
+        info!("🪝 Installed {} hook: {}", name, path.display());
+    }
+    Ok(())
+}
+
+/// `value` as one single-quoted `sh` word, whatever characters it holds.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Runs the static rules over changed Rust files and checks changed lockfiles
+/// offline. With `staged`, checks what is about to be committed; otherwise
+/// what is about to be pushed, as described by git on stdin (or everything
+/// since the upstream branch when run by hand). Returns whether the changes
+/// pass: no High or Critical findings and no high-risk dependency changes.
+pub async fn run(staged: bool) -> Result<bool> {
+    let root = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?);
+    let config = load_config(&root)?;
+
+    let ranges = if staged {
+        let base = git_ok(&["rev-parse", "--verify", "-q", "HEAD"]);
+        vec![(base, Revision::Staged)]
+    } else {
+        push_ranges()?
+    };
+
+    let mut scanner = Scanner::new(root.clone())?;
+    scanner.set_exclude_patterns(&config.scanner.exclude)?;
+    scanner.set_include_patterns(&config.scanner.include)?;
+    scanner.set_rule_packs(config.scanner.rule_packs);
+    scanner.set_grammars(&config.scanner.grammars)?;
+    let mut dependency_scanner = DependencyScanner::new();
+    dependency_scanner.set_offline(true);
+    dependency_scanner.add_trusted_packages(&config.dependencies.trusted);
+    dependency_scanner.add_approved_sources(&config.dependencies.approved_sources);
+    dependency_scanner.set_metadata_source(&config.dependencies);
+    dependency_scanner.set_severity_overrides(config.severity.clone());
+
+    let mut blocking = 0;
+    let mut files = 0;
+    for (base, head) in &ranges {
+        for path in changed_files(base.as_deref(), head)? {
+            if path.ends_with("Cargo.lock") {
+                blocking +=
+                    check_lockfile(&dependency_scanner, &path, base.as_deref(), head).await?;
+                continue;
+            }
+            if !scanner.is_scanned(&root.join(&path)) {
+                continue;
+            }
+            files += 1;
+            let content = git(&["show", &head.object(&path)])?;
+            let mut patterns = scanner
+                .analyze_source(&root.join(&path), content)?
+                .static_patterns;
+            severity::apply_to_patterns(&config.severity, &mut patterns);
+            blocking += report_patterns(&path, &patterns);
+        }
+    }
+
+    if blocking > 0 {
+        error!(
+            "❌ {} blocking findings; fix them or bypass the hook with --no-verify",
+            blocking
+        );
+        return Ok(false);
+    }
+    info!("✅ rustrecon: {} changed files checked", files);
+    Ok(true)
+}
+
+/// User config if there is one (the hook needs no API key), plus the
+/// repository's `.rustrecon.toml`.
+fn load_config(root: &Path) -> Result<Config> {
+    let mut config = Config::load_or_default()?;
+    config.apply_project_file(root)?;
+    Ok(config)
+}
+
+/// `(base commit, head)` pairs for the refs being pushed. Git passes one line
+/// per ref on stdin: `<local ref> <local sha> <remote ref> <remote sha>`.
+fn push_ranges() -> Result<Vec<(Option<String>, Revision)>> {
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        // Run by hand: whatever the upstream branch does not have yet
+        let base = git_ok(&["rev-parse", "--verify", "-q", "@{upstream}"]);
+        return Ok(vec![(base, Revision::Commit(git(&["rev-parse", "HEAD"])?))]);
+    }
+
+    let mut ranges = Vec::new();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [_, local_sha, _, remote_sha] = fields[..] else {
+            continue;
+        };
+        if local_sha == NULL_SHA {
+            continue; // Deleting a remote branch
+        }
+        let base = if remote_sha == NULL_SHA {
+            // New branch: compare with the remote's default branch if known
+            git_ok(&["merge-base", local_sha, "refs/remotes/origin/HEAD"])
+        } else if git_ok(&["cat-file", "-e", &format!("{}^{{commit}}", remote_sha)]).is_some() {
+            Some(remote_sha.to_string())
+        } else {
+            None
+        };
+        ranges.push((base, Revision::Commit(local_sha.to_string())));
+    }
+    Ok(ranges)
+}
+
+/// Files added or modified between `base` and `head`, relative to the
+/// repository root. Without a base every file in `head` counts as changed.
+fn changed_files(base: Option<&str>, head: &Revision) -> Result<Vec<String>> {
+    let output = match (base, head) {
+        (_, Revision::Staged) => git(&["diff", "--cached", "--name-only", "--diff-filter=ACMR"])?,
+        (Some(base), Revision::Commit(head)) => {
+            git(&["diff", "--name-only", "--diff-filter=ACMR", base, head])?
+        }
+        (None, Revision::Commit(head)) => git(&["ls-tree", "-r", "--name-only", head])?,
+    };
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+/// Offline check of the dependencies a lockfile change adds or bumps; returns
+/// the number of high-risk ones.
+async fn check_lockfile(
+    dependency_scanner: &DependencyScanner,
+    path: &str,
+    base: Option<&str>,
+    head: &Revision,
+) -> Result<usize> {
+    let base_packages = match base.and_then(|base| git_ok(&["show", &format!("{}:{}", base, path)]))
+    {
+        Some(content) => lockfile::parse_lockfile_str(&content)?,
+        None => Vec::new(),
+    };
+    let head_packages = lockfile::parse_lockfile_str(&git(&["show", &head.object(path)])?)?;
+    let changed = lockfile::diff_lockfiles(&base_packages, &head_packages).changed_packages();
+    if changed.is_empty() {
+        return Ok(0);
+    }
+
+    let results = dependency_scanner.scan_lockfile_fast(&changed).await?;
+    let mut high_risk = 0;
+    for dep in &results {
+        let flags: Vec<&str> = dep
+            .metadata_flags
+            .iter()
+            .map(|f| f.description.as_str())
+            .collect();
+        match dep.risk_score {
+            RiskScore::Critical | RiskScore::High => {
+                high_risk += 1;
+                error!(
+                    "{}: {} v{} is {:?} risk: {}",
+                    path,
+                    dep.package_name,
+                    dep.version,
+                    dep.risk_score,
+                    flags.join("; ")
+                );
+            }
+            RiskScore::Medium => warn!(
+                "{}: {} v{} is Medium risk: {}",
+                path,
+                dep.package_name,
+                dep.version,
+                flags.join("; ")
+            ),
+            _ => {}
+        }
+    }
+    info!(
+        "🔀 {}: {} dependencies added or updated",
+        path,
+        changed.len()
+    );
+    Ok(high_risk)
+}
+
+/// Logs the findings of one file; returns how many block the hook.
+fn report_patterns(path: &str, patterns: &[FlaggedPattern]) -> usize {
+    let mut blocking = 0;
+    for pattern in patterns {
+        let message = format!(
+            "{}:{} [{}] {}",
+            path, pattern.line, pattern.severity, pattern.description
+        );
+        if matches!(pattern.severity.as_str(), "Critical" | "High") {
+            blocking += 1;
+            error!("{}", message);
+        } else {
+            warn!("{}", message);
+        }
+    }
+    blocking
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+/// Output of a git command that is allowed to fail, e.g. a lookup of a ref
+/// that may not exist.
+fn git_ok(args: &[&str]) -> Option<String> {
+    git(args).ok().filter(|output| !output.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(
+            shell_quote("/usr/local/bin/rustrecon"),
+            "'/usr/local/bin/rustrecon'"
+        );
+        let quoted = shell_quote("/home/o'brien/bin/rustrecon");
+        assert_eq!(quoted, "'/home/o'\\''brien/bin/rustrecon'");
+        let output = Command::new("sh")
+            .args(["-c", &format!("printf %s {}", quoted)])
+            .output()
+            .unwrap();
+        assert_eq!(output.stdout, b"/home/o'brien/bin/rustrecon");
+    }
+}
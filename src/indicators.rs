@@ -0,0 +1,435 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::sync::OnceLock;
+use tree_sitter::{Node, Parser, Tree};
+use walkdir::WalkDir;
+
+use crate::static_rules::string_value;
+
+/// Top-level domains recognized in bare domain literals. File extensions that
+/// are also TLDs (`.rs`, `.sh`, `.py`, `.md`, `.in`...) are left out.
+const TLDS: &[&str] = &[
+    "com", "net", "org", "io", "co", "dev", "app", "xyz", "top", "info", "biz", "me", "tk", "ml",
+    "ga", "cf", "gq", "pw", "cc", "su", "ru", "cn", "ir", "kp", "onion", "site", "online", "club",
+    "live", "tech", "cloud", "ai", "gg", "us", "uk", "de", "fr", "jp", "kr", "br", "eu", "ws",
+];
+
+/// Schemes sent in cleartext.
+const INSECURE_SCHEMES: &[&str] = &["http", "ws", "ftp", "telnet"];
+
+/// Free dynamic DNS, tunneling, paste and request-capture services that
+/// malware uses as command-and-control or drop points. Matched on the host
+/// and its parent domains.
+const C2_SERVICE_DOMAINS: &[&str] = &[
+    "duckdns.org",
+    "no-ip.com",
+    "no-ip.org",
+    "ddns.net",
+    "hopto.org",
+    "zapto.org",
+    "ngrok.io",
+    "ngrok-free.app",
+    "trycloudflare.com",
+    "serveo.net",
+    "pastebin.com",
+    "paste.ee",
+    "hastebin.com",
+    "transfer.sh",
+    "webhook.site",
+    "requestbin.net",
+    "pipedream.net",
+    "burpcollaborator.net",
+    "oastify.com",
+    "interact.sh",
+    "oast.fun",
+    "oast.live",
+    "oast.me",
+    "oast.online",
+    "oast.pro",
+    "oast.site",
+];
+
+/// URL prefixes (after the scheme) of chat APIs that double as C2 or
+/// exfiltration channels.
+const C2_SERVICE_URLS: &[&str] = &[
+    "discord.com/api/webhooks/",
+    "discordapp.com/api/webhooks/",
+    "api.telegram.org/bot",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndicatorKind {
+    Url,
+    Ip,
+    Domain,
+    /// A cryptocurrency wallet address
+    Wallet,
+}
+
+impl IndicatorKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            IndicatorKind::Url => "URL",
+            IndicatorKind::Ip => "IP",
+            IndicatorKind::Domain => "domain",
+            IndicatorKind::Wallet => "wallet",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum IndicatorFlag {
+    /// A literal IP address instead of a host name
+    RawIp,
+    /// A cleartext scheme such as `http://`
+    NonHttps,
+    /// Host or path built at runtime, e.g. `format!("https://{}.example.com", id)`
+    Dynamic,
+    /// A Tor hidden service (`.onion`)
+    Onion,
+    /// A dynamic DNS, tunneling, paste or webhook service often used for C2
+    C2Service,
+    /// Listed by a configured threat-intel feed
+    ThreatIntel,
+}
+
+/// A URL, IP address, domain or wallet address found in a string literal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkIndicator {
+    pub value: String,
+    pub kind: IndicatorKind,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<IndicatorFlag>,
+    /// The threat-intel feed that lists the indicator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threat_feed: Option<String>,
+    /// `path:line` of each occurrence
+    pub locations: Vec<String>,
+}
+
+fn url_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r#"(?i)\b(https?|wss?|ftp|telnet)://[^\s"'<>\\`]*"#).expect("valid URL pattern")
+    })
+}
+
+fn ip_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(?:^|[^\d.])(\d{1,3}(?:\.\d{1,3}){3})(?:$|[^\d.])").expect("valid IP pattern")
+    })
+}
+
+/// Monero addresses anywhere in a literal; Bitcoin and Ethereum addresses only
+/// as the whole literal, since shorter base58 and hex strings are common.
+fn wallet_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"\b4[0-9AB][1-9A-HJ-NP-Za-km-z]{93}\b|^(?:bc1[02-9ac-hj-np-z]{39,59}|[13][1-9A-HJ-NP-Za-km-z]{25,34}|0x[0-9a-fA-F]{40})$",
+        )
+        .expect("valid wallet pattern")
+    })
+}
+
+fn domain_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(&format!(
+            r"(?i)(?:^|[^\w.-])((?:\}}\.)?(?:[a-z0-9](?:[a-z0-9-]{{0,61}}[a-z0-9])?\.)+(?:{}))(?:$|[^\w.-])",
+            TLDS.join("|")
+        ))
+        .expect("valid domain pattern")
+    })
+}
+
+/// Indicators in the string literals of a parsed Rust file, located at
+/// `path:line`. Comments and doc links are not code, so they are skipped.
+pub fn extract(tree: &Tree, source: &str, path: &str) -> Vec<NetworkIndicator> {
+    let mut indicators = Vec::new();
+    visit(tree.root_node(), source, path, &mut indicators);
+    merge(indicators)
+}
+
+/// Indicators in a script or configuration file, one line at a time,
+/// skipping lines that start with `line_comment`.
+pub fn extract_text(source: &str, path: &str, line_comment: Option<&str>) -> Vec<NetworkIndicator> {
+    let mut indicators = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        if line_comment.is_some_and(|comment| line.trim_start().starts_with(comment)) {
+            continue;
+        }
+        let location = format!("{}:{}", path, index + 1);
+        indicators.extend(from_literal(line, &location));
+        for wallet in wallet_regex().find_iter(line) {
+            indicators.push(NetworkIndicator {
+                value: wallet.as_str().to_string(),
+                kind: IndicatorKind::Wallet,
+                flags: Vec::new(),
+                threat_feed: None,
+                locations: vec![location.clone()],
+            });
+        }
+    }
+    merge(indicators)
+}
+
+/// Indicators in every `.rs` file under `dir` (e.g. a dependency's source),
+/// with paths relative to `dir`.
+pub fn scan_dir(dir: &Path) -> Vec<NetworkIndicator> {
+    let mut parser = Parser::new();
+    if parser.set_language(tree_sitter_rust::language()).is_err() {
+        return Vec::new();
+    }
+    let mut indicators = Vec::new();
+    for entry in WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != "target")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "rs"))
+    {
+        let Ok(content) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        if let Some(tree) = parser.parse(&content, None) {
+            let relative = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+            let path = relative.to_string_lossy().replace('\\', "/");
+            indicators.extend(extract(&tree, &content, &path));
+        }
+    }
+    merge(indicators)
+}
+
+/// Combines indicators with the same value: threat-intel matches first, then
+/// the most flagged.
+pub fn merge(indicators: impl IntoIterator<Item = NetworkIndicator>) -> Vec<NetworkIndicator> {
+    let mut by_value: BTreeMap<String, NetworkIndicator> = BTreeMap::new();
+    for indicator in indicators {
+        match by_value.get_mut(&indicator.value) {
+            Some(existing) => {
+                for location in indicator.locations {
+                    if !existing.locations.contains(&location) {
+                        existing.locations.push(location);
+                    }
+                }
+            }
+            None => {
+                by_value.insert(indicator.value.clone(), indicator);
+            }
+        }
+    }
+    let mut merged: Vec<NetworkIndicator> = by_value.into_values().collect();
+    merged.sort_by_key(|indicator| {
+        (
+            !indicator.flags.contains(&IndicatorFlag::ThreatIntel),
+            std::cmp::Reverse(indicator.flags.len()),
+        )
+    });
+    merged
+}
+
+/// The host of an indicator: the host part of a URL, or the value itself.
+pub fn host(indicator: &NetworkIndicator) -> &str {
+    match indicator.kind {
+        IndicatorKind::Url => url_host(&indicator.value),
+        _ => &indicator.value,
+    }
+}
+
+/// `host` and its parent domains, down to the registrable two labels
+/// (`a.b.example.com`, `b.example.com`, `example.com`).
+pub fn domain_suffixes(host: &str) -> impl Iterator<Item = &str> {
+    let labels = host.split('.').count();
+    std::iter::successors(Some(host), |domain| {
+        domain.split_once('.').map(|(_, parent)| parent)
+    })
+    .take(labels.saturating_sub(1).max(1))
+}
+
+fn url_host(url: &str) -> &str {
+    let after = url.split_once("://").map_or("", |(_, after)| after);
+    let authority = after.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_port = authority.rsplit('@').next().unwrap_or_default();
+    host_port
+        .rsplit_once(':')
+        .filter(|(_, port)| port.chars().all(|c| c.is_ascii_digit()))
+        .map_or(host_port, |(host, _)| host)
+}
+
+/// Flags for hosts that are suspicious by name alone.
+fn host_flags(host: &str, url: &str) -> Vec<IndicatorFlag> {
+    let host = host.to_lowercase();
+    let mut flags = Vec::new();
+    if host.ends_with(".onion") {
+        flags.push(IndicatorFlag::Onion);
+    }
+    let after_scheme = url.split_once("://").map_or(url, |(_, after)| after);
+    if domain_suffixes(&host).any(|domain| C2_SERVICE_DOMAINS.contains(&domain))
+        || C2_SERVICE_URLS
+            .iter()
+            .any(|prefix| after_scheme.to_lowercase().starts_with(prefix))
+    {
+        flags.push(IndicatorFlag::C2Service);
+    }
+    flags
+}
+
+fn visit(node: Node, source: &str, path: &str, indicators: &mut Vec<NetworkIndicator>) {
+    if matches!(node.kind(), "string_literal" | "raw_string_literal") {
+        let literal = node.utf8_text(source.as_bytes()).unwrap_or_default();
+        let location = format!("{}:{}", path, node.start_position().row + 1);
+        indicators.extend(from_literal(literal, &location));
+        for wallet in wallet_regex().find_iter(string_value(node, source)) {
+            indicators.push(NetworkIndicator {
+                value: wallet.as_str().to_string(),
+                kind: IndicatorKind::Wallet,
+                flags: Vec::new(),
+                threat_feed: None,
+                locations: vec![location.clone()],
+            });
+        }
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        visit(child, source, path, indicators);
+    }
+}
+
+fn from_literal(literal: &str, location: &str) -> Vec<NetworkIndicator> {
+    let mut indicators = Vec::new();
+    let indicator = |value: &str, kind, flags| NetworkIndicator {
+        value: value.to_string(),
+        kind,
+        flags,
+        threat_feed: None,
+        locations: vec![location.to_string()],
+    };
+
+    // URLs first; the hosts inside them are not reported again
+    let mut rest = literal.to_string();
+    for url in url_regex().find_iter(literal) {
+        rest = rest.replacen(url.as_str(), " ", 1);
+        let value = url.as_str().trim_end_matches(['.', ',', ')', ';']);
+        let (scheme, _) = value.split_once("://").unwrap_or_default();
+        let host = url_host(value);
+        if is_local(host) {
+            continue;
+        }
+        let mut flags = Vec::new();
+        if host.parse::<Ipv4Addr>().is_ok() {
+            flags.push(IndicatorFlag::RawIp);
+        }
+        if INSECURE_SCHEMES.contains(&scheme.to_lowercase().as_str()) {
+            flags.push(IndicatorFlag::NonHttps);
+        }
+        if host.is_empty() || value.contains('{') {
+            flags.push(IndicatorFlag::Dynamic);
+        }
+        flags.extend(host_flags(host, value));
+        indicators.push(indicator(value, IndicatorKind::Url, flags));
+    }
+
+    for captures in ip_regex().captures_iter(&rest) {
+        let ip = &captures[1];
+        if ip.parse::<Ipv4Addr>().is_ok() && !is_local(ip) {
+            indicators.push(indicator(ip, IndicatorKind::Ip, vec![IndicatorFlag::RawIp]));
+        }
+    }
+
+    for captures in domain_regex().captures_iter(&rest) {
+        let matched = &captures[1];
+        // `{}.example.com`: a subdomain filled in at runtime, as in DNS exfiltration
+        let (domain, mut flags) = match matched.strip_prefix("}.") {
+            Some(domain) => (domain, vec![IndicatorFlag::Dynamic]),
+            None => (matched, Vec::new()),
+        };
+        flags.extend(host_flags(domain, domain));
+        if !is_local(domain) {
+            indicators.push(indicator(
+                &domain.to_lowercase(),
+                IndicatorKind::Domain,
+                flags,
+            ));
+        }
+    }
+    indicators
+}
+
+/// Loopback and unspecified addresses, which never leave the machine.
+fn is_local(host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost")
+        || host == "::1"
+        || host
+            .parse::<Ipv4Addr>()
+            .is_ok_and(|ip| ip.is_loopback() || ip.is_unspecified())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_urls_ips_and_domains() {
+        let source = r#"
+// See https://docs.rs/reqwest for details
+fn beacon(id: &str) {
+    let a = "http://203.0.113.7:8080/c2";
+    let b = format!("https://{}/upload", host());
+    let c = format!("{}.exfil.xyz", id);
+    let d = "https://api.example.com/v1";
+    let e = "198.51.100.20";
+    let f = "http://localhost:3000";
+    let g = "config.rs";
+    let h = "http://abcdefghijklmnopqrstuvwxyz234567abcdefghijklmnopqrstuv.onion/gate";
+    let i = "beacon.duckdns.org";
+    let j = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+}
+"#;
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_rust::language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let indicators = extract(&tree, source, "src/lib.rs");
+
+        let find = |value: &str| indicators.iter().find(|i| i.value == value);
+        let c2 = find("http://203.0.113.7:8080/c2").unwrap();
+        assert_eq!(
+            c2.flags,
+            vec![IndicatorFlag::RawIp, IndicatorFlag::NonHttps]
+        );
+        assert_eq!(c2.locations, vec!["src/lib.rs:4"]);
+        assert_eq!(
+            find("https://{}/upload").unwrap().flags,
+            vec![IndicatorFlag::Dynamic]
+        );
+        assert_eq!(
+            find("exfil.xyz").unwrap().flags,
+            vec![IndicatorFlag::Dynamic]
+        );
+        assert!(find("https://api.example.com/v1").unwrap().flags.is_empty());
+        assert_eq!(find("198.51.100.20").unwrap().kind, IndicatorKind::Ip);
+        assert_eq!(
+            find("http://abcdefghijklmnopqrstuvwxyz234567abcdefghijklmnopqrstuv.onion/gate")
+                .unwrap()
+                .flags,
+            vec![IndicatorFlag::NonHttps, IndicatorFlag::Onion]
+        );
+        assert_eq!(
+            find("beacon.duckdns.org").unwrap().flags,
+            vec![IndicatorFlag::C2Service]
+        );
+        assert_eq!(
+            find("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq")
+                .unwrap()
+                .kind,
+            IndicatorKind::Wallet
+        );
+        assert_eq!(indicators.len(), 8);
+    }
+}
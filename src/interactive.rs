@@ -0,0 +1,27 @@
+use anyhow::{bail, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// `--non-interactive`: nothing may wait for a person at the terminal, so the
+/// tool can run as a container entrypoint or in a pipeline without hanging.
+pub fn disable() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_disabled() -> bool {
+    DISABLED.load(Ordering::Relaxed)
+}
+
+/// Fails under `--non-interactive` with a hint at the non-interactive way
+/// to do `what`.
+pub fn require(what: &str, instead: &str) -> Result<()> {
+    if is_disabled() {
+        bail!(
+            "{} needs a terminal, but --non-interactive is set; {}",
+            what,
+            instead
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use anyhow::{Context, Result};
+use keyring::Entry;
+
+/// Service name under which RustRecon secrets are stored in the OS keychain
+/// (Windows Credential Manager, macOS Keychain, or the Secret Service on Linux).
+const KEYCHAIN_SERVICE: &str = "rustrecon";
+
+/// Default keychain entry name for the LLM provider API key.
+pub const DEFAULT_API_KEY_ENTRY: &str = "gemini_api_key";
+
+pub fn store_api_key(entry_name: &str, api_key: &str) -> Result<()> {
+    Entry::new(KEYCHAIN_SERVICE, entry_name)?
+        .set_password(api_key)
+        .with_context(|| format!("Failed to store '{}' in the OS keychain", entry_name))
+}
+
+pub fn load_api_key(entry_name: &str) -> Result<String> {
+    Entry::new(KEYCHAIN_SERVICE, entry_name)?
+        .get_password()
+        .with_context(|| {
+            format!(
+                "Failed to read '{}' from the OS keychain. Run `rustrecon init --keychain` to store it.",
+                entry_name
+            )
+        })
+}
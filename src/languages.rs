@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use crate::grammars::Grammar;
+
+/// Kinds of file the scanner analyzes. Rust source is parsed; scripts and
+/// configuration shipped inside crates, where payloads are easy to overlook,
+/// are read line by line; files of a grammar plugin are parsed with it.
+/// `Native` is the build script and C/C++ of a `-sys` dependency, which only
+/// dependency analysis sends for review.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    Rust,
+    Shell,
+    PowerShell,
+    Toml,
+    Json,
+    Plugin(&'static Grammar),
+    Native,
+}
+
+impl Language {
+    /// The built-in language of `path`, going by its extension.
+    pub fn from_path(path: &Path) -> Option<Language> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "rs" => Some(Language::Rust),
+            "sh" | "bash" => Some(Language::Shell),
+            "ps1" | "psm1" => Some(Language::PowerShell),
+            "toml" => Some(Language::Toml),
+            "json" => Some(Language::Json),
+            _ => None,
+        }
+    }
+
+    /// What the LLM is asked to analyze, e.g. "shell script".
+    pub fn subject(self) -> &'static str {
+        match self {
+            Language::Rust => "Rust code",
+            Language::Shell => "shell script",
+            Language::PowerShell => "PowerShell script",
+            Language::Toml => "TOML configuration",
+            Language::Json => "JSON file",
+            Language::Plugin(grammar) => &grammar.subject,
+            Language::Native => "build script and native code of a -sys crate",
+        }
+    }
+
+    /// Info string of the Markdown code fence around the code in prompts.
+    pub fn fence(self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::Shell => "sh",
+            Language::PowerShell => "powershell",
+            Language::Toml => "toml",
+            Language::Json => "json",
+            Language::Plugin(grammar) => &grammar.fence,
+            Language::Native => "c",
+        }
+    }
+
+    /// What to look for beyond the general instructions, as a sentence
+    /// appended to them. Empty for Rust.
+    pub fn focus(self) -> &'static str {
+        match self {
+            Language::Rust => "",
+            Language::Shell => " This script ships inside a Rust crate: look for downloads that are run, reverse shells, reading of credentials, persistence and destructive commands.",
+            Language::PowerShell => " This script ships inside a Rust crate: look for downloads that are run, hidden windows, execution policy bypasses, changes to Windows Defender and persistence.",
+            Language::Toml => " This configuration ships inside a Rust crate: look for compiler wrappers, runners, linker overrides and commands that tools run on build or checkout.",
+            Language::Json => " This file ships inside a Rust crate: look for install scripts, editor tasks that run on open and embedded commands or payloads.",
+            Language::Plugin(grammar) => &grammar.focus,
+            Language::Native => " The build script runs at compile time and the C/C++ is linked into every program using the crate: look for build steps that download or run programs or write outside OUT_DIR, sources that differ from the upstream library they claim to be, constructors and initializers that run at load time, network and process access, and hidden or obfuscated payloads.",
+        }
+    }
+
+    /// Marker of a line comment, whose lines hold no code or indicators.
+    pub fn line_comment(self) -> Option<&'static str> {
+        match self {
+            Language::Rust | Language::Native => Some("//"),
+            Language::Shell | Language::PowerShell | Language::Toml => Some("#"),
+            Language::Json | Language::Plugin(_) => None,
+        }
+    }
+}
@@ -1,22 +1,101 @@
 use async_trait::async_trait;
 use regex::Regex;
-use reqwest::{Client, Error as ReqwestError};
+use reqwest::{Certificate, Client, Error as ReqwestError, RequestBuilder, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use sha2::{Digest, Sha256};
+use std::time::Instant;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::blame::BlameInfo;
+use crate::cache::CacheKey;
+use crate::languages::Language;
+use crate::metrics;
+use crate::network;
+use crate::triage::TriageStatus;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LlmRequest {
     pub prompt: String,
+    /// Set to let `CachedLlmClient` answer the request from the analysis cache
+    #[serde(skip)]
+    pub cache_key: Option<CacheKey>,
+    /// Language of the code in a `chunk_prompt`, which picks the analysis prompt
+    #[serde(skip)]
+    pub language: Language,
     // Add other fields as necessary for the Gemini API, e.g., model, temperature, etc.
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LlmResponse {
     pub analysis: String,
     pub flagged_patterns: Vec<FlaggedPattern>,
+    /// `None` for analyses cached before versions were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<AnalysisVersion>,
     // Add other fields as necessary for the Gemini API response
 }
 
+/// Version of the analysis prompt. Bump it whenever a prompt change affects
+/// what gets flagged, so findings from older prompts can be told apart.
+/// 1: text `ANALYSIS:`/`PATTERNS:` format; 2: JSON response mode; 3: per-finding
+/// confidence.
+pub const PROMPT_VERSION: u32 = 3;
+
+/// Which prompt, analyzer and model produced an analysis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalysisVersion {
+    pub prompt_version: u32,
+    /// RustRecon version; empty when unknown
+    pub analyzer_version: String,
+    pub model: String,
+}
+
+impl AnalysisVersion {
+    pub fn current(model: &str) -> Self {
+        AnalysisVersion {
+            prompt_version: PROMPT_VERSION,
+            analyzer_version: env!("CARGO_PKG_VERSION").to_string(),
+            model: model.to_string(),
+        }
+    }
+
+    pub fn is_outdated(&self) -> bool {
+        self.prompt_version < PROMPT_VERSION
+    }
+}
+
+impl std::fmt::Display for AnalysisVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "prompt v{}", self.prompt_version)?;
+        if !self.analyzer_version.is_empty() {
+            write!(f, ", rustrecon {}", self.analyzer_version)?;
+        }
+        write!(f, ", {}", self.model)
+    }
+}
+
+/// A model offered by the Gemini API, from its `models` endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfo {
+    /// Name as used in requests and the config, e.g. `gemini-1.5-flash`
+    pub name: String,
+    #[serde(default)]
+    pub display_name: String,
+    #[serde(default)]
+    pub description: String,
+    pub input_token_limit: Option<usize>,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<ModelInfo>,
+    next_page_token: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct GeminiResponse {
     candidates: Vec<GeminiCandidate>,
@@ -43,37 +122,297 @@ pub struct FlaggedPattern {
     pub severity: String,
     pub description: String,
     pub code_snippet: String,
+    /// How likely the finding is real, from 0 to 1: the model's own estimate,
+    /// adjusted by checks against the source (see `utils::locate_pattern`)
+    #[serde(default = "default_confidence")]
+    pub confidence: f32,
+    /// CWE identifier such as `CWE-78`, filled in by `taxonomy::classify_patterns`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwe: Option<String>,
+    /// OWASP Top 10 category such as `A03:2021-Injection`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owasp: Option<String>,
+    /// Stable id in the findings database, set by `triage::record_findings`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub triage: Option<TriageStatus>,
+    /// Last author and commit of the flagged line, set by `scan --blame`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blame: Option<BlameInfo>,
+    /// Rule pack rule that found it, such as `RR-MINE-001`; see `rule_packs`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+}
+
+/// Confidence of findings without a better estimate, such as those from the
+/// text response format or from reports written before confidence existed.
+pub const DEFAULT_CONFIDENCE: f32 = 0.5;
+
+fn default_confidence() -> f32 {
+    DEFAULT_CONFIDENCE
 }
 
 #[async_trait]
 pub trait LlmClientTrait {
     async fn analyze_code(&self, request: LlmRequest) -> Result<LlmResponse, LlmClientError>;
+
+    /// Name of the model that produces the analyses.
+    fn model_name(&self) -> &str;
+
+    /// Changes whenever the prompt template or its settings change, so analyses
+    /// produced by an older prompt are not reused.
+    fn prompt_fingerprint(&self) -> String;
+
+    /// Requests answered from a cache so far; lets callers tell cache hits apart.
+    fn cache_hits(&self) -> usize {
+        0
+    }
+
+    /// Whether `request` would be answered from a cache, without sending it;
+    /// lets callers skip rate limiting for it.
+    fn is_cached(&self, _request: &LlmRequest) -> bool {
+        false
+    }
+}
+
+/// Controls how aggressively the model is asked to flag code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PromptProfile {
+    #[default]
+    Standard,
+    Strict, // Also report merely suspicious patterns, at the cost of more false positives
 }
 
+impl std::str::FromStr for PromptProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "standard" => Ok(PromptProfile::Standard),
+            "strict" => Ok(PromptProfile::Strict),
+            other => Err(format!(
+                "Unknown prompt profile '{}' (expected 'standard' or 'strict')",
+                other
+            )),
+        }
+    }
+}
+
+/// The prompt of a request to analyze `code`, which `GeminiClient` replaces
+/// with its full analysis prompt for `language`.
+pub fn chunk_prompt(language: Language, code: &str) -> String {
+    format!(
+        "Analyze the following {} for malicious behavior, backdoors, or unsafe patterns. Provide a summary of findings and specific flagged lines with severity (High, Medium, Low) and a brief description:\n\n{}",
+        language.subject(),
+        code
+    )
+}
+
+/// Model used unless the config names another.
+pub const DEFAULT_GEMINI_MODEL: &str = "gemini-1.5-flash";
+
+const ANALYSIS_PROMPT_TEMPLATE: &str = "Analyze this {subject} for security vulnerabilities, malicious behavior, backdoors, and unsafe patterns.{focus}
+
+            Please provide:
+            1. A brief security analysis summary
+            2. List any suspicious patterns found with:
+               - Line number (estimate if exact line unknown)
+               - Severity: High/Medium/Low
+               - Description of the issue
+               - Code snippet of the problematic code
+
+            Code to analyze:
+            ```{fence}
+            {code}
+            ```";
+
+/// Output instructions when the response is constrained by `analysis_response_schema`.
+const JSON_FORMAT_INSTRUCTIONS: &str = "
+
+            Respond with a JSON object: \"analysis\" holds the summary and \"findings\" the suspicious patterns (an empty list if no security issues were found). Give each finding a \"confidence\" from 0 to 1 that it is a real issue rather than benign code.";
+
+/// Output instructions for endpoints without structured output, read back by
+/// `parse_text_response`.
+const TEXT_FORMAT_INSTRUCTIONS: &str = "
+
+            Format your response as:
+            ANALYSIS: [Your analysis summary]
+
+            PATTERNS:
+            - Line: [number], Severity: [High/Medium/Low], Description: [description], Code: [snippet]
+            - Line: [number], Severity: [High/Medium/Low], Description: [description], Code: [snippet]
+
+            If no security issues found, respond with:
+            ANALYSIS: No significant security issues detected.
+            PATTERNS: None";
+
+const STRICT_PROMPT_SUFFIX: &str = "\n\nBe strict: also report code that is merely suspicious or unusual for its stated purpose (obfuscation, hidden network or process access, environment probing), even if it might be benign.";
+
 pub struct GeminiClient {
     api_key: String,
     api_endpoint: String,
+    model: String,
     http_client: Client,
+    prompt_profile: PromptProfile,
+    max_output_tokens: u32,
+    temperature: f32,
+    structured_output: bool,
+}
+
+/// Shape of a response in JSON mode; see `analysis_response_schema`.
+#[derive(Debug, Deserialize)]
+struct StructuredAnalysis {
+    analysis: String,
+    #[serde(default)]
+    findings: Vec<StructuredFinding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StructuredFinding {
+    line: usize,
+    severity: String,
+    description: String,
+    #[serde(default)]
+    code_snippet: String,
+    confidence: Option<f32>,
+}
+
+/// Gemini `responseSchema` for analyses: a summary plus a list of findings.
+fn analysis_response_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "OBJECT",
+        "properties": {
+            "analysis": {"type": "STRING"},
+            "findings": {
+                "type": "ARRAY",
+                "items": {
+                    "type": "OBJECT",
+                    "properties": {
+                        "line": {"type": "INTEGER"},
+                        "severity": {"type": "STRING", "enum": ["High", "Medium", "Low"]},
+                        "description": {"type": "STRING"},
+                        "code_snippet": {"type": "STRING"},
+                        "confidence": {"type": "NUMBER"}
+                    },
+                    "required": ["line", "severity", "description", "code_snippet"]
+                }
+            }
+        },
+        "required": ["analysis", "findings"]
+    })
 }
 
 impl GeminiClient {
     pub fn new(api_key: String, api_endpoint: String) -> Self {
-        let http_client = Client::builder()
-            .timeout(Duration::from_secs(30))
+        let http_client = network::client_builder()
+            .timeout(network::timeouts().llm)
             .build()
             .expect("Failed to build HTTP client");
         GeminiClient {
             api_key,
             api_endpoint,
+            model: DEFAULT_GEMINI_MODEL.to_string(),
             http_client,
+            prompt_profile: PromptProfile::default(),
+            max_output_tokens: 2048,
+            temperature: 0.7,
+            structured_output: true,
         }
     }
 
+    /// Trusts only `certificates` for the endpoint (`llm.pinned_ca_bundle`).
+    pub fn with_pinned_certificates(mut self, certificates: &[Certificate]) -> Self {
+        self.http_client = network::pinned_client_builder(certificates)
+            .timeout(network::timeouts().llm)
+            .build()
+            .expect("Failed to build HTTP client");
+        self
+    }
+
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    pub fn with_prompt_profile(mut self, prompt_profile: PromptProfile) -> Self {
+        self.prompt_profile = prompt_profile;
+        self
+    }
+
+    pub fn with_max_output_tokens(mut self, max_output_tokens: u32) -> Self {
+        self.max_output_tokens = max_output_tokens;
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Gemini's JSON response mode is used by default; turn it off for
+    /// endpoints that do not support `responseSchema`.
+    pub fn with_structured_output(mut self, structured_output: bool) -> Self {
+        self.structured_output = structured_output;
+        self
+    }
+
+    fn build_prompt(&self, code: &str, language: Language) -> String {
+        let format_instructions = if self.structured_output {
+            JSON_FORMAT_INSTRUCTIONS
+        } else {
+            TEXT_FORMAT_INSTRUCTIONS
+        };
+        let prompt = ANALYSIS_PROMPT_TEMPLATE
+            .replace("{subject}", language.subject())
+            .replace("{focus}", language.focus())
+            .replace("{fence}", language.fence())
+            .replace("{code}", code)
+            + format_instructions;
+        match self.prompt_profile {
+            PromptProfile::Standard => prompt,
+            PromptProfile::Strict => format!("{}{}", prompt, STRICT_PROMPT_SUFFIX),
+        }
+    }
+
+    /// Reads a JSON-mode response, falling back to the text format when the
+    /// endpoint ignored the schema.
     fn parse_analysis_response(
         &self,
         response: &str,
     ) -> Result<(String, Vec<FlaggedPattern>), LlmClientError> {
-        let mut analysis = String::new();
+        if self.structured_output {
+            if let Ok(structured) = serde_json::from_str::<StructuredAnalysis>(response.trim()) {
+                let patterns = structured
+                    .findings
+                    .into_iter()
+                    .map(|finding| FlaggedPattern {
+                        line: finding.line,
+                        severity: finding.severity,
+                        description: finding.description,
+                        code_snippet: finding.code_snippet,
+                        confidence: finding
+                            .confidence
+                            .map_or(DEFAULT_CONFIDENCE, |c| c.clamp(0.0, 1.0)),
+                        cwe: None,
+                        owasp: None,
+                        id: None,
+                        triage: None,
+                        blame: None,
+                        rule: None,
+                    })
+                    .collect();
+                return Ok((structured.analysis, patterns));
+            }
+        }
+        self.parse_text_response(response)
+    }
+
+    fn parse_text_response(
+        &self,
+        response: &str,
+    ) -> Result<(String, Vec<FlaggedPattern>), LlmClientError> {
+        let mut analysis;
         let mut patterns = Vec::new();
 
         // Split response into analysis and patterns sections
@@ -101,6 +440,13 @@ impl GeminiClient {
                                 severity: captures[2].to_string(),
                                 description: captures[3].trim().to_string(),
                                 code_snippet: captures[4].trim().to_string(),
+                                confidence: DEFAULT_CONFIDENCE,
+                                cwe: None,
+                                owasp: None,
+                                id: None,
+                                triage: None,
+                                blame: None,
+                                rule: None,
                             });
                         }
                     }
@@ -120,47 +466,102 @@ impl GeminiClient {
 
         Ok((analysis, patterns))
     }
-}
 
-#[async_trait]
-impl LlmClientTrait for GeminiClient {
-    async fn analyze_code(&self, request: LlmRequest) -> Result<LlmResponse, LlmClientError> {
-        let url = format!(
-            "{}/v1beta/models/gemini-1.5-flash:generateContent?key={}",
-            self.api_endpoint, self.api_key
-        );
-
-        // Enhanced prompt for better security analysis
-        let enhanced_prompt = format!(
-            "Analyze this Rust code for security vulnerabilities, malicious behavior, backdoors, and unsafe patterns.
+    /// The body of a successful response, or the error the status stands for.
+    async fn response_text(response: reqwest::Response) -> Result<String, LlmClientError> {
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(if status == StatusCode::TOO_MANY_REQUESTS {
+                LlmClientError::QuotaExhausted(error_text)
+            } else if status.is_server_error() {
+                LlmClientError::ServerError(status.as_u16(), error_text)
+            } else {
+                LlmClientError::ApiError(format!("API request failed: {}", error_text))
+            });
+        }
+        Ok(response.text().await?)
+    }
 
-            Please provide:
-            1. A brief security analysis summary
-            2. List any suspicious patterns found with:
-               - Line number (estimate if exact line unknown)
-               - Severity: High/Medium/Low
-               - Description of the issue
-               - Code snippet of the problematic code
+    /// Where the endpoint's models are: Gemini API endpoints are a host, and
+    /// Vertex AI endpoints end in `/publishers/google`.
+    fn models_url(&self) -> String {
+        let endpoint = self.api_endpoint.trim_end_matches('/');
+        if endpoint.ends_with("/publishers/google") {
+            format!("{}/models", endpoint)
+        } else {
+            format!("{}/v1beta/models", endpoint)
+        }
+    }
 
-            Code to analyze:
-            ```rust
-            {}
-            ```
+    /// Adds the key to a request, in a header rather than the URL so that it
+    /// stays out of error messages. Vertex AI endpoints of a project take an
+    /// OAuth access token; the Gemini API and Vertex AI express mode take an
+    /// API key.
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        if self.api_endpoint.contains("/projects/") {
+            request.bearer_auth(&self.api_key)
+        } else {
+            request.header("x-goog-api-key", &self.api_key)
+        }
+    }
 
-            Format your response as:
-            ANALYSIS: [Your analysis summary]
+    /// Models the API key can analyze code with, sorted by name. Gemini
+    /// rejects invalid keys here too, so this doubles as a key check.
+    pub async fn list_models(&self) -> Result<Vec<ModelInfo>, LlmClientError> {
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut request = self
+                .authorize(self.http_client.get(self.models_url()))
+                .query(&[("pageSize", "1000")]);
+            if let Some(token) = &page_token {
+                request = request.query(&[("pageToken", token)]);
+            }
+            let response = request.send().await?;
+            let text = Self::response_text(response).await?;
+            let page: ModelList = serde_json::from_str(&text)?;
+            models.extend(page.models);
+            page_token = page.next_page_token.filter(|token| !token.is_empty());
+            if page_token.is_none() {
+                break;
+            }
+        }
+        let mut models: Vec<ModelInfo> = models
+            .into_iter()
+            .filter(|model| {
+                model
+                    .supported_generation_methods
+                    .iter()
+                    .any(|method| method == "generateContent")
+            })
+            .map(|mut model| {
+                if let Some(name) = model.name.strip_prefix("models/") {
+                    model.name = name.to_string();
+                }
+                model
+            })
+            .collect();
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(models)
+    }
 
-            PATTERNS:
-            - Line: [number], Severity: [High/Medium/Low], Description: [description], Code: [snippet]
-            - Line: [number], Severity: [High/Medium/Low], Description: [description], Code: [snippet]
+    /// Sends one request to the Gemini API and parses the answer.
+    async fn generate(&self, request: LlmRequest) -> Result<LlmResponse, LlmClientError> {
+        let url = format!("{}/{}:generateContent", self.models_url(), self.model);
 
-            If no security issues found, respond with:
-            ANALYSIS: No significant security issues detected.
-            PATTERNS: None",
-            request.prompt.replace("Analyze the following Rust code for malicious behavior, backdoors, or unsafe patterns. Provide a summary of findings and specific flagged lines with severity (High, Medium, Low) and a brief description:\n\n", "")
+        // Enhanced prompt for better security analysis
+        let enhanced_prompt = self.build_prompt(
+            &request
+                .prompt
+                .replace(&chunk_prompt(request.language, ""), ""),
+            request.language,
         );
 
-        let gemini_request_body = serde_json::json!({
+        let mut gemini_request_body = serde_json::json!({
             "contents": [
                 {
                     "parts": [
@@ -169,34 +570,26 @@ impl LlmClientTrait for GeminiClient {
                 }
             ],
             "generationConfig": {
-                "temperature": 0.7,
-                "maxOutputTokens": 2048
+                "temperature": self.temperature,
+                "maxOutputTokens": self.max_output_tokens
             }
         });
+        if self.structured_output {
+            gemini_request_body["generationConfig"]["responseMimeType"] = "application/json".into();
+            gemini_request_body["generationConfig"]["responseSchema"] = analysis_response_schema();
+        }
 
         let response = self
-            .http_client
-            .post(&url)
+            .authorize(self.http_client.post(&url))
             .json(&gemini_request_body)
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(LlmClientError::ApiError(format!(
-                "API request failed: {}",
-                error_text
-            )));
-        }
-
-        let response_text = response.text().await?;
+        let response_text = Self::response_text(response).await?;
 
         // Parse Gemini response
         let gemini_response: GeminiResponse =
-            serde_json::from_str(&response_text).map_err(|e| LlmClientError::JsonError(e))?;
+            serde_json::from_str(&response_text).map_err(LlmClientError::JsonError)?;
 
         if gemini_response.candidates.is_empty() {
             return Err(LlmClientError::ApiError(
@@ -212,10 +605,39 @@ impl LlmClientTrait for GeminiClient {
         Ok(LlmResponse {
             analysis,
             flagged_patterns,
+            version: Some(AnalysisVersion::current(&self.model)),
         })
     }
 }
 
+#[async_trait]
+impl LlmClientTrait for GeminiClient {
+    async fn analyze_code(&self, request: LlmRequest) -> Result<LlmResponse, LlmClientError> {
+        let started = Instant::now();
+        let result = self.generate(request).await;
+        metrics::record_llm_request(
+            &self.model,
+            started.elapsed(),
+            result.as_ref().err().map(LlmClientError::kind),
+        );
+        result
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn prompt_fingerprint(&self) -> String {
+        let settings = format!(
+            "{}\0{}\0{}",
+            self.build_prompt("", Language::Rust),
+            self.max_output_tokens,
+            self.temperature
+        );
+        format!("{:x}", Sha256::digest(settings.as_bytes()))[..16].to_string()
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum LlmClientError {
     #[error("HTTP request error: {0}")]
@@ -226,4 +648,92 @@ pub enum LlmClientError {
     ApiError(String),
     #[error("Other error: {0}")]
     Other(String),
+    #[error("LLM quota or rate limit exhausted: {0}")]
+    QuotaExhausted(String),
+    #[error("LLM service error ({0}): {1}")]
+    ServerError(u16, String),
+    #[error("LLM analysis paused after {0} consecutive failures")]
+    CircuitOpen(usize),
+    #[error("LLM analysis timed out after {0}s")]
+    TimedOut(u64),
+    #[error("scan budget spent: {0}")]
+    BudgetSpent(String),
+}
+
+impl LlmClientError {
+    /// Short name of the failure, used as a metrics label.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LlmClientError::HttpRequest(_) => "network",
+            LlmClientError::JsonError(_) => "invalid_response",
+            LlmClientError::ApiError(_) => "api",
+            LlmClientError::Other(_) => "other",
+            LlmClientError::QuotaExhausted(_) => "quota",
+            LlmClientError::ServerError(..) => "server",
+            LlmClientError::CircuitOpen(_) => "circuit_open",
+            LlmClientError::TimedOut(_) => "timeout",
+            LlmClientError::BudgetSpent(_) => "budget",
+        }
+    }
+
+    /// Failures of the service rather than of the request: quota, 5xx and
+    /// network errors, which are worth retrying and count toward the breaker.
+    pub fn is_service_failure(&self) -> bool {
+        matches!(
+            self,
+            LlmClientError::HttpRequest(_)
+                | LlmClientError::QuotaExhausted(_)
+                | LlmClientError::ServerError(..)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structured_response_with_text_fallback() {
+        let client = GeminiClient::new(String::new(), String::new());
+        let json = r#"{"analysis": "Reads a token.", "findings": [
+            {"line": 4, "severity": "Medium", "description": "Reads an env var", "code_snippet": "env::var(\"TOKEN\")", "confidence": 0.8}
+        ]}"#;
+        let (analysis, patterns) = client.parse_analysis_response(json).unwrap();
+        assert_eq!(analysis, "Reads a token.");
+        assert_eq!(patterns.len(), 1);
+        assert_eq!(patterns[0].line, 4);
+        assert_eq!(patterns[0].code_snippet, "env::var(\"TOKEN\")");
+        assert_eq!(patterns[0].confidence, 0.8);
+
+        // Endpoints that ignore the schema still answer in the text format
+        let text = "ANALYSIS: Spawns a shell.\nPATTERNS:\n- Line: 7, Severity: High, Description: Runs sh, Code: Command::new(\"sh\")";
+        let (analysis, patterns) = client.parse_analysis_response(text).unwrap();
+        assert_eq!(analysis, "Spawns a shell.");
+        assert_eq!(patterns[0].severity, "High");
+
+        // The key goes in a header, never the URL that errors print
+        let client = GeminiClient::new(
+            "secret".to_string(),
+            "https://generativelanguage.googleapis.com".to_string(),
+        );
+        let request = client
+            .authorize(
+                client
+                    .http_client
+                    .post(format!("{}/gemini-1.5-flash", client.models_url())),
+            )
+            .build()
+            .unwrap();
+        assert!(!request.url().as_str().contains("secret"));
+        assert_eq!(request.headers()["x-goog-api-key"], "secret");
+        let vertex = GeminiClient::new(
+            "token".to_string(),
+            "https://europe-west4-aiplatform.googleapis.com/v1/projects/p/locations/europe-west4/publishers/google".to_string(),
+        );
+        let request = vertex
+            .authorize(vertex.http_client.get(vertex.models_url()))
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["authorization"], "Bearer token");
+    }
 }
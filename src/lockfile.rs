@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// A single `[[package]]` entry from a `Cargo.lock` file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    pub source: Option<String>,
+    pub checksum: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+impl LockedPackage {
+    /// Workspace members and path dependencies have no `source` in the lockfile.
+    pub fn is_external(&self) -> bool {
+        self.source.is_some()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Lockfile {
+    #[serde(default, rename = "package")]
+    packages: Vec<LockedPackage>,
+}
+
+/// Parses a `Cargo.lock` file without requiring the rest of the source tree.
+pub fn parse_lockfile(path: &Path) -> Result<Vec<LockedPackage>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read lockfile {}", path.display()))?;
+    parse_lockfile_str(&content)
+        .with_context(|| format!("Failed to parse lockfile {}", path.display()))
+}
+
+pub fn parse_lockfile_str(content: &str) -> Result<Vec<LockedPackage>> {
+    let lockfile: Lockfile = toml::from_str(content)?;
+    Ok(lockfile.packages)
+}
+
+/// External packages that differ between two lockfiles.
+#[derive(Debug, Default)]
+pub struct LockfileDiff {
+    /// Packages whose name does not appear in the base lockfile at all
+    pub added: Vec<LockedPackage>,
+    /// New versions of packages already in the base lockfile
+    pub updated: Vec<UpdatedPackage>,
+    /// Names present in the base lockfile but not in the head
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct UpdatedPackage {
+    pub package: LockedPackage,
+    pub previous_versions: Vec<String>,
+}
+
+impl LockfileDiff {
+    /// The packages that need analysis: everything added or bumped.
+    pub fn changed_packages(&self) -> Vec<LockedPackage> {
+        self.added
+            .iter()
+            .cloned()
+            .chain(self.updated.iter().map(|u| u.package.clone()))
+            .collect()
+    }
+}
+
+/// Compares the external packages of two lockfiles. A package is keyed by name and
+/// version, since a lockfile can hold several versions of the same crate.
+pub fn diff_lockfiles(base: &[LockedPackage], head: &[LockedPackage]) -> LockfileDiff {
+    let mut base_versions: HashMap<&str, Vec<&str>> = HashMap::new();
+    for package in base.iter().filter(|p| p.is_external()) {
+        base_versions
+            .entry(package.name.as_str())
+            .or_default()
+            .push(package.version.as_str());
+    }
+
+    let mut diff = LockfileDiff::default();
+    let mut head_names = HashSet::new();
+    for package in head.iter().filter(|p| p.is_external()) {
+        head_names.insert(package.name.as_str());
+        match base_versions.get(package.name.as_str()) {
+            None => diff.added.push(package.clone()),
+            Some(versions) if !versions.contains(&package.version.as_str()) => {
+                diff.updated.push(UpdatedPackage {
+                    package: package.clone(),
+                    previous_versions: versions.iter().map(|v| v.to_string()).collect(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    diff.removed = base_versions
+        .keys()
+        .filter(|name| !head_names.contains(*name))
+        .map(|name| name.to_string())
+        .collect();
+    diff.removed.sort();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lockfile_str() {
+        let content = r#"
+version = 3
+
+[[package]]
+name = "my-app"
+version = "0.1.0"
+dependencies = [
+ "serde",
+]
+
+[[package]]
+name = "serde"
+version = "1.0.210"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "c8e3592472072e6e22e0a54d5904d9febf8508f65fb8552499a1abc7d1078c3a"
+"#;
+        let packages = parse_lockfile_str(content).unwrap();
+        assert_eq!(packages.len(), 2);
+        assert!(!packages[0].is_external());
+        assert_eq!(packages[0].dependencies, vec!["serde".to_string()]);
+        assert!(packages[1].is_external());
+        assert_eq!(packages[1].version, "1.0.210");
+    }
+
+    #[test]
+    fn test_diff_lockfiles() {
+        let package = |name: &str, version: &str| LockedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            source: Some("registry+https://github.com/rust-lang/crates.io-index".to_string()),
+            checksum: None,
+            dependencies: Vec::new(),
+        };
+        let base = vec![package("serde", "1.0.200"), package("log", "0.4.20")];
+        let head = vec![package("serde", "1.0.210"), package("tokio", "1.40.0")];
+
+        let diff = diff_lockfiles(&base, &head);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].name, "tokio");
+        assert_eq!(diff.updated.len(), 1);
+        assert_eq!(
+            diff.updated[0].previous_versions,
+            vec!["1.0.200".to_string()]
+        );
+        assert_eq!(diff.removed, vec!["log".to_string()]);
+    }
+}
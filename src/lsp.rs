@@ -0,0 +1,425 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tower_lsp::jsonrpc;
+use tower_lsp::lsp_types::*;
+use tower_lsp::{Client, LanguageServer, LspService, Server};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::llm_client::{FlaggedPattern, LlmClientTrait};
+use crate::scanner::{FileAnalysisResult, Scanner};
+use crate::utils;
+use crate::{analyze_chunks, chunk_request, scan_client, scan_context_budget, ScanClient};
+
+/// Command behind the "analyze with the LLM" code action; its argument is
+/// the document's URI.
+const ANALYZE_COMMAND: &str = "rustrecon.analyzeFile";
+
+/// How much of the LLM a document's diagnostics may use.
+#[derive(Clone, Copy, PartialEq)]
+enum LlmUse {
+    /// Static rules only, for edits in progress
+    None,
+    /// Add the LLM findings cached for exactly this content
+    Cached,
+    /// Send the file to the LLM when it is not cached
+    Analyze,
+}
+
+/// The folder the editor opened, set up once `initialize` arrives.
+struct Workspace {
+    root: PathBuf,
+    crate_name: String,
+    scanner: Mutex<Scanner>,
+    /// `None` when the profile runs static rules only or no LLM is configured
+    llm: Option<ScanClient>,
+}
+
+struct Backend {
+    client: Client,
+    profile: String,
+    workspace: OnceLock<Workspace>,
+    /// Text of the open Rust documents
+    documents: Mutex<HashMap<Url, String>>,
+}
+
+/// Runs the language server on stdin and stdout until the editor exits.
+/// `profile` is the scan profile whose LLM settings analyses use.
+pub async fn serve(profile: String) -> Result<()> {
+    let (service, socket) = LspService::new(|client| Backend {
+        client,
+        profile,
+        workspace: OnceLock::new(),
+        documents: Mutex::new(HashMap::new()),
+    });
+    Server::new(tokio::io::stdin(), tokio::io::stdout(), socket)
+        .serve(service)
+        .await;
+    Ok(())
+}
+
+impl Backend {
+    async fn open_workspace(&self, root: PathBuf) -> Result<Workspace> {
+        let config = Config::load_for_project(&root)?;
+        let profile = config.scan_profile(&self.profile)?;
+        let llm = if profile.use_llm {
+            match scan_client(&config, &profile, true, Arc::default()).await {
+                Ok(client) => Some(client),
+                Err(e) => {
+                    warn!(
+                        "⚠️  LLM analysis unavailable, using static rules only: {}",
+                        e
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let mut scanner = Scanner::new(root.clone())?;
+        scanner.set_context_budget(scan_context_budget(llm.as_ref(), &config, &profile));
+        scanner.set_rule_packs(config.scanner.rule_packs);
+        scanner.set_grammars(&config.scanner.grammars)?;
+        Ok(Workspace {
+            crate_name: utils::get_crate_name_from_path(&root),
+            root,
+            scanner: Mutex::new(scanner),
+            llm,
+        })
+    }
+
+    /// Findings in `text` as the content of `uri`.
+    async fn findings(&self, uri: &Url, text: String, llm: LlmUse) -> Result<Vec<FlaggedPattern>> {
+        let workspace = self
+            .workspace
+            .get()
+            .context("The language server is not initialized")?;
+        let path = uri
+            .to_file_path()
+            .map_err(|_| anyhow::anyhow!("{} is not a local file", uri))?;
+        let FileAnalysisResult {
+            chunks,
+            static_patterns,
+            language,
+            ..
+        } = workspace
+            .scanner
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Scanner unavailable"))?
+            .analyze_source(&path, text)?;
+        let Some(client) = workspace.llm.as_ref().filter(|_| llm != LlmUse::None) else {
+            return Ok(static_patterns);
+        };
+
+        // Same path as in `scan`, so both share cached analyses
+        let relative_path = path
+            .strip_prefix(&workspace.root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .to_string();
+        let cached = chunks.iter().all(|chunk| {
+            client.is_cached(&chunk_request(
+                &workspace.crate_name,
+                &relative_path,
+                language,
+                chunk,
+            ))
+        });
+        if llm == LlmUse::Cached && !cached {
+            return Ok(static_patterns);
+        }
+        let response = analyze_chunks(
+            client,
+            &workspace.crate_name,
+            &relative_path,
+            language,
+            &chunks,
+        )
+        .await?;
+        let mut patterns = response.flagged_patterns;
+        utils::adjust_confidence(&mut patterns, &static_patterns, Path::new(&relative_path));
+        Ok(static_patterns.into_iter().chain(patterns).collect())
+    }
+
+    /// Replaces the diagnostics of `uri` with the findings in `text`.
+    async fn publish(&self, uri: Url, text: String, llm: LlmUse) -> Result<usize> {
+        let patterns = self.findings(&uri, text.clone(), llm).await?;
+        let diagnostics: Vec<Diagnostic> = patterns
+            .iter()
+            .map(|pattern| diagnostic(pattern, &text))
+            .collect();
+        let count = diagnostics.len();
+        self.client
+            .publish_diagnostics(uri, diagnostics, None)
+            .await;
+        Ok(count)
+    }
+
+    async fn refresh(&self, uri: Url, text: String, llm: LlmUse) {
+        if !is_rust(&uri) {
+            return;
+        }
+        self.documents
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(uri.clone(), text.clone());
+        if let Err(e) = self.publish(uri.clone(), text, llm).await {
+            self.client
+                .log_message(MessageType::WARNING, format!("RustRecon: {}: {:#}", uri, e))
+                .await;
+        }
+    }
+
+    fn document(&self, uri: &Url) -> Option<String> {
+        self.documents
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(uri)
+            .cloned()
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for Backend {
+    async fn initialize(&self, params: InitializeParams) -> jsonrpc::Result<InitializeResult> {
+        #[allow(deprecated)] // Older clients only send `root_uri`
+        let root = params
+            .workspace_folders
+            .and_then(|folders| folders.into_iter().next().map(|folder| folder.uri))
+            .or(params.root_uri)
+            .and_then(|uri| uri.to_file_path().ok())
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("."));
+        let workspace = self.open_workspace(root).await.map_err(internal_error)?;
+        info!(
+            "Language server ready for {} ({})",
+            workspace.root.display(),
+            if workspace.llm.is_some() {
+                "static rules and LLM"
+            } else {
+                "static rules only"
+            }
+        );
+        let commands = if workspace.llm.is_some() {
+            vec![ANALYZE_COMMAND.to_string()]
+        } else {
+            Vec::new()
+        };
+        let _ = self.workspace.set(workspace);
+        Ok(InitializeResult {
+            capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Options(
+                    TextDocumentSyncOptions {
+                        open_close: Some(true),
+                        change: Some(TextDocumentSyncKind::FULL),
+                        save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                        ..TextDocumentSyncOptions::default()
+                    },
+                )),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands,
+                    ..ExecuteCommandOptions::default()
+                }),
+                ..ServerCapabilities::default()
+            },
+            server_info: Some(ServerInfo {
+                name: "rustrecon".to_string(),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }),
+        })
+    }
+
+    async fn shutdown(&self) -> jsonrpc::Result<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let document = params.text_document;
+        self.refresh(document.uri, document.text, LlmUse::Cached)
+            .await;
+    }
+
+    // Cached analyses are looked up on open and save only: a lookup per
+    // keystroke would mostly miss, and may go to the remote cache
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        if let Some(change) = params.content_changes.into_iter().last() {
+            self.refresh(params.text_document.uri, change.text, LlmUse::None)
+                .await;
+        }
+    }
+
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        let uri = params.text_document.uri;
+        if let Some(text) = params.text.or_else(|| self.document(&uri)) {
+            self.refresh(uri, text, LlmUse::Cached).await;
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.documents
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&uri);
+        self.client.publish_diagnostics(uri, Vec::new(), None).await;
+    }
+
+    async fn code_action(
+        &self,
+        params: CodeActionParams,
+    ) -> jsonrpc::Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let has_llm = self
+            .workspace
+            .get()
+            .is_some_and(|workspace| workspace.llm.is_some());
+        if !has_llm || !is_rust(&uri) {
+            return Ok(None);
+        }
+        Ok(Some(vec![CodeActionOrCommand::Command(Command {
+            title: "RustRecon: analyze this file with the LLM".to_string(),
+            command: ANALYZE_COMMAND.to_string(),
+            arguments: Some(vec![serde_json::json!(uri)]),
+        })]))
+    }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> jsonrpc::Result<Option<serde_json::Value>> {
+        if params.command != ANALYZE_COMMAND {
+            return Err(jsonrpc::Error::method_not_found());
+        }
+        let uri: Url = params
+            .arguments
+            .into_iter()
+            .next()
+            .and_then(|argument| serde_json::from_value(argument).ok())
+            .ok_or_else(|| jsonrpc::Error::invalid_params("Expected the document URI"))?;
+        let text = match self.document(&uri) {
+            Some(text) => text,
+            None => uri
+                .to_file_path()
+                .ok()
+                .and_then(|path| std::fs::read_to_string(path).ok())
+                .ok_or_else(|| jsonrpc::Error::invalid_params("Unknown document"))?,
+        };
+        let name = uri
+            .path_segments()
+            .and_then(|mut s| s.next_back())
+            .unwrap_or_default()
+            .to_string();
+        match self.publish(uri, text, LlmUse::Analyze).await {
+            Ok(count) => {
+                self.client
+                    .show_message(
+                        MessageType::INFO,
+                        format!("RustRecon: {} findings in {}", count, name),
+                    )
+                    .await;
+                Ok(None)
+            }
+            Err(e) => {
+                self.client
+                    .show_message(
+                        MessageType::ERROR,
+                        format!("RustRecon could not analyze {}: {:#}", name, e),
+                    )
+                    .await;
+                Err(internal_error(e))
+            }
+        }
+    }
+}
+
+fn is_rust(uri: &Url) -> bool {
+    uri.scheme() == "file" && uri.path().ends_with(".rs")
+}
+
+fn internal_error(e: anyhow::Error) -> jsonrpc::Error {
+    jsonrpc::Error {
+        code: jsonrpc::ErrorCode::InternalError,
+        message: format!("{:#}", e).into(),
+        data: None,
+    }
+}
+
+/// A finding as an editor diagnostic covering its whole line. Findings
+/// without a line go on the first.
+fn diagnostic(pattern: &FlaggedPattern, text: &str) -> Diagnostic {
+    let line = pattern.line.saturating_sub(1);
+    // Positions count UTF-16 code units
+    let length = text
+        .lines()
+        .nth(line)
+        .map_or(0, |content| content.encode_utf16().count());
+    let severity = match pattern.severity.to_ascii_lowercase().as_str() {
+        "critical" | "high" => DiagnosticSeverity::ERROR,
+        "medium" => DiagnosticSeverity::WARNING,
+        "low" => DiagnosticSeverity::INFORMATION,
+        _ => DiagnosticSeverity::HINT,
+    };
+    Diagnostic {
+        range: Range::new(
+            Position::new(line as u32, 0),
+            Position::new(line as u32, length as u32),
+        ),
+        severity: Some(severity),
+        code: pattern.cwe.clone().map(NumberOrString::String),
+        source: Some("rustrecon".to_string()),
+        message: format!(
+            "{} ({:.0}% confidence)",
+            pattern.description,
+            pattern.confidence * 100.0
+        ),
+        ..Diagnostic::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finding_diagnostics() {
+        let text = "fn main() {\n    Command::new(\"sh\"); // ünïcode\n}\n";
+        let pattern = FlaggedPattern {
+            line: 2,
+            severity: "High".to_string(),
+            description: "Spawns a shell".to_string(),
+            code_snippet: "Command::new(\"sh\")".to_string(),
+            confidence: 0.8,
+            cwe: Some("CWE-78".to_string()),
+            owasp: None,
+            id: None,
+            triage: None,
+            blame: None,
+            rule: None,
+        };
+        let found = diagnostic(&pattern, text);
+        assert_eq!(found.severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(found.range.start, Position::new(1, 0));
+        assert_eq!(found.range.end, Position::new(1, 34));
+        assert_eq!(
+            found.code,
+            Some(NumberOrString::String("CWE-78".to_string()))
+        );
+        assert_eq!(found.message, "Spawns a shell (80% confidence)");
+
+        let unplaced = diagnostic(
+            &FlaggedPattern {
+                line: 0,
+                severity: "Info".to_string(),
+                ..pattern
+            },
+            text,
+        );
+        assert_eq!(unplaced.range.start, Position::new(0, 0));
+        assert_eq!(unplaced.severity, Some(DiagnosticSeverity::HINT));
+        assert!(is_rust(&Url::parse("file:///work/src/lib.rs").unwrap()));
+        assert!(!is_rust(&Url::parse("untitled:Untitled-1").unwrap()));
+    }
+}
@@ -1,185 +1,2599 @@
-use anyhow::Result;
-use clap::Parser;
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::EnvFilter;
 
+mod artifacts;
+mod baseline;
+mod batch;
+mod blame;
+mod cache;
+mod changes;
+mod checksum;
+mod circuit_breaker;
 mod cli;
 mod config;
+mod config_upgrade;
+mod context_budget;
+mod crates_index;
+mod database;
+mod dependency_graph;
 mod dependency_scanner;
+mod exit_code;
+mod github;
+mod grammars;
+mod hook;
+mod indicators;
+mod interactive;
+mod keychain;
+mod languages;
 mod llm_client;
+mod lockfile;
+mod lsp;
+mod manifest_lint;
+mod mcp;
+mod metrics;
+mod models;
+mod native_code;
+mod network;
+mod notify;
+mod org_audit;
+mod osv;
+mod policy;
+mod progress;
+mod rate_limit;
+mod remote_cache;
 mod report;
+mod report_stream;
+mod review;
+mod rule_packs;
+mod scan_budget;
 mod scanner;
+mod server;
+mod severity;
+mod signing;
+mod source_verify;
+mod static_rules;
+mod taxonomy;
+mod threat_intel;
+mod triage;
+mod typosquat;
+mod unsafe_metrics;
 mod utils;
 
-use cli::{Cli, Commands};
-use config::Config;
-use dependency_scanner::DependencyScanner;
-use llm_client::{GeminiClient, LlmClientTrait, LlmRequest};
-use report::RiskReport;
+use cache::{CacheExport, CacheKey, CacheStore, CachedLlmClient, CACHE_EXPORT_FORMAT_VERSION};
+use circuit_breaker::CircuitBreaker;
+use cli::{CacheAction, Cli, Commands, ConfigAction, HookAction, ModelsAction, TriageAction};
+use config::{
+    CacheConfig, Config, IssueLevel, LlmConfig, RateLimitConfig, ScanProfile, ScannerConfig,
+};
+use context_budget::ContextBudget;
+use database::Database;
+use dependency_scanner::{DependencyScan, DependencyScanner, RiskScore};
+use languages::Language;
+use llm_client::{
+    GeminiClient, LlmClientError, LlmClientTrait, LlmRequest, LlmResponse, ModelInfo,
+    PromptProfile, DEFAULT_GEMINI_MODEL,
+};
+use policy::Policy;
+use progress::{EtaTracker, ProgressEvent};
+use remote_cache::{RemoteCache, TieredCacheStore};
+use report::{CrateFinding, RiskReport};
+use report_stream::{ReportStream, StreamFormat};
+use scan_budget::ScanBudget;
 use scanner::Scanner;
+use utils::CodeChunk;
+
+/// Earlier scans shown in a report's trend section.
+const HISTORY_IN_REPORT: usize = 9;
+/// How long a stored dependency scan is reused. Registry metadata (yanks,
+/// owner changes, new releases) changes without the lockfile changing.
+const DEPENDENCY_SCAN_MAX_AGE_HOURS: i64 = 24;
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
+async fn main() {
+    // Usage errors exit with CONFIG_ERROR rather than clap's own status 2
+    let cli = Cli::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        std::process::exit(if e.use_stderr() {
+            exit_code::CONFIG_ERROR
+        } else {
+            exit_code::CLEAN
+        })
+    });
+    init_logging(&cli);
+    let code = run(&cli).await.unwrap_or_else(|e| {
+        eprintln!("Error: {:?}", e);
+        exit_code::for_error(&e)
+    });
+    if code != exit_code::CLEAN {
+        std::process::exit(code);
+    }
+}
+
+async fn run(cli: &Cli) -> Result<i32> {
+    if cli.non_interactive {
+        interactive::disable();
+        progress::hide_bars();
+    }
+
+    if let Some(dir) = &cli.generate_man {
+        let dir = Path::new(dir);
+        std::fs::create_dir_all(dir)?;
+        clap_mangen::generate_to(Cli::command(), dir)?;
+        info!("Man pages written to {}", dir.display());
+        return Ok(exit_code::CLEAN);
+    }
 
+    if cli.no_network_except_llm {
+        enable_network_gate();
+    }
+
+    // Commands that only touch local files work even when [network] is broken
+    if !matches!(
+        cli.command,
+        Some(
+            Commands::Init { .. }
+                | Commands::Config { .. }
+                | Commands::Diagnose
+                | Commands::Metrics { .. }
+                | Commands::Verify { .. }
+        )
+    ) {
+        apply_user_config().map_err(exit_code::config_error)?;
+    }
+
+    let result = run_command(cli).await;
+    let blocked = network::blocked_hosts();
+    if !blocked.is_empty() {
+        // The command may have carried on without the blocked requests
+        if let Err(e) = &result {
+            error!("❌ {:#}", e);
+        }
+        anyhow::bail!(
+            "--no-network-except-llm blocked requests to {}; add hosts you trust to `allowed_hosts` under [network]",
+            blocked.join(", ")
+        );
+    }
+    result
+}
+
+/// Runs the command and gives its exit status. The process exits only in
+/// `main`, after `run` has checked for blocked hosts.
+async fn run_command(cli: &Cli) -> Result<i32> {
+    let result = run_subcommand(cli).await;
+    // LLM requests made outside a scan, such as by `deps` or `test`
+    if metrics::is_enabled() {
+        if let Some(db) = open_database() {
+            flush_metrics(&db);
+        }
+    }
+    result
+}
+
+async fn run_subcommand(cli: &Cli) -> Result<i32> {
     match &cli.command {
-        Some(Commands::Init { config_path }) => {
-            println!("Initializing configuration file at: {}", config_path);
-            Config::generate_default_config(PathBuf::from(config_path))?;
-            println!("Default configuration written successfully.");
+        Some(Commands::Init {
+            config_path,
+            keychain,
+            api_key,
+            provider: _,
+            model,
+            endpoint,
+            yes,
+            no_verify,
+        }) => {
+            info!("Initializing configuration file at: {}", config_path);
+            if Path::new(config_path).exists() && !yes {
+                interactive::require(
+                    &format!("Replacing {}", config_path),
+                    "pass --yes to replace it",
+                )?;
+                print!("{} already exists. Replace it? [y/N] ", config_path);
+                std::io::stdout().flush()?;
+                let mut answer = String::new();
+                std::io::stdin().read_line(&mut answer)?;
+                if !matches!(answer.trim(), "y" | "Y" | "yes") {
+                    anyhow::bail!("{} was left unchanged", config_path);
+                }
+            }
+            let mut llm = LlmConfig::gemini_defaults();
+            if let Some(endpoint) = endpoint {
+                llm.gemini_api_endpoint = endpoint.trim_end_matches('/').to_string();
+            }
+            let api_key = if *keychain {
+                let mut api_key = api_key
+                    .clone()
+                    .or_else(|| std::env::var(LlmConfig::API_KEY_ENV_VAR).ok())
+                    .unwrap_or_default();
+                if api_key.is_empty() {
+                    interactive::require(
+                        "Entering the API key",
+                        &format!("pass --api-key or set {}", LlmConfig::API_KEY_ENV_VAR),
+                    )?;
+                    print!("Enter your Gemini API key: ");
+                    std::io::stdout().flush()?;
+                    std::io::stdin().read_line(&mut api_key)?;
+                }
+                let api_key = api_key.trim().to_string();
+                if api_key.is_empty() {
+                    anyhow::bail!("No API key entered");
+                }
+                Some(api_key)
+            } else {
+                api_key.as_ref().map(|key| key.trim().to_string())
+            };
+
+            let verified = match &api_key {
+                Some(api_key) if !no_verify => {
+                    verified_model(&llm, api_key, model.as_deref(), *yes).await?
+                }
+                _ => None,
+            };
+            match verified {
+                Some(chosen) => {
+                    info!("✅ API key accepted; using {}", chosen.name);
+                    llm.context_window = chosen.input_token_limit;
+                    llm.model = Some(chosen.name);
+                }
+                None => {
+                    if let Some(model) = model {
+                        llm.model = Some(model.clone());
+                    }
+                }
+            }
+
+            if let Some(api_key) = api_key {
+                if *keychain {
+                    keychain::store_api_key(keychain::DEFAULT_API_KEY_ENTRY, &api_key)?;
+                    info!("🔐 API key stored in the OS keychain.");
+                    llm.keychain_entry = Some(keychain::DEFAULT_API_KEY_ENTRY.to_string());
+                } else {
+                    llm.gemini_api_key = api_key;
+                }
+            }
+            Config::generate_default_config(PathBuf::from(config_path), llm)?;
+            info!("Default configuration written successfully.");
         }
         Some(Commands::Test) => {
-            println!("🔍 Testing LLM API connection...");
+            info!("🔍 Testing LLM API connection...");
 
             // Load configuration
             let config = Config::load_from_default_paths()?;
             let llm_config = config.llm.ok_or_else(|| {
-                anyhow::anyhow!("LLM configuration not found. Please run 'init' first and configure your API key.")
+                exit_code::config_error(anyhow::anyhow!("LLM configuration not found. Please run 'init' first and configure your API key."))
             })?;
 
             // Initialize LLM client
-            let gemini_client = GeminiClient::new(
-                llm_config.gemini_api_key.clone(),
-                llm_config.gemini_api_endpoint.clone(),
-            );
+            let api_key = llm_config.resolve_api_key()?;
+            if api_key.is_empty() || api_key.starts_with("YOUR_") || api_key.starts_with("PASTE_") {
+                return Err(exit_code::config_error(anyhow::anyhow!(
+                    "The API key is missing or still a placeholder; set llm.gemini_api_key in rustrecon_config.toml or {}",
+                    LlmConfig::API_KEY_ENV_VAR
+                )));
+            }
+            let gemini_client = gemini_client(&llm_config, api_key)?;
 
             // Simple test request
             let test_request = LlmRequest {
                 prompt: "Hello! Please respond with 'API test successful' to confirm the connection is working.".to_string(),
+                cache_key: None,
+                language: Language::Rust,
             };
 
-            match gemini_client.analyze_code(test_request).await {
-                Ok(response) => {
-                    println!("✅ API connection successful!");
-                    println!("📋 Test response: {}", response.analysis);
-                    if !response.flagged_patterns.is_empty() {
-                        println!("🔍 Found {} test patterns", response.flagged_patterns.len());
-                    }
-                    println!("\n🎉 Your Gemini API is configured correctly!");
-                    println!("   You can now run: cargo run -- scan . -o report.md");
-                }
-                Err(e) => {
-                    println!("❌ API test failed: {}", e);
-                    println!("\n💡 Check your configuration:");
-                    println!("   1. Verify your API key in rustrecon_config.toml");
-                    println!("   2. Ensure internet connectivity");
-                    println!("   3. Check if you've exceeded rate limits");
-                    if llm_config.gemini_api_key.starts_with("PASTE_")
-                        || llm_config.gemini_api_key.len() < 20
-                    {
-                        println!(
-                            "   4. Your API key looks like a placeholder - please set a real key"
-                        );
+            let response = gemini_client.analyze_code(test_request).await.context(
+                "API test failed; check the API key, internet connectivity and rate limits",
+            )?;
+            info!("✅ API connection successful!");
+            info!("📋 Test response: {}", response.analysis);
+            if !response.flagged_patterns.is_empty() {
+                info!("🔍 Found {} test patterns", response.flagged_patterns.len());
+            }
+            info!("🎉 Your Gemini API is configured correctly! You can now run: rustrecon scan . -o report.md");
+        }
+        Some(Commands::Config { action }) => match action {
+            ConfigAction::Validate { config_path } => {
+                let path = resolve_config_path(config_path.as_deref())?;
+                info!("🔍 Validating {}", path.display());
+
+                let issues = config::validate_file(&path)?;
+                let errors = issues
+                    .iter()
+                    .filter(|issue| issue.level == IssueLevel::Error)
+                    .count();
+                for issue in &issues {
+                    match issue.level {
+                        IssueLevel::Error => println!("❌ {}", issue.message),
+                        IssueLevel::Warning => println!("⚠️  {}", issue.message),
                     }
                 }
+
+                if errors > 0 {
+                    return Err(exit_code::config_error(anyhow::anyhow!(
+                        "Configuration has {} error(s)",
+                        errors
+                    )));
+                }
+                if issues.is_empty() {
+                    println!("✅ Configuration is valid");
+                } else {
+                    println!("✅ Configuration is usable ({} warning(s))", issues.len());
+                }
+            }
+            ConfigAction::Show {
+                config_path,
+                project,
+            } => {
+                let path = resolve_config_path(config_path.as_deref())?;
+                let mut config = Config::load_from_path(&path)?;
+                config.apply_env();
+                config.apply_project_file(&PathBuf::from(project))?;
+                println!("# Effective configuration ({})", path.display());
+                print!("{}", config.to_redacted_toml()?);
+            }
+            ConfigAction::Upgrade {
+                config_path,
+                dry_run,
+            } => {
+                let path = resolve_config_path(config_path.as_deref())?;
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let (upgraded, added) = config_upgrade::upgrade(&content)
+                    .with_context(|| format!("Cannot upgrade {}", path.display()))?;
+                if *dry_run {
+                    print!("{}", upgraded);
+                } else if added.is_empty() {
+                    println!("✅ {} is up to date", path.display());
+                } else {
+                    let backup = PathBuf::from(format!("{}.bak", path.display()));
+                    std::fs::copy(&path, &backup)
+                        .with_context(|| format!("Failed to back up {}", path.display()))?;
+                    std::fs::write(&path, upgraded)
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    println!("✅ Upgraded {}: added {}", path.display(), added.join(", "));
+                    println!("   The previous version is in {}", backup.display());
+                }
+            }
+        },
+        Some(Commands::CheckName { crate_name }) => {
+            info!("🔍 Checking crate name: {}", crate_name);
+
+            let user_config = Config::load_or_default()?;
+            let mut dependency_scanner = DependencyScanner::new();
+            dependency_scanner.set_metadata_source(&user_config.dependencies);
+            dependency_scanner
+                .load_popular_packages(open_database().as_mut())
+                .await;
+            let result = dependency_scanner.check_crate_name(crate_name).await?;
+
+            if result.exists_on_crates_io {
+                println!("📦 Found on crates.io");
+            } else {
+                println!("📦 Not published on crates.io (registry checks skipped)");
+            }
+
+            if result.metadata_flags.is_empty() {
+                println!("   No red flags found.");
+            } else {
+                println!("🚩 Flags:");
+                for flag in &result.metadata_flags {
+                    println!(
+                        "   - {} ({}): {}",
+                        flag.severity, flag.flag_type, flag.description
+                    );
+                }
+            }
+
+            if !result.closest_popular.is_empty() {
+                println!("🔗 Closest popular packages:");
+                for (name, distance) in &result.closest_popular {
+                    println!("   - {} (edit distance {})", name, distance);
+                }
+            }
+
+            let verdict = match result.risk_score {
+                RiskScore::Critical | RiskScore::High => {
+                    "❌ Do not add this crate without a manual review"
+                }
+                RiskScore::Medium => "⚠️  Review this crate carefully before adding it",
+                RiskScore::Low | RiskScore::Clean => "✅ No significant concerns",
+            };
+            println!("\nVerdict: {:?} - {}", result.risk_score, verdict);
+        }
+        Some(Commands::Deps {
+            lockfile,
+            fast,
+            output,
+        }) => {
+            let lockfile_path = PathBuf::from(lockfile);
+            let mut database = open_database();
+            let user_config = Config::load_or_default()?;
+            let mut dependency_scanner = DependencyScanner::new();
+            dependency_scanner.set_metadata_source(&user_config.dependencies);
+            dependency_scanner
+                .load_popular_packages(database.as_mut())
+                .await;
+
+            let packages = lockfile::parse_lockfile(&lockfile_path)?;
+            dependency_scanner.load_checksums(&packages);
+            dependency_scanner
+                .load_vulnerabilities(&packages, database.as_ref())
+                .await;
+
+            let dependency_scan = if *fast {
+                info!(
+                    "⚡ Fast lockfile scan: {} packages in {}",
+                    packages.len(),
+                    lockfile_path.display()
+                );
+                DependencyScan {
+                    results: dependency_scanner.scan_lockfile_fast(&packages).await?,
+                    skipped: Vec::new(),
+                }
+            } else {
+                let project_path = match lockfile_path.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                    _ => PathBuf::from("."),
+                };
+
+                let config = Config::load_for_project(&project_path)?;
+                let profile = config.scan_profile("standard")?;
+                let llm_config = config.llm.ok_or_else(|| {
+                    exit_code::config_error(anyhow::anyhow!(
+                        "LLM configuration not found. Please run `init` or use `--fast`."
+                    ))
+                })?;
+                let gemini_client = cached_client(
+                    gemini_client(&llm_config, llm_config.resolve_api_key()?)?
+                        .with_prompt_profile(parse_prompt_profile(&config.scanner)?)
+                        .with_max_output_tokens(profile.max_output_tokens)
+                        .with_temperature(profile.temperature),
+                    &config.rate_limiting,
+                    Some(&config.cache),
+                    Arc::default(),
+                );
+                dependency_scanner.add_trusted_packages(&config.dependencies.trusted);
+                dependency_scanner.add_approved_sources(&config.dependencies.approved_sources);
+                dependency_scanner.set_severity_overrides(config.severity);
+                dependency_scanner.set_request_interval(config.rate_limiting.request_interval());
+                dependency_scanner.set_limits(&config.dependencies);
+                dependency_scanner
+                    .set_native_review(config.dependencies.native_review.unwrap_or(false));
+
+                scan_dependencies_cached(
+                    &dependency_scanner,
+                    &project_path,
+                    Some(&gemini_client),
+                    database.as_mut(),
+                )
+                .await?
+            };
+            let DependencyScan {
+                results: dependency_results,
+                skipped,
+            } = dependency_scan;
+
+            let high_risk = dependency_results
+                .iter()
+                .filter(|d| matches!(d.risk_score, RiskScore::Critical | RiskScore::High))
+                .count();
+            let json = serde_json::to_string_pretty(&serde_json::json!({
+                "lockfile": lockfile_path.display().to_string(),
+                "total_dependencies": dependency_results.len(),
+                "high_risk_dependencies": high_risk,
+                "dependencies": dependency_results,
+                "skipped_dependencies": skipped,
+            }))?;
+
+            if let Some(out_path) = output {
+                std::fs::write(out_path, json)?;
+                info!("Results written to {}", out_path);
+            } else {
+                println!("{}", json);
+            }
+        }
+        Some(Commands::DepsDiff { base, head, output }) => {
+            let base_packages = lockfile::parse_lockfile(Path::new(base))?;
+            let head_packages = lockfile::parse_lockfile(Path::new(head))?;
+            let diff = lockfile::diff_lockfiles(&base_packages, &head_packages);
+            info!(
+                "🔀 {} added, {} updated, {} removed dependencies",
+                diff.added.len(),
+                diff.updated.len(),
+                diff.removed.len()
+            );
+
+            let mut database = open_database();
+            let user_config = Config::load_or_default()?;
+            let mut dependency_scanner = DependencyScanner::new();
+            dependency_scanner.set_metadata_source(&user_config.dependencies);
+            dependency_scanner
+                .load_popular_packages(database.as_mut())
+                .await;
+            let changed_packages = diff.changed_packages();
+            dependency_scanner
+                .load_vulnerabilities(&changed_packages, database.as_ref())
+                .await;
+            let dependency_results = dependency_scanner
+                .scan_lockfile_fast(&changed_packages)
+                .await?;
+
+            let high_risk = dependency_results
+                .iter()
+                .filter(|d| matches!(d.risk_score, RiskScore::Critical | RiskScore::High))
+                .count();
+            let json = serde_json::to_string_pretty(&serde_json::json!({
+                "base": base,
+                "head": head,
+                "added": diff.added.iter().map(|p| format!("{} {}", p.name, p.version)).collect::<Vec<_>>(),
+                "updated": diff.updated.iter().map(|u| serde_json::json!({
+                    "name": u.package.name,
+                    "version": u.package.version,
+                    "previous_versions": u.previous_versions,
+                })).collect::<Vec<_>>(),
+                "removed": diff.removed,
+                "high_risk_dependencies": high_risk,
+                "dependencies": dependency_results,
+            }))?;
+
+            if let Some(out_path) = output {
+                std::fs::write(out_path, json)?;
+                info!("Results written to {}", out_path);
+            } else {
+                println!("{}", json);
+            }
+
+            // Non-zero exit so CI can block the pull request
+            if high_risk > 0 {
+                error!("❌ {} high-risk dependency changes", high_risk);
+                return Ok(exit_code::FINDINGS);
             }
         }
         Some(Commands::Scan {
             crate_path,
             format,
+            template,
             output,
             scan_dependencies,
             skip_dependencies,
+            dependency_depth,
+            max_dependencies,
+            max_llm_calls,
+            max_duration,
+            max_api_calls,
+            reproducible,
+            absolute_paths,
+            resume,
+            profile,
+            offline,
+            verify_sources,
+            expand_macros,
+            no_cache,
+            progress_format,
+            exclude,
+            include,
+            only,
+            files,
+            changed,
+            since,
+            blame,
+            no_ignore,
+            min_confidence,
+            policy,
+            policy_baseline,
+            fail_on,
+            no_notify,
+            stream,
+            sign,
+            attest,
+            signing_key,
         }) => {
-            println!("Scanning crate: {}", crate_path);
-            println!("Output format: {}", format);
+            if let Some(min_confidence) = *min_confidence {
+                check_min_confidence(min_confidence)?;
+            }
+            if let Some(level) = fail_on {
+                check_fail_on(level)?;
+            }
+            // Read the policy up front so a broken file fails before the scan
+            let policy = policy
+                .as_ref()
+                .map(|path| Policy::load(Path::new(path)))
+                .transpose()
+                .map_err(exit_code::config_error)?;
+            let policy_baseline = policy_baseline
+                .as_ref()
+                .map(|path| RiskReport::load(Path::new(path)))
+                .transpose()
+                .map_err(exit_code::config_error)?;
+            if policy.as_ref().is_some_and(Policy::needs_baseline) && policy_baseline.is_none() {
+                return Err(exit_code::config_error(anyhow::anyhow!(
+                    "The policy counts new findings; pass the previous JSON report with --policy-baseline"
+                )));
+            }
+            if progress_format == "json" {
+                progress::enable_json_events();
+            }
+            // `cargo recon scan` passes the workspace root in CARGO_MANIFEST_DIR
+            let crate_path = crate_path
+                .clone()
+                .or_else(|| std::env::var("CARGO_MANIFEST_DIR").ok())
+                .unwrap_or_else(|| ".".to_string());
+            info!("Scanning crate: {}", crate_path);
+            info!("Output format: {}", format);
             if let Some(out_path) = output {
-                println!("Output file: {}", out_path);
+                info!("Output file: {}", out_path);
             }
 
-            // Load configuration
-            let config = Config::load_from_default_paths()?;
-            let llm_config = config.llm.ok_or_else(|| {
-                anyhow::anyhow!("LLM configuration not found. Please run `init` or provide config.")
-            })?;
+            let project_path = PathBuf::from(&crate_path);
+            let changes = if *changed {
+                let changes = changes::since(&project_path, since.as_deref())?;
+                info!(
+                    "🔀 Changes since {}: {} files, {} dependencies added or updated",
+                    changes::short(&changes.base),
+                    changes.files.len(),
+                    changes.packages.len()
+                );
+                Some(changes)
+            } else {
+                None
+            };
 
-            // Initialize LLM client
-            let gemini_client =
-                GeminiClient::new(llm_config.gemini_api_key, llm_config.gemini_api_endpoint);
-
-            // Initialize scanners
-            let project_path = PathBuf::from(crate_path);
-            let mut scanner = Scanner::new(project_path.clone())?;
-            let file_analysis_results = scanner.scan_crate()?;
-
-            let mut risk_report =
-                RiskReport::new(crate::utils::get_crate_name_from_path(&project_path));
-
-            // Scan dependencies if enabled
-            let should_scan_deps = *scan_dependencies && !skip_dependencies;
-            if should_scan_deps {
-                println!("🔍 Starting dependency analysis for supply chain security...");
-                let dependency_scanner = DependencyScanner::new();
-                match dependency_scanner
-                    .scan_dependencies(&project_path, &gemini_client)
-                    .await
-                {
-                    Ok(dependency_results) => {
+            // Checked up front so an unsupported format fails before the scan
+            let stream_to = match output.as_ref().filter(|_| *stream) {
+                Some(out_path) => {
+                    Some((PathBuf::from(out_path), StreamFormat::for_format(format)?))
+                }
+                None => None,
+            };
+
+            // Load configuration (user config plus the crate's .rustrecon.toml)
+            let mut config = Config::load_for_project(&project_path)?;
+            if let Some(depth) = dependency_depth {
+                config.dependencies.depth = Some(depth.parse()?);
+            }
+            if let Some(max_dependencies) = max_dependencies {
+                config.dependencies.max_packages = Some(*max_dependencies as usize);
+            }
+            if let Some(max_llm_calls) = max_llm_calls {
+                config.dependencies.max_llm_calls = Some(*max_llm_calls);
+            }
+            let signing_key = match signing_key {
+                Some(key) => Some(PathBuf::from(key)),
+                None if *sign => Some(config.signing.key.clone().ok_or_else(|| {
+                    exit_code::config_error(anyhow::anyhow!("--sign needs a key: pass --signing-key or set key under [signing] in the config"))
+                })?),
+                None => None,
+            };
+            let risk_report = run_scan(
+                &project_path,
+                &config,
+                &ScanOptions {
+                    profile: profile.clone(),
+                    scan_dependencies: *scan_dependencies && !skip_dependencies,
+                    resume: *resume,
+                    offline: *offline,
+                    verify_sources: *verify_sources,
+                    expand_macros: *expand_macros,
+                    no_cache: *no_cache,
+                    exclude: exclude.clone(),
+                    include: include.clone(),
+                    only: only.clone(),
+                    files: files.clone(),
+                    changes,
+                    blame: *blame,
+                    no_ignore: *no_ignore,
+                    min_confidence: *min_confidence,
+                    skip_code: false,
+                    max_duration: *max_duration,
+                    max_api_calls: max_api_calls.map(|calls| calls as usize),
+                    reproducible: *reproducible,
+                    absolute_paths: *absolute_paths,
+                    stream: stream_to,
+                },
+            )
+            .await?;
+
+            let output_path = output.as_ref().map(PathBuf::from);
+            match template {
+                _ if *stream => {}
+                Some(template) => risk_report
+                    .generate_templated_report(Path::new(template), output_path.as_deref())?,
+                None => risk_report.generate_report(format, output_path.as_deref())?,
+            }
+            if let Some(out_path) = &output_path {
+                if *attest {
+                    let statement =
+                        signing::attestation(out_path, &risk_report, &project_path, format)?;
+                    let statement_path = signing::attestation_path(out_path);
+                    std::fs::write(&statement_path, serde_json::to_string_pretty(&statement)?)?;
+                    info!("📜 Attestation written to {}", statement_path.display());
+                    if let Some(key) = &signing_key {
+                        signing::sign_file(&statement_path, key)?;
+                    }
+                }
+                if let Some(key) = &signing_key {
+                    let signature = signing::sign_file(out_path, key)?;
+                    info!("🔏 Signature written to {}", signature.display());
+                }
+            }
+            if !no_notify {
+                notify::send_alerts(&config.notifications, &risk_report, output.as_deref()).await;
+            }
+
+            info!("Scan complete. Report generated.");
+
+            if let Some(policy) = policy {
+                let outcomes =
+                    policy.evaluate(&risk_report, policy_baseline.as_ref(), &project_path)?;
+                let broken = outcomes.iter().filter(|o| !o.passed()).count();
+                for outcome in &outcomes {
+                    if outcome.passed() {
+                        info!("✅ {}", outcome);
+                    } else {
+                        error!("❌ {}", outcome);
+                    }
+                }
+                // Non-zero exit so CI can block the change
+                if broken > 0 {
+                    error!(
+                        "❌ Policy check failed: {} of {} rules broken",
+                        broken,
+                        outcomes.len()
+                    );
+                    return Ok(exit_code::FINDINGS);
+                }
+                info!("✅ Policy check passed ({} rules)", outcomes.len());
+            }
+            if let Some(level) = fail_on {
+                let blocking = risk_report.count_at_least(level);
+                // Non-zero exit so CI can block the change
+                if blocking > 0 {
+                    error!("❌ {} findings at {} or above", blocking, level);
+                    return Ok(exit_code::FINDINGS);
+                }
+            }
+        }
+        Some(Commands::ScanMany {
+            targets,
+            jobs,
+            format,
+            output,
+            reports_dir,
+            profile,
+            offline,
+            skip_dependencies,
+            min_confidence,
+        }) => {
+            if let Some(min_confidence) = *min_confidence {
+                check_min_confidence(min_confidence)?;
+            }
+            let projects = batch::resolve_targets(targets)?;
+            if projects.is_empty() {
+                anyhow::bail!("No projects found for '{}'", targets);
+            }
+            info!(
+                "Scanning {} projects, {} at a time",
+                projects.len(),
+                jobs.max(&1)
+            );
+            let options = ScanOptions {
+                profile: profile.clone(),
+                scan_dependencies: !skip_dependencies,
+                resume: false,
+                offline: *offline,
+                verify_sources: false,
+                expand_macros: false,
+                no_cache: false,
+                exclude: Vec::new(),
+                include: Vec::new(),
+                only: Vec::new(),
+                files: Vec::new(),
+                changes: None,
+                blame: false,
+                no_ignore: false,
+                min_confidence: *min_confidence,
+                skip_code: false,
+                max_duration: None,
+                max_api_calls: None,
+                reproducible: false,
+                absolute_paths: false,
+                stream: None,
+            };
+            let aggregate = batch::scan_all(
+                &projects,
+                *jobs,
+                &options,
+                reports_dir.as_deref().map(Path::new),
+            )?;
+
+            let content = aggregate.render(format)?;
+            match output {
+                Some(out_path) => {
+                    std::fs::write(out_path, content)?;
+                    info!("Aggregate report written to {}", out_path);
+                }
+                None => print!("{}", content),
+            }
+
+            let failed = aggregate.failed();
+            if failed > 0 {
+                error!("❌ {} of {} project scans failed", failed, projects.len());
+                return Ok(exit_code::SCAN_ERROR);
+            }
+        }
+        Some(Commands::AuditOrg {
+            github: owner,
+            token,
+            api_url,
+            include_forks,
+            include_archived,
+            clone_dir,
+            jobs,
+            format,
+            output,
+            reports_dir,
+            profile,
+            offline,
+        }) => {
+            let token = token
+                .clone()
+                .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+                .filter(|token| !token.is_empty());
+            let client = github::GitHub::new(api_url, token.as_deref())?;
+            let filter = org_audit::RepoFilter {
+                include_forks: *include_forks,
+                include_archived: *include_archived,
+            };
+            let repos = org_audit::list_rust_repos(&client, owner, &filter).await?;
+            if repos.is_empty() {
+                anyhow::bail!("{} has no Rust repositories to audit", owner);
+            }
+            info!("Auditing {} Rust repositories of {}", repos.len(), owner);
+
+            let mut temp_dir = None;
+            let dir = match clone_dir {
+                Some(dir) => PathBuf::from(dir),
+                None => temp_dir
+                    .insert(org_audit::TempCloneDir::create(owner)?)
+                    .path()
+                    .to_path_buf(),
+            };
+            let (checkouts, clone_failures) = org_audit::clone_all(&repos, &dir, token.as_deref())?;
+
+            // Dependency-only: the organization's own code is left to per-repository scans
+            let options = ScanOptions {
+                profile: profile.clone(),
+                scan_dependencies: true,
+                resume: false,
+                offline: *offline,
+                verify_sources: false,
+                expand_macros: false,
+                no_cache: false,
+                exclude: Vec::new(),
+                include: Vec::new(),
+                only: Vec::new(),
+                files: Vec::new(),
+                changes: None,
+                blame: false,
+                no_ignore: false,
+                min_confidence: None,
+                skip_code: true,
+                max_duration: None,
+                max_api_calls: None,
+                reproducible: false,
+                absolute_paths: false,
+                stream: None,
+            };
+            let mut dashboard = batch::scan_all(
+                &checkouts,
+                *jobs,
+                &options,
+                reports_dir.as_deref().map(Path::new),
+            )?;
+            dashboard.extend(clone_failures);
+            // List repositories as owner/name rather than by their clone paths
+            for project in &mut dashboard.projects {
+                if let Ok(name) = project.path.strip_prefix(&dir) {
+                    project.path = Path::new(owner.as_str()).join(name);
+                }
+            }
+
+            let content = dashboard.render(format)?;
+            match output {
+                Some(out_path) => {
+                    std::fs::write(out_path, content)?;
+                    info!("Dashboard written to {}", out_path);
+                }
+                None => print!("{}", content),
+            }
+
+            let failed = dashboard.failed();
+            if failed > 0 {
+                error!(
+                    "❌ {} of {} repositories could not be audited",
+                    failed,
+                    repos.len()
+                );
+                return Ok(exit_code::SCAN_ERROR);
+            }
+        }
+        Some(Commands::History { crate_ref, limit }) => {
+            let database = Database::open_default()?;
+            // Scans are recorded under the canonical crate path; fall back to the crate name
+            let history = match Path::new(crate_ref).canonicalize() {
+                Ok(path) => database.scan_history(&path.to_string_lossy(), *limit)?,
+                Err(_) => Vec::new(),
+            };
+            let history = if history.is_empty() {
+                database.scan_history(crate_ref, *limit)?
+            } else {
+                history
+            };
+            if history.is_empty() {
+                println!("No recorded scans for {}", crate_ref);
+                return Ok(exit_code::CLEAN);
+            }
+
+            println!(
+                "{:<20} {:>6} {:>9} {:>5} {:>7} {:>5} {:>6} {:>10}",
+                "Scanned", "Files", "Patterns", "High", "Medium", "Low", "Deps", "High-risk"
+            );
+            for entry in &history {
+                let summary = &entry.summary;
+                let severity =
+                    |level: &str| summary.severity_counts.get(level).copied().unwrap_or(0);
+                let scanned_at = chrono::DateTime::parse_from_rfc3339(&entry.timestamp)
+                    .map(|ts| ts.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|_| entry.timestamp.clone());
+                println!(
+                    "{:<20} {:>6} {:>9} {:>5} {:>7} {:>5} {:>6} {:>10}",
+                    scanned_at,
+                    summary.total_files_scanned,
+                    summary.total_flagged_patterns,
+                    severity("High"),
+                    severity("Medium"),
+                    severity("Low"),
+                    summary.total_dependencies_scanned,
+                    summary.high_risk_dependencies
+                );
+            }
+
+            let patterns: Vec<usize> = history
+                .iter()
+                .map(|e| e.summary.total_flagged_patterns)
+                .collect();
+            let high_risk: Vec<usize> = history
+                .iter()
+                .map(|e| e.summary.high_risk_dependencies)
+                .collect();
+            println!("\nFlagged patterns:       {}", report::sparkline(&patterns));
+            println!("High-risk dependencies: {}", report::sparkline(&high_risk));
+        }
+        Some(Commands::Report {
+            report,
+            format,
+            template,
+            output,
+            min_confidence,
+        }) => {
+            let mut risk_report = RiskReport::load(Path::new(report))?;
+            if let Some(min_confidence) = *min_confidence {
+                check_min_confidence(min_confidence)?;
+                risk_report.apply_min_confidence(min_confidence);
+            }
+            let output_path = output.as_ref().map(PathBuf::from);
+            match template {
+                Some(template) => risk_report
+                    .generate_templated_report(Path::new(template), output_path.as_deref())?,
+                None => risk_report.generate_report(format, output_path.as_deref())?,
+            }
+        }
+        Some(Commands::Verify {
+            report,
+            allowed_signers,
+            identity,
+        }) => {
+            let report = Path::new(report);
+            let allowed_signers = Path::new(allowed_signers);
+            signing::verify_file(
+                report,
+                &signing::signature_path(report),
+                allowed_signers,
+                identity,
+            )?;
+            println!("✅ {} is signed by {}", report.display(), identity);
+            // Unsigned attestations prove nothing, so only signed ones are checked
+            let statement = signing::attestation_path(report);
+            if signing::signature_path(&statement).exists() {
+                signing::verify_file(
+                    &statement,
+                    &signing::signature_path(&statement),
+                    allowed_signers,
+                    identity,
+                )?;
+                signing::check_attestation_subject(&statement, report)?;
+                println!(
+                    "✅ {} is signed and matches the report",
+                    statement.display()
+                );
+            }
+        }
+        Some(Commands::Review { report, baseline }) => {
+            interactive::require(
+                "`review`",
+                "use `rustrecon triage` to record decisions without one",
+            )?;
+            review::run(Path::new(report), Path::new(baseline))?;
+        }
+        Some(Commands::Completions { shell }) => {
+            clap_complete::generate(
+                *shell,
+                &mut Cli::command(),
+                env!("CARGO_PKG_NAME"),
+                &mut std::io::stdout(),
+            );
+        }
+        Some(Commands::PrComment {
+            repo,
+            pr,
+            report,
+            root,
+            baseline,
+            token,
+            api_url,
+            fail_on,
+        }) => {
+            if let Some(level) = fail_on {
+                check_fail_on(level)?;
+            }
+            let token = token
+                .clone()
+                .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+                .filter(|token| !token.is_empty())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("No GitHub token; pass --token or set GITHUB_TOKEN")
+                })?;
+            let root = match root {
+                Some(root) => PathBuf::from(root),
+                None => git_toplevel().unwrap_or_else(|| PathBuf::from(".")),
+            };
+            let report = RiskReport::load(Path::new(report))?;
+            let baseline = baseline::Baseline::load(Path::new(baseline))?;
+            let target = github::PrTarget {
+                api_url,
+                repo,
+                number: *pr,
+                token: &token,
+            };
+            let findings = github::comment_on_pr(&target, &report, &root, &baseline).await?;
+
+            if let Some(level) = fail_on {
+                let blocking = findings
+                    .iter()
+                    .filter(|finding| github::is_blocking(finding, level))
+                    .count();
+                // Non-zero exit so CI can block the pull request
+                if blocking > 0 {
+                    error!("❌ {} new findings at {} or above", blocking, level);
+                    return Ok(exit_code::FINDINGS);
+                }
+            }
+        }
+        Some(Commands::Serve {
+            listen,
+            workers,
+            profile,
+            allow_local_paths,
+            token,
+        }) => {
+            let listen = listen
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid --listen address '{}': {}", listen, e))?;
+            server::serve(server::ServeOptions {
+                listen,
+                workers: *workers,
+                profile: profile.clone(),
+                allow_local_paths: *allow_local_paths,
+                token: token.clone().or_else(|| {
+                    std::env::var(server::TOKEN_ENV_VAR)
+                        .ok()
+                        .filter(|token| !token.is_empty())
+                }),
+            })
+            .await?;
+        }
+        Some(Commands::Lsp { profile }) => lsp::serve(profile.clone()).await?,
+        Some(Commands::Mcp { profile }) => mcp::serve(profile.clone()).await?,
+        Some(Commands::Hook { action }) => match action {
+            HookAction::Install { pre_commit, force } => hook::install(*pre_commit, *force)?,
+            HookAction::Run { staged } => {
+                if !hook::run(*staged).await? {
+                    return Ok(exit_code::FINDINGS);
+                }
+            }
+        },
+        Some(Commands::Diagnose) => {
+            println!("🩺 RustRecon {}", env!("CARGO_PKG_VERSION"));
+            match Config::find_default_path() {
+                Some(path) => println!("   Config: {}", path.display()),
+                None => println!("   Config: not found (run `rustrecon init`)"),
+            }
+
+            let database_path = Database::default_path()?;
+            println!("   Database: {}", database_path.display());
+            match Database::open(&database_path) {
+                Ok(database) => {
+                    println!("   Schema version: {}", database.schema_version()?);
+                    println!("   Cached analyses: {}", database.cache_stats()?.entries);
+                }
+                Err(e) => println!("   ❌ Database unavailable: {:#}", e),
+            }
+
+            for (tool, program, args, needed_for) in [
+                ("git", "git", &["--version"][..], "--verify-sources"),
+                (
+                    "cargo-expand",
+                    "cargo",
+                    &["expand", "--version"][..],
+                    "--expand-macros",
+                ),
+            ] {
+                let available = std::process::Command::new(program)
+                    .args(args)
+                    .output()
+                    .is_ok_and(|output| output.status.success());
+                let status = if available {
+                    "✅"
+                } else {
+                    "⚠️  not found:"
+                };
+                println!("   {} {} (needed for {})", status, tool, needed_for);
+            }
+        }
+        Some(Commands::Metrics {
+            format,
+            output,
+            reset,
+        }) => {
+            let database = Database::open_default()?;
+            if *reset {
+                let removed = database.reset_metrics()?;
+                info!("🗑️  Removed {} recorded metrics", removed);
+                return Ok(exit_code::CLEAN);
+            }
+            let values = database.load_metrics()?;
+            if values.is_empty() {
+                warn!("No metrics recorded; set `enabled = true` under [metrics] in the config to record them");
+            }
+            let content = match format.as_str() {
+                "prometheus" => metrics::to_prometheus(&values),
+                "json" => serde_json::to_string_pretty(&metrics::to_json(&values))? + "\n",
+                other => anyhow::bail!(
+                    "Unknown metrics format '{}' (expected prometheus or json)",
+                    other
+                ),
+            };
+            match output {
+                Some(path) => {
+                    // The textfile collector must never read a half-written file
+                    let partial = format!("{}.tmp", path);
+                    std::fs::write(&partial, content)?;
+                    std::fs::rename(&partial, path)?;
+                    info!("Metrics written to {}", path);
+                }
+                None => print!("{}", content),
+            }
+        }
+        Some(Commands::Models { action }) => match action {
+            ModelsAction::List { json } => {
+                let config = Config::load_from_default_paths()?;
+                let llm_config = config.llm.ok_or_else(|| {
+                    exit_code::config_error(anyhow::anyhow!(
+                        "LLM configuration not found. Please run `init` first."
+                    ))
+                })?;
+                let client = gemini_client(&llm_config, llm_config.resolve_api_key()?)?;
+                let catalog = models::fetch_catalog(
+                    &client,
+                    &llm_config.gemini_api_endpoint,
+                    open_database(),
+                )
+                .await
+                .with_context(|| {
+                    format!(
+                        "Could not fetch the model list from {}",
+                        llm_config.gemini_api_endpoint
+                    )
+                })?;
+                let configured = llm_config.model();
+                if *json {
+                    let listed: Vec<serde_json::Value> = catalog
+                        .iter()
+                        .map(|model| {
+                            serde_json::json!({
+                                "name": model.name,
+                                "display_name": model.display_name,
+                                "input_token_limit": model.input_token_limit,
+                                "deprecated": models::is_deprecated(model),
+                                "configured": model.name == configured,
+                            })
+                        })
+                        .collect();
+                    println!("{}", serde_json::to_string_pretty(&listed)?);
+                } else {
+                    println!("Models offered by {}:", llm_config.gemini_api_endpoint);
+                    for model in &catalog {
                         println!(
-                            "✅ Dependency scan completed. Found {} dependencies.",
-                            dependency_results.len()
+                            "{} {:<36} {:<32} {:>9}  {}",
+                            if model.name == configured { "*" } else { " " },
+                            model.name,
+                            model.display_name,
+                            model
+                                .input_token_limit
+                                .map(|limit| limit.to_string())
+                                .unwrap_or_default(),
+                            if models::is_deprecated(model) {
+                                "deprecated"
+                            } else {
+                                ""
+                            }
                         );
-                        risk_report.add_dependency_findings(dependency_results);
                     }
-                    Err(e) => {
-                        eprintln!("⚠️  Dependency scan failed: {}", e);
-                        println!("   Continuing with code-only analysis...");
+                    println!("\n* configured model");
+                }
+                if let Some(warning) = models::model_warning(&catalog, configured) {
+                    warn!("⚠️  {}", warning);
+                }
+            }
+        },
+        Some(Commands::Triage { action }) => {
+            let database = Database::open_default()?;
+            match action {
+                TriageAction::List {
+                    crate_name,
+                    state,
+                    json,
+                } => {
+                    let state = state
+                        .as_deref()
+                        .map(str::parse::<triage::TriageState>)
+                        .transpose()
+                        .map_err(anyhow::Error::msg)?;
+                    let findings = database.list_findings(crate_name.as_deref(), state)?;
+                    if *json {
+                        println!("{}", serde_json::to_string_pretty(&findings)?);
+                    } else if findings.is_empty() {
+                        println!("No recorded findings match.");
+                    } else {
+                        for finding in &findings {
+                            println!(
+                                "{}  {:<14}  {:<8}  {}  {}:{}  {}",
+                                finding.id,
+                                finding.status.state.label(),
+                                finding.severity,
+                                finding.crate_name,
+                                finding.file_path.display(),
+                                finding.line,
+                                finding.description
+                            );
+                            if let Some(note) = &finding.status.note {
+                                println!("{:14}  📝 {}", "", note);
+                            }
+                            for comment in database.finding_comments(&finding.id)? {
+                                println!("{:14}  💬 {}: {}", "", comment.author, comment.body);
+                            }
+                        }
                     }
                 }
-            } else {
-                println!("⏭️  Skipping dependency scan (disabled)");
+                TriageAction::Set {
+                    state,
+                    ids,
+                    note,
+                    reviewer,
+                } => {
+                    let state: triage::TriageState = state.parse().map_err(anyhow::Error::msg)?;
+                    let reviewer = triage::reviewer_name(reviewer.as_deref());
+                    for id in ids {
+                        let id = database.set_triage(id, state, note.as_deref(), &reviewer)?;
+                        info!("✅ {} marked {}", id, state.label());
+                    }
+                }
+                TriageAction::Comment { id, text, reviewer } => {
+                    let comment = triage::Comment {
+                        author: triage::reviewer_name(reviewer.as_deref()),
+                        created_at: chrono::Utc::now().to_rfc3339(),
+                        body: text.clone(),
+                    };
+                    let id = database.add_comment(id, &comment)?;
+                    info!("💬 Comment added to {}", id);
+                }
+                TriageAction::Export {
+                    output,
+                    crate_name,
+                    reviewer,
+                } => {
+                    let bundle = triage::AnnotationBundle {
+                        format_version: triage::BUNDLE_FORMAT_VERSION,
+                        reviewer: triage::reviewer_name(reviewer.as_deref()),
+                        exported_at: chrono::Utc::now().to_rfc3339(),
+                        findings: database.export_annotations(crate_name.as_deref())?,
+                    };
+                    std::fs::write(output, serde_json::to_string_pretty(&bundle)?)?;
+                    println!(
+                        "📤 Exported {} findings to {}",
+                        bundle.findings.len(),
+                        output
+                    );
+                }
+                TriageAction::Import { file } => {
+                    let content = std::fs::read_to_string(file)?;
+                    let bundle: triage::AnnotationBundle = serde_json::from_str(&content)
+                        .map_err(|e| anyhow::anyhow!("{} is not a triage bundle: {}", file, e))?;
+                    if bundle.format_version > triage::BUNDLE_FORMAT_VERSION {
+                        anyhow::bail!(
+                            "{} uses triage bundle format {}; this rustrecon reads up to {}",
+                            file,
+                            bundle.format_version,
+                            triage::BUNDLE_FORMAT_VERSION
+                        );
+                    }
+                    let summary = database.import_annotations(&bundle.findings)?;
+                    println!(
+                        "📥 Imported {} from {}: {} added, {} updated, {} kept (local decision as new or newer), {} comments added",
+                        file,
+                        bundle.reviewer,
+                        summary.added,
+                        summary.updated,
+                        summary.kept_local,
+                        summary.comments_added
+                    );
+                    if !summary.conflicts.is_empty() {
+                        println!(
+                            "⚠️  {} findings were triaged differently; the later decision was kept:",
+                            summary.conflicts.len()
+                        );
+                        for id in &summary.conflicts {
+                            println!("   {}", id);
+                        }
+                    }
+                }
+            }
+        }
+        Some(Commands::Cache {
+            clear,
+            invalidate_model,
+            export,
+            list,
+            package,
+            show,
+            delete,
+            action,
+        }) => {
+            let database = Database::open_default()?;
+            if *list {
+                let analyses = database.list_analyses(package.as_deref())?;
+                if analyses.is_empty() {
+                    println!("No cached analyses");
+                }
+                for stored in &analyses {
+                    let entry = &stored.entry;
+                    println!(
+                        "{:>6}  {}@{}  {}  {}  {} patterns",
+                        stored.id,
+                        entry.key.package,
+                        entry.key.version,
+                        entry.key.model,
+                        entry.scan_date.format("%Y-%m-%d %H:%M UTC"),
+                        entry.analysis.flagged_patterns.len()
+                    );
+                }
+                return Ok(exit_code::CLEAN);
+            }
+            if let Some(id) = show {
+                let stored = database
+                    .show_analysis(*id)?
+                    .ok_or_else(|| anyhow::anyhow!("No cached analysis with id {}", id))?;
+                let entry = &stored.entry;
+                println!("Package:  {}@{}", entry.key.package, entry.key.version);
+                println!("Model:    {}", entry.key.model);
+                println!("Prompt:   {}", entry.key.prompt_fingerprint);
+                println!("Content:  {}", entry.key.content_hash);
+                if let Some(version) = &entry.analysis.version {
+                    println!("Analyzer: {}", version);
+                }
+                println!("Scanned:  {}", entry.scan_date.format("%Y-%m-%d %H:%M UTC"));
+                println!("\n{}", entry.analysis.analysis);
+                for pattern in &entry.analysis.flagged_patterns {
+                    println!(
+                        "  - Line {} [{}, {:.0}% confidence] {}: {}",
+                        pattern.line,
+                        pattern.severity,
+                        pattern.confidence * 100.0,
+                        pattern.description,
+                        pattern.code_snippet
+                    );
+                }
+                return Ok(exit_code::CLEAN);
+            }
+            if let Some(package) = delete {
+                let removed = database.delete_package(package)?;
+                println!("🧹 Removed {} cached analyses of {}", removed, package);
+                return Ok(exit_code::CLEAN);
+            }
+            if let Some(CacheAction::Import { file }) = action {
+                let content = std::fs::read_to_string(file)?;
+                let import: CacheExport = serde_json::from_str(&content)
+                    .map_err(|e| anyhow::anyhow!("{} is not a cache export: {}", file, e))?;
+                if import.format_version > CACHE_EXPORT_FORMAT_VERSION {
+                    anyhow::bail!(
+                        "{} uses cache export format {}; this rustrecon reads up to {}",
+                        file,
+                        import.format_version,
+                        CACHE_EXPORT_FORMAT_VERSION
+                    );
+                }
+                let summary = database.import_analyses(&import.entries)?;
+                println!(
+                    "📥 Imported {}: {} added, {} updated, {} kept (local copy as new or newer)",
+                    file, summary.added, summary.updated, summary.kept_local
+                );
+                return Ok(exit_code::CLEAN);
+            }
+            if let Some(out_path) = export {
+                let entries = database.export_analyses()?;
+                let export = CacheExport {
+                    format_version: CACHE_EXPORT_FORMAT_VERSION,
+                    exported_at: chrono::Utc::now(),
+                    entries,
+                };
+                std::fs::write(out_path, serde_json::to_string_pretty(&export)?)?;
+                println!(
+                    "📤 Exported {} cached analyses to {}",
+                    export.entries.len(),
+                    out_path
+                );
+                return Ok(exit_code::CLEAN);
+            }
+            if *clear {
+                let removed = database.clear_analyses()?;
+                println!("🧹 Removed {} cached analyses", removed);
+                return Ok(exit_code::CLEAN);
+            }
+            if let Some(model) = invalidate_model {
+                let removed = database.invalidate_model(model)?;
+                println!("🧹 Removed {} cached analyses from {}", removed, model);
+                return Ok(exit_code::CLEAN);
+            }
+
+            let stats = database.cache_stats()?;
+            println!("📦 Analysis cache: {}", Database::default_path()?.display());
+            println!("   Entries: {}", stats.entries);
+            println!(
+                "   Hits: {}, misses: {} ({:.1}% hit rate)",
+                stats.hits,
+                stats.misses,
+                stats.hit_rate()
+            );
+            if let (Some(oldest), Some(newest)) = (stats.oldest, stats.newest) {
+                println!(
+                    "   Oldest entry: {}, newest: {}",
+                    oldest.format("%Y-%m-%d %H:%M UTC"),
+                    newest.format("%Y-%m-%d %H:%M UTC")
+                );
+            }
+        }
+        None => {
+            // If no subcommand is provided, print help
+            let mut cmd = Cli::command();
+            cmd.print_help()?;
+        }
+    }
+
+    Ok(exit_code::CLEAN)
+}
+
+/// Settings of one scan, from the `scan` flags or a `serve` job.
+#[derive(Debug, Clone)]
+struct ScanOptions {
+    profile: String,
+    scan_dependencies: bool,
+    resume: bool,
+    offline: bool,
+    verify_sources: bool,
+    expand_macros: bool,
+    no_cache: bool,
+    exclude: Vec<String>,
+    include: Vec<String>,
+    /// `--only` directories and globs; with `files`, replace `include`
+    only: Vec<String>,
+    files: Vec<String>,
+    /// `--changed`: scan only these files and dependencies
+    changes: Option<changes::ChangeSet>,
+    /// Add `git blame` ownership to findings
+    blame: bool,
+    no_ignore: bool,
+    min_confidence: Option<f32>,
+    /// Scan only the dependencies, not the crate's own source
+    skip_code: bool,
+    /// `--max-duration` and `--max-api-calls` of the scan's LLM requests
+    max_duration: Option<std::time::Duration>,
+    max_api_calls: Option<usize>,
+    /// `--reproducible`: leave out the timestamp and scan history
+    reproducible: bool,
+    /// `--absolute-paths`: keep absolute file paths instead of ones relative
+    /// to the crate
+    absolute_paths: bool,
+    /// Append results to this file as they complete instead of keeping them
+    stream: Option<(PathBuf, StreamFormat)>,
+}
+
+/// Checkpoint key of a scan: the crate's path and a hash of `Cargo.lock`
+/// and of every setting that decides what the scan covers and how. A
+/// `--resume` only picks up progress made under the same settings.
+fn checkpoint_key(
+    scan_key: &str,
+    project_path: &Path,
+    config: &Config,
+    profile: &ScanProfile,
+    options: &ScanOptions,
+) -> String {
+    let lockfile = manifest_lint::workspace_root(project_path).join("Cargo.lock");
+    let changes = options.changes.as_ref().map(|changes| {
+        let mut packages: Vec<&String> = changes.packages.iter().collect();
+        packages.sort();
+        (&changes.files, packages)
+    });
+    let severity: BTreeMap<&String, &String> = config.severity.iter().collect();
+    let settings = format!(
+        "{:?}",
+        (
+            profile,
+            config.llm.as_ref().map(|llm| llm.model()),
+            serde_json::to_string(&config.scanner).unwrap_or_default(),
+            serde_json::to_string(&config.dependencies).unwrap_or_default(),
+            severity,
+            (
+                &options.exclude,
+                &options.include,
+                &options.only,
+                &options.files,
+                changes,
+            ),
+            (
+                options.scan_dependencies,
+                options.offline,
+                options.verify_sources,
+                options.expand_macros,
+                options.no_ignore,
+                options.skip_code,
+            ),
+            content_hash(&std::fs::read_to_string(lockfile).unwrap_or_default()),
+        )
+    );
+    format!("{}#{}", scan_key, &content_hash(&settings)[..16])
+}
+
+fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Scans the crate at `project_path` and records the scan in the history.
+/// `config` is the user config plus the crate's .rustrecon.toml. Writing the
+/// report is up to the caller.
+async fn run_scan(
+    project_path: &Path,
+    config: &Config,
+    options: &ScanOptions,
+) -> Result<RiskReport> {
+    let scan_started = std::time::Instant::now();
+    let budget = Arc::new(ScanBudget::new(options.max_duration, options.max_api_calls));
+
+    let mut profile = config.scan_profile(&options.profile)?;
+    if options.offline {
+        profile.use_llm = false;
+        profile.deep_dependencies = false;
+        info!("📴 Offline mode: no LLM or registry requests will be made");
+    }
+    info!("Scan profile: {}", profile.name);
+
+    // Initialize LLM client (not needed when the profile only runs static rules)
+    let gemini_client = if profile.use_llm {
+        Some(scan_client(config, &profile, !options.no_cache, budget.clone()).await?)
+    } else {
+        None
+    };
+    let context_budget = scan_context_budget(gemini_client.as_ref(), config, &profile);
+    debug!(
+        "Context budget: {} tokens of code per request",
+        context_budget.input_tokens()
+    );
+
+    // Initialize scanners
+    let mut scanner = Scanner::new(project_path.to_path_buf())?;
+    scanner.set_context_budget(context_budget);
+    scanner
+        .set_exclude_patterns(&[config.scanner.exclude.as_slice(), &options.exclude].concat())?;
+    scanner
+        .set_include_patterns(&[config.scanner.include.as_slice(), &options.include].concat())?;
+    scanner.set_rule_packs(config.scanner.rule_packs);
+    scanner.set_grammars(&config.scanner.grammars)?;
+    scanner.set_targets(&options.only, &options.files)?;
+    if let Some(changes) = &options.changes {
+        scanner.set_changed_files(&changes.files);
+    }
+    scanner.set_respect_ignore_files(!options.no_ignore);
+    if scanner.has_targets() {
+        info!("🎯 Scanning only the selected files and directories");
+    }
+    let mut blamer = if options.blame {
+        Some(blame::Blamer::new(project_path)?)
+    } else {
+        None
+    };
+    let mut file_analysis_results = if options.skip_code {
+        Vec::new()
+    } else {
+        scanner.scan_crate()?
+    };
+    if options.expand_macros && !options.skip_code {
+        info!("🔬 Expanding macros with cargo expand...");
+        match scanner.expand_macros() {
+            Ok(Some(expanded)) => file_analysis_results.push(expanded),
+            Ok(None) => warn!(
+                "⚠️  cargo-expand is not installed (cargo install cargo-expand); skipping macro expansion"
+            ),
+            Err(e) => warn!("⚠️  Macro expansion failed: {:#}", e),
+        }
+    }
+
+    let mut risk_report = RiskReport::new(crate::utils::get_crate_name_from_path(project_path));
+    let report_path = |path: &Path| {
+        if options.absolute_paths {
+            path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+        } else {
+            utils::relative_path(path, project_path)
+        }
+    };
+    if options.reproducible {
+        // Nothing that differs between runs over the same code: no timestamp
+        // and no trend over earlier scans
+        risk_report.timestamp.clear();
+    }
+    risk_report.set_severity_overrides(config.severity.clone());
+    risk_report.source_files = scanner.source_files();
+    let mut stream = options
+        .stream
+        .as_ref()
+        .map(|(path, format)| ReportStream::create(path, *format, &risk_report))
+        .transpose()?;
+
+    // Scan history is kept per crate
+    let scan_key = project_path
+        .canonicalize()
+        .unwrap_or_else(|_| project_path.to_path_buf())
+        .to_string_lossy()
+        .to_string();
+    // Checkpointing lets an interrupted scan pick up where it stopped
+    let checkpoint_key = checkpoint_key(&scan_key, project_path, config, &profile, options);
+    let mut database = open_database();
+    let checkpoint = match &database {
+        Some(db) if options.resume => db.load_checkpoint(&checkpoint_key)?,
+        _ => None,
+    };
+    if let Some(db) = &database {
+        if checkpoint.is_none() {
+            if options.resume {
+                info!(
+                    "No checkpoint found for this crate and these settings, starting a fresh scan."
+                );
             }
+            db.start_checkpoint(&checkpoint_key)?;
+        }
+    }
+    let checkpoint = checkpoint.unwrap_or_default();
 
-            for file_result in file_analysis_results {
-                println!("Analyzing file: {}", file_result.path.display());
+    // Files changed since their analysis are analyzed again
+    let content_hashes: HashMap<&Path, String> = file_analysis_results
+        .iter()
+        .map(|file| (file.path.as_path(), content_hash(&file.content)))
+        .collect();
+    let mut completed_files = HashSet::new();
+    let mut resumed = Vec::new();
+    for (finding, hash) in checkpoint.completed_files {
+        if content_hashes.get(finding.file_path.as_path()) == Some(&hash) {
+            completed_files.insert(finding.file_path.clone());
+            resumed.push(finding);
+        }
+    }
+    if !resumed.is_empty() {
+        info!("⏩ Resuming scan: {} files already analyzed", resumed.len());
+    }
+    for finding in resumed {
+        risk_report.add_crate_finding(finding);
+    }
 
-                // Placeholder for actual LLM interaction
-                let prompt = format!(
-                    "Analyze the following Rust code for malicious behavior, backdoors, or unsafe patterns. Provide a summary of findings and specific flagged lines with severity (High, Medium, Low) and a brief description:\n\n{}",
-                    file_result.content
+    // Not checkpointed: found again, cheaply, when a scan resumes
+    if !options.skip_code {
+        for artifact in scanner.find_artifacts() {
+            warn!(
+                "⚠️  {}: {} ({})",
+                artifact.path.display(),
+                artifact.kind.label(),
+                artifact.detail
+            );
+            risk_report.add_crate_finding(artifact.into_finding());
+        }
+    }
+
+    let workspace_root = manifest_lint::workspace_root(project_path);
+    for manifest in scanner.manifests() {
+        match manifest_lint::finding(&manifest, &workspace_root) {
+            Ok(Some(finding)) => {
+                warn!(
+                    "⚠️  {}: {} risky manifest settings",
+                    manifest.display(),
+                    finding.flagged_patterns.len()
+                );
+                risk_report.add_crate_finding(finding);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("⚠️  Skipping manifest lint: {:#}", e),
+        }
+    }
+
+    let pending_files = file_analysis_results
+        .iter()
+        .filter(|f| !completed_files.contains(&f.path))
+        .count();
+    let file_bar = progress::file_bar(pending_files);
+
+    // Scan dependencies if enabled
+    // A scan of part of the crate is about that code, not the dependencies
+    // except for the dependencies a change adds
+    let should_scan_deps = options.scan_dependencies
+        && profile.scan_dependencies
+        && match &options.changes {
+            Some(changes) => !changes.packages.is_empty(),
+            None => !scanner.has_targets(),
+        };
+    if let Some(dependency_results) = checkpoint.dependency_findings {
+        info!(
+            "⏩ Reusing {} dependency results from checkpoint",
+            dependency_results.len()
+        );
+        risk_report.add_dependency_findings(dependency_results);
+    } else if should_scan_deps {
+        info!("🔍 Starting dependency analysis for supply chain security...");
+        let mut dependency_scanner = DependencyScanner::new();
+        dependency_scanner.add_trusted_packages(&config.dependencies.trusted);
+        dependency_scanner.add_approved_sources(&config.dependencies.approved_sources);
+        dependency_scanner.set_metadata_source(&config.dependencies);
+        dependency_scanner.set_severity_overrides(config.severity.clone());
+        dependency_scanner.set_request_interval(config.rate_limiting.request_interval());
+        dependency_scanner.set_deep_analysis(profile.deep_dependencies);
+        dependency_scanner.set_context_budget(context_budget);
+        dependency_scanner.set_offline(options.offline);
+        dependency_scanner.set_source_verification(options.verify_sources);
+        dependency_scanner.set_limits(&config.dependencies);
+        dependency_scanner.set_native_review(config.dependencies.native_review.unwrap_or(false));
+        dependency_scanner.set_budget(budget.clone());
+        if let Some(changes) = &options.changes {
+            dependency_scanner.set_only_packages(changes.packages.clone());
+        }
+        dependency_scanner
+            .load_popular_packages(database.as_mut())
+            .await;
+        match lockfile::parse_lockfile(&project_path.join("Cargo.lock")) {
+            Ok(packages) => {
+                dependency_scanner.load_checksums(&packages);
+                dependency_scanner
+                    .load_vulnerabilities(&packages, database.as_ref())
+                    .await
+            }
+            Err(e) => warn!(
+                "⚠️  Skipping OSV vulnerability lookup and checksum verification: {:#}",
+                e
+            ),
+        }
+        match scan_dependencies_cached(
+            &dependency_scanner,
+            project_path,
+            gemini_client.as_ref(),
+            database.as_mut().filter(|_| !options.no_cache),
+        )
+        .await
+        {
+            Ok(dependency_scan) => {
+                info!(
+                    "✅ Dependency scan completed. Found {} dependencies.",
+                    dependency_scan.results.len()
                 );
-                let llm_request = LlmRequest { prompt };
+                if let Some(db) = &database {
+                    db.save_checkpoint_dependencies(&checkpoint_key, &dependency_scan.results)?;
+                }
+                risk_report.add_dependency_findings(dependency_scan.results);
+                risk_report.skipped_dependencies = dependency_scan.skipped;
+            }
+            Err(e) => {
+                warn!("⚠️  Dependency scan failed: {}", e);
+                info!("   Continuing with code-only analysis...");
+            }
+        }
+    } else if options.changes.is_some() {
+        info!("⏭️  Skipping dependency scan (no dependencies added or updated)");
+    } else if scanner.has_targets() {
+        info!("⏭️  Skipping dependency scan (scanning selected files only)");
+    } else {
+        info!("⏭️  Skipping dependency scan (disabled)");
+    }
+    if let Some(stream) = &mut stream {
+        stream.write_dependencies(&risk_report)?;
+    }
+    stream_findings(
+        &mut risk_report,
+        &report_path,
+        stream.as_mut(),
+        options.min_confidence,
+        database.as_ref(),
+        blamer.as_mut(),
+    )?;
+
+    progress::emit(ProgressEvent::ScanStarted {
+        crate_name: &risk_report.crate_name,
+        files: pending_files,
+    });
+    let mut eta = EtaTracker::new(pending_files);
+    // One pathological file must not stall the whole scan
+    let file_timeout = config.scanner.file_timeout();
 
-                match gemini_client.analyze_code(llm_request).await {
+    for file_result in file_analysis_results {
+        risk_report.add_unsafe_metrics(file_result.path.clone(), file_result.unsafe_metrics);
+        for path in &file_result.identical_files {
+            risk_report.add_unsafe_metrics(path.clone(), file_result.unsafe_metrics);
+        }
+        let relative_path = file_result
+            .path
+            .strip_prefix(project_path)
+            .unwrap_or(&file_result.path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        risk_report.add_network_indicators(match (&file_result.tree, file_result.language) {
+            (Some(tree), Language::Rust) => {
+                indicators::extract(tree, &file_result.content, &relative_path)
+            }
+            _ => indicators::extract_text(
+                &file_result.content,
+                &relative_path,
+                file_result.language.line_comment(),
+            ),
+        });
+        if completed_files.contains(&file_result.path) {
+            continue;
+        }
+        info!("Analyzing file: {}", file_result.path.display());
+        if !file_result.identical_files.is_empty() {
+            info!(
+                "   Findings also apply to {} identical files",
+                file_result.identical_files.len()
+            );
+        }
+        let display_path = file_result.path.display().to_string();
+        let index = eta.done() + 1;
+        let file_started = std::time::Instant::now();
+        let hits_before = gemini_client.as_ref().map_or(0, |c| c.cache_hits());
+        let file_hash = content_hash(&file_result.content);
+        progress::emit(ProgressEvent::FileStarted {
+            path: &display_path,
+            index,
+            total: pending_files,
+        });
+
+        // Failed LLM analyses are not checkpointed, so `--resume` retries the file
+        let (finding, checkpoint) = match &gemini_client {
+            None => (
+                CrateFinding {
+                    file_path: file_result.path,
+                    llm_analysis: format!(
+                        "Static analysis only ({} profile): {} patterns matched.",
+                        profile.name,
+                        file_result.static_patterns.len()
+                    ),
+                    flagged_patterns: file_result.static_patterns,
+                    analysis_version: None,
+                    identical_files: file_result.identical_files,
+                    truncation: None,
+                },
+                true,
+            ),
+            Some(gemini_client) => {
+                let relative_path = file_result
+                    .path
+                    .strip_prefix(project_path)
+                    .unwrap_or(&file_result.path)
+                    .to_string_lossy()
+                    .to_string();
+                if file_result.chunks.len() > 1 {
+                    debug!(
+                        "Splitting {} into {} chunks",
+                        relative_path,
+                        file_result.chunks.len()
+                    );
+                }
+
+                match tokio::time::timeout(
+                    file_timeout,
+                    analyze_chunks(
+                        gemini_client,
+                        &risk_report.crate_name,
+                        &relative_path,
+                        file_result.language,
+                        &file_result.chunks,
+                    ),
+                )
+                .await
+                .unwrap_or(Err(LlmClientError::TimedOut(file_timeout.as_secs())))
+                {
                     Ok(llm_response) => {
-                        println!(
+                        debug!(
                             "LLM Analysis for {}: {}",
                             file_result.path.display(),
                             llm_response.analysis
                         );
-                        risk_report.add_file_finding(
-                            file_result.path,
-                            llm_response.analysis,
-                            llm_response.flagged_patterns,
+                        let mut llm_patterns = llm_response.flagged_patterns;
+                        utils::adjust_confidence(
+                            &mut llm_patterns,
+                            &file_result.static_patterns,
+                            Path::new(&relative_path),
                         );
+                        let mut flagged_patterns = file_result.static_patterns;
+                        flagged_patterns.extend(llm_patterns);
+                        (
+                            CrateFinding {
+                                file_path: file_result.path,
+                                llm_analysis: llm_response.analysis,
+                                flagged_patterns,
+                                analysis_version: llm_response.version,
+                                identical_files: file_result.identical_files,
+                                truncation: file_result.truncation,
+                            },
+                            true,
+                        )
                     }
                     Err(e) => {
-                        eprintln!(
-                            "Error calling LLM for {}: {}",
-                            file_result.path.display(),
-                            e
-                        );
-                        // Add an empty finding or a finding indicating an error
-                        risk_report.add_file_finding(
-                            file_result.path,
-                            format!("LLM analysis failed: {}", e),
-                            vec![],
-                        );
+                        let llm_analysis = match e {
+                            LlmClientError::CircuitOpen(_) | LlmClientError::BudgetSpent(_) => {
+                                debug!("Skipping LLM analysis of {}", file_result.path.display());
+                                risk_report.llm_skipped_files.push(file_result.path.clone());
+                                format!("LLM analysis failed: {}", e)
+                            }
+                            LlmClientError::TimedOut(_) => {
+                                warn!(
+                                    "⏱️  {} for {}; keeping its static findings",
+                                    e,
+                                    file_result.path.display()
+                                );
+                                risk_report
+                                    .llm_timed_out_files
+                                    .push(file_result.path.clone());
+                                e.to_string()
+                            }
+                            _ => {
+                                error!(
+                                    "Error calling LLM for {}: {}",
+                                    file_result.path.display(),
+                                    e
+                                );
+                                format!("LLM analysis failed: {}", e)
+                            }
+                        };
+                        // Keep the static findings
+                        (
+                            CrateFinding {
+                                file_path: file_result.path,
+                                llm_analysis,
+                                flagged_patterns: file_result.static_patterns,
+                                analysis_version: None,
+                                identical_files: file_result.identical_files,
+                                truncation: None,
+                            },
+                            false,
+                        )
                     }
                 }
             }
+        };
+        risk_report.add_crate_finding(finding);
+        if let (true, Some(db), Some(finding)) =
+            (checkpoint, &database, risk_report.findings.last())
+        {
+            db.save_checkpoint_file(&checkpoint_key, finding, &file_hash)?;
+        }
 
-            let output_path = output.as_ref().map(PathBuf::from);
-            risk_report.generate_report(format, output_path.as_deref())?;
+        let cached = gemini_client.as_ref().map_or(0, |c| c.cache_hits()) > hits_before;
+        eta.record(cached, file_started.elapsed());
+        file_bar.inc(1);
+        progress::show_eta(&file_bar, &eta);
+        progress::emit(ProgressEvent::FileCompleted {
+            path: &display_path,
+            index,
+            total: pending_files,
+            patterns: risk_report
+                .findings
+                .last()
+                .map_or(0, |f| f.flagged_patterns.len()),
+            eta_secs: eta.eta().map(|d| d.as_secs()),
+        });
+        stream_findings(
+            &mut risk_report,
+            &report_path,
+            stream.as_mut(),
+            options.min_confidence,
+            database.as_ref(),
+            blamer.as_mut(),
+        )?;
+    }
+
+    file_bar.finish_and_clear();
+    risk_report.partial = budget.spent().map(str::to_string);
+    if !risk_report.llm_skipped_files.is_empty() {
+        warn!(
+            "⚠️  {} files got static analysis only because {}; rerun with --resume to analyze them",
+            risk_report.llm_skipped_files.len(),
+            risk_report
+                .partial
+                .as_deref()
+                .unwrap_or("the LLM kept failing")
+        );
+    }
+    if !risk_report.llm_timed_out_files.is_empty() {
+        warn!(
+            "⚠️  {} files got static analysis only because their LLM analysis took longer than {}s; raise scanner.file_timeout_secs and rerun with --resume to analyze them",
+            risk_report.llm_timed_out_files.len(),
+            file_timeout.as_secs()
+        );
+    }
 
-            println!("Scan complete. Report generated.");
+    risk_report.map_paths(&report_path);
+    risk_report.sort_findings();
+    if let Some(db) = database.as_ref().filter(|_| !options.reproducible) {
+        match db.scan_history(&scan_key, HISTORY_IN_REPORT) {
+            Ok(history) => risk_report.set_history(history),
+            Err(e) => warn!("⚠️  Could not load scan history: {}", e),
         }
-        None => {
-            // If no subcommand is provided, print help
-            use clap::CommandFactory;
-            let mut cmd = Cli::command();
-            cmd.print_help()?;
+    }
+
+    if let Some(min_confidence) = options.min_confidence {
+        let dropped = risk_report.apply_min_confidence(min_confidence);
+        if dropped > 0 {
+            info!(
+                "Left out {} findings with confidence below {}",
+                dropped, min_confidence
+            );
+        }
+    }
+    if let Some(blamer) = &mut blamer {
+        blamer.annotate(&mut risk_report);
+    }
+    if !config.threat_intel.feeds.is_empty() {
+        let feeds = threat_intel::ThreatFeeds::load(
+            &config.threat_intel,
+            database.as_mut(),
+            options.offline,
+        )
+        .await;
+        let listed = feeds.annotate_report(&mut risk_report);
+        if listed > 0 {
+            warn!("🚨 {} indicators are listed by a threat-intel feed", listed);
+        }
+    }
+
+    metrics::record_scan(scan_started.elapsed());
+    if let Some(db) = &database {
+        record_triage(db, &mut risk_report);
+        // Kept when the breaker opened or files timed out, so `--resume` only
+        // analyzes the skipped files
+        if risk_report.llm_skipped_files.is_empty() && risk_report.llm_timed_out_files.is_empty() {
+            db.clear_checkpoint(&checkpoint_key)?;
+        }
+        // A scan of part of the crate would skew the trends
+        if !scanner.has_targets() {
+            if let Err(e) = db.record_scan(
+                &scan_key,
+                &risk_report.crate_name,
+                &risk_report.history_entry(),
+            ) {
+                warn!("⚠️  Could not record scan history: {}", e);
+            }
+        }
+        // Flushed per scan so a long-running `serve` keeps its totals current
+        flush_metrics(db);
+    }
+
+    if let Some(stream) = stream {
+        let path = stream.path().display().to_string();
+        stream.finish(&risk_report)?;
+        info!("Streamed report written to {}", path);
+    }
+
+    progress::emit(ProgressEvent::ScanCompleted {
+        files: risk_report.summary.total_files_scanned,
+        dependencies: risk_report.summary.total_dependencies_scanned,
+        elapsed_secs: scan_started.elapsed().as_secs(),
+    });
+    Ok(risk_report)
+}
+
+/// Moves the findings collected so far from `report` to `stream`, keeping only
+/// their counts in the summary. Does nothing when the scan is not streamed.
+fn stream_findings(
+    report: &mut RiskReport,
+    report_path: &dyn Fn(&Path) -> PathBuf,
+    stream: Option<&mut ReportStream>,
+    min_confidence: Option<f32>,
+    database: Option<&Database>,
+    blamer: Option<&mut blame::Blamer>,
+) -> Result<()> {
+    let Some(stream) = stream else {
+        return Ok(());
+    };
+    if let Some(blamer) = blamer {
+        blamer.annotate(report);
+    }
+    // Before triage, whose finding ids include the path
+    for finding in &mut report.findings {
+        finding.map_paths(report_path);
+    }
+    // Dropped findings never reach the stream, so filter before writing
+    if let Some(min_confidence) = min_confidence {
+        report.apply_min_confidence(min_confidence);
+    }
+    if let Some(db) = database {
+        record_triage(db, report);
+    }
+    for finding in report.findings.drain(..) {
+        stream.write_finding(&finding)?;
+    }
+    Ok(())
+}
+
+/// Checks `api_key` against the provider's model list and picks the model to
+/// write: `requested` if the key can use it, otherwise the user's choice from
+/// the list, or the default when there is nobody to ask. `None` when the
+/// provider cannot be reached, so `init` still works offline.
+async fn verified_model(
+    llm: &LlmConfig,
+    api_key: &str,
+    requested: Option<&str>,
+    yes: bool,
+) -> Result<Option<ModelInfo>> {
+    let client = gemini_client(llm, api_key.to_string())?;
+    let models = match client.list_models().await {
+        Ok(models) => models,
+        Err(e) if e.is_service_failure() => {
+            warn!(
+                "⚠️  Could not reach {} to check the API key; writing the config unchecked: {}",
+                llm.gemini_api_endpoint, e
+            );
+            return Ok(None);
+        }
+        Err(e) => anyhow::bail!("The API key was rejected: {}", e),
+    };
+    if models.is_empty() {
+        anyhow::bail!("The API key works but has access to no models that can analyze code");
+    }
+
+    if let Some(requested) = requested {
+        let requested = requested.strip_prefix("models/").unwrap_or(requested);
+        return match models.iter().find(|model| model.name == requested) {
+            Some(model) => Ok(Some(model.clone())),
+            None => anyhow::bail!(
+                "Model '{}' is not available to this API key. Available models: {}",
+                requested,
+                models
+                    .iter()
+                    .map(|model| model.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+    }
+
+    let default = models
+        .iter()
+        .position(|model| model.name == DEFAULT_GEMINI_MODEL && !models::is_deprecated(model))
+        .or_else(|| {
+            models
+                .iter()
+                .position(|model| !models::is_deprecated(model))
+        })
+        .unwrap_or(0);
+    if yes || interactive::is_disabled() || !std::io::stdin().is_terminal() {
+        return Ok(Some(models[default].clone()));
+    }
+    println!("Models available to this API key:");
+    for (i, model) in models.iter().enumerate() {
+        println!(
+            "  {:>2}. {:<32} {}{}",
+            i + 1,
+            model.name,
+            model.display_name,
+            if models::is_deprecated(model) {
+                " (deprecated)"
+            } else {
+                ""
+            }
+        );
+    }
+    loop {
+        print!("Model to use [{}]: ", default + 1);
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(Some(models[default].clone()));
         }
+        let chosen = answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| models.get(i))
+            .or_else(|| models.iter().find(|model| model.name == answer));
+        match chosen {
+            Some(model) => return Ok(Some(model.clone())),
+            None => println!("Enter a number from the list or a model name."),
+        }
+    }
+}
+
+/// Root of the git repository containing the current directory.
+fn git_toplevel() -> Option<PathBuf> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// Records the report's findings for `triage` and tags them with their ids
+/// and triage states.
+fn record_triage(db: &Database, report: &mut RiskReport) {
+    if let Err(e) = triage::record_findings(db, report) {
+        warn!("⚠️  Could not record findings for triage: {}", e);
+    }
+}
+
+/// Saves the metrics recorded so far, when metrics are enabled.
+fn flush_metrics(db: &Database) {
+    if metrics::is_enabled() {
+        if let Err(e) = metrics::flush(db) {
+            warn!("⚠️  Could not save metrics: {}", e);
+        }
+    }
+}
+
+/// Turns on `--no-network-except-llm` with the hosts from the user config
+/// and logs what each of them is sent.
+fn enable_network_gate() {
+    let config = Config::load_or_default().unwrap_or_default();
+    let llm_endpoint = config
+        .llm
+        .as_ref()
+        .map(|llm| llm.gemini_api_endpoint.clone())
+        .unwrap_or_else(|| LlmConfig::gemini_defaults().gemini_api_endpoint);
+    let llm_host = network::url_host(&llm_endpoint).unwrap_or(llm_endpoint);
+    let mut crates_io: Vec<String> = ["crates.io", "static.crates.io", "index.crates.io"]
+        .map(String::from)
+        .to_vec();
+    if let Some(host) = config
+        .dependencies
+        .metadata_url
+        .as_deref()
+        .and_then(network::url_host)
+    {
+        crates_io.push(host);
     }
+    let extra = &config.network.allowed_hosts;
 
+    info!("🔒 Network restricted by --no-network-except-llm. Data leaving this machine:");
+    info!(
+        "   → {}: the API key, and the source of scanned files and dependencies for LLM analysis",
+        llm_host
+    );
+    info!(
+        "   → {}: names and versions of dependencies for metadata; crate archives are downloaded",
+        crates_io.join(", ")
+    );
+    if !extra.is_empty() {
+        info!(
+            "   → {}: allowed by `allowed_hosts` under [network]",
+            extra.join(", ")
+        );
+    }
+    info!("   Requests to any other host are blocked and fail the command");
+
+    network::enable_gate(
+        std::iter::once(llm_host)
+            .chain(crates_io)
+            .chain(extra.iter().cloned()),
+    );
+}
+
+/// Applies the process-wide settings of the user's configuration: `[network]`
+/// for every HTTP client the command goes on to create, and `[metrics]`.
+fn apply_user_config() -> Result<()> {
+    let Some(path) = Config::find_default_path() else {
+        return Ok(());
+    };
+    // Commands that need the configuration report its parse errors themselves
+    let Ok(config) = Config::load_from_path(&path) else {
+        return Ok(());
+    };
+    if config.metrics.enabled {
+        metrics::enable();
+    }
+    network::configure(&config.network)
+        .with_context(|| format!("Invalid [network] settings in {}", path.display()))
+}
+
+/// Sends status messages to stderr so stdout carries only command output:
+/// plain lines by default, one JSON object per line with `--log-json`.
+fn init_logging(cli: &Cli) {
+    let level = match (&cli.log_level, cli.quiet, cli.verbose) {
+        (Some(level), _, _) => Some(level.as_str()),
+        (None, true, _) => Some("off"),
+        (None, false, 0) => None,
+        (None, false, 1) => Some("debug"),
+        (None, false, _) => Some("trace"),
+    };
+    // Dependencies only report warnings, so -v does not bring in HTTP client internals
+    let filter = match level {
+        Some("off") => EnvFilter::new("off"),
+        Some(level) => EnvFilter::new(format!("warn,rustrecon={}", level)),
+        None => EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| EnvFilter::new("warn,rustrecon=info")),
+    };
+
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(|| progress::LogWriter)
+        .with_ansi(std::io::stderr().is_terminal());
+    if cli.quiet || cli.log_json {
+        progress::hide_bars();
+    }
+    if cli.log_json {
+        subscriber.json().with_current_span(false).init();
+    } else {
+        subscriber
+            .without_time()
+            .with_target(false)
+            .with_level(false)
+            .init();
+    }
+}
+
+fn check_min_confidence(min_confidence: f32) -> Result<()> {
+    if !(0.0..=1.0).contains(&min_confidence) {
+        return Err(exit_code::config_error(anyhow::anyhow!(
+            "--min-confidence must be between 0.0 and 1.0"
+        )));
+    }
     Ok(())
 }
+
+fn check_fail_on(level: &str) -> Result<()> {
+    if !severity::LEVELS.contains(&severity::canonical(level).unwrap_or_default()) {
+        return Err(exit_code::config_error(anyhow::anyhow!(
+            "--fail-on must be one of {}",
+            severity::LEVELS.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+fn resolve_config_path(config_path: Option<&str>) -> Result<PathBuf> {
+    match config_path {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => Config::find_default_path().ok_or_else(|| {
+            exit_code::config_error(anyhow::anyhow!(
+                "No configuration file found. Please run `rustrecon init` first."
+            ))
+        }),
+    }
+}
+
+/// Opens the local database, warning instead of failing when it is unavailable.
+fn open_database() -> Option<Database> {
+    match Database::open_default() {
+        Ok(db) => Some(db),
+        Err(e) => {
+            warn!(
+                "⚠️  Local database unavailable (checkpoints and caching disabled): {}",
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Sends each chunk of a file in its own request and merges the answers.
+async fn analyze_chunks<C: LlmClientTrait>(
+    client: &C,
+    crate_name: &str,
+    relative_path: &str,
+    language: Language,
+    chunks: &[CodeChunk],
+) -> Result<LlmResponse, LlmClientError> {
+    let mut parts = Vec::new();
+    for chunk in chunks {
+        let llm_request = chunk_request(crate_name, relative_path, language, chunk);
+        parts.push((chunk, client.analyze_code(llm_request).await?));
+    }
+    Ok(utils::merge_chunk_responses(parts))
+}
+
+/// The LLM request for one chunk of a crate's file, cached under the file's
+/// path within the crate.
+fn chunk_request(
+    crate_name: &str,
+    relative_path: &str,
+    language: Language,
+    chunk: &CodeChunk,
+) -> LlmRequest {
+    let prompt = llm_client::chunk_prompt(language, &chunk.text);
+    LlmRequest {
+        cache_key: Some(CacheKey::new(crate_name, relative_path, &prompt)),
+        prompt,
+        language,
+    }
+}
+
+/// A client for the configured endpoint and model. Refuses endpoints missing
+/// from `approved_endpoints`, and trusts only `pinned_ca_bundle` when set.
+fn gemini_client(llm_config: &LlmConfig, api_key: String) -> Result<GeminiClient> {
+    llm_config.check_endpoint()?;
+    let mut client = GeminiClient::new(api_key, llm_config.gemini_api_endpoint.clone())
+        .with_model(llm_config.model())
+        .with_structured_output(llm_config.structured_output.unwrap_or(true));
+    if let Some(path) = &llm_config.pinned_ca_bundle {
+        let certificates = network::read_ca_bundle(path).context("Invalid llm.pinned_ca_bundle")?;
+        client = client.with_pinned_certificates(&certificates);
+    }
+    Ok(client)
+}
+
+/// The LLM client file analyses go through.
+type ScanClient = CachedLlmClient<CircuitBreaker<GeminiClient>>;
+
+/// Builds the LLM client for a scan with `profile`, warning first when the
+/// configured model is gone or deprecated.
+async fn scan_client(
+    config: &Config,
+    profile: &ScanProfile,
+    use_cache: bool,
+    budget: Arc<ScanBudget>,
+) -> Result<ScanClient> {
+    let llm_config = config.llm.as_ref().ok_or_else(|| {
+        exit_code::config_error(anyhow::anyhow!(
+            "LLM configuration not found. Please run `init` or use `--profile quick`."
+        ))
+    })?;
+    let client = gemini_client(llm_config, llm_config.resolve_api_key()?)?
+        .with_prompt_profile(parse_prompt_profile(&config.scanner)?)
+        .with_max_output_tokens(profile.max_output_tokens)
+        .with_temperature(profile.temperature);
+    models::check_configured_model(&client, &llm_config.gemini_api_endpoint).await;
+    Ok(cached_client(
+        client,
+        &config.rate_limiting,
+        use_cache.then_some(&config.cache),
+        budget,
+    ))
+}
+
+/// Code per request: what the model's context window leaves after the answer.
+fn scan_context_budget(
+    client: Option<&ScanClient>,
+    config: &Config,
+    profile: &ScanProfile,
+) -> ContextBudget {
+    match (client, &config.llm) {
+        (Some(client), Some(llm_config)) => ContextBudget::new(
+            llm_config
+                .context_window
+                .unwrap_or_else(|| context_budget::model_context_window(client.model_name())),
+            profile.max_output_tokens,
+        ),
+        _ => ContextBudget::default(),
+    }
+}
+
+/// Runs the dependency scan, or reuses an earlier one with the same
+/// [fingerprint](DependencyScanner::fingerprint) from the last day, which
+/// makes no crates.io or LLM requests at all. Scans with failed analyses are
+/// not stored, so the next scan retries them.
+async fn scan_dependencies_cached<C: LlmClientTrait>(
+    dependency_scanner: &DependencyScanner,
+    project_path: &Path,
+    llm_client: Option<&C>,
+    database: Option<&mut Database>,
+) -> Result<DependencyScan> {
+    let model = llm_client.map(|client| client.model_name().to_string());
+    let fingerprint = database
+        .is_some()
+        .then(|| dependency_scanner.fingerprint(project_path, model.as_deref()))
+        .flatten();
+    let expired = chrono::Utc::now() - chrono::Duration::hours(DEPENDENCY_SCAN_MAX_AGE_HOURS);
+    if let (Some(db), Some(fingerprint)) = (database.as_deref(), &fingerprint) {
+        match db.load_dependency_scan(fingerprint) {
+            Ok(Some((scanned_at, scan))) if scanned_at > expired => {
+                info!(
+                    "⏩ Cargo.lock unchanged: reusing {} dependency results from {}",
+                    scan.results.len(),
+                    scanned_at.format("%Y-%m-%d %H:%M UTC")
+                );
+                return Ok(scan);
+            }
+            Ok(_) => {}
+            Err(e) => debug!("Could not read the cached dependency scan: {}", e),
+        }
+    }
+
+    let scan = dependency_scanner
+        .scan_dependencies(project_path, llm_client)
+        .await?;
+    if let (Some(db), Some(fingerprint)) = (database, fingerprint) {
+        if dependency_scanner.incomplete_analyses() == 0 {
+            if let Err(e) = db.save_dependency_scan(&fingerprint, model.as_deref(), &scan, expired)
+            {
+                warn!("⚠️  Could not cache the dependency scan: {}", e);
+            }
+        }
+    }
+    Ok(scan)
+}
+
+/// Wraps the LLM client with the circuit breaker and the analysis cache (local,
+/// plus the shared remote cache when configured); `None` disables caching.
+/// Cached answers are still served once the breaker has opened or `budget`
+/// is spent.
+fn cached_client(
+    client: GeminiClient,
+    rate_limiting: &RateLimitConfig,
+    cache_config: Option<&CacheConfig>,
+    budget: Arc<ScanBudget>,
+) -> ScanClient {
+    let client = CircuitBreaker::new(
+        client,
+        rate_limiting.failure_threshold(),
+        rate_limiting.retry_budget(),
+    )
+    .with_budget(budget);
+    let Some(cache_config) = cache_config else {
+        return CachedLlmClient::new(client, None);
+    };
+    let local = open_database();
+    let store: Option<Box<dyn CacheStore + Send>> = match RemoteCache::new(cache_config) {
+        Some(remote) => Some(Box::new(TieredCacheStore::new(local, remote))),
+        None => local.map(|db| Box::new(db) as Box<dyn CacheStore + Send>),
+    };
+    CachedLlmClient::new(client, store)
+}
+
+fn parse_prompt_profile(scanner_config: &ScannerConfig) -> Result<PromptProfile> {
+    scanner_config
+        .prompt_profile
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(anyhow::Error::msg)
+}
@@ -0,0 +1,407 @@
+use anyhow::{Context, Result};
+use std::path::{Component, Path, PathBuf};
+use toml::{Table, Value};
+
+use crate::llm_client::FlaggedPattern;
+use crate::report::CrateFinding;
+
+/// Crates so widely depended on that a `[patch]` of one swaps code most
+/// reviewers assume they already know.
+const POPULAR_CRATES: &[&str] = &[
+    "serde",
+    "serde_json",
+    "serde_derive",
+    "tokio",
+    "futures",
+    "clap",
+    "reqwest",
+    "hyper",
+    "http",
+    "anyhow",
+    "thiserror",
+    "log",
+    "tracing",
+    "rand",
+    "regex",
+    "syn",
+    "quote",
+    "proc-macro2",
+    "libc",
+    "bytes",
+    "chrono",
+    "uuid",
+    "once_cell",
+    "lazy_static",
+    "base64",
+    "sha2",
+    "ring",
+    "rustls",
+    "openssl",
+    "itertools",
+];
+
+/// Dependency tables of a manifest, also found under `[target.'cfg(...)']`.
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Risky constructs in a `Cargo.toml`: git dependencies that follow a branch,
+/// path dependencies outside `workspace_root`, wildcard versions, `[patch]`
+/// overrides, and unusual `links` and build script settings.
+pub fn lint(manifest: &Path, workspace_root: &Path) -> Result<Vec<FlaggedPattern>> {
+    let text = std::fs::read_to_string(manifest)
+        .with_context(|| format!("Failed to read {}", manifest.display()))?;
+    let table: Table =
+        toml::from_str(&text).with_context(|| format!("Failed to parse {}", manifest.display()))?;
+    // Relative paths in the manifest resolve against its directory
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let manifest_dir = canonical(manifest.parent().unwrap_or(Path::new(".")));
+    let workspace_root = canonical(workspace_root);
+    let mut lint = Lint {
+        text: &text,
+        patterns: Vec::new(),
+    };
+
+    let mut dependency_tables: Vec<&Table> = DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|name| table.get(*name)?.as_table())
+        .collect();
+    if let Some(Value::Table(targets)) = table.get("target") {
+        for target in targets.values().filter_map(Value::as_table) {
+            dependency_tables.extend(
+                DEPENDENCY_TABLES
+                    .iter()
+                    .filter_map(|name| target.get(*name)?.as_table()),
+            );
+        }
+    }
+    if let Some(dependencies) = table
+        .get("workspace")
+        .and_then(|workspace| workspace.get("dependencies"))
+        .and_then(Value::as_table)
+    {
+        dependency_tables.push(dependencies);
+    }
+    for (name, spec) in dependency_tables.into_iter().flatten() {
+        lint.dependency(name, spec, &manifest_dir, &workspace_root);
+    }
+
+    // `[patch.crates-io]`, `[patch.'https://...']` and the older `[replace]`
+    let patches = table
+        .get("patch")
+        .and_then(Value::as_table)
+        .into_iter()
+        .flat_map(|registries| registries.values().filter_map(Value::as_table))
+        .chain(table.get("replace").and_then(Value::as_table));
+    for (name, spec) in patches.flatten() {
+        lint.patch(name, spec);
+    }
+
+    if let Some(package) = table.get("package").and_then(Value::as_table) {
+        lint.package(package, &manifest_dir);
+    }
+    Ok(lint.patterns)
+}
+
+/// The crate findings for `manifest`, or `None` if it is clean.
+pub fn finding(manifest: &Path, workspace_root: &Path) -> Result<Option<CrateFinding>> {
+    let patterns = lint(manifest, workspace_root)?;
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(CrateFinding {
+        file_path: manifest.to_path_buf(),
+        llm_analysis: format!(
+            "Manifest review: {} risky Cargo.toml settings.",
+            patterns.len()
+        ),
+        flagged_patterns: patterns,
+        analysis_version: None,
+        identical_files: Vec::new(),
+        truncation: None,
+    }))
+}
+
+/// The root of the workspace `crate_path` belongs to: the nearest directory
+/// (itself included) whose `Cargo.toml` has a `[workspace]` table, or
+/// `crate_path` itself.
+pub fn workspace_root(crate_path: &Path) -> PathBuf {
+    let crate_path = crate_path
+        .canonicalize()
+        .unwrap_or_else(|_| crate_path.to_path_buf());
+    crate_path
+        .ancestors()
+        .find(|dir| {
+            std::fs::read_to_string(dir.join("Cargo.toml"))
+                .ok()
+                .and_then(|text| toml::from_str::<Table>(&text).ok())
+                .is_some_and(|table| table.contains_key("workspace"))
+        })
+        .unwrap_or(&crate_path)
+        .to_path_buf()
+}
+
+struct Lint<'a> {
+    text: &'a str,
+    patterns: Vec<FlaggedPattern>,
+}
+
+impl Lint<'_> {
+    fn flag(&mut self, key: &str, section: Option<&str>, severity: &str, description: String) {
+        let (line, snippet) = locate(self.text, key, section);
+        self.patterns.push(FlaggedPattern {
+            line,
+            severity: severity.to_string(),
+            description: format!("[static] {}", description),
+            code_snippet: snippet,
+            confidence: 1.0,
+            cwe: None,
+            owasp: None,
+            id: None,
+            triage: None,
+            blame: None,
+            rule: None,
+        });
+    }
+
+    fn dependency(&mut self, name: &str, spec: &Value, manifest_dir: &Path, workspace_root: &Path) {
+        let version = match spec {
+            Value::String(version) => Some(version.as_str()),
+            Value::Table(spec) => spec.get("version").and_then(Value::as_str),
+            _ => None,
+        };
+        if let Some(version) = version.filter(|v| v.contains('*')) {
+            let severity = if version.trim() == "*" {
+                "Medium"
+            } else {
+                "Low"
+            };
+            self.flag(
+                name,
+                None,
+                severity,
+                format!(
+                    "Wildcard version `{}` for `{}` accepts any release, including a compromised one",
+                    version, name
+                ),
+            );
+        }
+        let Value::Table(spec) = spec else {
+            return;
+        };
+
+        if let Some(url) = spec.get("git").and_then(Value::as_str) {
+            let pinned = |key: &str| spec.get(key).and_then(Value::as_str);
+            if pinned("rev").is_none() {
+                match (pinned("branch"), pinned("tag")) {
+                    (Some(branch), _) => self.flag(
+                        name,
+                        None,
+                        "Medium",
+                        format!(
+                            "Git dependency `{}` follows branch `{}` of {} instead of a pinned `rev`",
+                            name, branch, url
+                        ),
+                    ),
+                    (None, Some(tag)) => self.flag(
+                        name,
+                        None,
+                        "Low",
+                        format!(
+                            "Git dependency `{}` is pinned to tag `{}` of {}, which can be moved; prefer a `rev`",
+                            name, tag, url
+                        ),
+                    ),
+                    (None, None) => self.flag(
+                        name,
+                        None,
+                        "Medium",
+                        format!(
+                            "Git dependency `{}` follows the default branch of {} instead of a pinned `rev`",
+                            name, url
+                        ),
+                    ),
+                }
+            }
+        }
+
+        if let Some(path) = spec.get("path").and_then(Value::as_str) {
+            let target = normalize(&manifest_dir.join(path));
+            if !target.starts_with(workspace_root) {
+                self.flag(
+                    name,
+                    None,
+                    "High",
+                    format!(
+                        "Path dependency `{}` points outside the workspace, to `{}`",
+                        name, path
+                    ),
+                );
+            }
+        }
+    }
+
+    fn patch(&mut self, name: &str, spec: &Value) {
+        let source = match spec {
+            Value::Table(spec) => ["git", "path", "registry"]
+                .into_iter()
+                .find_map(|key| Some(format!(" with {} `{}`", key, spec.get(key)?.as_str()?)))
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+        let popular = POPULAR_CRATES.contains(&name);
+        self.flag(
+            name,
+            Some("[patch"),
+            if popular { "High" } else { "Low" },
+            format!(
+                "`[patch]` replaces {}`{}`{} for the whole dependency graph",
+                if popular {
+                    "the widely used crate "
+                } else {
+                    ""
+                },
+                name,
+                source
+            ),
+        );
+    }
+
+    fn package(&mut self, package: &Table, manifest_dir: &Path) {
+        let name = package.get("name").and_then(Value::as_str).unwrap_or("");
+        if let Some(links) = package.get("links").and_then(Value::as_str) {
+            if !name.ends_with("-sys") {
+                self.flag(
+                    "links",
+                    Some("[package]"),
+                    "Medium",
+                    format!(
+                        "Declares `links = \"{}\"` but is not a `-sys` crate; its build script links a native library",
+                        links
+                    ),
+                );
+            }
+        }
+
+        if let Some(build) = package
+            .get("build")
+            .and_then(Value::as_str)
+            .filter(|build| *build != "build.rs")
+        {
+            let target = normalize(&manifest_dir.join(build));
+            let (severity, reason) = if target.starts_with(manifest_dir) {
+                ("Medium", "at a non-standard path")
+            } else {
+                ("High", "outside the package")
+            };
+            self.flag(
+                "build",
+                Some("[package]"),
+                severity,
+                format!("Runs a build script {}: `{}`", reason, build),
+            );
+        }
+    }
+}
+
+/// Line number and text of the first line defining `key` (as `key = ...` or
+/// `[....key]`), searching from the first line starting with `section` if given.
+fn locate(text: &str, key: &str, section: Option<&str>) -> (usize, String) {
+    let start = section
+        .and_then(|section| {
+            text.lines()
+                .position(|line| line.trim_start().starts_with(section))
+        })
+        .unwrap_or(0);
+    let defines = |line: &str| {
+        let line = line.trim_start();
+        let name = line.trim_start_matches('"');
+        name.strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start_matches('"').trim_start().starts_with('='))
+            || (line.starts_with('[') && line.trim_end().ends_with(&format!(".{}]", key)))
+    };
+    text.lines()
+        .enumerate()
+        .skip(start)
+        .find(|(_, line)| defines(line))
+        .map_or((1, String::new()), |(index, line)| {
+            (index + 1, line.trim().to_string())
+        })
+}
+
+/// `path` with `.` and `..` resolved without touching the file system, since a
+/// dependency outside the workspace may not exist on this machine.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_risky_manifest() {
+        let dir =
+            std::env::temp_dir().join(format!("rustrecon-test-{}-manifest", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("Cargo.toml");
+        std::fs::write(
+            &manifest,
+            r#"[package]
+name = "demo"
+links = "z"
+build = "tools/gen.rs"
+
+[dependencies]
+serde = "1"
+anything = "*"
+fork = { git = "https://example.com/fork.git", branch = "main" }
+pinned = { git = "https://example.com/pinned.git", rev = "abc123" }
+local = { path = "crates/local" }
+outside = { path = "../../elsewhere" }
+
+[target.'cfg(unix)'.dependencies]
+minor = "1.*"
+
+[patch.crates-io]
+serde = { git = "https://example.com/serde.git" }
+"#,
+        )
+        .unwrap();
+        let patterns = lint(&manifest, &dir).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let found: Vec<(usize, &str)> = patterns
+            .iter()
+            .map(|p| (p.line, p.severity.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (8, "Medium"),
+                (9, "Medium"),
+                (12, "High"),
+                (15, "Low"),
+                (18, "High"),
+                (3, "Medium"),
+                (4, "Medium"),
+            ]
+        );
+        assert!(patterns[4]
+            .description
+            .contains("widely used crate `serde`"));
+        assert_eq!(
+            normalize(Path::new("/a/b/../c/./d")),
+            PathBuf::from("/a/c/d")
+        );
+    }
+}
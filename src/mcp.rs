@@ -0,0 +1,368 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing::{debug, info};
+
+use crate::cache::CacheStore;
+use crate::config::Config;
+use crate::database::Database;
+use crate::lockfile::LockedPackage;
+use crate::scanner::Scanner;
+use crate::utils;
+use crate::{analyze_chunks, network, osv, progress};
+use crate::{run_scan, scan_client, scan_context_budget, ScanOptions};
+
+/// Protocol revisions the server speaks, newest first.
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const CRATES_IO_SOURCE: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Runs a Model Context Protocol server on stdin and stdout, one JSON-RPC
+/// message per line, until stdin closes. Requests are handled one at a
+/// time. `profile` is the scan profile of tools that do not name one.
+pub async fn serve(profile: String) -> Result<()> {
+    // Bars would only add noise to the client's log of stderr
+    progress::hide_bars();
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    info!("MCP server ready on stdin/stdout");
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle(&message, &profile).await,
+            Err(e) => Some(error(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        if let Some(response) = response {
+            stdout
+                .write_all(format!("{}\n", response).as_bytes())
+                .await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+/// The response to one message; `None` for notifications.
+async fn handle(message: &Value, profile: &str) -> Option<Value> {
+    let method = message["method"].as_str().unwrap_or_default();
+    // Notifications carry no id and get no response
+    let id = message.get("id")?.clone();
+    let params = &message["params"];
+    debug!("MCP request: {}", method);
+    let result = match method {
+        "initialize" => {
+            let requested = params["protocolVersion"].as_str().unwrap_or_default();
+            let version = PROTOCOL_VERSIONS
+                .iter()
+                .find(|&&version| version == requested)
+                .unwrap_or(&PROTOCOL_VERSIONS[0]);
+            json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "rustrecon", "version": env!("CARGO_PKG_VERSION") },
+            })
+        }
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": tools() }),
+        "tools/call" => {
+            let name = params["name"].as_str().unwrap_or_default();
+            let arguments = &params["arguments"];
+            let outcome = match name {
+                "scan_file" => scan_file(arguments, profile).await,
+                "scan_crate" => scan_crate(arguments, profile).await,
+                "query_cache" => query_cache(arguments),
+                "fetch_advisories" => fetch_advisories(arguments).await,
+                _ => {
+                    return Some(error(
+                        id,
+                        INVALID_PARAMS,
+                        &format!("Unknown tool '{}'", name),
+                    ))
+                }
+            };
+            // Tool failures are results the model can read, not protocol errors
+            match outcome {
+                Ok(text) => json!({ "content": [{ "type": "text", "text": text }] }),
+                Err(e) => json!({
+                    "content": [{ "type": "text", "text": format!("{:#}", e) }],
+                    "isError": true,
+                }),
+            }
+        }
+        _ => {
+            return Some(error(
+                id,
+                METHOD_NOT_FOUND,
+                &format!("Unknown method '{}'", method),
+            ))
+        }
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// The tools `tools/list` offers, with JSON schemas of their arguments.
+fn tools() -> Value {
+    json!([
+        {
+            "name": "scan_file",
+            "description": "Check one Rust source file, or a shell, PowerShell, TOML or JSON file, for malicious or unsafe code. Runs the static rules, and the LLM analysis when `llm` is true (answered from the analysis cache when possible). Returns the findings as JSON.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Path of the file" },
+                    "llm": { "type": "boolean", "description": "Also run the LLM analysis", "default": false },
+                },
+                "required": ["path"],
+            },
+        },
+        {
+            "name": "scan_crate",
+            "description": "Scan a crate's source, and optionally its dependencies, and return the report. Can take minutes and many LLM calls on large crates.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Directory of the crate" },
+                    "profile": { "type": "string", "description": "Scan profile: quick (static rules only), standard, deep or one from the config" },
+                    "scan_dependencies": { "type": "boolean", "description": "Also scan the dependencies", "default": false },
+                    "format": { "type": "string", "description": "Report format: json, markdown, condensed, summary, summary-json, sarif, ...", "default": "json" },
+                },
+                "required": ["path"],
+            },
+        },
+        {
+            "name": "query_cache",
+            "description": "Look up cached LLM analyses. Without arguments, lists every analysis; `package` narrows the list to one crate, and `id` returns one analysis in full.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "package": { "type": "string", "description": "Crate name the analyses are for" },
+                    "id": { "type": "integer", "description": "Id of an analysis from the list" },
+                },
+            },
+        },
+        {
+            "name": "fetch_advisories",
+            "description": "Fetch the known vulnerabilities (OSV and RustSec advisories) of a crates.io package version.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Crate name" },
+                    "version": { "type": "string", "description": "Exact version, such as 1.0.3" },
+                },
+                "required": ["name", "version"],
+            },
+        },
+    ])
+}
+
+fn string_argument<'a>(arguments: &'a Value, name: &str) -> Result<&'a str> {
+    arguments[name]
+        .as_str()
+        .filter(|value| !value.is_empty())
+        .with_context(|| format!("Missing argument '{}'", name))
+}
+
+/// The crate a file belongs to: the nearest directory with a Cargo.toml.
+fn crate_root(file: &Path) -> PathBuf {
+    file.ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .or_else(|| file.parent())
+        .unwrap_or(Path::new("."))
+        .to_path_buf()
+}
+
+async fn scan_file(arguments: &Value, profile: &str) -> Result<String> {
+    let path = Path::new(string_argument(arguments, "path")?).canonicalize()?;
+    let root = crate_root(&path);
+    let config = Config::load_for_project(&root)?;
+    let profile = config.scan_profile(profile)?;
+    let client = if arguments["llm"].as_bool().unwrap_or(false) {
+        Some(scan_client(&config, &profile, true, Arc::default()).await?)
+    } else {
+        None
+    };
+    let mut scanner = Scanner::new(root.clone())?;
+    scanner.set_context_budget(scan_context_budget(client.as_ref(), &config, &profile));
+    scanner.set_rule_packs(config.scanner.rule_packs);
+    scanner.set_grammars(&config.scanner.grammars)?;
+    if scanner.language(&path).is_none() {
+        bail!("{} is not a kind of file RustRecon scans", path.display());
+    }
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let file = scanner.analyze_source(&path, content)?;
+
+    let mut findings = file.static_patterns.clone();
+    let mut analysis = None;
+    if let Some(client) = &client {
+        // Keyed like `scan <crate>`, so both share cached analyses
+        let relative_path = path
+            .strip_prefix(&root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .to_string();
+        let crate_name = utils::get_crate_name_from_path(&root);
+        let response = analyze_chunks(
+            client,
+            &crate_name,
+            &relative_path,
+            file.language,
+            &file.chunks,
+        )
+        .await?;
+        let mut patterns = response.flagged_patterns;
+        utils::adjust_confidence(
+            &mut patterns,
+            &file.static_patterns,
+            Path::new(&relative_path),
+        );
+        findings.extend(patterns);
+        analysis = Some(response.analysis);
+    }
+    Ok(serde_json::to_string_pretty(&json!({
+        "file": path,
+        "llm_analysis": analysis,
+        "findings": findings,
+        "unsafe_metrics": file.unsafe_metrics,
+    }))?)
+}
+
+async fn scan_crate(arguments: &Value, profile: &str) -> Result<String> {
+    let path = PathBuf::from(string_argument(arguments, "path")?);
+    if !path.is_dir() {
+        bail!("{} is not a directory", path.display());
+    }
+    let format = arguments["format"].as_str().unwrap_or("json");
+    let config = Config::load_for_project(&path)?;
+    let options = ScanOptions {
+        profile: arguments["profile"].as_str().unwrap_or(profile).to_string(),
+        scan_dependencies: arguments["scan_dependencies"].as_bool().unwrap_or(false),
+        resume: false,
+        offline: false,
+        verify_sources: false,
+        expand_macros: false,
+        no_cache: false,
+        exclude: Vec::new(),
+        include: Vec::new(),
+        only: Vec::new(),
+        files: Vec::new(),
+        changes: None,
+        blame: false,
+        no_ignore: false,
+        min_confidence: None,
+        skip_code: false,
+        max_duration: None,
+        max_api_calls: None,
+        reproducible: false,
+        absolute_paths: false,
+        stream: None,
+    };
+    let report = run_scan(&path, &config, &options).await?;
+    report.render(format)
+}
+
+fn query_cache(arguments: &Value) -> Result<String> {
+    let database = Database::open_default()?;
+    if let Some(id) = arguments["id"].as_i64() {
+        let stored = database
+            .show_analysis(id)?
+            .with_context(|| format!("No cached analysis with id {}", id))?;
+        return Ok(serde_json::to_string_pretty(&stored.entry)?);
+    }
+    let analyses: Vec<Value> = database
+        .list_analyses(arguments["package"].as_str())?
+        .into_iter()
+        .map(|stored| {
+            let entry = stored.entry;
+            json!({
+                "id": stored.id,
+                "package": entry.key.package,
+                "version": entry.key.version,
+                "model": entry.key.model,
+                "scan_date": entry.scan_date,
+                "flagged_patterns": entry.analysis.flagged_patterns.len(),
+            })
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&analyses)?)
+}
+
+async fn fetch_advisories(arguments: &Value) -> Result<String> {
+    let package = LockedPackage {
+        name: string_argument(arguments, "name")?.to_string(),
+        version: string_argument(arguments, "version")?.to_string(),
+        source: Some(CRATES_IO_SOURCE.to_string()),
+        checksum: None,
+        dependencies: Vec::new(),
+    };
+    let client = network::client_builder()
+        .timeout(network::timeouts().request)
+        .user_agent(concat!("rustrecon/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    let database = Database::open_default().ok();
+    let vulnerabilities = osv::lookup_package(&client, &package, database.as_ref())
+        .await
+        .context("OSV lookup failed")?;
+    Ok(serde_json::to_string_pretty(&vulnerabilities)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_protocol_messages() {
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "protocolVersion": "2024-11-05", "capabilities": {} },
+        });
+        let response = handle(&initialize, "quick").await.unwrap();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["protocolVersion"], "2024-11-05");
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(handle(&notification, "quick").await.is_none());
+
+        let list = json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" });
+        let tools = handle(&list, "quick").await.unwrap();
+        let names: Vec<&str> = tools["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            ["scan_file", "scan_crate", "query_cache", "fetch_advisories"]
+        );
+
+        let missing = json!({
+            "jsonrpc": "2.0",
+            "id": 3,
+            "method": "tools/call",
+            "params": { "name": "fetch_advisories", "arguments": { "name": "hex" } },
+        });
+        let result = handle(&missing, "quick").await.unwrap();
+        assert_eq!(result["result"]["isError"], true);
+
+        let unknown = json!({ "jsonrpc": "2.0", "id": 4, "method": "resources/list" });
+        let result = handle(&unknown, "quick").await.unwrap();
+        assert_eq!(result["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
@@ -0,0 +1,211 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::database::Database;
+
+/// Prefix of every exported metric name.
+const PREFIX: &str = "rustrecon_";
+
+/// Help text of each metric, in export order. All of them are counters.
+const METRICS: &[(&str, &str)] = &[
+    ("scans_total", "Completed scans"),
+    (
+        "scan_duration_seconds_total",
+        "Time spent in completed scans",
+    ),
+    ("cache_hits_total", "LLM analyses answered from the cache"),
+    (
+        "cache_misses_total",
+        "Cacheable LLM analyses sent to the model",
+    ),
+    (
+        "llm_requests_total",
+        "Requests sent to the LLM, failed ones included",
+    ),
+    ("llm_errors_total", "Failed LLM requests by kind of failure"),
+    (
+        "llm_request_duration_seconds_total",
+        "Time spent waiting for LLM responses",
+    ),
+];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Values recorded since the last `flush`, keyed by name and labels.
+static PENDING: Mutex<BTreeMap<(&'static str, Labels), f64>> = Mutex::new(BTreeMap::new());
+
+type Labels = BTreeMap<String, String>;
+
+/// A metric total as stored in the database and exported.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricValue {
+    pub name: String,
+    pub labels: Labels,
+    pub value: f64,
+}
+
+/// Starts recording. Metrics stay off unless `[metrics] enabled = true`, and
+/// are only ever kept in the local database.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn add(name: &'static str, labels: &[(&str, &str)], value: f64) {
+    if !is_enabled() {
+        return;
+    }
+    let labels = labels
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    if let Ok(mut pending) = PENDING.lock() {
+        *pending.entry((name, labels)).or_default() += value;
+    }
+}
+
+pub fn record_scan(duration: Duration) {
+    add("scans_total", &[], 1.0);
+    add("scan_duration_seconds_total", &[], duration.as_secs_f64());
+}
+
+pub fn record_cache_lookup(hit: bool) {
+    let name = if hit {
+        "cache_hits_total"
+    } else {
+        "cache_misses_total"
+    };
+    add(name, &[], 1.0);
+}
+
+/// `error` is the `LlmClientError::kind` of a failed request.
+pub fn record_llm_request(model: &str, latency: Duration, error: Option<&str>) {
+    add("llm_requests_total", &[("model", model)], 1.0);
+    add(
+        "llm_request_duration_seconds_total",
+        &[("model", model)],
+        latency.as_secs_f64(),
+    );
+    if let Some(kind) = error {
+        add("llm_errors_total", &[("kind", kind), ("model", model)], 1.0);
+    }
+}
+
+/// Adds the values recorded since the last flush to the totals in `db`.
+pub fn flush(db: &Database) -> Result<()> {
+    let pending = match PENDING.lock() {
+        Ok(mut pending) => std::mem::take(&mut *pending),
+        Err(_) => return Ok(()),
+    };
+    let values: Vec<MetricValue> = pending
+        .into_iter()
+        .map(|((name, labels), value)| MetricValue {
+            name: name.to_string(),
+            labels,
+            value,
+        })
+        .collect();
+    db.add_metrics(&values)
+}
+
+/// Prometheus text exposition format, for the node exporter's textfile
+/// collector or a scrape endpoint.
+pub fn to_prometheus(values: &[MetricValue]) -> String {
+    let mut text = String::new();
+    for (name, help) in METRICS {
+        let samples: Vec<&MetricValue> = values.iter().filter(|v| v.name == *name).collect();
+        if samples.is_empty() {
+            continue;
+        }
+        text.push_str(&format!("# HELP {}{} {}.\n", PREFIX, name, help));
+        text.push_str(&format!("# TYPE {}{} counter\n", PREFIX, name));
+        for sample in samples {
+            let labels: Vec<String> = sample
+                .labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+                .collect();
+            let labels = if labels.is_empty() {
+                String::new()
+            } else {
+                format!("{{{}}}", labels.join(","))
+            };
+            text.push_str(&format!("{}{}{} {}\n", PREFIX, name, labels, sample.value));
+        }
+    }
+    text
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// JSON export: the totals, plus the cache hit rate and average latencies
+/// worked out from them.
+pub fn to_json(values: &[MetricValue]) -> serde_json::Value {
+    let total = |name: &str| -> f64 {
+        values
+            .iter()
+            .filter(|v| v.name == name)
+            .fold(0.0, |sum, v| sum + v.value)
+    };
+    let ratio =
+        |numerator: f64, denominator: f64| (denominator > 0.0).then(|| numerator / denominator);
+    let hits = total("cache_hits_total");
+    serde_json::json!({
+        "metrics": values,
+        "derived": {
+            "cache_hit_rate": ratio(hits, hits + total("cache_misses_total")),
+            "average_scan_seconds": ratio(total("scan_duration_seconds_total"), total("scans_total")),
+            "average_llm_latency_seconds": ratio(
+                total("llm_request_duration_seconds_total"),
+                total("llm_requests_total"),
+            ),
+            "llm_error_rate": ratio(total("llm_errors_total"), total("llm_requests_total")),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_export() {
+        let value = |name: &str, labels: &[(&str, &str)], value: f64| MetricValue {
+            name: name.to_string(),
+            labels: labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            value,
+        };
+        let values = vec![
+            value("cache_hits_total", &[], 3.0),
+            value("cache_misses_total", &[], 1.0),
+            value(
+                "llm_errors_total",
+                &[("kind", "quota"), ("model", "gemini")],
+                2.0,
+            ),
+        ];
+        let text = to_prometheus(&values);
+        assert!(text.contains("# TYPE rustrecon_cache_hits_total counter\n"));
+        assert!(text.contains("rustrecon_cache_hits_total 3\n"));
+        assert!(text.contains("rustrecon_llm_errors_total{kind=\"quota\",model=\"gemini\"} 2\n"));
+        assert!(!text.contains("scans_total"));
+
+        let json = to_json(&values);
+        assert_eq!(json["derived"]["cache_hit_rate"], 0.75);
+        assert!(json["derived"]["average_scan_seconds"].is_null());
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::Result;
+use tracing::{debug, warn};
+
+use crate::database::Database;
+use crate::llm_client::{GeminiClient, LlmClientTrait, ModelInfo};
+
+/// How long the scan-start check trusts a cached model list.
+const CATALOG_MAX_AGE_HOURS: i64 = 24;
+
+/// Models Google has shut down. The models endpoint may keep listing a
+/// model for a while after its shutdown is announced, and configs written
+/// for it are still around afterwards. Longer names (`gemini-1.0-pro-001`,
+/// `gemini-pro-vision`) count as their family.
+const RETIRED_MODELS: &[&str] = &["gemini-pro", "gemini-1.0-pro"];
+
+/// Wording the API uses in a model's description once it is on its way out.
+const DEPRECATION_WORDS: &[&str] = &["deprecated", "discontinued", "will be removed"];
+
+/// Whether `name` belongs to a model family that has been shut down.
+pub fn is_retired(name: &str) -> bool {
+    let name = name.strip_prefix("models/").unwrap_or(name);
+    RETIRED_MODELS.iter().any(|retired| {
+        name.strip_prefix(retired)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+    })
+}
+
+/// Whether `model` is retired or described by the API as deprecated.
+pub fn is_deprecated(model: &ModelInfo) -> bool {
+    let text = format!("{} {}", model.display_name, model.description).to_lowercase();
+    is_retired(&model.name) || DEPRECATION_WORDS.iter().any(|word| text.contains(word))
+}
+
+/// Fetches the models `client`'s endpoint offers and caches the list for the
+/// scan-start check. Takes the database by value so the future stays `Send`
+/// (connections cannot be shared between threads).
+pub async fn fetch_catalog(
+    client: &GeminiClient,
+    endpoint: &str,
+    db: Option<Database>,
+) -> Result<Vec<ModelInfo>> {
+    let models = client.list_models().await?;
+    if let Some(db) = db {
+        if let Err(e) = db.save_model_catalog(endpoint, &models) {
+            warn!("⚠️  Could not cache the model list: {}", e);
+        }
+    }
+    Ok(models)
+}
+
+/// What is wrong with using `configured` on an endpoint offering `models`,
+/// if anything.
+pub fn model_warning(models: &[ModelInfo], configured: &str) -> Option<String> {
+    let configured = configured.strip_prefix("models/").unwrap_or(configured);
+    // An endpoint listing nothing (a proxy or a mock) says nothing either way
+    if models.is_empty() {
+        return is_retired(configured).then(|| {
+            format!(
+                "Model '{}' has been retired; set another `model` under [llm]",
+                configured
+            )
+        });
+    }
+    match models.iter().find(|model| model.name == configured) {
+        None => Some(format!(
+            "Model '{}' is no longer offered by the LLM endpoint and requests to it will fail; \
+             run `rustrecon models list` and set another `model` under [llm]",
+            configured
+        )),
+        Some(model) if is_deprecated(model) => Some(format!(
+            "Model '{}' is deprecated and may stop working; run `rustrecon models list` for current models",
+            configured
+        )),
+        Some(_) => None,
+    }
+}
+
+/// Warns before a scan when the configured model is gone or deprecated, so
+/// the problem shows up once instead of as a 404 for every file. Uses the
+/// cached model list while it is less than a day old; when the list cannot
+/// be fetched the check falls back to a stale copy or is skipped.
+pub async fn check_configured_model(client: &GeminiClient, endpoint: &str) {
+    let db = Database::open_default().ok();
+    let cached = db.as_ref().and_then(|db| {
+        db.load_model_catalog(endpoint).unwrap_or_else(|e| {
+            debug!("Could not read the cached model list: {}", e);
+            None
+        })
+    });
+    let models = match cached {
+        Some((fetched_at, models))
+            if chrono::Utc::now()
+                .signed_duration_since(fetched_at)
+                .num_hours()
+                < CATALOG_MAX_AGE_HOURS =>
+        {
+            models
+        }
+        stale => match fetch_catalog(client, endpoint, db).await {
+            Ok(models) => models,
+            Err(e) => {
+                debug!(
+                    "Skipping the model check; the model list is unavailable: {}",
+                    e
+                );
+                match stale {
+                    Some((_, models)) => models,
+                    None => return,
+                }
+            }
+        },
+    };
+    if let Some(warning) = model_warning(&models, client.model_name()) {
+        warn!("⚠️  {}", warning);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_warnings() {
+        let model = |name: &str, description: &str| -> ModelInfo {
+            serde_json::from_value(serde_json::json!({
+                "name": name,
+                "description": description,
+            }))
+            .unwrap()
+        };
+        let models = vec![
+            model("gemini-1.0-pro-001", "The original Gemini model"),
+            model("gemini-1.5-flash", "Fast and versatile"),
+            model(
+                "gemini-1.5-pro-002",
+                "Deprecated: use gemini-2.5-pro instead",
+            ),
+        ];
+        assert!(is_deprecated(&models[0]));
+        assert!(!is_deprecated(&models[1]));
+        assert!(is_deprecated(&models[2]));
+        assert!(!is_retired("gemini-2.5-pro"));
+
+        assert!(model_warning(&models, "models/gemini-1.5-flash").is_none());
+        assert!(model_warning(&models, "gemini-1.5-pro-002")
+            .unwrap()
+            .contains("deprecated"));
+        assert!(model_warning(&models, "gemini-2.0-flash")
+            .unwrap()
+            .contains("no longer offered"));
+        assert!(model_warning(&[], "gemini-2.0-flash").is_none());
+        assert!(model_warning(&[], "gemini-pro").is_some());
+    }
+}
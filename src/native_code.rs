@@ -0,0 +1,221 @@
+use cargo_metadata::{DependencyKind, Package};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Extensions of the C and C++ sources and headers a `-sys` crate bundles.
+const NATIVE_EXTENSIONS: &[&str] = &["c", "cc", "cpp", "cxx", "h", "hh", "hpp", "hxx"];
+
+/// Build dependencies that compile native code or generate bindings to it.
+const NATIVE_BUILD_TOOLS: &[&str] = &[
+    "autotools",
+    "bindgen",
+    "cc",
+    "cmake",
+    "cxx-build",
+    "nasm-rs",
+    "pkg-config",
+    "vcpkg",
+];
+
+/// Native sources read for review once the sample is this large; the
+/// context budget trims it further.
+const MAX_SAMPLE_BYTES: usize = 256 * 1024;
+
+/// The native code behind a `-sys` crate: the C and C++ it ships and the
+/// build steps that compile or bind it.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NativeFootprint {
+    /// C and C++ sources and headers in the package
+    pub files: usize,
+    pub lines: usize,
+    pub bytes: u64,
+    /// Build dependencies that compile or bind native code, e.g. `cc`, `bindgen`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub build_tools: Vec<String>,
+    /// Native library named by the package's `links` key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<String>,
+}
+
+impl NativeFootprint {
+    /// The footprint of `package` if it is a `-sys` crate.
+    pub fn of(package: &Package) -> Option<Self> {
+        if !is_sys_crate(package) {
+            return None;
+        }
+        let mut build_tools: Vec<String> = package
+            .dependencies
+            .iter()
+            .filter(|d| d.kind == DependencyKind::Build)
+            .map(|d| d.name.clone())
+            .filter(|name| NATIVE_BUILD_TOOLS.contains(&name.as_str()))
+            .collect();
+        build_tools.sort();
+        build_tools.dedup();
+        let mut footprint = NativeFootprint {
+            build_tools,
+            links: package.links.clone(),
+            ..NativeFootprint::default()
+        };
+        if let Some(dir) = package.manifest_path.parent() {
+            for path in native_files(dir.as_std_path()) {
+                let Ok(content) = std::fs::read(&path) else {
+                    continue;
+                };
+                footprint.files += 1;
+                footprint.lines += content.iter().filter(|&&b| b == b'\n').count();
+                footprint.bytes += content.len() as u64;
+            }
+        }
+        Some(footprint)
+    }
+
+    /// Whether the crate compiles anything itself, rather than only linking
+    /// a system library.
+    pub fn builds_native_code(&self) -> bool {
+        self.files > 0 || !self.build_tools.is_empty()
+    }
+
+    /// One-line form for reports, e.g. `12 C/C++ files (3400 lines, 96 KB);
+    /// built with cc, bindgen; links z`.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![if self.files == 0 {
+            "no bundled C/C++ files".to_string()
+        } else {
+            format!(
+                "{} C/C++ files ({} lines, {} KB)",
+                self.files,
+                self.lines,
+                self.bytes.div_ceil(1024)
+            )
+        }];
+        if !self.build_tools.is_empty() {
+            parts.push(format!("built with {}", self.build_tools.join(", ")));
+        }
+        if let Some(links) = &self.links {
+            parts.push(format!("links {}", links));
+        }
+        parts.join("; ")
+    }
+}
+
+/// Whether `package` binds a native library: it is named `*-sys` or has a
+/// `links` key.
+pub fn is_sys_crate(package: &Package) -> bool {
+    package.name.ends_with("-sys") || package.links.is_some()
+}
+
+/// The build script of the package in `dir` and a sample of its native
+/// sources, in the `// file:` form of the other source excerpts. Sources the
+/// build script names come first, then other sources before headers.
+pub fn review_source(dir: &Path) -> String {
+    let build_script = std::fs::read_to_string(dir.join("build.rs")).unwrap_or_default();
+    let mut files = native_files(dir);
+    files.sort_by_cached_key(|path| {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let header = path
+            .extension()
+            .is_some_and(|ext| ext.to_string_lossy().starts_with('h'));
+        (!build_script.contains(&name), header, path.clone())
+    });
+
+    let mut source = String::new();
+    if !build_script.is_empty() {
+        source.push_str(&format!("// file: build.rs\n{}\n", build_script));
+    }
+    for path in files {
+        if source.len() >= MAX_SAMPLE_BYTES {
+            break;
+        }
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let relative = path.strip_prefix(dir).unwrap_or(&path);
+        source.push_str(&format!("// file: {}\n{}\n", relative.display(), content));
+    }
+    source
+}
+
+/// C and C++ files under `dir`, skipping build output and version control.
+fn native_files(dir: &Path) -> Vec<PathBuf> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || (e.file_name() != "target" && e.file_name() != ".git"))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            e.path().extension().is_some_and(|ext| {
+                NATIVE_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+            })
+        })
+        .map(|e| e.into_path())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_native_footprint() {
+        let dir =
+            std::env::temp_dir().join(format!("rustrecon-test-{}-native", std::process::id()));
+        std::fs::create_dir_all(dir.join("vendor/include")).unwrap();
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        std::fs::write(
+            dir.join("build.rs"),
+            "fn main() { cc::Build::new().file(\"vendor/zmain.c\").compile(\"z\"); }\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("vendor/util.c"), "int util(void) { return 0; }\n").unwrap();
+        std::fs::write(
+            dir.join("vendor/zmain.c"),
+            "#include \"z.h\"\nint z(void);\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("vendor/include/z.h"), "int z(void);\n").unwrap();
+        std::fs::write(dir.join("target/out.c"), "int generated;\n").unwrap();
+
+        assert_eq!(native_files(&dir).len(), 3);
+
+        let source = review_source(&dir);
+        let order: Vec<&str> = source
+            .lines()
+            .filter_map(|line| line.strip_prefix("// file: "))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                "build.rs",
+                "vendor/zmain.c",
+                "vendor/util.c",
+                "vendor/include/z.h"
+            ]
+        );
+
+        let footprint = NativeFootprint {
+            files: 3,
+            lines: 4,
+            bytes: 2048,
+            build_tools: vec!["cc".to_string()],
+            links: Some("z".to_string()),
+        };
+        assert!(footprint.builds_native_code());
+        assert_eq!(
+            footprint.describe(),
+            "3 C/C++ files (4 lines, 2 KB); built with cc; links z"
+        );
+        let system = NativeFootprint {
+            links: Some("ssl".to_string()),
+            ..NativeFootprint::default()
+        };
+        assert!(!system.builds_native_code());
+        assert_eq!(system.describe(), "no bundled C/C++ files; links ssl");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
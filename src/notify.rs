@@ -0,0 +1,231 @@
+use anyhow::Result;
+use reqwest::Client;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::config::{NotificationConfig, WebhookKind};
+use crate::network;
+use crate::report::RiskReport;
+use crate::severity;
+
+/// Findings and dependencies listed in chat messages; the rest are counted.
+const MAX_LISTED: usize = 5;
+
+/// Discord rejects messages longer than this.
+const DISCORD_MAX_CHARS: usize = 2000;
+
+/// What in a report is severe enough to alert on.
+struct Alert {
+    /// `(location, severity, description)` of each alerting code finding
+    findings: Vec<(String, String, String)>,
+    /// `(name@version, risk)` of each alerting dependency
+    dependencies: Vec<(String, String)>,
+}
+
+impl Alert {
+    fn from_report(report: &RiskReport, min_severity: &str) -> Option<Self> {
+        let mut findings = Vec::new();
+        for finding in &report.findings {
+            for pattern in &finding.flagged_patterns {
+                if severity::is_at_least(&pattern.severity, min_severity) {
+                    findings.push((
+                        format!("{}:{}", finding.file_path.display(), pattern.line),
+                        pattern.severity.clone(),
+                        pattern.description.clone(),
+                    ));
+                }
+            }
+        }
+        let dependencies: Vec<_> = report
+            .dependency_findings
+            .iter()
+            .map(|dep| (dep, format!("{:?}", dep.risk_score)))
+            .filter(|(_, risk)| severity::is_at_least(risk, min_severity))
+            .map(|(dep, risk)| (format!("{}@{}", dep.package_name, dep.version), risk))
+            .collect();
+        (!findings.is_empty() || !dependencies.is_empty()).then_some(Self {
+            findings,
+            dependencies,
+        })
+    }
+
+    fn title(&self, crate_name: &str, min_severity: &str) -> String {
+        format!(
+            "RustRecon: {} has {} findings and {} dependencies at {} or above",
+            crate_name,
+            self.findings.len(),
+            self.dependencies.len(),
+            min_severity
+        )
+    }
+
+    /// Markdown body shared by the chat formats.
+    fn text(&self, report_link: Option<&str>) -> String {
+        let mut lines = Vec::new();
+        for (location, severity, description) in self.findings.iter().take(MAX_LISTED) {
+            lines.push(format!("- [{}] `{}` {}", severity, location, description));
+        }
+        if self.findings.len() > MAX_LISTED {
+            lines.push(format!(
+                "- ...and {} more findings",
+                self.findings.len() - MAX_LISTED
+            ));
+        }
+        for (package, risk) in self.dependencies.iter().take(MAX_LISTED) {
+            lines.push(format!("- [{}] dependency `{}`", risk, package));
+        }
+        if self.dependencies.len() > MAX_LISTED {
+            lines.push(format!(
+                "- ...and {} more dependencies",
+                self.dependencies.len() - MAX_LISTED
+            ));
+        }
+        if let Some(link) = report_link {
+            lines.push(format!("Report: {}", link));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Sends an alert to every configured webhook if the report has findings or
+/// dependencies at `min_severity` or above. Delivery failures are logged, not
+/// returned, so they never fail a scan.
+pub async fn send_alerts(config: &NotificationConfig, report: &RiskReport, output: Option<&str>) {
+    if config.webhooks.is_empty() {
+        return;
+    }
+    let min_severity = config.min_severity();
+    let Some(alert) = Alert::from_report(report, min_severity) else {
+        return;
+    };
+    let report_link = config.report_url.as_deref().or(output);
+    let client = match network::client_builder()
+        .timeout(network::timeouts().request)
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("⚠️  Could not send notifications: {}", e);
+            return;
+        }
+    };
+
+    for webhook in &config.webhooks {
+        let payload = payload(webhook.kind, &alert, report, min_severity, report_link);
+        match post(&client, &webhook.url, &payload).await {
+            Ok(()) => info!("🔔 Sent {:?} notification", webhook.kind),
+            Err(e) => warn!("⚠️  {:?} notification failed: {:#}", webhook.kind, e),
+        }
+    }
+}
+
+async fn post(client: &Client, url: &str, payload: &serde_json::Value) -> Result<()> {
+    client
+        .post(url)
+        .json(payload)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+fn payload(
+    kind: WebhookKind,
+    alert: &Alert,
+    report: &RiskReport,
+    min_severity: &str,
+    report_link: Option<&str>,
+) -> serde_json::Value {
+    let title = alert.title(&report.crate_name, min_severity);
+    let text = alert.text(report_link);
+    match kind {
+        WebhookKind::Slack => json!({ "text": format!("🚨 *{}*\n{}", title, text) }),
+        WebhookKind::Discord => {
+            let content: String = format!("🚨 **{}**\n{}", title, text)
+                .chars()
+                .take(DISCORD_MAX_CHARS)
+                .collect();
+            json!({ "content": content })
+        }
+        WebhookKind::Teams => json!({
+            "@type": "MessageCard",
+            "@context": "https://schema.org/extensions",
+            "summary": title,
+            "themeColor": "B00020",
+            "title": format!("🚨 {}", title),
+            "text": text.replace('\n', "\n\n"),
+        }),
+        WebhookKind::Generic => json!({
+            "event": "scan.alert",
+            "crate": report.crate_name,
+            "min_severity": min_severity,
+            "summary": report.summary,
+            "findings": alert.findings.iter().map(|(location, severity, description)| json!({
+                "location": location,
+                "severity": severity,
+                "description": description,
+            })).collect::<Vec<_>>(),
+            "dependencies": alert.dependencies.iter().map(|(package, risk)| json!({
+                "package": package,
+                "risk": risk,
+            })).collect::<Vec<_>>(),
+            "report_url": report_link,
+            "report": report,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::FlaggedPattern;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_alert_only_at_min_severity() {
+        let mut report = RiskReport::new("demo".to_string());
+        let pattern = |line: usize, severity: &str| FlaggedPattern {
+            line,
+            severity: severity.to_string(),
+            description: "Spawns an external process".to_string(),
+            code_snippet: String::new(),
+            confidence: 1.0,
+            cwe: None,
+            owasp: None,
+            id: None,
+            triage: None,
+            blame: None,
+            rule: None,
+        };
+        report.add_file_finding(
+            PathBuf::from("src/main.rs"),
+            String::new(),
+            vec![pattern(3, "Medium"), pattern(7, "Critical")],
+        );
+
+        assert!(Alert::from_report(&report, "Critical").is_some());
+        assert!(Alert::from_report(&report, "High").is_some());
+        let alert = Alert::from_report(&report, "Medium").unwrap();
+        assert_eq!(alert.findings.len(), 2);
+
+        let slack = payload(
+            WebhookKind::Slack,
+            &alert,
+            &report,
+            "Medium",
+            Some("https://ci/r"),
+        );
+        let text = slack["text"].as_str().unwrap();
+        assert!(text.contains("demo has 2 findings and 0 dependencies at Medium or above"));
+        assert!(text.contains("- [Critical] `src/main.rs:7` Spawns an external process"));
+        assert!(text.ends_with("Report: https://ci/r"));
+
+        let mut clean = RiskReport::new("clean".to_string());
+        clean.add_file_finding(
+            PathBuf::from("src/lib.rs"),
+            String::new(),
+            vec![pattern(1, "Low")],
+        );
+        assert!(Alert::from_report(&clean, "High").is_none());
+    }
+}
@@ -0,0 +1,222 @@
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use reqwest::Method;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{error, info, warn};
+
+use crate::batch::ProjectResult;
+use crate::github::GitHub;
+use crate::network;
+
+/// A repository of the audited organization.
+#[derive(Debug)]
+pub struct OrgRepo {
+    pub name: String,
+    pub clone_url: String,
+}
+
+/// Which of an organization's repositories to audit.
+pub struct RepoFilter {
+    pub include_forks: bool,
+    pub include_archived: bool,
+}
+
+impl RepoFilter {
+    /// Whether `repo`, as listed by the GitHub API, is a Rust repository to audit.
+    fn matches(&self, repo: &Value) -> bool {
+        repo["language"].as_str() == Some("Rust")
+            && (self.include_forks || !repo["fork"].as_bool().unwrap_or(false))
+            && (self.include_archived || !repo["archived"].as_bool().unwrap_or(false))
+    }
+}
+
+/// Repositories whose main language is Rust, of an organization or a user.
+pub async fn list_rust_repos(
+    github: &GitHub,
+    owner: &str,
+    filter: &RepoFilter,
+) -> Result<Vec<OrgRepo>> {
+    // `/users/{name}` answers for organizations too, and says which it is
+    let account = github
+        .request(Method::GET, &format!("/users/{}", owner), None)
+        .await
+        .with_context(|| format!("No GitHub organization or user named '{}'", owner))?;
+    let path = if account["type"].as_str() == Some("Organization") {
+        format!("/orgs/{}/repos?type=all", owner)
+    } else {
+        format!("/users/{}/repos?type=owner", owner)
+    };
+    let mut repos: Vec<OrgRepo> = github
+        .get_all(&path)
+        .await?
+        .iter()
+        .filter(|repo| filter.matches(repo))
+        .filter_map(|repo| {
+            let name = repo["name"].as_str()?;
+            let clone_url = repo["clone_url"].as_str()?;
+            // The name becomes a directory and the URL a git argument
+            let safe = !name.contains(['/', '\\']) && !name.starts_with('.');
+            let remote = clone_url.starts_with("https://") || clone_url.starts_with("http://");
+            if !safe || !remote {
+                warn!(
+                    "⚠️  Skipping repository with unexpected name or URL: {}",
+                    name
+                );
+                return None;
+            }
+            Some(OrgRepo {
+                name: name.to_string(),
+                clone_url: clone_url.to_string(),
+            })
+        })
+        .collect();
+    repos.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(repos)
+}
+
+/// Shallow clones (or, if already cloned, updates) each repository into `dir`.
+/// Returns the checkouts, and the repositories that could not be fetched as
+/// failed dashboard entries.
+pub fn clone_all(
+    repos: &[OrgRepo],
+    dir: &Path,
+    token: Option<&str>,
+) -> Result<(Vec<PathBuf>, Vec<ProjectResult>)> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let mut checkouts = Vec::new();
+    let mut failures = Vec::new();
+    for (index, repo) in repos.iter().enumerate() {
+        let path = dir.join(&repo.name);
+        info!("⬇️  [{}/{}] Fetching {}", index + 1, repos.len(), repo.name);
+        match fetch(repo, &path, token) {
+            Ok(()) => checkouts.push(path),
+            Err(e) => {
+                error!("❌ {}: {:#}", repo.name, e);
+                failures.push(ProjectResult::failed(&path, &e, 0));
+            }
+        }
+    }
+    Ok((checkouts, failures))
+}
+
+fn fetch(repo: &OrgRepo, path: &Path, token: Option<&str>) -> Result<()> {
+    network::check_git_url(&repo.clone_url)?;
+    if path.join(".git").is_dir() {
+        git(
+            &["fetch", "-q", "--depth", "1", "origin", "HEAD"],
+            path,
+            repo,
+            token,
+        )?;
+        git(&["checkout", "-q", "FETCH_HEAD"], path, repo, token)
+    } else {
+        let target = path.to_string_lossy();
+        let args = [
+            "clone",
+            "-q",
+            "--depth",
+            "1",
+            "--",
+            &repo.clone_url,
+            &target,
+        ];
+        git(&args, Path::new("."), repo, token)
+    }
+}
+
+/// Runs git without prompting for credentials. The token, if any, is passed
+/// as an HTTP header for the repository's host through the environment, so it
+/// is neither stored in the clone's config nor shown in the process list.
+fn git(args: &[&str], dir: &Path, repo: &OrgRepo, token: Option<&str>) -> Result<()> {
+    let mut command = Command::new("git");
+    command
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_TERMINAL_PROMPT", "0");
+    if let Some(token) = token {
+        let credentials =
+            base64::engine::general_purpose::STANDARD.encode(format!("x-access-token:{}", token));
+        command
+            .env("GIT_CONFIG_COUNT", "1")
+            .env(
+                "GIT_CONFIG_KEY_0",
+                format!("http.{}.extraheader", url_origin(&repo.clone_url)),
+            )
+            .env(
+                "GIT_CONFIG_VALUE_0",
+                format!("AUTHORIZATION: basic {}", credentials),
+            );
+    }
+    let output = command.output().context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// `scheme://host[:port]/` of a URL, the scope of the authorization header.
+fn url_origin(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("https", url));
+    let host = rest.split('/').next().unwrap_or_default();
+    format!("{}://{}/", scheme, host)
+}
+
+/// Directory the clones go in when `--clone-dir` is not given, removed when
+/// the audit ends.
+pub struct TempCloneDir(PathBuf);
+
+impl TempCloneDir {
+    pub fn create(owner: &str) -> Result<Self> {
+        let path =
+            std::env::temp_dir().join(format!("rustrecon-audit-{}-{}", owner, std::process::id()));
+        if path.exists() {
+            fs::remove_dir_all(&path)?;
+        }
+        fs::create_dir_all(&path)?;
+        Ok(Self(path))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempCloneDir {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.0) {
+            warn!("⚠️  Could not remove {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_repo_filter_and_header_scope() {
+        let filter = RepoFilter {
+            include_forks: false,
+            include_archived: true,
+        };
+        let repo = |language: &str, fork: bool, archived: bool| json!({ "language": language, "fork": fork, "archived": archived });
+        assert!(filter.matches(&repo("Rust", false, false)));
+        assert!(filter.matches(&repo("Rust", false, true)));
+        assert!(!filter.matches(&repo("Rust", true, false)));
+        assert!(!filter.matches(&repo("Go", false, false)));
+        assert!(!filter.matches(&json!({ "language": null })));
+
+        assert_eq!(
+            url_origin("https://github.example.com:8443/acme/api.git"),
+            "https://github.example.com:8443/"
+        );
+    }
+}
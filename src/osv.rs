@@ -0,0 +1,316 @@
+use anyhow::{Context, Result};
+use futures::stream::{self, StreamExt};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tracing::warn;
+
+use crate::database::Database;
+use crate::dependency_graph::package_key;
+use crate::lockfile::LockedPackage;
+use crate::network::{self, Revalidated};
+
+const OSV_API: &str = "https://api.osv.dev/v1";
+/// The querybatch endpoint accepts at most 1000 queries per request.
+const OSV_BATCH_SIZE: usize = 1000;
+const OSV_DETAIL_CONCURRENCY: usize = 8;
+/// Cached lookups are reused for a day; advisories are published continuously.
+const OSV_CACHE_MAX_AGE_HOURS: i64 = 24;
+
+/// A known vulnerability affecting a specific package version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vulnerability {
+    pub id: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub summary: String,
+    pub severity: String,
+    /// Versions that contain the fix, when the advisory lists any
+    #[serde(default)]
+    pub fixed_versions: Vec<String>,
+}
+
+/// Looks up every external package in OSV, serving fresh results from the local
+/// cache and querying the API in batches for the rest. Returns vulnerabilities
+/// keyed by `name@version`; packages without any are omitted.
+pub async fn lookup(
+    client: &Client,
+    packages: &[LockedPackage],
+    db: Option<&Database>,
+    offline: bool,
+) -> HashMap<String, Vec<Vulnerability>> {
+    let mut found = HashMap::new();
+    let mut missing = Vec::new();
+
+    let mut seen = HashSet::new();
+    for package in packages.iter().filter(|p| p.is_external()) {
+        let key = package_key(&package.name, &package.version);
+        if !seen.insert(key.clone()) {
+            continue;
+        }
+        let cached = db.and_then(|db| {
+            db.load_osv_result(&package.name, &package.version)
+                .unwrap_or_else(|e| {
+                    warn!("⚠️  Could not read cached OSV results: {}", e);
+                    None
+                })
+        });
+        match cached {
+            Some((fetched_at, vulnerabilities))
+                if offline
+                    || chrono::Utc::now()
+                        .signed_duration_since(fetched_at)
+                        .num_hours()
+                        < OSV_CACHE_MAX_AGE_HOURS =>
+            {
+                if !vulnerabilities.is_empty() {
+                    found.insert(key, vulnerabilities);
+                }
+            }
+            _ => missing.push(package),
+        }
+    }
+
+    if offline || missing.is_empty() {
+        return found;
+    }
+
+    for batch in missing.chunks(OSV_BATCH_SIZE) {
+        match query_batch(client, batch, db).await {
+            Ok(results) => {
+                for (package, vulnerabilities) in batch.iter().zip(results) {
+                    if let Some(db) = db {
+                        if let Err(e) =
+                            db.save_osv_result(&package.name, &package.version, &vulnerabilities)
+                        {
+                            warn!("⚠️  Could not cache OSV results: {}", e);
+                        }
+                    }
+                    if !vulnerabilities.is_empty() {
+                        found.insert(
+                            package_key(&package.name, &package.version),
+                            vulnerabilities,
+                        );
+                    }
+                }
+            }
+            Err(e) => warn!("⚠️  OSV vulnerability lookup failed: {}", e),
+        }
+    }
+
+    found
+}
+
+/// Vulnerabilities of one package version, served from the cache while fresh.
+/// Unlike `lookup`, a failed query is an error instead of an empty list.
+pub async fn lookup_package(
+    client: &Client,
+    package: &LockedPackage,
+    db: Option<&Database>,
+) -> Result<Vec<Vulnerability>> {
+    if let Some((fetched_at, vulnerabilities)) = db
+        .map(|db| db.load_osv_result(&package.name, &package.version))
+        .transpose()?
+        .flatten()
+    {
+        if chrono::Utc::now()
+            .signed_duration_since(fetched_at)
+            .num_hours()
+            < OSV_CACHE_MAX_AGE_HOURS
+        {
+            return Ok(vulnerabilities);
+        }
+    }
+    let vulnerabilities = query_batch(client, &[package], db)
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    if let Some(db) = db {
+        db.save_osv_result(&package.name, &package.version, &vulnerabilities)?;
+    }
+    Ok(vulnerabilities)
+}
+
+/// Returns one vulnerability list per package, in the same order.
+async fn query_batch(
+    client: &Client,
+    packages: &[&LockedPackage],
+    db: Option<&Database>,
+) -> Result<Vec<Vec<Vulnerability>>> {
+    let queries: Vec<serde_json::Value> = packages
+        .iter()
+        .map(|p| {
+            serde_json::json!({
+                "package": {"name": p.name, "ecosystem": "crates.io"},
+                "version": p.version,
+            })
+        })
+        .collect();
+
+    let response = network::send(
+        client
+            .post(format!("{}/querybatch", OSV_API))
+            .json(&serde_json::json!({ "queries": queries })),
+    )
+    .await?
+    .error_for_status()?;
+    let body: serde_json::Value = response.json().await?;
+
+    // The batch endpoint only returns IDs; details come from /vulns/{id}
+    let ids: Vec<Vec<String>> = body["results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|result| {
+            result["vulns"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|v| v["id"].as_str().map(str::to_string))
+                .collect()
+        })
+        .collect();
+
+    let unique: HashSet<&String> = ids.iter().flatten().collect();
+    let details: HashMap<String, Vulnerability> = stream::iter(unique)
+        .map(|id| async move {
+            fetch_vulnerability(client, id, db)
+                .await
+                .map(|v| (id.clone(), v))
+        })
+        .buffer_unordered(OSV_DETAIL_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<_>>()?;
+
+    Ok((0..packages.len())
+        .map(|i| {
+            ids.get(i)
+                .into_iter()
+                .flatten()
+                .filter_map(|id| details.get(id).cloned())
+                .collect()
+        })
+        .collect())
+}
+
+/// Fetches an advisory's details. A saved copy is reused for a day and then
+/// revalidated, so unchanged advisories cost a 304 instead of a download.
+async fn fetch_vulnerability(
+    client: &Client,
+    id: &str,
+    db: Option<&Database>,
+) -> Result<Vulnerability> {
+    let saved = db.and_then(|db| {
+        db.load_osv_advisory(id).unwrap_or_else(|e| {
+            warn!("⚠️  Could not read the cached advisory {}: {}", id, e);
+            None
+        })
+    });
+    let body = match saved {
+        Some(saved) if saved.is_fresh(OSV_CACHE_MAX_AGE_HOURS) => saved.body,
+        saved => {
+            let url = format!("{}/vulns/{}", OSV_API, id);
+            let validators = saved.as_ref().map(|saved| &saved.validators);
+            let (body, validators) = match network::conditional_get(client, &url, validators)
+                .await
+                .with_context(|| format!("OSV request for {} failed", id))?
+            {
+                Revalidated::Modified { body, validators } => (body, validators),
+                Revalidated::NotModified => {
+                    let saved = saved.context("304 without a saved advisory")?;
+                    (saved.body, saved.validators)
+                }
+            };
+            if let Some(db) = db {
+                if let Err(e) = db.save_osv_advisory(id, &body, &validators) {
+                    warn!("⚠️  Could not cache the advisory {}: {}", id, e);
+                }
+            }
+            body
+        }
+    };
+    let body: serde_json::Value = serde_json::from_str(&body)?;
+    Ok(parse_vulnerability(id, &body))
+}
+
+fn parse_vulnerability(id: &str, body: &serde_json::Value) -> Vulnerability {
+    let mut fixed_versions: Vec<String> = body["affected"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|affected| affected["ranges"].as_array().into_iter().flatten())
+        .flat_map(|range| range["events"].as_array().into_iter().flatten())
+        .filter_map(|event| event["fixed"].as_str().map(str::to_string))
+        .collect();
+    fixed_versions.sort();
+    fixed_versions.dedup();
+
+    Vulnerability {
+        id: id.to_string(),
+        aliases: body["aliases"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|a| a.as_str().map(str::to_string))
+            .collect(),
+        summary: body["summary"]
+            .as_str()
+            .or_else(|| body["details"].as_str())
+            .unwrap_or("No summary provided")
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        severity: severity_of(body).to_string(),
+        fixed_versions,
+    }
+}
+
+/// GitHub advisories carry a severity label; RustSec marks unmaintained or
+/// unsound crates as informational. Anything else is treated as High.
+fn severity_of(body: &serde_json::Value) -> &'static str {
+    let labelled = body["database_specific"]["severity"]
+        .as_str()
+        .or_else(|| body["affected"][0]["database_specific"]["severity"].as_str());
+    match labelled.map(str::to_ascii_uppercase).as_deref() {
+        Some("CRITICAL") => return "Critical",
+        Some("HIGH") => return "High",
+        Some("MODERATE") | Some("MEDIUM") => return "Medium",
+        Some("LOW") => return "Low",
+        _ => {}
+    }
+    if body["affected"][0]["database_specific"]["informational"].is_string() {
+        return "Low";
+    }
+    "High"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vulnerability() {
+        let body = serde_json::json!({
+            "id": "RUSTSEC-2021-0001",
+            "aliases": ["CVE-2021-0001"],
+            "summary": "Use after free in Foo",
+            "affected": [{
+                "ranges": [{"type": "SEMVER", "events": [
+                    {"introduced": "0.0.0-0"}, {"fixed": "1.2.3"}, {"introduced": "2.0.0"}, {"fixed": "2.0.1"}
+                ]}],
+                "database_specific": {"informational": "unsound"}
+            }]
+        });
+        let vulnerability = parse_vulnerability("RUSTSEC-2021-0001", &body);
+        assert_eq!(vulnerability.aliases, vec!["CVE-2021-0001".to_string()]);
+        assert_eq!(
+            vulnerability.fixed_versions,
+            vec!["1.2.3".to_string(), "2.0.1".to_string()]
+        );
+        assert_eq!(vulnerability.severity, "Low");
+    }
+}
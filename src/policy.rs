@@ -0,0 +1,313 @@
+use anyhow::{bail, Context, Result};
+use globset::{Glob, GlobMatcher};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::dependency_scanner::RiskScore;
+use crate::report::RiskReport;
+use crate::severity;
+
+/// Rules a scan must satisfy, read from a TOML file given with `--policy`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(rename = "rule", default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+/// A limit on one count taken from the report, e.g. "at most 3 findings of
+/// High severity or above".
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    pub name: String,
+    pub description: Option<String>,
+    pub metric: Metric,
+    /// Largest allowed value
+    pub max: usize,
+    /// Only count findings or advisories of this severity or above
+    pub min_severity: Option<String>,
+    /// Only count dependencies of this risk or above
+    pub min_risk: Option<RiskScore>,
+    /// Only count findings or unsafe blocks in files matching this glob,
+    /// relative to the crate root
+    pub path: Option<String>,
+    /// Only count findings with this CWE, e.g. `CWE-78`
+    pub cwe: Option<String>,
+    /// Limit the increase over `--policy-baseline` instead of the total
+    #[serde(default)]
+    pub new: bool,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// Code findings in the scanned crate
+    Findings,
+    /// Scanned dependencies
+    Dependencies,
+    /// `unsafe` blocks in the scanned crate
+    UnsafeBlocks,
+    /// Known advisories affecting dependencies
+    Vulnerabilities,
+}
+
+/// How a rule fared against a report.
+#[derive(Debug)]
+pub struct RuleOutcome {
+    pub name: String,
+    pub description: Option<String>,
+    pub value: usize,
+    pub max: usize,
+    pub new: bool,
+}
+
+impl RuleOutcome {
+    pub fn passed(&self) -> bool {
+        self.value <= self.max
+    }
+}
+
+impl fmt::Display for RuleOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}{} (max {})",
+            self.name,
+            self.value,
+            if self.new { " new" } else { "" },
+            self.max
+        )?;
+        if let Some(description) = &self.description {
+            write!(f, " - {}", description)?;
+        }
+        Ok(())
+    }
+}
+
+impl Policy {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read policy {}", path.display()))?;
+        content
+            .parse()
+            .with_context(|| format!("Invalid policy {}", path.display()))
+    }
+
+    pub fn needs_baseline(&self) -> bool {
+        self.rules.iter().any(|rule| rule.new)
+    }
+
+    /// Evaluates every rule. File paths in the reports are taken relative to
+    /// `root`, the scanned crate.
+    pub fn evaluate(
+        &self,
+        report: &RiskReport,
+        baseline: Option<&RiskReport>,
+        root: &Path,
+    ) -> Result<Vec<RuleOutcome>> {
+        let mut outcomes = Vec::new();
+        for rule in &self.rules {
+            let mut value = rule.count(report, root)?;
+            if rule.new {
+                let Some(baseline) = baseline else {
+                    bail!(
+                        "Policy rule '{}' counts new findings; pass the previous JSON report with --policy-baseline",
+                        rule.name
+                    );
+                };
+                value = value.saturating_sub(rule.count(baseline, root)?);
+            }
+            outcomes.push(RuleOutcome {
+                name: rule.name.clone(),
+                description: rule.description.clone(),
+                value,
+                max: rule.max,
+                new: rule.new,
+            });
+        }
+        Ok(outcomes)
+    }
+}
+
+impl std::str::FromStr for Policy {
+    type Err = anyhow::Error;
+
+    fn from_str(content: &str) -> Result<Self> {
+        let policy: Policy = toml::from_str(content)?;
+        for rule in &policy.rules {
+            rule.validate()
+                .with_context(|| format!("rule '{}'", rule.name))?;
+        }
+        Ok(policy)
+    }
+}
+
+impl PolicyRule {
+    fn validate(&self) -> Result<()> {
+        let applies = |filter: &str, metrics: &[&str]| -> Result<()> {
+            let metric = format!("{:?}", self.metric);
+            if !metrics.contains(&metric.as_str()) {
+                bail!("{} does not apply to the {:?} metric", filter, self.metric);
+            }
+            Ok(())
+        };
+        if let Some(level) = &self.min_severity {
+            applies("min_severity", &["Findings", "Vulnerabilities"])?;
+            if !severity::LEVELS.contains(&severity::canonical(level).unwrap_or_default()) {
+                bail!(
+                    "min_severity \"{}\" is not one of {}",
+                    level,
+                    severity::LEVELS.join(", ")
+                );
+            }
+        }
+        if self.min_risk.is_some() {
+            applies("min_risk", &["Dependencies"])?;
+        }
+        if self.path.is_some() {
+            applies("path", &["Findings", "UnsafeBlocks"])?;
+            self.path_matcher()?;
+        }
+        if self.cwe.is_some() {
+            applies("cwe", &["Findings"])?;
+        }
+        Ok(())
+    }
+
+    fn path_matcher(&self) -> Result<Option<GlobMatcher>> {
+        self.path
+            .as_deref()
+            .map(|pattern| {
+                Glob::new(pattern)
+                    .map(|glob| glob.compile_matcher())
+                    .with_context(|| format!("Invalid path pattern '{}'", pattern))
+            })
+            .transpose()
+    }
+
+    fn severity_matches(&self, severity: &str) -> bool {
+        self.min_severity
+            .as_deref()
+            .is_none_or(|min| severity::is_at_least(severity, min))
+    }
+
+    fn count(&self, report: &RiskReport, root: &Path) -> Result<usize> {
+        let matcher = self.path_matcher()?;
+        let path_matches = |file: &Path| {
+            matcher.as_ref().is_none_or(|matcher| {
+                let relative = file.strip_prefix(root).unwrap_or(file);
+                matcher.is_match(relative)
+            })
+        };
+        Ok(match self.metric {
+            Metric::Findings => report
+                .findings
+                .iter()
+                .filter(|finding| finding.paths().any(|path| path_matches(path)))
+                .flat_map(|finding| &finding.flagged_patterns)
+                .filter(|pattern| self.severity_matches(&pattern.severity))
+                .filter(|pattern| self.cwe.is_none() || pattern.cwe == self.cwe)
+                .count(),
+            Metric::Dependencies => report
+                .dependency_findings
+                .iter()
+                .filter(|dep| {
+                    self.min_risk
+                        .as_ref()
+                        .is_none_or(|min| dep.risk_score.rank() >= min.rank())
+                })
+                .count(),
+            Metric::UnsafeBlocks => report
+                .unsafe_surface
+                .files
+                .iter()
+                .filter(|file| path_matches(&file.file_path))
+                .map(|file| file.metrics.blocks)
+                .sum(),
+            Metric::Vulnerabilities => report
+                .dependency_findings
+                .iter()
+                .flat_map(|dep| &dep.vulnerabilities)
+                .filter(|vulnerability| self.severity_matches(&vulnerability.severity))
+                .count(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::FlaggedPattern;
+    use crate::unsafe_metrics::UnsafeMetrics;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_rules_count_report_and_baseline() {
+        let policy: Policy = r#"
+            [[rule]]
+            name = "few-high"
+            metric = "findings"
+            min_severity = "High"
+            max = 1
+
+            [[rule]]
+            name = "no-new-unsafe-in-net"
+            metric = "unsafe_blocks"
+            path = "src/net/**"
+            new = true
+            max = 0
+        "#
+        .parse()
+        .unwrap();
+
+        let report = |unsafe_blocks: usize| {
+            let mut report = RiskReport::new("demo".to_string());
+            let pattern = |severity: &str| FlaggedPattern {
+                line: 1,
+                severity: severity.to_string(),
+                description: "Runs a shell command".to_string(),
+                code_snippet: String::new(),
+                confidence: 1.0,
+                cwe: None,
+                owasp: None,
+                id: None,
+                triage: None,
+                blame: None,
+                rule: None,
+            };
+            report.add_file_finding(
+                PathBuf::from("crate/src/main.rs"),
+                String::new(),
+                vec![pattern("Critical"), pattern("High"), pattern("Low")],
+            );
+            let metrics = UnsafeMetrics {
+                blocks: unsafe_blocks,
+                ..UnsafeMetrics::default()
+            };
+            report.add_unsafe_metrics(PathBuf::from("crate/src/net/socket.rs"), metrics);
+            report.add_unsafe_metrics(PathBuf::from("crate/src/ffi.rs"), metrics);
+            report
+        };
+
+        let outcomes = policy
+            .evaluate(&report(3), Some(&report(2)), Path::new("crate"))
+            .unwrap();
+        assert_eq!(outcomes[0].value, 2);
+        assert!(!outcomes[0].passed());
+        assert_eq!(outcomes[1].value, 1);
+        assert_eq!(
+            outcomes[1].to_string(),
+            "no-new-unsafe-in-net: 1 new (max 0)"
+        );
+        assert!(policy
+            .evaluate(&report(3), None, Path::new("crate"))
+            .is_err());
+
+        let invalid =
+            "[[rule]]\nname = \"x\"\nmetric = \"dependencies\"\npath = \"src/**\"\nmax = 0\n";
+        assert!(invalid.parse::<Policy>().is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::Serialize;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+static JSON_EVENTS: AtomicBool = AtomicBool::new(false);
+static BARS: OnceLock<MultiProgress> = OnceLock::new();
+
+/// Turns on `--progress-format json`: every event is written to stderr as one
+/// JSON object per line, in place of the progress bars.
+pub fn enable_json_events() {
+    JSON_EVENTS.store(true, Ordering::Relaxed);
+    hide_bars();
+}
+
+/// Container for the scan's progress bars. They draw only when stderr is a
+/// terminal; log lines go through `LogWriter` so they print above the bars.
+fn bars() -> &'static MultiProgress {
+    BARS.get_or_init(|| {
+        let target = if io::stderr().is_terminal() {
+            ProgressDrawTarget::stderr()
+        } else {
+            ProgressDrawTarget::hidden()
+        };
+        MultiProgress::with_draw_target(target)
+    })
+}
+
+pub fn hide_bars() {
+    bars().set_draw_target(ProgressDrawTarget::hidden());
+}
+
+/// Top-level bar over the files of a scan.
+pub fn file_bar(total: usize) -> ProgressBar {
+    add_bar(total, "Files       ")
+}
+
+/// Bar for dependency analysis, shown below the file bar.
+pub fn dependency_bar(total: usize) -> ProgressBar {
+    add_bar(total, "Dependencies")
+}
+
+fn add_bar(total: usize, label: &str) -> ProgressBar {
+    let bar = bars().add(ProgressBar::new(total as u64));
+    bar.set_style(
+        ProgressStyle::with_template(&format!("{} [{{bar:30}}] {{pos}}/{{len}} {{msg}}", label))
+            .expect("valid progress template")
+            .progress_chars("=> "),
+    );
+    bar
+}
+
+/// Sets the bar's message to the tracker's estimate, e.g. `ETA 2m 05s`.
+pub fn show_eta(bar: &ProgressBar, eta: &EtaTracker) {
+    if let Some(remaining) = eta.eta() {
+        let secs = remaining.as_secs();
+        bar.set_message(format!("ETA {}m {:02}s", secs / 60, secs % 60));
+    }
+}
+
+/// Stderr writer for log output that hides the progress bars while a line is
+/// written, so logs and bars do not overwrite each other.
+pub struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        bars().suspend(|| io::stderr().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// Machine-readable scan progress for IDE extensions and CI wrappers.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    ScanStarted {
+        crate_name: &'a str,
+        files: usize,
+    },
+    FileStarted {
+        path: &'a str,
+        index: usize,
+        total: usize,
+    },
+    FileCompleted {
+        path: &'a str,
+        index: usize,
+        total: usize,
+        patterns: usize,
+        eta_secs: Option<u64>,
+    },
+    CacheHit {
+        package: &'a str,
+        version: &'a str,
+    },
+    DependencyAnalyzed {
+        package: &'a str,
+        version: &'a str,
+        risk: String,
+        index: usize,
+        total: usize,
+    },
+    ScanCompleted {
+        files: usize,
+        dependencies: usize,
+        elapsed_secs: u64,
+    },
+}
+
+pub fn emit(event: ProgressEvent) {
+    if !JSON_EVENTS.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Ok(line) = serde_json::to_string(&event) {
+        let _ = writeln!(std::io::stderr().lock(), "{}", line);
+    }
+}
+
+/// Estimates the remaining time of an LLM-backed loop. Cache hits and API
+/// calls are timed separately and weighted by the hit rate seen so far, and API
+/// calls never count as faster than the rate-limit interval.
+pub struct EtaTracker {
+    total: usize,
+    cached: (usize, Duration),
+    uncached: (usize, Duration),
+    request_interval: Duration,
+}
+
+impl EtaTracker {
+    pub fn new(total: usize) -> Self {
+        EtaTracker {
+            total,
+            cached: (0, Duration::ZERO),
+            uncached: (0, Duration::ZERO),
+            request_interval: Duration::ZERO,
+        }
+    }
+
+    pub fn with_request_interval(mut self, request_interval: Duration) -> Self {
+        self.request_interval = request_interval;
+        self
+    }
+
+    pub fn record(&mut self, cached: bool, elapsed: Duration) {
+        let (count, time) = if cached {
+            &mut self.cached
+        } else {
+            &mut self.uncached
+        };
+        *count += 1;
+        *time += elapsed;
+    }
+
+    pub fn done(&self) -> usize {
+        self.cached.0 + self.uncached.0
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        let done = self.done();
+        if done == 0 {
+            return None;
+        }
+        let average =
+            |(count, time): (usize, Duration)| time.checked_div(count as u32).unwrap_or_default();
+        let hit_rate = self.cached.0 as f64 / done as f64;
+        let per_uncached = average(self.uncached).max(self.request_interval);
+        let per_item = average(self.cached).as_secs_f64() * hit_rate
+            + per_uncached.as_secs_f64() * (1.0 - hit_rate);
+        let remaining = self.total.saturating_sub(done) as f64;
+        Some(Duration::from_secs_f64(per_item * remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta_weighs_cache_hits_and_rate_limit() {
+        let mut eta = EtaTracker::new(10).with_request_interval(Duration::from_secs(4));
+        assert_eq!(eta.eta(), None);
+
+        // One fast cache hit and one API call that finished before the interval
+        eta.record(true, Duration::ZERO);
+        eta.record(false, Duration::from_secs(1));
+        // 8 remaining at a 50% hit rate: 8 * (0.5 * 0s + 0.5 * 4s)
+        assert_eq!(eta.eta(), Some(Duration::from_secs(16)));
+    }
+}
@@ -0,0 +1,48 @@
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+
+/// Paces requests to a rate-limited API across concurrent tasks: each request
+/// gets the next free slot, `interval` after the previous one, and waits for it.
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(interval: Duration) -> Self {
+        RateLimiter {
+            interval,
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Waits until this caller may send its request.
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrent_requests_are_spaced() {
+        let limiter = RateLimiter::new(Duration::from_millis(30));
+        let started = Instant::now();
+        futures::future::join_all((0..3).map(|_| limiter.acquire())).await;
+        // The first request goes at once, the third two intervals later
+        assert!(started.elapsed() >= Duration::from_millis(60));
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,280 @@
+use anyhow::{anyhow, bail, Result};
+use reqwest::{Client, StatusCode};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use tokio::runtime::Runtime;
+use tracing::warn;
+
+use crate::cache::{CacheEntry, CacheKey, CacheStats, CacheStore, ImportSummary, StoredAnalysis};
+use crate::config::CacheConfig;
+use crate::database::Database;
+use crate::llm_client::LlmResponse;
+use crate::network;
+
+/// Client for a shared analysis cache service.
+///
+/// Protocol: `GET {url}/v1/analyses?package=&version=&content_hash=&model=&prompt_fingerprint=`
+/// returns a `CacheEntry` as JSON or 404; `PUT {url}/v1/analyses` stores one.
+/// Requests carry `Authorization: Bearer <token>` when a token is configured.
+pub struct RemoteCache {
+    client: Client,
+    base_url: String,
+    token: Option<String>,
+    /// Requests for the thread that sends them
+    jobs: mpsc::Sender<Job>,
+}
+
+type Job = Box<dyn FnOnce(&Runtime) + Send>;
+
+impl RemoteCache {
+    pub fn new(config: &CacheConfig) -> Option<Self> {
+        let base_url = config
+            .remote_url
+            .as_ref()?
+            .trim_end_matches('/')
+            .to_string();
+        let client = network::client_builder()
+            .timeout(config.remote_timeout())
+            .build()
+            .ok()?;
+        // `CacheStore` is synchronous (the SQLite backend is), and callers run
+        // on any kind of runtime, so requests go to a thread with a runtime
+        // of its own while the caller waits
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .ok()?;
+        let (jobs, queue) = mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name("remote-cache".to_string())
+            .spawn(move || {
+                for job in queue {
+                    job(&runtime);
+                }
+            })
+            .ok()?;
+        Some(RemoteCache {
+            client,
+            base_url,
+            token: config.resolve_remote_token(),
+            jobs,
+        })
+    }
+
+    /// Runs `future` on the request thread and waits for its output.
+    fn block_on<F>(&self, future: F) -> Result<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (reply, output) = mpsc::sync_channel(1);
+        self.jobs
+            .send(Box::new(move |runtime: &Runtime| {
+                let _ = reply.send(runtime.block_on(future));
+            }))
+            .map_err(|_| anyhow!("The remote cache request thread has stopped"))?;
+        output
+            .recv()
+            .map_err(|_| anyhow!("The remote cache request thread has stopped"))
+    }
+
+    fn request(&self, method: reqwest::Method) -> reqwest::RequestBuilder {
+        let request = self
+            .client
+            .request(method, format!("{}/v1/analyses", self.base_url));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    fn get(&self, key: &CacheKey) -> Result<Option<LlmResponse>> {
+        let request = self.request(reqwest::Method::GET).query(&[
+            ("package", &key.package),
+            ("version", &key.version),
+            ("content_hash", &key.content_hash),
+            ("model", &key.model),
+            ("prompt_fingerprint", &key.prompt_fingerprint),
+        ]);
+        self.block_on(async move {
+            let response = request.send().await?;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            let entry: CacheEntry = response.error_for_status()?.json().await?;
+            Ok(Some(entry.analysis))
+        })?
+    }
+
+    fn put(&self, key: &CacheKey, response: &LlmResponse) -> Result<()> {
+        let entry = CacheEntry {
+            key: key.clone(),
+            scan_date: chrono::Utc::now(),
+            analysis: response.clone(),
+        };
+        let request = self.request(reqwest::Method::PUT).json(&entry);
+        self.block_on(async move {
+            request.send().await?.error_for_status()?;
+            Ok(())
+        })?
+    }
+}
+
+/// Local database backed by a shared remote cache: lookups read through to the
+/// remote on a local miss and store what they find, new analyses are written to
+/// both. After the first remote error the remote is skipped for the rest of the
+/// run. Maintenance operations (stats, listing, deletion, export, import) are
+/// local only.
+pub struct TieredCacheStore {
+    local: Option<Database>,
+    remote: RemoteCache,
+    remote_failed: AtomicBool,
+}
+
+impl TieredCacheStore {
+    pub fn new(local: Option<Database>, remote: RemoteCache) -> Self {
+        TieredCacheStore {
+            local,
+            remote,
+            remote_failed: AtomicBool::new(false),
+        }
+    }
+
+    fn local(&self) -> Result<&Database> {
+        match &self.local {
+            Some(local) => Ok(local),
+            None => bail!("The local cache database is unavailable"),
+        }
+    }
+
+    fn remote<R>(&self, operation: impl FnOnce(&RemoteCache) -> Result<R>) -> Option<R> {
+        if self.remote_failed.load(Ordering::Relaxed) {
+            return None;
+        }
+        match operation(&self.remote) {
+            Ok(result) => Some(result),
+            Err(e) => {
+                self.remote_failed.store(true, Ordering::Relaxed);
+                warn!(
+                    "⚠️  Remote cache unavailable, using the local cache only: {}",
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+impl CacheStore for TieredCacheStore {
+    fn get_analysis(&self, key: &CacheKey) -> Result<Option<LlmResponse>> {
+        if let Some(local) = &self.local {
+            if let Some(response) = local.get_analysis(key)? {
+                return Ok(Some(response));
+            }
+        }
+        let Some(Some(response)) = self.remote(|remote| remote.get(key)) else {
+            return Ok(None);
+        };
+        if let Some(local) = &self.local {
+            local.put_analysis(key, &response)?;
+        }
+        Ok(Some(response))
+    }
+
+    fn put_analysis(&self, key: &CacheKey, response: &LlmResponse) -> Result<()> {
+        self.remote(|remote| remote.put(key, response));
+        match &self.local {
+            Some(local) => local.put_analysis(key, response),
+            None => Ok(()),
+        }
+    }
+
+    fn cache_stats(&self) -> Result<CacheStats> {
+        self.local()?.cache_stats()
+    }
+
+    fn clear_analyses(&self) -> Result<usize> {
+        self.local()?.clear_analyses()
+    }
+
+    fn invalidate_model(&self, model: &str) -> Result<usize> {
+        self.local()?.invalidate_model(model)
+    }
+
+    fn export_analyses(&self) -> Result<Vec<CacheEntry>> {
+        self.local()?.export_analyses()
+    }
+
+    fn list_analyses(&self, package: Option<&str>) -> Result<Vec<StoredAnalysis>> {
+        self.local()?.list_analyses(package)
+    }
+
+    fn show_analysis(&self, id: i64) -> Result<Option<StoredAnalysis>> {
+        self.local()?.show_analysis(id)
+    }
+
+    fn delete_package(&self, package: &str) -> Result<usize> {
+        self.local()?.delete_package(package)
+    }
+
+    fn import_analyses(&self, entries: &[CacheEntry]) -> Result<ImportSummary> {
+        self.local()?.import_analyses(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_tiered_cache_on_current_thread_runtime() {
+        // Answers lookups with 404 and accepts stores
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut request = [0u8; 8192];
+                let read = stream.read(&mut request).unwrap_or(0);
+                let status = if request[..read].starts_with(b"GET") {
+                    "404 Not Found"
+                } else {
+                    "200 OK"
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+            }
+        });
+
+        let config = CacheConfig {
+            remote_url: Some(format!("http://{}", address)),
+            ..CacheConfig::default()
+        };
+        let store = TieredCacheStore::new(None, RemoteCache::new(&config).unwrap());
+        let key = CacheKey::new("demo", "1.0.0", "fn main() {}");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            assert!(store.get_analysis(&key).unwrap().is_none());
+            store
+                .put_analysis(
+                    &key,
+                    &LlmResponse {
+                        analysis: "No issues".to_string(),
+                        flagged_patterns: Vec::new(),
+                        version: None,
+                    },
+                )
+                .unwrap();
+        });
+        assert!(!store.remote_failed.load(Ordering::Relaxed));
+    }
+}
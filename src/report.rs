@@ -1,20 +1,90 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::File;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 
+use anyhow::Context;
+use handlebars::Handlebars;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::info;
 
+use crate::context_budget::Truncation;
 use crate::dependency_scanner::{DependencyAnalysisResult, RiskScore};
-use crate::llm_client::FlaggedPattern;
+use crate::indicators::{self, IndicatorFlag, IndicatorKind, NetworkIndicator};
+use crate::llm_client::{AnalysisVersion, FlaggedPattern, PROMPT_VERSION};
+use crate::native_code::NativeFootprint;
+use crate::rule_packs;
+use crate::severity;
+use crate::taxonomy;
+use crate::triage::TriageState;
+use crate::unsafe_metrics::UnsafeMetrics;
+
+/// Network indicators listed in markdown and HTML reports; JSON has them all.
+const MAX_INDICATOR_ROWS: usize = 100;
+
+/// Locations shown per network indicator; the rest are counted.
+const MAX_INDICATOR_LOCATIONS: usize = 3;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RiskReport {
     pub crate_name: String,
+    /// RFC 3339 start of the scan; empty in `--reproducible` reports
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub timestamp: String,
     pub findings: Vec<CrateFinding>,
     pub dependency_findings: Vec<DependencyAnalysisResult>,
     pub summary: ReportSummary,
+    #[serde(default)]
+    pub unsafe_surface: UnsafeSurface,
+    /// URLs, IPs and domains in the crate's own code; dependencies carry their own
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network_indicators: Vec<NetworkIndicator>,
+    /// Files left to static analysis because the LLM circuit breaker was open
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub llm_skipped_files: Vec<PathBuf>,
+    /// Files left to static analysis because their LLM analysis ran past
+    /// `scanner.file_timeout_secs`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub llm_timed_out_files: Vec<PathBuf>,
+    /// Why the scan stopped sending LLM requests early (`--max-duration` or
+    /// `--max-api-calls`); later files and dependencies got static checks only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial: Option<String>,
+    /// `name@version` of dependencies left out by `max_packages`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_dependencies: Vec<String>,
+    /// Summaries of earlier scans of the same crate, oldest first
+    #[serde(default)]
+    pub history: Vec<HistoryEntry>,
+    /// `[severity]` overrides applied to code findings as they are added
+    #[serde(skip)]
+    severity_overrides: HashMap<String, String>,
+    /// Files of the crate the scan read, for the attestation's source digest
+    #[serde(skip)]
+    pub source_files: Vec<PathBuf>,
+}
+
+/// Summary of one completed scan, kept in the local database for trends.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: String,
+    pub summary: ReportSummary,
+}
+
+/// `unsafe` usage in the scanned crate. Dependency counts live on each
+/// `DependencyAnalysisResult`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UnsafeSurface {
+    pub total: UnsafeMetrics,
+    /// Only files that contain `unsafe`
+    pub files: Vec<FileUnsafeMetrics>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileUnsafeMetrics {
+    pub file_path: PathBuf,
+    pub metrics: UnsafeMetrics,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,17 +92,51 @@ pub struct CrateFinding {
     pub file_path: PathBuf,
     pub llm_analysis: String,
     pub flagged_patterns: Vec<FlaggedPattern>,
+    /// Prompt and analyzer behind `llm_analysis`; `None` for static-only findings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub analysis_version: Option<AnalysisVersion>,
+    /// Other files with the same content; the findings apply to them too
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identical_files: Vec<PathBuf>,
+    /// Set when part of the file did not fit the LLM's context budget
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncation: Option<Truncation>,
     // Potentially add findings from initial static analysis here
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl CrateFinding {
+    /// `file_path` followed by the identical files.
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.file_path).chain(&self.identical_files)
+    }
+
+    /// Rewrites the finding's paths, e.g. relative to the scanned directory.
+    pub fn map_paths(&mut self, map: &dyn Fn(&Path) -> PathBuf) {
+        self.file_path = map(&self.file_path);
+        for path in &mut self.identical_files {
+            *path = map(path);
+        }
+    }
+}
+
+/// Flagged patterns whose line one author last changed, per `git blame`.
+#[derive(Debug, Default)]
+pub struct AuthorFindings {
+    /// `Name <email>`
+    pub owner: String,
+    pub findings: usize,
+    pub severity_counts: BTreeMap<String, usize>,
+    pub files: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReportSummary {
     pub total_files_scanned: usize,
     pub total_flagged_patterns: usize,
     pub total_dependencies_scanned: usize,
     pub high_risk_dependencies: usize,
-    pub severity_counts: HashMap<String, usize>,
-    pub dependency_risk_counts: HashMap<String, usize>,
+    pub severity_counts: BTreeMap<String, usize>,
+    pub dependency_risk_counts: BTreeMap<String, usize>,
     // Overall risk score or other high-level metrics
 }
 
@@ -48,38 +152,434 @@ impl RiskReport {
                 total_flagged_patterns: 0,
                 total_dependencies_scanned: 0,
                 high_risk_dependencies: 0,
-                severity_counts: HashMap::new(),
-                dependency_risk_counts: HashMap::new(),
+                severity_counts: BTreeMap::new(),
+                dependency_risk_counts: BTreeMap::new(),
             },
+            unsafe_surface: UnsafeSurface::default(),
+            network_indicators: Vec::new(),
+            llm_skipped_files: Vec::new(),
+            llm_timed_out_files: Vec::new(),
+            partial: None,
+            skipped_dependencies: Vec::new(),
+            history: Vec::new(),
+            severity_overrides: HashMap::new(),
+            source_files: Vec::new(),
         }
     }
 
+    /// Flagged patterns grouped by the author who last changed their line,
+    /// most findings first, and the number of patterns without an owner.
+    /// `None` when the scan did not run `git blame`.
+    pub fn findings_by_author(&self) -> Option<(Vec<AuthorFindings>, usize)> {
+        let patterns = self
+            .findings
+            .iter()
+            .flat_map(|finding| finding.flagged_patterns.iter().map(move |p| (finding, p)));
+        if !patterns.clone().any(|(_, pattern)| pattern.blame.is_some()) {
+            return None;
+        }
+        let mut authors: BTreeMap<String, AuthorFindings> = BTreeMap::new();
+        let mut unowned = 0;
+        for (finding, pattern) in patterns {
+            let Some(blame) = &pattern.blame else {
+                unowned += 1;
+                continue;
+            };
+            let owner = blame.owner();
+            let entry = authors
+                .entry(owner.clone())
+                .or_insert_with(|| AuthorFindings {
+                    owner,
+                    ..AuthorFindings::default()
+                });
+            entry.findings += 1;
+            *entry
+                .severity_counts
+                .entry(pattern.severity.clone())
+                .or_insert(0) += 1;
+            entry.files.insert(report_path(&finding.file_path));
+        }
+        let mut authors: Vec<AuthorFindings> = authors.into_values().collect();
+        authors.sort_by(|a, b| b.findings.cmp(&a.findings).then(a.owner.cmp(&b.owner)));
+        Some((authors, unowned))
+    }
+
+    /// The `## Findings by Author` section of the markdown report, when the
+    /// scan ran `git blame`.
+    pub(crate) fn push_authors(&self, md: &mut String) {
+        let Some((authors, unowned)) = self.findings_by_author() else {
+            return;
+        };
+        push_anchor(md, "authors");
+        md.push_str(
+            "## Findings by Author
+",
+        );
+        md.push_str("| Author | Findings |");
+        for severity in severity::LEVELS {
+            md.push_str(&format!(" {} |", severity));
+        }
+        md.push_str(
+            " Files |
+|---|---|",
+        );
+        md.push_str(&"---|".repeat(severity::LEVELS.len()));
+        md.push_str(
+            "---|
+",
+        );
+        for author in &authors {
+            md.push_str(&format!("| {} | {} |", author.owner, author.findings));
+            for severity in severity::LEVELS {
+                md.push_str(&format!(
+                    " {} |",
+                    author.severity_counts.get(*severity).unwrap_or(&0)
+                ));
+            }
+            let files: Vec<String> = author.files.iter().map(|f| format!("`{}`", f)).collect();
+            md.push_str(&format!(
+                " {} |
+",
+                files.join(", ")
+            ));
+        }
+        if unowned > 0 {
+            md.push_str(&format!(
+                "
+*{} findings have no owner: their lines are uncommitted or outside git.*
+",
+                unowned
+            ));
+        }
+        md.push('\n');
+    }
+
+    /// Reads a report written by `scan --format json`.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read report {}", path.display()))?;
+        let mut report: RiskReport = serde_json::from_str(&content).with_context(|| {
+            format!(
+                "{} is not a JSON report (scan with --format json)",
+                path.display()
+            )
+        })?;
+        // Reports from before categories existed
+        for finding in &mut report.findings {
+            taxonomy::classify_patterns(&mut finding.flagged_patterns);
+        }
+        Ok(report)
+    }
+
+    pub fn add_network_indicators(&mut self, indicators: Vec<NetworkIndicator>) {
+        let existing = std::mem::take(&mut self.network_indicators);
+        self.network_indicators = indicators::merge(existing.into_iter().chain(indicators));
+    }
+
+    /// Indicators of the crate and its dependencies, with dependency
+    /// locations prefixed by `name@version`.
+    fn all_network_indicators(&self) -> Vec<NetworkIndicator> {
+        let dependencies = self.dependency_findings.iter().flat_map(|dep| {
+            dep.network_indicators.iter().map(move |indicator| {
+                let mut indicator = indicator.clone();
+                for location in &mut indicator.locations {
+                    *location = format!("{}@{} {}", dep.package_name, dep.version, location);
+                }
+                indicator
+            })
+        });
+        indicators::merge(self.network_indicators.iter().cloned().chain(dependencies))
+    }
+
+    pub(crate) fn push_network_indicators(&self, md: &mut String) {
+        let all = self.all_network_indicators();
+        if all.is_empty() {
+            return;
+        }
+        push_anchor(md, "network-indicators");
+        md.push_str("## Network Indicators\n");
+        md.push_str(&format!(
+            "{} URLs, IP addresses, domains and wallet addresses in string literals ({} flagged).\n\n",
+            all.len(),
+            all.iter().filter(|i| !i.flags.is_empty()).count()
+        ));
+        md.push_str("| Indicator | Type | Flags | Found in |\n|---|---|---|---|\n");
+        for indicator in all.iter().take(MAX_INDICATOR_ROWS) {
+            md.push_str(&format!(
+                "| `{}` | {} | {} | {} |\n",
+                indicator.value.replace('|', "\\|"),
+                indicator.kind.label(),
+                indicator_flags(indicator),
+                indicator_locations(indicator)
+            ));
+        }
+        if all.len() > MAX_INDICATOR_ROWS {
+            md.push_str(&format!(
+                "\n*...and {} more in the JSON report.*\n",
+                all.len() - MAX_INDICATOR_ROWS
+            ));
+        }
+    }
+
+    /// Indicators of compromise: wallet addresses, onion services, C2-prone
+    /// services and threat-intel matches, from the crate and its dependencies.
+    fn iocs(&self) -> Vec<NetworkIndicator> {
+        self.all_network_indicators()
+            .into_iter()
+            .filter(is_ioc)
+            .collect()
+    }
+
+    /// The `## IOC Appendix` section of the markdown report, listing every
+    /// indicator of compromise for blocklists and threat-intel tooling.
+    pub(crate) fn push_ioc_appendix(&self, md: &mut String) {
+        let iocs = self.iocs();
+        if iocs.is_empty() {
+            return;
+        }
+        push_anchor(md, "ioc-appendix");
+        md.push_str("## IOC Appendix\n");
+        md.push_str(&format!(
+            "{} indicators of compromise ({} listed by a threat-intel feed).\n\n",
+            iocs.len(),
+            iocs.iter().filter(|i| i.threat_feed.is_some()).count()
+        ));
+        md.push_str("| Indicator | Type | Threat intel | Found in |\n|---|---|---|---|\n");
+        for indicator in &iocs {
+            md.push_str(&format!(
+                "| `{}` | {} | {} | {} |\n",
+                indicator.value.replace('|', "\\|"),
+                ioc_type(indicator),
+                indicator.threat_feed.as_deref().unwrap_or("-"),
+                indicator_locations(indicator)
+            ));
+        }
+    }
+
+    pub fn add_unsafe_metrics(&mut self, file_path: PathBuf, metrics: UnsafeMetrics) {
+        self.unsafe_surface.total += metrics;
+        if metrics.total() > 0 {
+            self.unsafe_surface
+                .files
+                .push(FileUnsafeMetrics { file_path, metrics });
+        }
+    }
+
+    /// Sum over dependencies whose source was scanned, and how many of them use `unsafe`.
+    fn dependency_unsafe_totals(&self) -> (UnsafeMetrics, usize) {
+        let mut total = UnsafeMetrics::default();
+        let mut packages = 0;
+        for metrics in self
+            .dependency_findings
+            .iter()
+            .filter_map(|d| d.unsafe_metrics)
+        {
+            total += metrics;
+            if metrics.total() > 0 {
+                packages += 1;
+            }
+        }
+        (total, packages)
+    }
+
+    pub fn set_history(&mut self, history: Vec<HistoryEntry>) {
+        self.history = history;
+    }
+
+    /// Rewrites every file path in the report, e.g. relative to the scanned
+    /// directory so reports do not depend on where the crate was checked out
+    /// or reveal the user's home directory.
+    pub fn map_paths(&mut self, map: &dyn Fn(&Path) -> PathBuf) {
+        for finding in &mut self.findings {
+            finding.map_paths(map);
+        }
+        for file in &mut self.unsafe_surface.files {
+            file.file_path = map(&file.file_path);
+        }
+        for path in self
+            .llm_skipped_files
+            .iter_mut()
+            .chain(&mut self.llm_timed_out_files)
+        {
+            *path = map(path);
+        }
+    }
+
+    /// Orders code findings by path, so a resumed scan lists them as a fresh
+    /// one does.
+    pub fn sort_findings(&mut self) {
+        self.findings.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    }
+
+    /// Summary of this scan for the history table.
+    pub fn history_entry(&self) -> HistoryEntry {
+        HistoryEntry {
+            // The history keeps the real time even when the report does not
+            timestamp: if self.timestamp.is_empty() {
+                chrono::Utc::now().to_rfc3339()
+            } else {
+                self.timestamp.clone()
+            },
+            summary: self.summary.clone(),
+        }
+    }
+
+    /// Sparklines of flagged patterns and high-risk dependencies across earlier
+    /// scans and this one; `None` without history.
+    fn trend(&self) -> Option<(String, String)> {
+        if self.history.is_empty() {
+            return None;
+        }
+        let summaries: Vec<&ReportSummary> = self
+            .history
+            .iter()
+            .map(|entry| &entry.summary)
+            .chain(std::iter::once(&self.summary))
+            .collect();
+        let patterns: Vec<usize> = summaries.iter().map(|s| s.total_flagged_patterns).collect();
+        let high_risk: Vec<usize> = summaries.iter().map(|s| s.high_risk_dependencies).collect();
+        Some((sparkline(&patterns), sparkline(&high_risk)))
+    }
+
+    fn analysis_versions(&self) -> impl Iterator<Item = &AnalysisVersion> {
+        self.findings
+            .iter()
+            .filter_map(|f| f.analysis_version.as_ref())
+            .chain(
+                self.dependency_findings
+                    .iter()
+                    .filter_map(|d| d.analysis_version.as_ref()),
+            )
+    }
+
+    /// Summary line naming the prompts and analyzers behind the LLM analyses,
+    /// plus a warning when some came from an outdated prompt.
+    fn analysis_version_lines(&self) -> Vec<String> {
+        let versions: BTreeSet<String> = self.analysis_versions().map(|v| v.to_string()).collect();
+        if versions.is_empty() {
+            return Vec::new();
+        }
+        let mut lines = vec![format!(
+            "Analyzed with: {}",
+            versions.into_iter().collect::<Vec<_>>().join("; ")
+        )];
+        let outdated = self.analysis_versions().filter(|v| v.is_outdated()).count();
+        if outdated > 0 {
+            lines.push(format!(
+                "⚠️ {} analyses come from an older prompt than the current v{}; re-scan with `--no-cache` to refresh them",
+                outdated, PROMPT_VERSION
+            ));
+        }
+        lines
+    }
+
+    /// Number of code findings per CWE/OWASP category, unclassified ones left out.
+    fn category_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for pattern in self.findings.iter().flat_map(|f| &f.flagged_patterns) {
+            if let Some(label) = taxonomy::label(pattern) {
+                *counts.entry(label).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Shorthand for tests: a finding with no analysis version or identical files.
+    #[cfg(test)]
     pub fn add_file_finding(
         &mut self,
         file_path: PathBuf,
         llm_analysis: String,
         flagged_patterns: Vec<FlaggedPattern>,
     ) {
-        self.findings.push(CrateFinding {
+        self.add_crate_finding(CrateFinding {
             file_path,
             llm_analysis,
-            flagged_patterns: flagged_patterns.clone(),
+            flagged_patterns,
+            analysis_version: None,
+            identical_files: Vec::new(),
+            truncation: None,
         });
-        self.summary.total_files_scanned += 1;
-        self.summary.total_flagged_patterns += flagged_patterns.len();
-        for pattern in flagged_patterns {
+    }
+
+    pub fn set_severity_overrides(&mut self, overrides: HashMap<String, String>) {
+        self.severity_overrides = overrides;
+    }
+
+    pub fn add_crate_finding(&mut self, mut finding: CrateFinding) {
+        severity::apply_to_patterns(&self.severity_overrides, &mut finding.flagged_patterns);
+        self.summary.total_files_scanned += 1 + finding.identical_files.len();
+        self.summary.total_flagged_patterns += finding.flagged_patterns.len();
+        for pattern in &finding.flagged_patterns {
             *self
                 .summary
                 .severity_counts
-                .entry(pattern.severity)
+                .entry(pattern.severity.clone())
                 .or_insert(0) += 1;
         }
+        self.findings.push(finding);
+    }
+
+    /// Code findings and dependencies at `min` severity or above, for
+    /// `scan --fail-on`. Findings triaged as false positives do not count.
+    pub fn count_at_least(&self, min: &str) -> usize {
+        let findings = self
+            .findings
+            .iter()
+            .flat_map(|finding| &finding.flagged_patterns)
+            .filter(|pattern| {
+                pattern
+                    .triage
+                    .as_ref()
+                    .is_none_or(|triage| triage.state != TriageState::FalsePositive)
+            })
+            .filter(|pattern| severity::is_at_least(&pattern.severity, min))
+            .count();
+        let dependencies = self
+            .dependency_findings
+            .iter()
+            .filter(|dep| severity::is_at_least(&format!("{:?}", dep.risk_score), min))
+            .count();
+        findings + dependencies
+    }
+
+    /// Drops crate findings whose confidence is below `min_confidence` and
+    /// updates the summary. Returns how many were dropped.
+    pub fn apply_min_confidence(&mut self, min_confidence: f32) -> usize {
+        let mut dropped = 0;
+        for finding in &mut self.findings {
+            finding.flagged_patterns.retain(|pattern| {
+                let keep = pattern.confidence >= min_confidence;
+                if !keep {
+                    dropped += 1;
+                    if let Some(count) = self.summary.severity_counts.get_mut(&pattern.severity) {
+                        *count -= 1;
+                    }
+                }
+                keep
+            });
+        }
+        self.summary.severity_counts.retain(|_, count| *count > 0);
+        self.summary.total_flagged_patterns -= dropped;
+        dropped
     }
 
-    pub fn add_dependency_findings(&mut self, dependency_findings: Vec<DependencyAnalysisResult>) {
+    pub fn add_dependency_findings(
+        &mut self,
+        mut dependency_findings: Vec<DependencyAnalysisResult>,
+    ) {
         self.summary.total_dependencies_scanned = dependency_findings.len();
+        // Highest risk first, then by name, whatever order the analyses finished in
+        dependency_findings.sort_by(|a, b| {
+            b.risk_score
+                .rank()
+                .cmp(&a.risk_score.rank())
+                .then_with(|| a.package_name.cmp(&b.package_name))
+                .then_with(|| a.version.cmp(&b.version))
+        });
 
-        for finding in &dependency_findings {
+        for finding in &mut dependency_findings {
+            taxonomy::classify_patterns(&mut finding.suspicious_patterns);
             // Count risk levels
             let risk_key = match finding.risk_score {
                 RiskScore::Critical => "Critical",
@@ -104,22 +604,51 @@ impl RiskReport {
     }
 
     pub fn generate_report(&self, format: &str, output_path: Option<&Path>) -> anyhow::Result<()> {
-        let report_content = match format {
+        write_output(&self.render(format)?, output_path)
+    }
+
+    /// The report in one of the built-in formats.
+    pub fn render(&self, format: &str) -> anyhow::Result<String> {
+        Ok(match format {
             "json" => self.to_json()?,
             "markdown" => self.to_markdown()?,
             "condensed" => self.to_markdown_condensed()?,
             "summary" => self.to_summary()?,
+            "summary-json" => self.to_summary_json()?,
+            "gitlab-codequality" => self.to_gitlab_codequality()?,
+            "junit" => self.to_junit(),
+            "sarif" => self.to_sarif()?,
+            "html" => self.to_html(),
             _ => anyhow::bail!("Unsupported report format: {}", format),
-        };
+        })
+    }
 
-        if let Some(path) = output_path {
-            let mut file = File::create(path)?;
-            file.write_all(report_content.as_bytes())?;
-            println!("Report successfully written to {}", path.display());
-        } else {
-            io::stdout().write_all(report_content.as_bytes())?;
+    /// Renders the report with a user-supplied Handlebars template. The
+    /// template sees the same fields as the JSON report, plus `generated_by`.
+    /// Output is HTML-escaped only for templates named like `*.html.hbs`.
+    pub fn generate_templated_report(
+        &self,
+        template_path: &Path,
+        output_path: Option<&Path>,
+    ) -> anyhow::Result<()> {
+        let template = std::fs::read_to_string(template_path)
+            .with_context(|| format!("Failed to read template {}", template_path.display()))?;
+        let mut handlebars = Handlebars::new();
+        handlebars.set_strict_mode(true);
+        let is_html = template_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.contains(".htm"));
+        if !is_html {
+            handlebars.register_escape_fn(handlebars::no_escape);
         }
-        Ok(())
+
+        let mut context = serde_json::to_value(self)?;
+        context["generated_by"] = format!("rustrecon {}", env!("CARGO_PKG_VERSION")).into();
+        let report_content = handlebars
+            .render_template(&template, &context)
+            .with_context(|| format!("Failed to render template {}", template_path.display()))?;
+        write_output(&report_content, output_path)
     }
 
     fn to_json(&self) -> anyhow::Result<String> {
@@ -129,35 +658,168 @@ impl RiskReport {
     fn to_markdown(&self) -> anyhow::Result<String> {
         let mut md = String::new();
         md.push_str(&format!("# RustRecon Scan Report: {}\n", self.crate_name));
-        md.push_str(&format!("*Timestamp: {}*\n\n", self.timestamp));
+        push_timestamp(&mut md, &self.timestamp);
 
-        md.push_str("## Summary\n");
-        md.push_str(&format!(
-            "- Total files scanned: {}\n",
-            self.summary.total_files_scanned
-        ));
-        md.push_str(&format!(
-            "- Total flagged patterns: {}\n",
-            self.summary.total_flagged_patterns
-        ));
-        md.push_str(&format!(
-            "- Total dependencies scanned: {}\n",
-            self.summary.total_dependencies_scanned
-        ));
-        md.push_str(&format!(
-            "- High-risk dependencies: {}\n",
-            self.summary.high_risk_dependencies
-        ));
-        md.push_str("### Severity Counts:\n");
-        for (severity, count) in &self.summary.severity_counts {
-            md.push_str(&format!("  - {}: {}\n", severity, count));
+        let trend = self.trend();
+        let (flagged, clean): (Vec<&CrateFinding>, Vec<&CrateFinding>) = self
+            .findings
+            .iter()
+            .partition(|finding| !finding.flagged_patterns.is_empty());
+        self.push_contents(&mut md, trend.is_some(), &flagged, clean.len());
+
+        self.push_summary(&mut md, true);
+
+        if let Some((patterns, high_risk)) = trend {
+            let previous = self.history.last().map(|entry| &entry.summary);
+            push_anchor(&mut md, "trend");
+            md.push_str(&format!(
+                "## Trend (last {} scans)\n",
+                self.history.len() + 1
+            ));
+            md.push_str(&format!(
+                "- Flagged patterns: {} ({})\n",
+                patterns,
+                change(
+                    previous.map(|p| p.total_flagged_patterns),
+                    self.summary.total_flagged_patterns
+                )
+            ));
+            md.push_str(&format!(
+                "- High-risk dependencies: {} ({})\n\n",
+                high_risk,
+                change(
+                    previous.map(|p| p.high_risk_dependencies),
+                    self.summary.high_risk_dependencies
+                )
+            ));
         }
-        md.push_str("### Dependency Risk Counts:\n");
-        for (risk, count) in &self.summary.dependency_risk_counts {
-            md.push_str(&format!("  - {}: {}\n", risk, count));
+
+        self.push_supply_chain(&mut md);
+        self.push_unsafe_surface(&mut md);
+        self.push_network_indicators(&mut md);
+        self.push_authors(&mut md);
+
+        push_anchor(&mut md, "code-findings");
+        md.push_str("## Detailed Code Findings\n");
+        if self.findings.is_empty() {
+            md.push_str("No suspicious patterns or findings detected.\n");
+        } else {
+            self.push_severity_index(&mut md);
+            for finding in flagged {
+                push_finding(&mut md, finding);
+            }
+            push_clean_files(&mut md, &clean);
+        }
+        self.push_ioc_appendix(&mut md);
+
+        Ok(md)
+    }
+
+    /// The `## Contents` list at the top of the markdown report, with a link
+    /// to every section and to each file with flagged patterns.
+    fn push_contents(
+        &self,
+        md: &mut String,
+        has_trend: bool,
+        flagged: &[&CrateFinding],
+        clean_files: usize,
+    ) {
+        md.push_str("## Contents\n- [Summary](#summary)\n");
+        if has_trend {
+            md.push_str("- [Trend](#trend)\n");
+        }
+        md.push_str("- [Supply Chain Analysis](#supply-chain)\n");
+        md.push_str("- [Unsafe Surface](#unsafe-surface)\n");
+        if !self.all_network_indicators().is_empty() {
+            md.push_str("- [Network Indicators](#network-indicators)\n");
+        }
+        if self.findings_by_author().is_some() {
+            md.push_str("- [Findings by Author](#authors)\n");
+        }
+        md.push_str("- [Detailed Code Findings](#code-findings)\n");
+        if !flagged.is_empty() {
+            md.push_str("  - [Findings by Severity](#findings-by-severity)\n");
+        }
+        for finding in flagged {
+            md.push_str(&format!(
+                "  - [`{}`](#{}) ({})\n",
+                finding.file_path.display(),
+                file_anchor(&finding.file_path),
+                finding.flagged_patterns.len()
+            ));
+        }
+        if clean_files > 0 {
+            md.push_str(&format!(
+                "  - [Clean Files](#clean-files) ({})\n",
+                clean_files
+            ));
+        }
+        if !self.iocs().is_empty() {
+            md.push_str("- [IOC Appendix](#ioc-appendix)\n");
+        }
+    }
+
+    /// Files with flagged patterns of each severity, most severe first, and
+    /// how many patterns of that severity each file has.
+    fn files_by_severity(&self) -> Vec<(&str, Vec<(&CrateFinding, usize)>)> {
+        let mut by_severity: BTreeMap<&str, Vec<(&CrateFinding, usize)>> = BTreeMap::new();
+        for finding in &self.findings {
+            let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for pattern in &finding.flagged_patterns {
+                *counts.entry(pattern.severity.as_str()).or_default() += 1;
+            }
+            for (severity, count) in counts {
+                by_severity
+                    .entry(severity)
+                    .or_default()
+                    .push((finding, count));
+            }
+        }
+        let mut levels: Vec<_> = by_severity.into_iter().collect();
+        levels.sort_by_key(|(level, _)| {
+            severity::LEVELS
+                .iter()
+                .position(|l| l == level)
+                .unwrap_or(severity::LEVELS.len())
+        });
+        levels
+    }
+
+    /// The `### Findings by Severity` index at the top of the detailed
+    /// findings, which the summary's severity counts link to.
+    fn push_severity_index(&self, md: &mut String) {
+        let levels = self.files_by_severity();
+        if levels.is_empty() {
+            return;
+        }
+        push_anchor(md, "findings-by-severity");
+        md.push_str("### Findings by Severity\n");
+        for (severity, files) in levels {
+            let links: Vec<String> = files
+                .iter()
+                .map(|(finding, count)| {
+                    format!(
+                        "[`{}`](#{}) ({})",
+                        finding.file_path.display(),
+                        file_anchor(&finding.file_path),
+                        count
+                    )
+                })
+                .collect();
+            md.push_str(&format!(
+                "- <a id=\"{}\"></a>**{}** ({}): {}\n",
+                severity_anchor(severity),
+                severity,
+                files.iter().map(|(_, count)| count).sum::<usize>(),
+                links.join(", ")
+            ));
         }
-        md.push_str("\n");
+        md.push('\n');
+    }
 
+    /// The `## Supply Chain Analysis` section of the markdown report.
+    pub(crate) fn push_supply_chain(&self, md: &mut String) {
+        push_anchor(md, "supply-chain");
         md.push_str("## Supply Chain Analysis\n");
         if self.dependency_findings.is_empty() {
             md.push_str("No dependency analysis performed.\n");
@@ -169,30 +831,42 @@ impl RiskReport {
                 .collect();
 
             if !high_risk_deps.is_empty() {
+                push_anchor(md, "high-risk-dependencies");
                 md.push_str("### ⚠️ High-Risk Dependencies\n");
                 for dep in high_risk_deps {
-                    md.push_str(&format!(
-                        "#### {} v{} - {:?}\n",
-                        dep.package_name, dep.version, dep.risk_score
-                    ));
-                    if !dep.metadata_flags.is_empty() {
-                        md.push_str("**Flags:**\n");
-                        for flag in &dep.metadata_flags {
-                            md.push_str(&format!(
-                                "- {} ({}): {}\n",
-                                flag.severity,
-                                format!("{:?}", flag.flag_type).replace("_", " "),
-                                flag.description
-                            ));
-                        }
-                    }
-                    if let Some(analysis) = &dep.code_analysis {
-                        md.push_str(&format!("**Analysis:** {}\n", analysis));
+                    push_dependency_details(md, dep);
+                }
+            }
+
+            let vulnerable: Vec<_> = self
+                .dependency_findings
+                .iter()
+                .filter(|d| !d.vulnerabilities.is_empty())
+                .collect();
+            if !vulnerable.is_empty() {
+                md.push_str("### 🛡️ Known Vulnerabilities (OSV)\n");
+                for dep in vulnerable {
+                    for vulnerability in &dep.vulnerabilities {
+                        let fix = if vulnerability.fixed_versions.is_empty() {
+                            "no fix available".to_string()
+                        } else {
+                            format!("upgrade to {}", vulnerability.fixed_versions.join(" or "))
+                        };
+                        md.push_str(&format!(
+                            "- **{}** v{} - {} [{}]: {} ({})\n",
+                            dep.package_name,
+                            dep.version,
+                            vulnerability.id,
+                            vulnerability.severity,
+                            vulnerability.summary,
+                            fix
+                        ));
                     }
-                    md.push_str("\n");
                 }
+                md.push('\n');
             }
 
+            push_anchor(md, "all-dependencies");
             md.push_str("### All Dependencies\n");
             for dep in &self.dependency_findings {
                 md.push_str(&format!(
@@ -200,43 +874,235 @@ impl RiskReport {
                     dep.package_name, dep.version, dep.risk_score
                 ));
             }
+
+            if self.dependency_findings.iter().any(|d| d.depth == 1) {
+                md.push_str("\n### Dependency Tree\n");
+                md.push_str("*Repeated subtrees are marked (\\*).*\n\n");
+                self.push_dependency_tree(md);
+            }
+        }
+        if !self.skipped_dependencies.is_empty() {
+            md.push_str(&format!(
+                "\n### ⏭️ Not Scanned ({} dependencies over max_packages)\n",
+                self.skipped_dependencies.len()
+            ));
+            for key in &self.skipped_dependencies {
+                md.push_str(&format!("- {}\n", key));
+            }
         }
+    }
 
-        md.push_str("\n## Detailed Code Findings\n");
-        if self.findings.is_empty() {
-            md.push_str("No suspicious patterns or findings detected.\n");
-        } else {
-            for finding in &self.findings {
-                md.push_str(&format!("### File: `{}`\n", finding.file_path.display()));
-                md.push_str(&format!(
-                    "#### LLM Analysis:\n```\n{}\n```\n",
-                    finding.llm_analysis
+    /// The warning partial scans carry at the top of their summary.
+    fn partial_note(&self) -> Option<String> {
+        self.partial.as_ref().map(|reason| {
+            format!(
+                "Partial scan: {}; later files and dependencies got static and metadata checks only",
+                reason
+            )
+        })
+    }
+
+    /// The `## Summary` section of the markdown report. With `links`, counts
+    /// link to the sections listing what they count; the streamed report has
+    /// no severity index for them to point at.
+    pub(crate) fn push_summary(&self, md: &mut String, links: bool) {
+        let link = |count: usize, anchor: &str| {
+            if links && count > 0 {
+                format!("[{}](#{})", count, anchor)
+            } else {
+                count.to_string()
+            }
+        };
+        push_anchor(md, "summary");
+        md.push_str("## Summary\n");
+        if let Some(note) = self.partial_note() {
+            md.push_str(&format!("- ⚠️ **{}**\n", note));
+        }
+        md.push_str(&format!(
+            "- Total files scanned: {}\n",
+            self.summary.total_files_scanned
+        ));
+        md.push_str(&format!(
+            "- Total flagged patterns: {}\n",
+            link(self.summary.total_flagged_patterns, "findings-by-severity")
+        ));
+        md.push_str(&format!(
+            "- Total dependencies scanned: {}\n",
+            link(self.summary.total_dependencies_scanned, "all-dependencies")
+        ));
+        md.push_str(&format!(
+            "- High-risk dependencies: {}\n",
+            link(
+                self.summary.high_risk_dependencies,
+                "high-risk-dependencies"
+            )
+        ));
+        for line in self.analysis_version_lines() {
+            md.push_str(&format!("- {}\n", line));
+        }
+        md.push_str("### Severity Counts:\n");
+        let flagged: BTreeSet<&str> = self
+            .findings
+            .iter()
+            .flat_map(|finding| &finding.flagged_patterns)
+            .map(|pattern| pattern.severity.as_str())
+            .collect();
+        for (severity, count) in severity::by_level(&self.summary.severity_counts) {
+            let count = if flagged.contains(severity) {
+                link(count, &severity_anchor(severity))
+            } else {
+                count.to_string()
+            };
+            md.push_str(&format!("  - {}: {}\n", severity, count));
+        }
+        let categories = self.category_counts();
+        if !categories.is_empty() {
+            md.push_str("### Categories:\n");
+            for (category, count) in &categories {
+                md.push_str(&format!("  - {}: {}\n", category, count));
+            }
+        }
+        md.push_str("### Dependency Risk Counts:\n");
+        for (risk, count) in severity::by_level(&self.summary.dependency_risk_counts) {
+            let anchor = if matches!(risk, "Critical" | "High") {
+                "high-risk-dependencies"
+            } else {
+                "all-dependencies"
+            };
+            md.push_str(&format!("  - {}: {}\n", risk, link(count, anchor)));
+        }
+        if !self.llm_skipped_files.is_empty() {
+            md.push_str(&format!(
+                "### ⛔ Skipped LLM Analysis ({} files, static rules only):\n",
+                self.llm_skipped_files.len()
+            ));
+            for path in &self.llm_skipped_files {
+                md.push_str(&format!("  - `{}`\n", path.display()));
+            }
+        }
+        if !self.llm_timed_out_files.is_empty() {
+            md.push_str(&format!(
+                "### ⏱️ LLM Analysis Timed Out ({} files, static rules only):\n",
+                self.llm_timed_out_files.len()
+            ));
+            for path in &self.llm_timed_out_files {
+                md.push_str(&format!("  - `{}`\n", path.display()));
+            }
+        }
+        md.push('\n');
+    }
+
+    /// Renders direct dependencies and their transitive dependencies as nested
+    /// lists, expanding each package only the first time it appears.
+    fn push_dependency_tree(&self, md: &mut String) {
+        let by_key: HashMap<String, &DependencyAnalysisResult> = self
+            .dependency_findings
+            .iter()
+            .map(|d| (d.key(), d))
+            .collect();
+        let mut direct: Vec<&DependencyAnalysisResult> = self
+            .dependency_findings
+            .iter()
+            .filter(|d| d.depth == 1)
+            .collect();
+        direct.sort_by(|a, b| a.package_name.cmp(&b.package_name));
+
+        let mut expanded = HashSet::new();
+        for dep in direct {
+            push_tree_node(md, dep, &by_key, &mut expanded, 0);
+        }
+    }
+
+    pub(crate) fn push_unsafe_surface(&self, md: &mut String) {
+        const TOP_DEPENDENCIES: usize = 15;
+        let (dependency_total, dependency_packages) = self.dependency_unsafe_totals();
+
+        push_anchor(md, "unsafe-surface");
+        md.push_str("## Unsafe Surface\n");
+        md.push_str(&format!(
+            "- Crate: {}\n",
+            self.unsafe_surface.total.describe()
+        ));
+        md.push_str(&format!(
+            "- Dependencies: {} in {} packages\n",
+            dependency_total.describe(),
+            dependency_packages
+        ));
+
+        let row = |name: String, m: &UnsafeMetrics| {
+            format!(
+                "| {} | {} | {} | {} | {} |\n",
+                name, m.blocks, m.functions, m.impls, m.traits
+            )
+        };
+        let header = "| Blocks | Functions | Impls | Traits |\n|---|---|---|---|---|\n";
+
+        if !self.unsafe_surface.files.is_empty() {
+            md.push_str(&format!("\n### Files\n| File {}", header));
+            for file in &self.unsafe_surface.files {
+                md.push_str(&row(
+                    format!("`{}`", file.file_path.display()),
+                    &file.metrics,
                 ));
-                if !finding.flagged_patterns.is_empty() {
-                    md.push_str("#### Flagged Patterns:\n");
-                    for pattern in &finding.flagged_patterns {
-                        md.push_str(&format!(
-                            "- **Severity**: {}\n  - **Line**: {}\n  - **Description**: {}\n  - **Code Snippet**:\n```rust\n{}\n```\n\n",
-                            pattern.severity, pattern.line, pattern.description, pattern.code_snippet
-                        ));
-                    }
-                } else {
-                    md.push_str("No specific patterns flagged by LLM in this file.\n\n");
-                }
-                md.push_str("---\n\n");
             }
         }
 
-        Ok(md)
+        let mut dependencies: Vec<(&DependencyAnalysisResult, UnsafeMetrics)> = self
+            .dependency_findings
+            .iter()
+            .filter_map(|d| d.unsafe_metrics.map(|m| (d, m)))
+            .filter(|(_, m)| m.total() > 0)
+            .collect();
+        if !dependencies.is_empty() {
+            dependencies.sort_by_key(|(_, m)| std::cmp::Reverse(m.total()));
+            md.push_str(&format!(
+                "\n### Dependencies (top {})\n| Package {}",
+                TOP_DEPENDENCIES.min(dependencies.len()),
+                header
+            ));
+            for (dep, metrics) in dependencies.iter().take(TOP_DEPENDENCIES) {
+                md.push_str(&row(
+                    format!("{} v{}", dep.package_name, dep.version),
+                    metrics,
+                ));
+            }
+        }
+
+        let mut native: Vec<(&DependencyAnalysisResult, &NativeFootprint)> = self
+            .dependency_findings
+            .iter()
+            .filter_map(|d| d.native_footprint.as_ref().map(|f| (d, f)))
+            .collect();
+        if !native.is_empty() {
+            native.sort_by_key(|(_, f)| std::cmp::Reverse(f.lines));
+            md.push_str("\n### Native Code (-sys crates)\n");
+            md.push_str(
+                "| Package | C/C++ Files | Lines | Build Tools | Links |\n|---|---|---|---|---|\n",
+            );
+            for (dep, footprint) in native {
+                md.push_str(&format!(
+                    "| {} v{} | {} | {} | {} | {} |\n",
+                    dep.package_name,
+                    dep.version,
+                    footprint.files,
+                    footprint.lines,
+                    footprint.build_tools.join(", "),
+                    footprint.links.as_deref().unwrap_or("")
+                ));
+            }
+        }
     }
 
     fn to_markdown_condensed(&self) -> anyhow::Result<String> {
         let mut md = String::new();
         md.push_str(&format!("# RustRecon Scan Report: {}\n", self.crate_name));
-        md.push_str(&format!("*Timestamp: {}*\n\n", self.timestamp));
+        push_timestamp(&mut md, &self.timestamp);
 
         // Summary section
         md.push_str("## Summary\n");
+        if let Some(note) = self.partial_note() {
+            md.push_str(&format!("- ⚠️ **{}**\n", note));
+        }
         md.push_str(&format!(
             "- **Files**: {} | **Flagged Patterns**: {} | **Dependencies**: {} | **High-Risk Deps**: {}\n",
             self.summary.total_files_scanned,
@@ -244,32 +1110,48 @@ impl RiskReport {
             self.summary.total_dependencies_scanned,
             self.summary.high_risk_dependencies
         ));
+        for line in self.analysis_version_lines() {
+            md.push_str(&format!("- {}\n", line));
+        }
 
         // Only show severity/risk counts if they exist
         if !self.summary.severity_counts.is_empty() {
             md.push_str("- **Severity**: ");
-            let severity_summary: Vec<String> = self
-                .summary
-                .severity_counts
-                .iter()
+            let severity_summary: Vec<String> = severity::by_level(&self.summary.severity_counts)
+                .into_iter()
                 .map(|(k, v)| format!("{}: {}", k, v))
                 .collect();
             md.push_str(&severity_summary.join(" | "));
-            md.push_str("\n");
+            md.push('\n');
         }
 
         if !self.summary.dependency_risk_counts.is_empty() {
             md.push_str("- **Dependency Risk**: ");
-            let risk_summary: Vec<String> = self
-                .summary
-                .dependency_risk_counts
-                .iter()
-                .map(|(k, v)| format!("{}: {}", k, v))
-                .collect();
+            let risk_summary: Vec<String> =
+                severity::by_level(&self.summary.dependency_risk_counts)
+                    .into_iter()
+                    .map(|(k, v)| format!("{}: {}", k, v))
+                    .collect();
             md.push_str(&risk_summary.join(" | "));
-            md.push_str("\n");
+            md.push('\n');
+        }
+
+        let (dependency_unsafe, dependency_unsafe_packages) = self.dependency_unsafe_totals();
+        md.push_str(&format!(
+            "- **Unsafe Surface**: crate {} | dependencies {} in {} packages\n",
+            self.unsafe_surface.total.total(),
+            dependency_unsafe.total(),
+            dependency_unsafe_packages
+        ));
+        if let Some((patterns, high_risk)) = self.trend() {
+            md.push_str(&format!(
+                "- **Trend** (last {} scans): patterns {} | high-risk deps {}\n",
+                self.history.len() + 1,
+                patterns,
+                high_risk
+            ));
         }
-        md.push_str("\n");
+        md.push('\n');
 
         // High-risk dependencies only (condensed)
         let high_risk_deps: Vec<_> = self
@@ -290,19 +1172,13 @@ impl RiskReport {
                     let flag_summary: Vec<String> = dep
                         .metadata_flags
                         .iter()
-                        .map(|f| {
-                            format!(
-                                "{} ({})",
-                                f.severity,
-                                format!("{:?}", f.flag_type).replace("_", " ")
-                            )
-                        })
+                        .map(|f| format!("{} ({})", f.severity, f.flag_type))
                         .collect();
                     md.push_str(&format!(" - Flags: {}", flag_summary.join(", ")));
                 }
-                md.push_str("\n");
+                md.push('\n');
             }
-            md.push_str("\n");
+            md.push('\n');
         }
 
         // Code findings - only show files with issues
@@ -342,12 +1218,15 @@ impl RiskReport {
                     let pattern_summary: Vec<String> = finding
                         .flagged_patterns
                         .iter()
-                        .map(|p| format!("{} (L{})", p.severity, p.line))
+                        .map(|p| match &p.cwe {
+                            Some(cwe) => format!("{} (L{}, {})", p.severity, p.line, cwe),
+                            None => format!("{} (L{})", p.severity, p.line),
+                        })
                         .collect();
                     md.push_str(&pattern_summary.join(", "));
-                    md.push_str("\n");
+                    md.push('\n');
                 }
-                md.push_str("\n");
+                md.push('\n');
             }
         } else {
             md.push_str("## Code Findings\n");
@@ -378,6 +1257,26 @@ impl RiskReport {
             self.summary.total_dependencies_scanned,
             self.summary.high_risk_dependencies
         ));
+        summary.push_str(&format!(
+            " | Unsafe: {}/{}",
+            self.unsafe_surface.total.total(),
+            self.dependency_unsafe_totals().0.total()
+        ));
+        if !self.llm_skipped_files.is_empty() {
+            summary.push_str(&format!(
+                " | ⛔ No LLM: {} files",
+                self.llm_skipped_files.len()
+            ));
+        }
+        if self.partial.is_some() {
+            summary.push_str(" | ⚠️ Partial");
+        }
+        if !self.llm_timed_out_files.is_empty() {
+            summary.push_str(&format!(
+                " | ⏱️ Timed out: {} files",
+                self.llm_timed_out_files.len()
+            ));
+        }
 
         // Show high-risk dependencies inline if any
         let high_risk_deps: Vec<_> = self
@@ -395,6 +1294,26 @@ impl RiskReport {
             summary.push_str(&risk_names.join(", "));
         }
 
+        let mut cwe_counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for cwe in self
+            .findings
+            .iter()
+            .flat_map(|f| &f.flagged_patterns)
+            .filter_map(|p| p.cwe.as_deref())
+        {
+            *cwe_counts.entry(cwe).or_insert(0) += 1;
+        }
+        let categories: Vec<String> = cwe_counts
+            .into_iter()
+            .map(|(cwe, count)| match count {
+                1 => cwe.to_string(),
+                _ => format!("{}×{}", cwe, count),
+            })
+            .collect();
+        if !categories.is_empty() {
+            summary.push_str(&format!(" | CWE: {}", categories.join(", ")));
+        }
+
         // Show files with issues inline if any
         let files_with_issues: Vec<_> = self
             .findings
@@ -429,4 +1348,1036 @@ impl RiskReport {
         summary.push('\n');
         Ok(summary)
     }
+
+    /// GitLab Code Quality report (a JSON array of issues), shown in merge
+    /// request widgets when uploaded as a `codequality` artifact.
+    /// The `summary-json` format: the summary's counts and the top risks as
+    /// one line of JSON, for `jq` in shell scripts and status dashboards.
+    fn to_summary_json(&self) -> anyhow::Result<String> {
+        const TOP_RISKS: usize = 5;
+        let mut patterns: Vec<(&CrateFinding, &FlaggedPattern)> = self
+            .findings
+            .iter()
+            .flat_map(|f| f.flagged_patterns.iter().map(move |p| (f, p)))
+            .collect();
+        patterns.sort_by_key(|(_, p)| std::cmp::Reverse(severity::weight(&p.severity)));
+        let top_findings: Vec<serde_json::Value> = patterns
+            .iter()
+            .take(TOP_RISKS)
+            .map(|(finding, pattern)| {
+                serde_json::json!({
+                    "file": report_path(&finding.file_path),
+                    "line": pattern.line,
+                    "severity": pattern.severity,
+                    "cwe": pattern.cwe,
+                    "description": pattern.description,
+                })
+            })
+            .collect();
+        let top_dependencies: Vec<serde_json::Value> = self
+            .dependency_findings
+            .iter()
+            .filter(|d| matches!(d.risk_score, RiskScore::Critical | RiskScore::High))
+            .take(TOP_RISKS)
+            .map(|d| {
+                serde_json::json!({
+                    "package": d.package_name,
+                    "version": d.version,
+                    "risk": format!("{:?}", d.risk_score),
+                    "vulnerabilities": d.vulnerabilities.len(),
+                })
+            })
+            .collect();
+
+        let mut summary = serde_json::json!({
+            "crate": self.crate_name,
+            "files": self.summary.total_files_scanned,
+            "flagged_patterns": self.summary.total_flagged_patterns,
+            "dependencies": self.summary.total_dependencies_scanned,
+            "high_risk_dependencies": self.summary.high_risk_dependencies,
+            "severity_counts": self.summary.severity_counts,
+            "dependency_risk_counts": self.summary.dependency_risk_counts,
+            "unsafe": {
+                "crate": self.unsafe_surface.total.total(),
+                "dependencies": self.dependency_unsafe_totals().0.total(),
+            },
+            "llm_skipped_files": self.llm_skipped_files.len(),
+            "llm_timed_out_files": self.llm_timed_out_files.len(),
+            "partial": self.partial,
+            "clean": patterns.is_empty() && top_dependencies.is_empty(),
+            "top_findings": top_findings,
+            "top_dependencies": top_dependencies,
+        });
+        if !self.timestamp.is_empty() {
+            summary["timestamp"] = serde_json::json!(self.timestamp);
+        }
+        Ok(format!("{}\n", serde_json::to_string(&summary)?))
+    }
+
+    fn to_gitlab_codequality(&self) -> anyhow::Result<String> {
+        let mut issues = Vec::new();
+        for finding in &self.findings {
+            let path = report_path(&finding.file_path);
+            for pattern in &finding.flagged_patterns {
+                issues.push(serde_json::json!({
+                    "type": "issue",
+                    "check_name": pattern.cwe.as_deref().unwrap_or("rustrecon"),
+                    "description": pattern.description,
+                    "categories": ["Security"],
+                    "severity": match pattern.severity.as_str() {
+                        "Critical" => "blocker",
+                        "High" => "critical",
+                        "Medium" => "major",
+                        "Low" => "minor",
+                        _ => "info",
+                    },
+                    // Stable while the code moves, like baseline matching
+                    "fingerprint": fingerprint(&[&path, &pattern.description, &pattern.code_snippet]),
+                    "location": { "path": path, "lines": { "begin": pattern.line } },
+                }));
+            }
+        }
+        for dep in &self.dependency_findings {
+            let severity = match dep.risk_score {
+                RiskScore::Critical => "blocker",
+                RiskScore::High => "critical",
+                RiskScore::Medium => "major",
+                RiskScore::Low | RiskScore::Clean => continue,
+            };
+            issues.push(serde_json::json!({
+                "type": "issue",
+                "check_name": "rustrecon-dependency",
+                "description": format!(
+                    "Dependency {} v{} is {:?} risk",
+                    dep.package_name, dep.version, dep.risk_score
+                ),
+                "categories": ["Security"],
+                "severity": severity,
+                "fingerprint": fingerprint(&["Cargo.lock", &dep.package_name, &dep.version]),
+                "location": { "path": "Cargo.lock", "lines": { "begin": 1 } },
+            }));
+        }
+        Ok(serde_json::to_string_pretty(&issues)?)
+    }
+
+    /// JUnit XML with one test case per scanned file and per dependency. Files
+    /// with findings of Medium severity or above and High or Critical risk dependencies fail.
+    fn to_junit(&self) -> String {
+        let mut files = String::new();
+        let mut file_failures = 0;
+        for finding in &self.findings {
+            let path = report_path(&finding.file_path);
+            files.push_str(&format!(
+                "    <testcase classname=\"rustrecon.code\" name=\"{}\">\n",
+                xml_escape(&path)
+            ));
+            let details: Vec<String> = finding
+                .flagged_patterns
+                .iter()
+                .map(|p| {
+                    let category = p.cwe.as_ref().map_or(String::new(), |c| format!(" {}", c));
+                    format!(
+                        "{}:{} [{}{}] {}",
+                        path, p.line, p.severity, category, p.description
+                    )
+                })
+                .collect();
+            let failing = finding
+                .flagged_patterns
+                .iter()
+                .filter(|p| matches!(p.severity.as_str(), "Critical" | "High" | "Medium"))
+                .count();
+            if failing > 0 {
+                file_failures += 1;
+                files.push_str(&format!(
+                    "      <failure message=\"{} findings of Medium severity or above\" type=\"security\">{}</failure>\n",
+                    failing,
+                    xml_escape(&details.join("\n"))
+                ));
+            } else if !details.is_empty() {
+                files.push_str(&format!(
+                    "      <system-out>{}</system-out>\n",
+                    xml_escape(&details.join("\n"))
+                ));
+            }
+            files.push_str("    </testcase>\n");
+        }
+
+        let mut dependencies = String::new();
+        let mut dependency_failures = 0;
+        for dep in &self.dependency_findings {
+            dependencies.push_str(&format!(
+                "    <testcase classname=\"rustrecon.dependencies\" name=\"{} v{}\">\n",
+                xml_escape(&dep.package_name),
+                xml_escape(&dep.version)
+            ));
+            if matches!(dep.risk_score, RiskScore::Critical | RiskScore::High) {
+                dependency_failures += 1;
+                let details: Vec<String> = dep
+                    .metadata_flags
+                    .iter()
+                    .map(|f| format!("{}: {}", f.severity, f.description))
+                    .chain(dep.code_analysis.clone())
+                    .collect();
+                dependencies.push_str(&format!(
+                    "      <failure message=\"{:?} risk\" type=\"supply-chain\">{}</failure>\n",
+                    dep.risk_score,
+                    xml_escape(&details.join("\n"))
+                ));
+            }
+            dependencies.push_str("    </testcase>\n");
+        }
+
+        let suite = |name: &str, tests: usize, failures: usize, cases: &str| {
+            format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n{}  </testsuite>\n",
+                name, tests, failures, cases
+            )
+        };
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"rustrecon {}\" tests=\"{}\" failures=\"{}\">\n{}{}</testsuites>\n",
+            xml_escape(&self.crate_name),
+            self.findings.len() + self.dependency_findings.len(),
+            file_failures + dependency_failures,
+            suite("code", self.findings.len(), file_failures, &files),
+            suite(
+                "dependencies",
+                self.dependency_findings.len(),
+                dependency_failures,
+                &dependencies
+            ),
+        )
+    }
+
+    /// SARIF 2.1.0 log for code scanning tools such as GitHub code scanning.
+    /// Rule pack and grammar plugin findings use their rule id as the rule,
+    /// others their CWE.
+    fn to_sarif(&self) -> anyhow::Result<String> {
+        let mut rules: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+        let mut results = Vec::new();
+        for finding in &self.findings {
+            let path = report_path(&finding.file_path);
+            for pattern in &finding.flagged_patterns {
+                let pack_rule = pattern.rule.as_deref().and_then(rule_packs::rule);
+                let rule_id = pattern
+                    .rule
+                    .as_deref()
+                    .or(pattern.cwe.as_deref())
+                    .unwrap_or("rustrecon/finding");
+                rules.entry(rule_id.to_string()).or_insert_with(|| {
+                    let mut tags = vec!["security".to_string()];
+                    let mut name = match &pattern.cwe {
+                        Some(cwe) => {
+                            tags.push(format!("external/cwe/{}", cwe.to_lowercase()));
+                            taxonomy::cwe_name(cwe).unwrap_or(cwe.as_str())
+                        }
+                        None => "Suspicious code",
+                    };
+                    if let Some(rule) = pack_rule {
+                        name = rule.description;
+                        tags.push(rule.pack.label().to_lowercase());
+                    }
+                    tags.extend(pattern.owasp.clone());
+                    serde_json::json!({
+                        "id": rule_id,
+                        "shortDescription": { "text": name },
+                        "properties": { "tags": tags },
+                    })
+                });
+                let mut result = serde_json::json!({
+                    "ruleId": rule_id,
+                    "level": sarif_level(&pattern.severity),
+                    "message": { "text": pattern.description },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": path },
+                            "region": { "startLine": pattern.line.max(1) },
+                        },
+                    }],
+                    // The same code in identical files
+                    "relatedLocations": finding.identical_files.iter().enumerate().map(|(id, file)| serde_json::json!({
+                        "id": id + 1,
+                        "message": { "text": "Identical file" },
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": report_path(file) },
+                            "region": { "startLine": pattern.line.max(1) },
+                        },
+                    })).collect::<Vec<_>>(),
+                    "partialFingerprints": {
+                        "rustrecon/v1": fingerprint(&[&path, &pattern.description, &pattern.code_snippet]),
+                    },
+                    "properties": { "confidence": pattern.confidence },
+                });
+                if let Some(id) = &pattern.id {
+                    result["properties"]["findingId"] = id.as_str().into();
+                }
+                if let Some(triage) = &pattern.triage {
+                    result["properties"]["triage"] = triage.state.label().into();
+                    // Code scanning hides suppressed results
+                    if triage.state == TriageState::FalsePositive {
+                        result["suppressions"] = serde_json::json!([{
+                            "kind": "external",
+                            "justification": triage.note.as_deref().unwrap_or("Triaged as a false positive"),
+                        }]);
+                    }
+                }
+                results.push(result);
+            }
+        }
+
+        let risky_dependencies: Vec<&DependencyAnalysisResult> = self
+            .dependency_findings
+            .iter()
+            .filter(|d| {
+                matches!(
+                    d.risk_score,
+                    RiskScore::Critical | RiskScore::High | RiskScore::Medium
+                )
+            })
+            .collect();
+        if !risky_dependencies.is_empty() {
+            rules.insert(
+                "rustrecon/dependency-risk".to_string(),
+                serde_json::json!({
+                    "id": "rustrecon/dependency-risk",
+                    "shortDescription": { "text": "Risky dependency" },
+                    "properties": { "tags": ["security", "supply-chain"] },
+                }),
+            );
+        }
+        for dep in risky_dependencies {
+            results.push(serde_json::json!({
+                "ruleId": "rustrecon/dependency-risk",
+                "level": match dep.risk_score {
+                    RiskScore::Critical | RiskScore::High => "error",
+                    _ => "warning",
+                },
+                "message": {
+                    "text": format!("Dependency {} v{} is {:?} risk", dep.package_name, dep.version, dep.risk_score),
+                },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": "Cargo.lock" },
+                        "region": { "startLine": 1 },
+                    },
+                }],
+                "partialFingerprints": {
+                    "rustrecon/v1": fingerprint(&["Cargo.lock", &dep.package_name, &dep.version]),
+                },
+            }));
+        }
+
+        let sarif = serde_json::json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "RustRecon",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules.into_values().collect::<Vec<_>>(),
+                    },
+                },
+                "results": results,
+            }],
+        });
+        Ok(serde_json::to_string_pretty(&sarif)?)
+    }
+
+    /// Standalone HTML page with the summary, code findings and risky
+    /// dependencies.
+    fn to_html(&self) -> String {
+        let mut html = String::new();
+        html.push_str(&format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>RustRecon Scan Report: {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+            xml_escape(&self.crate_name),
+            HTML_STYLE
+        ));
+        html.push_str(&format!(
+            "<h1>RustRecon Scan Report: {}</h1>\n",
+            xml_escape(&self.crate_name)
+        ));
+        if !self.timestamp.is_empty() {
+            html.push_str(&format!(
+                "<p class=\"muted\">Generated {}</p>\n",
+                xml_escape(&self.timestamp)
+            ));
+        }
+
+        html.push_str("<h2>Summary</h2>\n<ul>\n");
+        if let Some(note) = self.partial_note() {
+            html.push_str(&format!(
+                "<li class=\"high\">⚠️ {}</li>\n",
+                xml_escape(&note)
+            ));
+        }
+        html.push_str(&format!(
+            "<li>Files scanned: {}</li>\n<li>Flagged patterns: {}</li>\n<li>Dependencies scanned: {}</li>\n<li>High-risk dependencies: {}</li>\n",
+            self.summary.total_files_scanned,
+            self.summary.total_flagged_patterns,
+            self.summary.total_dependencies_scanned,
+            self.summary.high_risk_dependencies
+        ));
+        for &severity in severity::LEVELS {
+            if let Some(count) = self.summary.severity_counts.get(severity) {
+                html.push_str(&format!(
+                    "<li><span class=\"{}\">{}</span>: {}</li>\n",
+                    severity.to_lowercase(),
+                    severity,
+                    count
+                ));
+            }
+        }
+        for line in self.analysis_version_lines() {
+            html.push_str(&format!("<li>{}</li>\n", xml_escape(&line)));
+        }
+        html.push_str("</ul>\n");
+
+        let risky_dependencies: Vec<&DependencyAnalysisResult> = self
+            .dependency_findings
+            .iter()
+            .filter(|d| matches!(d.risk_score, RiskScore::Critical | RiskScore::High))
+            .collect();
+        if !risky_dependencies.is_empty() {
+            html.push_str("<h2>High-Risk Dependencies</h2>\n<table>\n<tr><th>Package</th><th>Risk</th><th>Flags</th></tr>\n");
+            for dep in risky_dependencies {
+                let flags: Vec<String> = dep
+                    .metadata_flags
+                    .iter()
+                    .map(|f| xml_escape(&format!("{}: {}", f.severity, f.description)))
+                    .collect();
+                html.push_str(&format!(
+                    "<tr><td>{} v{}</td><td class=\"high\">{:?}</td><td>{}</td></tr>\n",
+                    xml_escape(&dep.package_name),
+                    xml_escape(&dep.version),
+                    dep.risk_score,
+                    flags.join("<br>")
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+
+        let indicators = self.all_network_indicators();
+        if !indicators.is_empty() {
+            html.push_str("<h2>Network Indicators</h2>\n<table>\n<tr><th>Indicator</th><th>Type</th><th>Flags</th><th>Found in</th></tr>\n");
+            for indicator in indicators.iter().take(MAX_INDICATOR_ROWS) {
+                let class = if indicator.flags.is_empty() {
+                    ""
+                } else {
+                    " class=\"medium\""
+                };
+                html.push_str(&format!(
+                    "<tr><td><code>{}</code></td><td>{}</td><td{}>{}</td><td>{}</td></tr>\n",
+                    xml_escape(&indicator.value),
+                    indicator.kind.label(),
+                    class,
+                    xml_escape(&indicator_flags(indicator)),
+                    xml_escape(&indicator_locations(indicator))
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+
+        let authors = self.findings_by_author();
+        if let Some((authors, unowned)) = &authors {
+            html.push_str(
+                "<h2>Findings by Author</h2>\n<table>\n<tr><th>Author</th><th>Findings</th>",
+            );
+            for severity in severity::LEVELS {
+                html.push_str(&format!("<th>{}</th>", severity));
+            }
+            html.push_str("<th>Files</th></tr>\n");
+            for author in authors {
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td>",
+                    xml_escape(&author.owner),
+                    author.findings
+                ));
+                for severity in severity::LEVELS {
+                    html.push_str(&format!(
+                        "<td>{}</td>",
+                        author.severity_counts.get(*severity).unwrap_or(&0)
+                    ));
+                }
+                let files: Vec<&str> = author.files.iter().map(String::as_str).collect();
+                html.push_str(&format!(
+                    "<td>{}</td></tr>\n",
+                    xml_escape(&files.join(", "))
+                ));
+            }
+            html.push_str("</table>\n");
+            if *unowned > 0 {
+                html.push_str(&format!(
+                    "<p class=\"muted\">{} findings have no owner: their lines are uncommitted or outside git.</p>\n",
+                    unowned
+                ));
+            }
+        }
+
+        html.push_str("<h2>Code Findings</h2>\n");
+        for finding in &self.findings {
+            html.push_str(&format!(
+                "<h3>{}</h3>\n<p>{}</p>\n",
+                xml_escape(&report_path(&finding.file_path)),
+                xml_escape(&finding.llm_analysis)
+            ));
+            if finding.flagged_patterns.is_empty() {
+                continue;
+            }
+            html.push_str("<table>\n<tr><th>Severity</th><th>Line</th><th>Category</th><th>Confidence</th><th>Triage</th>");
+            if authors.is_some() {
+                html.push_str("<th>Last changed by</th>");
+            }
+            html.push_str("<th>Description</th><th>Code</th></tr>\n");
+            for pattern in &finding.flagged_patterns {
+                let triage = pattern
+                    .triage
+                    .as_ref()
+                    .map_or(String::new(), |triage| match &triage.note {
+                        Some(note) => format!("{} ({})", triage.state.label(), note),
+                        None => triage.state.label().to_string(),
+                    });
+                html.push_str(&format!(
+                    "<tr><td class=\"{}\">{}</td><td>{}</td><td>{}</td><td>{:.0}%</td><td>{}</td>",
+                    xml_escape(&pattern.severity.to_lowercase()),
+                    xml_escape(&pattern.severity),
+                    pattern.line,
+                    xml_escape(&taxonomy::label(pattern).unwrap_or_default()),
+                    pattern.confidence * 100.0,
+                    xml_escape(&triage)
+                ));
+                if authors.is_some() {
+                    let owner = pattern.blame.as_ref().map_or(String::new(), |blame| {
+                        format!("{} ({})", blame.owner(), blame.short_commit())
+                    });
+                    html.push_str(&format!("<td>{}</td>", xml_escape(&owner)));
+                }
+                html.push_str(&format!(
+                    "<td>{}</td><td><code>{}</code></td></tr>\n",
+                    xml_escape(&pattern.description),
+                    xml_escape(&pattern.code_snippet)
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+
+        let iocs = self.iocs();
+        if !iocs.is_empty() {
+            html.push_str("<h2>IOC Appendix</h2>\n<table>\n<tr><th>Indicator</th><th>Type</th><th>Threat intel</th><th>Found in</th></tr>\n");
+            for indicator in &iocs {
+                html.push_str(&format!(
+                    "<tr><td><code>{}</code></td><td>{}</td><td{}>{}</td><td>{}</td></tr>\n",
+                    xml_escape(&indicator.value),
+                    ioc_type(indicator),
+                    if indicator.threat_feed.is_some() {
+                        " class=\"high\""
+                    } else {
+                        ""
+                    },
+                    xml_escape(indicator.threat_feed.as_deref().unwrap_or("-")),
+                    xml_escape(&indicator_locations(indicator))
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+fn indicator_flags(indicator: &NetworkIndicator) -> String {
+    let flags: Vec<&str> = indicator
+        .flags
+        .iter()
+        .map(|flag| match flag {
+            IndicatorFlag::RawIp => "raw IP",
+            IndicatorFlag::NonHttps => "non-HTTPS",
+            IndicatorFlag::Dynamic => "dynamic",
+            IndicatorFlag::Onion => "onion",
+            IndicatorFlag::C2Service => "C2 service",
+            IndicatorFlag::ThreatIntel => "threat intel",
+        })
+        .collect();
+    flags.join(", ")
+}
+
+fn is_ioc(indicator: &NetworkIndicator) -> bool {
+    indicator.kind == IndicatorKind::Wallet
+        || indicator.flags.iter().any(|flag| {
+            matches!(
+                flag,
+                IndicatorFlag::Onion | IndicatorFlag::C2Service | IndicatorFlag::ThreatIntel
+            )
+        })
+}
+
+fn ioc_type(indicator: &NetworkIndicator) -> &'static str {
+    if indicator.kind == IndicatorKind::Wallet {
+        "wallet address"
+    } else if indicator.flags.contains(&IndicatorFlag::Onion) {
+        "onion service"
+    } else if indicator.flags.contains(&IndicatorFlag::C2Service) {
+        "C2 service"
+    } else {
+        indicator.kind.label()
+    }
+}
+
+fn indicator_locations(indicator: &NetworkIndicator) -> String {
+    let mut locations =
+        indicator.locations[..indicator.locations.len().min(MAX_INDICATOR_LOCATIONS)].join(", ");
+    if indicator.locations.len() > MAX_INDICATOR_LOCATIONS {
+        locations.push_str(&format!(
+            " (+{} more)",
+            indicator.locations.len() - MAX_INDICATOR_LOCATIONS
+        ));
+    }
+    locations
+}
+
+fn write_output(report_content: &str, output_path: Option<&Path>) -> anyhow::Result<()> {
+    if let Some(path) = output_path {
+        let mut file = File::create(path)?;
+        file.write_all(report_content.as_bytes())?;
+        info!("Report successfully written to {}", path.display());
+    } else {
+        io::stdout().write_all(report_content.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// A file path as CI tools expect it: relative, with forward slashes.
+fn report_path(path: &Path) -> String {
+    path.strip_prefix(".")
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// The `*Timestamp: ...*` line under a markdown report's title, left out of
+/// reproducible reports.
+pub(crate) fn push_timestamp(md: &mut String, timestamp: &str) {
+    if timestamp.is_empty() {
+        md.push('\n');
+    } else {
+        md.push_str(&format!("*Timestamp: {}*\n\n", timestamp));
+    }
+}
+
+/// An explicit anchor for the markdown report's links, on its own line
+/// before the heading it marks. Generated heading ids vary between renderers
+/// and with the emoji and code in headings; these do not.
+fn push_anchor(md: &mut String, id: &str) {
+    md.push_str(&format!("\n<a id=\"{}\"></a>\n", id));
+}
+
+/// The anchor of a file's entry under `## Detailed Code Findings`: a
+/// readable slug of the path, and a hash of it so that paths with the same
+/// slug (`a_b.rs`, `a-b.rs`) get different anchors.
+fn file_anchor(path: &Path) -> String {
+    let path = report_path(path);
+    let slug: String = path
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let hash = format!("{:x}", Sha256::digest(path.as_bytes()));
+    format!("file-{}-{}", slug, &hash[..8])
+}
+
+/// The anchor of a severity's line in the severity index.
+fn severity_anchor(severity: &str) -> String {
+    format!("severity-{}", severity.to_lowercase())
+}
+
+const HTML_STYLE: &str =
+    "body{font-family:sans-serif;max-width:1100px;margin:2em auto;padding:0 1em}\
+table{border-collapse:collapse;width:100%;margin-bottom:1em}\
+th,td{border:1px solid #ccc;padding:4px 8px;text-align:left;vertical-align:top}\
+code{white-space:pre-wrap}.muted{color:#666}\
+.high,.critical{color:#b00020;font-weight:bold}.medium{color:#b26a00}.low{color:#1565c0}";
+
+fn sarif_level(severity: &str) -> &'static str {
+    match severity {
+        "Critical" | "High" => "error",
+        "Medium" => "warning",
+        _ => "note",
+    }
+}
+
+fn fingerprint(parts: &[&str]) -> String {
+    format!("{:x}", Sha256::digest(parts.join("\0").as_bytes()))
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// One file's section under `## Detailed Code Findings`.
+pub(crate) fn push_finding(md: &mut String, finding: &CrateFinding) {
+    push_anchor(md, &file_anchor(&finding.file_path));
+    md.push_str(&format!("### File: `{}`\n", finding.file_path.display()));
+    if !finding.identical_files.is_empty() {
+        let copies: Vec<String> = finding
+            .identical_files
+            .iter()
+            .map(|path| format!("`{}`", path.display()))
+            .collect();
+        md.push_str(&format!(
+            "*Identical files, analyzed once with the same findings: {}*\n",
+            copies.join(", ")
+        ));
+    }
+    md.push_str(&format!(
+        "#### LLM Analysis:\n```\n{}\n```\n",
+        finding.llm_analysis
+    ));
+    if let Some(version) = &finding.analysis_version {
+        md.push_str(&format!("*Analyzed with {}*\n", version));
+    }
+    if let Some(truncation) = &finding.truncation {
+        md.push_str(&format!(
+            "*The LLM saw about {}% of this file: lines too long for its context were cut*\n",
+            truncation.percent_analyzed()
+        ));
+    }
+    if !finding.flagged_patterns.is_empty() {
+        md.push_str("#### Flagged Patterns:\n");
+        for pattern in &finding.flagged_patterns {
+            md.push_str(&format!(
+                "- **Severity**: {}\n  - **Line**: {}\n  - **Confidence**: {:.0}%\n",
+                pattern.severity,
+                pattern.line,
+                pattern.confidence * 100.0
+            ));
+            if let Some(category) = taxonomy::label(pattern) {
+                md.push_str(&format!("  - **Category**: {}\n", category));
+            }
+            if let Some(id) = pattern.rule.as_deref() {
+                match rule_packs::rule(id) {
+                    Some(rule) => md.push_str(&format!(
+                        "  - **Rule**: `{}` ({})\n",
+                        rule.id,
+                        rule.pack.label()
+                    )),
+                    // A grammar plugin's rule
+                    None => md.push_str(&format!("  - **Rule**: `{}`\n", id)),
+                }
+            }
+            if let Some(id) = &pattern.id {
+                md.push_str(&format!("  - **ID**: `{}`\n", id));
+            }
+            if let Some(triage) = &pattern.triage {
+                md.push_str(&format!("  - **Triage**: {}", triage.state.label()));
+                if let Some(note) = &triage.note {
+                    md.push_str(&format!(" ({})", note));
+                }
+                md.push('\n');
+            }
+            if let Some(blame) = &pattern.blame {
+                md.push_str(&format!(
+                    "  - **Last changed by**: {} in `{}` ({})\n",
+                    blame.owner(),
+                    blame.short_commit(),
+                    blame.date.get(..10).unwrap_or(&blame.date)
+                ));
+            }
+            md.push_str(&format!(
+                "  - **Description**: {}\n  - **Code Snippet**:\n```rust\n{}\n```\n\n",
+                pattern.description, pattern.code_snippet
+            ));
+        }
+    } else {
+        md.push_str("No specific patterns flagged by LLM in this file.\n\n");
+    }
+    md.push_str("---\n\n");
+}
+
+/// The `### Clean Files` table closing `## Detailed Code Findings`: files
+/// without flagged patterns get a row with the start of their analysis
+/// instead of a section each.
+fn push_clean_files(md: &mut String, clean: &[&CrateFinding]) {
+    const MAX_ANALYSIS_CHARS: usize = 120;
+    if clean.is_empty() {
+        return;
+    }
+    push_anchor(md, "clean-files");
+    md.push_str(&format!(
+        "### Clean Files ({})\nNo patterns flagged by the LLM or static rules.\n\n",
+        clean.len()
+    ));
+    md.push_str("| File | LLM Analysis |\n|---|---|\n");
+    for finding in clean {
+        let mut file = format!("`{}`", finding.file_path.display());
+        if !finding.identical_files.is_empty() {
+            file.push_str(&format!(" (+{} identical)", finding.identical_files.len()));
+        }
+        let first_line = finding
+            .llm_analysis
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or("");
+        let mut analysis: String = first_line.chars().take(MAX_ANALYSIS_CHARS).collect();
+        if analysis.len() < first_line.len() {
+            analysis.push_str("...");
+        }
+        md.push_str(&format!(
+            "| <a id=\"{}\"></a>{} | {} |\n",
+            file_anchor(&finding.file_path),
+            file,
+            analysis.replace('|', "\\|")
+        ));
+    }
+    md.push('\n');
+}
+
+/// A high-risk dependency's entry: its flags, analysis and where it comes from.
+fn push_dependency_details(md: &mut String, dep: &DependencyAnalysisResult) {
+    md.push_str(&format!(
+        "#### {} v{} - {:?}\n",
+        dep.package_name, dep.version, dep.risk_score
+    ));
+    if !dep.metadata_flags.is_empty() {
+        md.push_str("**Flags:**\n");
+        for flag in &dep.metadata_flags {
+            md.push_str(&format!(
+                "- {} ({}): {}\n",
+                flag.severity, flag.flag_type, flag.description
+            ));
+        }
+    }
+    if let Some(analysis) = &dep.code_analysis {
+        md.push_str(&format!("**Analysis:** {}\n", analysis));
+    }
+    if let Some(footprint) = &dep.native_footprint {
+        md.push_str(&format!("**Native code:** {}\n", footprint.describe()));
+    }
+    if let Some(truncation) = &dep.source_truncation {
+        md.push_str(&format!(
+            "*The LLM saw about {}% of the source; the rest did not fit its context*\n",
+            truncation.percent_analyzed()
+        ));
+    }
+    if let Some(source) = &dep.elevated_by {
+        md.push_str(&format!("**Inherited from:** `{}`\n", source));
+    }
+    if !dep.dependents.is_empty() {
+        md.push_str(&format!("**Required by:** {}\n", dep.dependents.join(", ")));
+    }
+    md.push('\n');
+}
+
+/// Renders values as a row of block characters scaled to the largest value.
+pub fn sparkline(values: &[usize]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|&value| BARS[(value * (BARS.len() - 1)).checked_div(max).unwrap_or(0)])
+        .collect()
+}
+
+/// Describes the change from the previous scan, e.g. `+3 since last scan`.
+fn change(previous: Option<usize>, current: usize) -> String {
+    match previous {
+        Some(previous) if current > previous => {
+            format!("+{} since last scan", current - previous)
+        }
+        Some(previous) if current < previous => {
+            format!("-{} since last scan", previous - current)
+        }
+        Some(_) => "unchanged since last scan".to_string(),
+        None => "first scan".to_string(),
+    }
+}
+
+fn push_tree_node(
+    md: &mut String,
+    dep: &DependencyAnalysisResult,
+    by_key: &HashMap<String, &DependencyAnalysisResult>,
+    expanded: &mut HashSet<String>,
+    level: usize,
+) {
+    let indent = "  ".repeat(level);
+    let risk = match &dep.elevated_by {
+        Some(source) => format!("{:?} (via {})", dep.risk_score, source),
+        None => format!("{:?}", dep.risk_score),
+    };
+    if !expanded.insert(dep.key()) && !dep.dependencies.is_empty() {
+        md.push_str(&format!(
+            "{}- {} v{} - {} (\\*)\n",
+            indent, dep.package_name, dep.version, risk
+        ));
+        return;
+    }
+    md.push_str(&format!(
+        "{}- **{}** v{} - {}\n",
+        indent, dep.package_name, dep.version, risk
+    ));
+    for child_key in &dep.dependencies {
+        if let Some(child) = by_key.get(child_key) {
+            push_tree_node(md, child, by_key, expanded, level + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline_scales_to_maximum() {
+        assert_eq!(sparkline(&[0, 7, 14]), "▁▄█");
+        assert_eq!(sparkline(&[0, 0]), "▁▁");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn test_ci_formats() {
+        let mut report = RiskReport::new("demo".to_string());
+        report.add_file_finding(
+            PathBuf::from("./src/main.rs"),
+            "Static analysis only".to_string(),
+            vec![FlaggedPattern {
+                line: 4,
+                severity: "High".to_string(),
+                description: "[static] Spawns an external process".to_string(),
+                code_snippet: "Command::new(\"sh\")".to_string(),
+                confidence: 1.0,
+                cwe: None,
+                owasp: None,
+                id: None,
+                triage: None,
+                blame: None,
+                rule: None,
+            }],
+        );
+        report.add_file_finding(PathBuf::from("./src/a&b.rs"), String::new(), Vec::new());
+
+        let issues: serde_json::Value =
+            serde_json::from_str(&report.to_gitlab_codequality().unwrap()).unwrap();
+        assert_eq!(issues[0]["location"]["path"], "src/main.rs");
+        assert_eq!(issues[0]["location"]["lines"]["begin"], 4);
+        assert_eq!(issues[0]["severity"], "critical");
+        assert_eq!(issues[0]["check_name"], "CWE-78");
+
+        let sarif: serde_json::Value = serde_json::from_str(&report.to_sarif().unwrap()).unwrap();
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "CWE-78");
+        assert_eq!(run["results"][0]["level"], "error");
+        assert_eq!(
+            run["results"][0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "src/main.rs"
+        );
+
+        let junit = report.to_junit();
+        assert!(junit.contains("<testsuite name=\"code\" tests=\"2\" failures=\"1\">"));
+        assert!(junit.contains("name=\"src/a&amp;b.rs\""));
+        assert!(junit.contains("src/main.rs:4 [High CWE-78] [static] Spawns an external process"));
+
+        let summary = report.to_summary_json().unwrap();
+        assert_eq!(summary.lines().count(), 1);
+        let summary: serde_json::Value = serde_json::from_str(&summary).unwrap();
+        assert_eq!(summary["flagged_patterns"], 1);
+        assert_eq!(summary["severity_counts"]["High"], 1);
+        assert_eq!(summary["top_findings"][0]["file"], "src/main.rs");
+        assert_eq!(summary["top_findings"][0]["cwe"], "CWE-78");
+        assert_eq!(summary["clean"], false);
+    }
+
+    #[test]
+    fn test_reproducible_markdown() {
+        let pattern = |severity: &str| FlaggedPattern {
+            line: 1,
+            severity: severity.to_string(),
+            description: format!("{} finding", severity),
+            code_snippet: String::new(),
+            confidence: 1.0,
+            cwe: None,
+            owasp: None,
+            id: None,
+            triage: None,
+            blame: None,
+            rule: None,
+        };
+        let report = |paths: [&str; 2]| {
+            let mut report = RiskReport::new("demo".to_string());
+            report.timestamp.clear();
+            for path in paths {
+                let severity = if path.ends_with("a.rs") {
+                    "Low"
+                } else {
+                    "High"
+                };
+                report.add_file_finding(
+                    PathBuf::from(path),
+                    String::new(),
+                    vec![pattern(severity)],
+                );
+            }
+            report.sort_findings();
+            report.to_markdown().unwrap()
+        };
+        let markdown = report(["src/a.rs", "src/b.rs"]);
+        assert_eq!(markdown, report(["src/b.rs", "src/a.rs"]));
+        assert!(!markdown.contains("Timestamp"));
+        assert!(
+            markdown.find("  - High: [1](#severity-high)").unwrap()
+                < markdown.find("  - Low: [1](#severity-low)").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_markdown_contents() {
+        let mut report = RiskReport::new("demo".to_string());
+        report.add_file_finding(
+            PathBuf::from("src/net.rs"),
+            "Sends data home.".to_string(),
+            vec![FlaggedPattern {
+                line: 3,
+                severity: "High".to_string(),
+                description: "Exfiltration".to_string(),
+                code_snippet: String::new(),
+                confidence: 0.9,
+                cwe: None,
+                owasp: None,
+                id: None,
+                triage: None,
+                blame: None,
+                rule: None,
+            }],
+        );
+        report.add_file_finding(
+            PathBuf::from("src/lib.rs"),
+            "Nothing suspicious | all good.\nMore detail.".to_string(),
+            Vec::new(),
+        );
+        let markdown = report.to_markdown().unwrap();
+        assert!(markdown.contains("  - [`src/net.rs`](#file-src-net-rs-f64258ed) (1)\n"));
+        assert!(markdown.contains("  - [Clean Files](#clean-files) (1)\n"));
+        assert!(
+            markdown.contains("<a id=\"file-src-net-rs-f64258ed\"></a>\n### File: `src/net.rs`")
+        );
+        assert!(markdown.contains(
+            "- <a id=\"severity-high\"></a>**High** (1): [`src/net.rs`](#file-src-net-rs-f64258ed) (1)"
+        ));
+        assert!(markdown.contains(
+            "| <a id=\"file-src-lib-rs-b1a35a68\"></a>`src/lib.rs` | Nothing suspicious \\| all good. |"
+        ));
+        assert!(!markdown.contains("### File: `src/lib.rs`"));
+        let anchors: HashSet<String> = ["src/a_b.rs", "src/a-b.rs", "src/a.b.rs", "src/A_b.rs"]
+            .iter()
+            .map(|path| file_anchor(Path::new(path)))
+            .collect();
+        assert_eq!(anchors.len(), 4);
+    }
 }
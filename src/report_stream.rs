@@ -0,0 +1,194 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::report::{self, CrateFinding, RiskReport};
+
+/// How `scan --stream` lays out the report it appends to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// One JSON object per line, tagged with `"type"`
+    JsonLines,
+    /// The markdown report, with the summary moved to the end
+    Markdown,
+}
+
+impl StreamFormat {
+    /// The streaming layout for a `--format` value.
+    pub fn for_format(format: &str) -> Result<Self> {
+        match format {
+            "json" => Ok(StreamFormat::JsonLines),
+            "markdown" => Ok(StreamFormat::Markdown),
+            _ => anyhow::bail!(
+                "--stream supports the json (JSON Lines) and markdown formats, not {}",
+                format
+            ),
+        }
+    }
+}
+
+/// Writes a report piece by piece as the scan produces it, so memory stays
+/// flat on large workspaces and an interrupted scan leaves its results behind.
+/// Every record is flushed as soon as it is written.
+pub struct ReportStream {
+    file: File,
+    path: PathBuf,
+    format: StreamFormat,
+    findings_started: bool,
+}
+
+impl ReportStream {
+    /// Creates (or truncates) `path` and writes the report header.
+    pub fn create(path: &Path, format: StreamFormat, report: &RiskReport) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create report {}", path.display()))?;
+        let mut stream = ReportStream {
+            file,
+            path: path.to_path_buf(),
+            format,
+            findings_started: false,
+        };
+        match format {
+            StreamFormat::JsonLines => {
+                let mut started = serde_json::json!({ "crate_name": report.crate_name });
+                if !report.timestamp.is_empty() {
+                    started["timestamp"] = serde_json::json!(report.timestamp);
+                }
+                stream.record("scan_started", &started)?
+            }
+            StreamFormat::Markdown => {
+                let mut md = format!("# RustRecon Scan Report: {}\n", report.crate_name);
+                report::push_timestamp(&mut md, &report.timestamp);
+                stream.write(&md)?
+            }
+        }
+        Ok(stream)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends the results of the dependency scan.
+    pub fn write_dependencies(&mut self, report: &RiskReport) -> Result<()> {
+        match self.format {
+            StreamFormat::JsonLines => {
+                for dependency in &report.dependency_findings {
+                    self.record("dependency", dependency)?;
+                }
+                Ok(())
+            }
+            StreamFormat::Markdown => {
+                let mut md = String::new();
+                report.push_supply_chain(&mut md);
+                md.push('\n');
+                self.write(&md)
+            }
+        }
+    }
+
+    /// Appends one file's findings.
+    pub fn write_finding(&mut self, finding: &CrateFinding) -> Result<()> {
+        match self.format {
+            StreamFormat::JsonLines => self.record("finding", finding),
+            StreamFormat::Markdown => {
+                let mut md = String::new();
+                if !self.findings_started {
+                    md.push_str("## Detailed Code Findings\n");
+                    self.findings_started = true;
+                }
+                report::push_finding(&mut md, finding);
+                self.write(&md)
+            }
+        }
+    }
+
+    /// Appends the summary, unsafe surface and network indicators, which are
+    /// only known once every file has been analyzed.
+    pub fn finish(mut self, report: &RiskReport) -> Result<()> {
+        match self.format {
+            StreamFormat::JsonLines => self.record(
+                "summary",
+                &serde_json::json!({
+                    "summary": report.summary,
+                    "unsafe_surface": report.unsafe_surface,
+                    "network_indicators": report.network_indicators,
+                    "llm_skipped_files": report.llm_skipped_files,
+                    "llm_timed_out_files": report.llm_timed_out_files,
+                    "partial": report.partial,
+                    "skipped_dependencies": report.skipped_dependencies,
+                }),
+            ),
+            StreamFormat::Markdown => {
+                let mut md = String::new();
+                if !self.findings_started {
+                    md.push_str("## Detailed Code Findings\n");
+                    md.push_str("No suspicious patterns or findings detected.\n");
+                }
+                md.push('\n');
+                report.push_summary(&mut md, false);
+                report.push_unsafe_surface(&mut md);
+                report.push_network_indicators(&mut md);
+                self.write(&md)
+            }
+        }
+    }
+
+    fn record<T: Serialize>(&mut self, kind: &str, value: &T) -> Result<()> {
+        let mut record = serde_json::to_value(value)?;
+        if let Some(object) = record.as_object_mut() {
+            object.insert("type".to_string(), kind.into());
+        }
+        self.write(&format!("{}\n", serde_json::to_string(&record)?))
+    }
+
+    fn write(&mut self, text: &str) -> Result<()> {
+        self.file
+            .write_all(text.as_bytes())
+            .and_then(|_| self.file.flush())
+            .with_context(|| format!("Failed to write report {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_lines_stream() {
+        let path = std::env::temp_dir().join(format!(
+            "rustrecon-test-{}-stream.jsonl",
+            std::process::id()
+        ));
+        let mut report = RiskReport::new("demo".to_string());
+        let mut stream = ReportStream::create(&path, StreamFormat::JsonLines, &report).unwrap();
+        report.add_crate_finding(CrateFinding {
+            file_path: PathBuf::from("src/lib.rs"),
+            llm_analysis: "Nothing suspicious.".to_string(),
+            flagged_patterns: Vec::new(),
+            analysis_version: None,
+            identical_files: Vec::new(),
+            truncation: None,
+        });
+        for finding in report.findings.drain(..) {
+            stream.write_finding(&finding).unwrap();
+        }
+        stream.finish(&report).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let records: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let kinds: Vec<&str> = records
+            .iter()
+            .map(|r| r["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(kinds, vec!["scan_started", "finding", "summary"]);
+        assert_eq!(records[1]["file_path"], "src/lib.rs");
+        assert_eq!(records[2]["summary"]["total_files_scanned"], 1);
+    }
+}
@@ -0,0 +1,303 @@
+use anyhow::{bail, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashMap;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use crate::baseline::{Baseline, Disposition};
+use crate::llm_client::FlaggedPattern;
+use crate::report::RiskReport;
+use crate::taxonomy;
+
+/// Source lines shown above and below the flagged line.
+const CONTEXT_LINES: usize = 6;
+
+const HELP: &str =
+    "↑/↓ move  a accepted  f false-positive  n needs-fix  u clear  s save  q save and quit";
+
+struct ReviewItem {
+    file: PathBuf,
+    pattern: FlaggedPattern,
+}
+
+struct Review {
+    items: Vec<ReviewItem>,
+    state: ListState,
+    baseline: Baseline,
+    baseline_path: PathBuf,
+    /// Directory of the report, for file paths that were relative to the scan
+    report_dir: PathBuf,
+    sources: HashMap<PathBuf, Option<Vec<String>>>,
+    unsaved: bool,
+    status: Option<String>,
+}
+
+/// Lets a reviewer step through the findings of a JSON report and record a
+/// disposition for each one in the baseline file.
+pub fn run(report_path: &Path, baseline_path: &Path) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        bail!("`review` needs an interactive terminal");
+    }
+    let report = RiskReport::load(report_path)?;
+
+    let items: Vec<ReviewItem> = report
+        .findings
+        .into_iter()
+        .flat_map(|finding| {
+            let file = finding.file_path;
+            finding
+                .flagged_patterns
+                .into_iter()
+                .map(move |pattern| ReviewItem {
+                    file: file.clone(),
+                    pattern,
+                })
+        })
+        .collect();
+    if items.is_empty() {
+        println!("No findings to review in {}", report_path.display());
+        return Ok(());
+    }
+
+    let mut review = Review {
+        items,
+        state: ListState::default().with_selected(Some(0)),
+        baseline: Baseline::load(baseline_path)?,
+        baseline_path: baseline_path.to_path_buf(),
+        report_dir: report_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+        sources: HashMap::new(),
+        unsaved: false,
+        status: None,
+    };
+
+    let mut terminal = ratatui::init();
+    let result = review.event_loop(&mut terminal);
+    ratatui::restore();
+    result?;
+
+    println!(
+        "Reviewed {}/{} findings; dispositions are in {}",
+        review.reviewed(),
+        review.items.len(),
+        baseline_path.display()
+    );
+    Ok(())
+}
+
+impl Review {
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    if self.unsaved {
+                        self.save()?;
+                    }
+                    return Ok(());
+                }
+                KeyCode::Down | KeyCode::Char('j') => self.state.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.state.select_previous(),
+                KeyCode::Home | KeyCode::Char('g') => self.state.select_first(),
+                KeyCode::End | KeyCode::Char('G') => self.state.select_last(),
+                KeyCode::Char('a') => self.mark(Some(Disposition::Accepted)),
+                KeyCode::Char('f') => self.mark(Some(Disposition::FalsePositive)),
+                KeyCode::Char('n') => self.mark(Some(Disposition::NeedsFix)),
+                KeyCode::Char('u') => self.mark(None),
+                KeyCode::Char('s') => self.save()?,
+                _ => {}
+            }
+        }
+    }
+
+    fn selected(&self) -> usize {
+        self.state.selected().unwrap_or(0).min(self.items.len() - 1)
+    }
+
+    fn reviewed(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| {
+                self.baseline
+                    .disposition(&item.file, &item.pattern)
+                    .is_some()
+            })
+            .count()
+    }
+
+    /// Records the disposition and moves on to the next finding.
+    fn mark(&mut self, disposition: Option<Disposition>) {
+        let item = &self.items[self.selected()];
+        self.baseline.set(&item.file, &item.pattern, disposition);
+        self.unsaved = true;
+        self.status = None;
+        self.state.select_next();
+    }
+
+    fn save(&mut self) -> Result<()> {
+        self.baseline.save(&self.baseline_path)?;
+        self.unsaved = false;
+        self.status = Some(format!("Saved {}", self.baseline_path.display()));
+        Ok(())
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, detail_area] =
+            Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)])
+                .areas(main);
+
+        let selected = self.selected();
+        let context = self.source_context(selected);
+
+        let rows: Vec<ListItem> = self
+            .items
+            .iter()
+            .map(|item| {
+                let disposition = self.baseline.disposition(&item.file, &item.pattern);
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:<15}", disposition.map_or("", Disposition::label)),
+                        disposition_style(disposition),
+                    ),
+                    Span::styled(
+                        format!("{:<7}", item.pattern.severity),
+                        severity_style(&item.pattern.severity),
+                    ),
+                    Span::raw(format!(
+                        "{}:{} {}",
+                        item.file.display(),
+                        item.pattern.line,
+                        item.pattern.description
+                    )),
+                ]))
+            })
+            .collect();
+        let title = format!(
+            " Findings ({}/{} reviewed) ",
+            self.reviewed(),
+            self.items.len()
+        );
+        let list = List::new(rows)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.state);
+
+        let item = &self.items[selected];
+        let disposition = self.baseline.disposition(&item.file, &item.pattern);
+        let mut lines = vec![
+            Line::from(format!("{}:{}", item.file.display(), item.pattern.line)),
+            Line::from(vec![
+                Span::raw("Severity: "),
+                Span::styled(
+                    item.pattern.severity.clone(),
+                    severity_style(&item.pattern.severity),
+                ),
+                Span::raw(format!(
+                    "   Confidence: {:.0}%",
+                    item.pattern.confidence * 100.0
+                )),
+                Span::raw("   Disposition: "),
+                Span::styled(
+                    disposition.map_or("unreviewed", Disposition::label),
+                    disposition_style(disposition),
+                ),
+            ]),
+            Line::from(taxonomy::label(&item.pattern).unwrap_or_default()),
+            Line::from(""),
+            Line::from(item.pattern.description.clone()),
+            Line::from(""),
+        ];
+        lines.extend(context);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().title(" Finding "))
+                .wrap(Wrap { trim: false }),
+            detail_area,
+        );
+
+        let status = self.status.as_deref().unwrap_or(HELP);
+        frame.render_widget(
+            Paragraph::new(status).style(Style::default().fg(Color::DarkGray)),
+            footer,
+        );
+    }
+
+    /// The flagged line with surrounding source, or just the reported snippet
+    /// when the file cannot be read.
+    fn source_context(&mut self, index: usize) -> Vec<Line<'static>> {
+        let item = &self.items[index];
+        let report_dir = &self.report_dir;
+        let source = self
+            .sources
+            .entry(item.file.clone())
+            .or_insert_with(|| read_source(&item.file, report_dir));
+        let line = item.pattern.line;
+        match source {
+            Some(source) if line >= 1 && line <= source.len() => {
+                let start = line.saturating_sub(CONTEXT_LINES).max(1);
+                let end = (line + CONTEXT_LINES).min(source.len());
+                (start..=end)
+                    .map(|n| {
+                        let text = format!("{:>5} │ {}", n, source[n - 1]);
+                        if n == line {
+                            Line::styled(
+                                text,
+                                Style::default()
+                                    .fg(Color::Yellow)
+                                    .add_modifier(Modifier::BOLD),
+                            )
+                        } else {
+                            Line::raw(text)
+                        }
+                    })
+                    .collect()
+            }
+            _ => vec![Line::raw(item.pattern.code_snippet.clone())],
+        }
+    }
+}
+
+fn read_source(file: &Path, report_dir: &Path) -> Option<Vec<String>> {
+    let path = if file.exists() {
+        file.to_path_buf()
+    } else {
+        report_dir.join(file)
+    };
+    let content = fs::read_to_string(path).ok()?;
+    Some(content.lines().map(|l| l.replace('\t', "    ")).collect())
+}
+
+fn severity_style(severity: &str) -> Style {
+    match severity {
+        "Critical" => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        "High" => Style::default().fg(Color::Red),
+        "Medium" => Style::default().fg(Color::Yellow),
+        "Info" => Style::default().fg(Color::DarkGray),
+        _ => Style::default().fg(Color::Blue),
+    }
+}
+
+fn disposition_style(disposition: Option<Disposition>) -> Style {
+    match disposition {
+        Some(Disposition::Accepted) => Style::default().fg(Color::Green),
+        Some(Disposition::FalsePositive) => Style::default().fg(Color::DarkGray),
+        Some(Disposition::NeedsFix) => Style::default().fg(Color::Red),
+        None => Style::default(),
+    }
+}
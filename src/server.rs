@@ -0,0 +1,798 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Utc;
+use flate2::read::GzDecoder;
+use hyper::body::HttpBody;
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE, WWW_AUTHENTICATE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::fs;
+use std::io::{self, Read};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{mpsc, Arc, Mutex};
+use tempfile::TempDir;
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+use crate::exit_code::config_error;
+use crate::network;
+use crate::notify;
+use crate::report::RiskReport;
+use crate::{run_scan, ScanOptions};
+
+/// Finished jobs kept in memory; the oldest are dropped first.
+const MAX_FINISHED_JOBS: usize = 200;
+
+/// Largest accepted tarball upload.
+const MAX_UPLOAD_BYTES: usize = 200 * 1024 * 1024;
+/// Largest size a gzipped upload may unpack to.
+const MAX_UNPACKED_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Sets `--token` when the option is not given.
+pub const TOKEN_ENV_VAR: &str = "RUSTRECON_SERVE_TOKEN";
+
+pub struct ServeOptions {
+    pub listen: SocketAddr,
+    pub workers: usize,
+    /// Profile for jobs that do not ask for one
+    pub profile: String,
+    /// Accept `path` jobs and local git repositories
+    pub allow_local_paths: bool,
+    /// Bearer token required on every request but `/health`
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+/// What a job scans.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Source {
+    /// A directory on the server
+    Path { path: PathBuf },
+    /// A repository, shallow-fetched at `rev` (default branch if unset)
+    Git { url: String, rev: Option<String> },
+    /// An uploaded `.crate` or `.tar.gz` file
+    Tarball { bytes: usize },
+}
+
+#[derive(Debug, Serialize)]
+struct Job {
+    id: u64,
+    status: JobStatus,
+    source: Source,
+    profile: String,
+    submitted_at: String,
+    started_at: Option<String>,
+    finished_at: Option<String>,
+    error: Option<String>,
+    #[serde(skip)]
+    report: Option<RiskReport>,
+}
+
+impl Job {
+    fn to_json(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(report) = &self.report {
+            value["summary"] = serde_json::to_value(&report.summary).unwrap_or_default();
+            value["report"] = format!("/scans/{}/report", self.id).into();
+        }
+        value
+    }
+}
+
+/// A job waiting for a worker.
+struct Task {
+    id: u64,
+    source: Source,
+    tarball: Vec<u8>,
+    options: ScanOptions,
+}
+
+/// Body of a JSON job submission.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Submission {
+    path: Option<PathBuf>,
+    git: Option<String>,
+    rev: Option<String>,
+}
+
+struct State {
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    next_id: Mutex<u64>,
+    queue: mpsc::Sender<Task>,
+    options: ServeOptions,
+}
+
+impl State {
+    fn update(&self, id: u64, f: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.jobs.lock().unwrap().get_mut(&id) {
+            f(job);
+        }
+    }
+
+    /// Drops the oldest finished jobs beyond `MAX_FINISHED_JOBS`.
+    fn prune(&self) {
+        let mut jobs = self.jobs.lock().unwrap();
+        let finished: Vec<u64> = jobs
+            .values()
+            .filter(|job| matches!(job.status, JobStatus::Done | JobStatus::Failed))
+            .map(|job| job.id)
+            .collect();
+        for id in finished
+            .iter()
+            .take(finished.len().saturating_sub(MAX_FINISHED_JOBS))
+        {
+            jobs.remove(id);
+        }
+    }
+}
+
+/// Runs the REST API until Ctrl-C. Jobs wait in a queue for one of
+/// `workers` scan threads; all of them share the analysis cache and database.
+pub async fn serve(options: ServeOptions) -> Result<()> {
+    // Every scan spends the operator's LLM quota, so only loopback clients
+    // may go without a token
+    if options.token.is_none() && !options.listen.ip().is_loopback() {
+        return Err(config_error(anyhow!(
+            "Listening on {} needs a token: pass --token or set {}",
+            options.listen,
+            TOKEN_ENV_VAR
+        )));
+    }
+    // Fail now rather than in every job
+    Config::load_from_default_paths()?;
+    let (queue, receiver) = mpsc::channel();
+    let listen = options.listen;
+    let workers = options.workers.max(1);
+    let state = Arc::new(State {
+        jobs: Mutex::new(BTreeMap::new()),
+        next_id: Mutex::new(1),
+        queue,
+        options,
+    });
+
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..workers {
+        spawn_worker(state.clone(), receiver.clone())?;
+    }
+
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req))) }
+    });
+    let server = Server::try_bind(&listen)
+        .with_context(|| format!("Failed to listen on {}", listen))?
+        .serve(make_service);
+    info!("🌐 Listening on http://{} ({} workers)", listen, workers);
+    server
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await?;
+    Ok(())
+}
+
+/// Scans run on their own threads, each with a single-threaded runtime, so
+/// they need not be `Send` and a long scan never stalls the API.
+fn spawn_worker(state: Arc<State>, receiver: Arc<Mutex<mpsc::Receiver<Task>>>) -> Result<()> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    std::thread::spawn(move || loop {
+        let task = match receiver.lock().unwrap().recv() {
+            Ok(task) => task,
+            Err(_) => break,
+        };
+        state.update(task.id, |job| {
+            job.status = JobStatus::Running;
+            job.started_at = Some(Utc::now().to_rfc3339());
+        });
+        info!("▶️  Job {}: scanning {:?}", task.id, task.source);
+        let result = runtime.block_on(run_task(&task));
+        state.update(task.id, |job| {
+            job.finished_at = Some(Utc::now().to_rfc3339());
+            match result {
+                Ok(report) => {
+                    info!("✅ Job {} done", task.id);
+                    job.status = JobStatus::Done;
+                    job.report = Some(report);
+                }
+                Err(e) => {
+                    error!("❌ Job {} failed: {:#}", task.id, e);
+                    job.status = JobStatus::Failed;
+                    job.error = Some(format!("{:#}", e));
+                }
+            }
+        });
+        state.prune();
+    });
+    Ok(())
+}
+
+async fn run_task(task: &Task) -> Result<RiskReport> {
+    let mut work_dir = None;
+    let root = match &task.source {
+        Source::Path { path } => path.clone(),
+        Source::Git { url, rev } => {
+            let dir = work_dir.insert(create_work_dir(task.id)?);
+            let checkout = dir.path().join(repository_name(url));
+            fetch_repository(url, rev.as_deref(), &checkout)?;
+            checkout
+        }
+        Source::Tarball { .. } => {
+            let dir = work_dir.insert(create_work_dir(task.id)?);
+            unpack_tarball(&task.tarball, dir.path(), MAX_UNPACKED_BYTES)?
+        }
+    };
+    // A submitted project must not configure its own scan, e.g. exclude its
+    // files or trust its dependencies, so only server paths get theirs
+    let config = if work_dir.is_some() {
+        if root.join(crate::config::PROJECT_CONFIG_FILE_NAME).is_file() {
+            warn!(
+                "⚠️  Job {}: ignoring the submitted {}",
+                task.id,
+                crate::config::PROJECT_CONFIG_FILE_NAME
+            );
+        }
+        Config::load_from_default_paths()?
+    } else {
+        Config::load_for_project(&root)?
+    };
+    let report = run_scan(&root, &config, &task.options).await?;
+    notify::send_alerts(&config.notifications, &report, None).await;
+    Ok(report)
+}
+
+/// A problem with the request itself, answered with 400. Other errors are
+/// the server's own and get 500.
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+struct BadRequest(anyhow::Error);
+
+fn bad_request(error: impl Into<anyhow::Error>) -> anyhow::Error {
+    BadRequest(error.into()).into()
+}
+
+async fn handle(state: Arc<State>, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    if !authorized(&state, &req) {
+        let mut response =
+            error_response(StatusCode::UNAUTHORIZED, "Missing or wrong bearer token");
+        response
+            .headers_mut()
+            .insert(WWW_AUTHENTICATE, "Bearer".parse().unwrap());
+        return Ok(response);
+    }
+    let response = match route(&state, req).await {
+        Ok(response) => response,
+        Err(e) if e.downcast_ref::<BadRequest>().is_some() => {
+            error_response(StatusCode::BAD_REQUEST, &format!("{:#}", e))
+        }
+        Err(e) => {
+            error!("❌ {:#}", e);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("{:#}", e))
+        }
+    };
+    Ok(response)
+}
+
+/// Whether `req` carries the server's token, if it has one. `/health` is
+/// open so load balancers can probe it.
+fn authorized(state: &State, req: &Request<Body>) -> bool {
+    let Some(token) = &state.options.token else {
+        return true;
+    };
+    if req.uri().path() == "/health" {
+        return true;
+    }
+    let given = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    // Compare in constant time, so response timing does not reveal the token
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn route(state: &State, req: Request<Body>) -> Result<Response<Body>> {
+    let segments: Vec<String> = req
+        .uri()
+        .path()
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    let query = parse_query(req.uri().query().unwrap_or(""));
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    match (req.method(), segments.as_slice()) {
+        (&Method::GET, ["health"]) => Ok(json_response(
+            StatusCode::OK,
+            &serde_json::json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }),
+        )),
+        (&Method::POST, ["scans"]) => submit(state, req, &query).await,
+        (&Method::GET, ["scans"]) => {
+            let jobs = state.jobs.lock().unwrap();
+            let jobs: Vec<_> = jobs.values().map(Job::to_json).collect();
+            Ok(json_response(StatusCode::OK, &jobs))
+        }
+        (&Method::GET, ["scans", id]) => {
+            let jobs = state.jobs.lock().unwrap();
+            Ok(match id.parse().ok().and_then(|id: u64| jobs.get(&id)) {
+                Some(job) => json_response(StatusCode::OK, &job.to_json()),
+                None => error_response(StatusCode::NOT_FOUND, "No such scan"),
+            })
+        }
+        (&Method::GET, ["scans", id, "report"]) => {
+            let format = query.get("format").map_or("json", String::as_str);
+            let content_type = content_type(format)
+                .ok_or_else(|| bad_request(anyhow!("Unsupported report format: {}", format)))?;
+            let jobs = state.jobs.lock().unwrap();
+            let Some(job) = id.parse().ok().and_then(|id: u64| jobs.get(&id)) else {
+                return Ok(error_response(StatusCode::NOT_FOUND, "No such scan"));
+            };
+            Ok(match (&job.report, job.status) {
+                (Some(report), _) => Response::builder()
+                    .header(CONTENT_TYPE, content_type)
+                    .body(Body::from(report.render(format)?))?,
+                (None, JobStatus::Failed) => error_response(
+                    StatusCode::CONFLICT,
+                    &format!("Scan failed: {}", job.error.as_deref().unwrap_or("")),
+                ),
+                (None, _) => error_response(StatusCode::CONFLICT, "Scan has not finished yet"),
+            })
+        }
+        _ => Ok(error_response(StatusCode::NOT_FOUND, "Not found")),
+    }
+}
+
+/// Queues a job. The source is a JSON body with `path` or `git` (and
+/// optionally `rev`), or a tarball body; scan options come from the query.
+async fn submit(
+    state: &State,
+    req: Request<Body>,
+    query: &HashMap<String, String>,
+) -> Result<Response<Body>> {
+    let is_json = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    let body = read_body(req.into_body()).await?;
+
+    let (source, tarball) = if is_json {
+        let submission: Submission = serde_json::from_slice(&body)
+            .context("Invalid scan request")
+            .map_err(bad_request)?;
+        let source = match submission {
+            Submission {
+                path: Some(path),
+                git: None,
+                rev: None,
+            } => {
+                if !state.options.allow_local_paths {
+                    return Ok(error_response(
+                        StatusCode::FORBIDDEN,
+                        "Scanning server paths is disabled; start the server with --allow-local-paths",
+                    ));
+                }
+                Source::Path { path }
+            }
+            Submission {
+                path: None,
+                git: Some(url),
+                rev,
+            } => {
+                check_git_source(&url, rev.as_deref(), state.options.allow_local_paths)
+                    .map_err(bad_request)?;
+                Source::Git { url, rev }
+            }
+            _ => {
+                return Err(bad_request(anyhow!(
+                    "Give either \"path\" or \"git\" (with an optional \"rev\")"
+                )))
+            }
+        };
+        (source, Vec::new())
+    } else {
+        if body.is_empty() {
+            return Err(bad_request(anyhow!(
+                "Send a JSON body with \"path\" or \"git\", or a crate tarball"
+            )));
+        }
+        (Source::Tarball { bytes: body.len() }, body)
+    };
+
+    let flag = |name: &str| query.get(name).is_some_and(|v| v == "true" || v == "1");
+    let options = ScanOptions {
+        profile: query
+            .get("profile")
+            .cloned()
+            .unwrap_or_else(|| state.options.profile.clone()),
+        scan_dependencies: !flag("skip_dependencies"),
+        resume: false,
+        offline: flag("offline"),
+        verify_sources: false,
+        expand_macros: false,
+        no_cache: false,
+        exclude: Vec::new(),
+        include: Vec::new(),
+        only: Vec::new(),
+        files: Vec::new(),
+        changes: None,
+        blame: false,
+        // Nor hide files from it with ignore files
+        no_ignore: !matches!(source, Source::Path { .. }),
+        min_confidence: query
+            .get("min_confidence")
+            .map(|v| v.parse::<f32>())
+            .transpose()
+            .context("Invalid min_confidence")
+            .map_err(bad_request)?,
+        skip_code: false,
+        max_duration: None,
+        max_api_calls: None,
+        reproducible: false,
+        absolute_paths: false,
+        stream: None,
+    };
+
+    let id = {
+        let mut next_id = state.next_id.lock().unwrap();
+        *next_id += 1;
+        *next_id - 1
+    };
+    let job = Job {
+        id,
+        status: JobStatus::Queued,
+        source: source.clone(),
+        profile: options.profile.clone(),
+        submitted_at: Utc::now().to_rfc3339(),
+        started_at: None,
+        finished_at: None,
+        error: None,
+        report: None,
+    };
+    let response = json_response(StatusCode::ACCEPTED, &job.to_json());
+    state.jobs.lock().unwrap().insert(id, job);
+    info!("📥 Job {} queued", id);
+    state
+        .queue
+        .send(Task {
+            id,
+            source,
+            tarball,
+            options,
+        })
+        .context("Scan workers have stopped")?;
+    Ok(response)
+}
+
+async fn read_body(mut body: Body) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        bytes.extend_from_slice(&chunk.map_err(bad_request)?);
+        if bytes.len() > MAX_UPLOAD_BYTES {
+            return Err(bad_request(anyhow!(
+                "Request body is larger than {} bytes",
+                MAX_UPLOAD_BYTES
+            )));
+        }
+    }
+    Ok(bytes)
+}
+
+/// `a=1&b=2` as a map. Values are taken as they are, without percent-decoding.
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key.to_string(), value.to_string())
+        })
+        .collect()
+}
+
+/// Rejects URLs git would read as options or as local paths (unless allowed).
+fn check_git_source(url: &str, rev: Option<&str>, allow_local_paths: bool) -> Result<()> {
+    if url.starts_with('-') || rev.is_some_and(|rev| rev.starts_with('-')) {
+        bail!("Invalid repository URL or revision");
+    }
+    let remote = ["https://", "http://", "ssh://", "git://"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+        || url
+            .split_once(':')
+            .is_some_and(|(host, _)| host.contains('@') && !host.contains('/'));
+    if !remote && !allow_local_paths {
+        bail!("Only remote repository URLs (https, ssh or git) are accepted");
+    }
+    Ok(())
+}
+
+/// Last path segment of a repository URL, which becomes the crate name.
+fn repository_name(url: &str) -> String {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".git");
+    if name.is_empty() {
+        "repository".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn fetch_repository(url: &str, rev: Option<&str>, dir: &Path) -> Result<()> {
+    network::check_git_url(url)?;
+    fs::create_dir_all(dir)?;
+    git(&["init", "-q"], dir)?;
+    git(
+        &["fetch", "-q", "--depth", "1", url, rev.unwrap_or("HEAD")],
+        dir,
+    )?;
+    git(&["checkout", "-q", "FETCH_HEAD"], dir)
+}
+
+fn git(args: &[&str], dir: &Path) -> Result<()> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Unpacks a gzipped or plain tarball into `dir` and returns the directory
+/// holding `Cargo.toml`: `dir` itself, or its only subdirectory as in `.crate`
+/// files. A gzipped tarball may unpack to at most `max_unpacked` bytes.
+fn unpack_tarball(tarball: &[u8], dir: &Path, max_unpacked: u64) -> Result<PathBuf> {
+    if tarball.starts_with(&[0x1f, 0x8b]) {
+        tar::Archive::new(SizeLimit {
+            inner: GzDecoder::new(tarball),
+            limit: max_unpacked,
+            remaining: max_unpacked,
+        })
+        .unpack(dir)
+    } else {
+        tar::Archive::new(tarball).unpack(dir)
+    }
+    .context("Failed to unpack the uploaded tarball")?;
+
+    if dir.join("Cargo.toml").is_file() {
+        return Ok(dir.to_path_buf());
+    }
+    let subdirs: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    match subdirs.as_slice() {
+        [root] if root.join("Cargo.toml").is_file() => Ok(root.clone()),
+        _ => bail!("The tarball has no Cargo.toml at its root or in a single top-level directory"),
+    }
+}
+
+/// Scratch directory of one job, only accessible to the server's user and
+/// removed when the job ends.
+fn create_work_dir(id: u64) -> Result<TempDir> {
+    tempfile::Builder::new()
+        .prefix(&format!("rustrecon-serve-{}-", id))
+        .tempdir()
+        .context("Failed to create a scratch directory")
+}
+
+/// Reader that fails once more than `limit` bytes have been read, so a
+/// small upload cannot unpack to fill the disk.
+struct SizeLimit<R> {
+    inner: R,
+    limit: u64,
+    remaining: u64,
+}
+
+impl<R: Read> Read for SizeLimit<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.remaining = self.remaining.checked_sub(read as u64).ok_or_else(|| {
+            io::Error::other(format!(
+                "the tarball unpacks to more than {} bytes",
+                self.limit
+            ))
+        })?;
+        Ok(read)
+    }
+}
+
+/// Content type of a report format, or `None` for a format there is no
+/// renderer for.
+fn content_type(format: &str) -> Option<&'static str> {
+    Some(match format {
+        "json" | "summary-json" | "sarif" | "gitlab-codequality" => "application/json",
+        "junit" => "application/xml",
+        "html" => "text/html; charset=utf-8",
+        "markdown" | "condensed" => "text/markdown; charset=utf-8",
+        "summary" => "text/plain; charset=utf-8",
+        _ => return None,
+    })
+}
+
+fn json_response(status: StatusCode, value: &impl Serialize) -> Response<Body> {
+    let mut response = Response::new(Body::from(
+        serde_json::to_string_pretty(value).unwrap_or_default(),
+    ));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+    response
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, &serde_json::json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git_sources_and_query() {
+        assert!(check_git_source("https://github.com/serde-rs/serde.git", None, false).is_ok());
+        assert!(
+            check_git_source("git@github.com:serde-rs/serde.git", Some("v1.0.0"), false).is_ok()
+        );
+        assert!(check_git_source("/srv/repos/app", None, false).is_err());
+        assert!(check_git_source("/srv/repos/app", None, true).is_ok());
+        assert!(check_git_source("--upload-pack=touch x", None, true).is_err());
+        assert!(check_git_source("https://example.com/r.git", Some("-x"), false).is_err());
+
+        assert_eq!(
+            repository_name("https://github.com/serde-rs/serde.git"),
+            "serde"
+        );
+        assert_eq!(repository_name("git@github.com:tokio-rs/tokio"), "tokio");
+
+        let query = parse_query("profile=quick&offline=1&flag");
+        assert_eq!(query["profile"], "quick");
+        assert_eq!(query["offline"], "1");
+        assert_eq!(query["flag"], "");
+    }
+
+    fn test_state(token: Option<&str>) -> (Arc<State>, mpsc::Receiver<Task>) {
+        let (queue, receiver) = mpsc::channel();
+        let state = Arc::new(State {
+            jobs: Mutex::new(BTreeMap::new()),
+            next_id: Mutex::new(1),
+            queue,
+            options: ServeOptions {
+                listen: "127.0.0.1:0".parse().unwrap(),
+                workers: 1,
+                profile: "quick".to_string(),
+                allow_local_paths: false,
+                token: token.map(str::to_string),
+            },
+        });
+        (state, receiver)
+    }
+
+    #[tokio::test]
+    async fn test_error_statuses_and_token() {
+        let status = |state: &Arc<State>, request: Request<Body>| {
+            let state = state.clone();
+            async move { handle(state, request).await.unwrap().status() }
+        };
+        let submission = |body: &'static str| {
+            Request::post("/scans")
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let (state, receiver) = test_state(None);
+
+        // Bad input is the client's fault
+        assert_eq!(
+            status(&state, submission("{\"git\": 1}")).await,
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            status(&state, submission("{\"git\": \"/srv/repo\"}")).await,
+            StatusCode::BAD_REQUEST
+        );
+        let report = Request::get("/scans/1/report?format=pdf")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(status(&state, report).await, StatusCode::BAD_REQUEST);
+
+        // Stopped workers are the server's
+        drop(receiver);
+        let valid = submission("{\"git\": \"https://example.com/demo.git\"}");
+        assert_eq!(
+            status(&state, valid).await,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        // With a token, only /health is open
+        let (state, _receiver) = test_state(Some("s3cret"));
+        let scans = |token: Option<&str>| {
+            let request = Request::get("/scans");
+            match token {
+                Some(token) => request.header(AUTHORIZATION, format!("Bearer {}", token)),
+                None => request,
+            }
+            .body(Body::empty())
+            .unwrap()
+        };
+        assert_eq!(status(&state, scans(None)).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            status(&state, scans(Some("s3cre"))).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(status(&state, scans(Some("s3cret"))).await, StatusCode::OK);
+        let health = Request::get("/health").body(Body::empty()).unwrap();
+        assert_eq!(status(&state, health).await, StatusCode::OK);
+
+        let open = ServeOptions {
+            listen: "0.0.0.0:0".parse().unwrap(),
+            workers: 1,
+            profile: "quick".to_string(),
+            allow_local_paths: false,
+            token: None,
+        };
+        let error = serve(open).await.unwrap_err();
+        assert!(error.to_string().contains(TOKEN_ENV_VAR));
+    }
+
+    #[test]
+    fn test_unpack_tarball_limit() {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in [
+            ("demo-0.1.0/Cargo.toml", vec![b'#'; 10]),
+            ("demo-0.1.0/src/lib.rs", vec![b' '; 64 * 1024]),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, path, content.as_slice())
+                .unwrap();
+        }
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        std::io::Write::write_all(&mut gzip, &builder.into_inner().unwrap()).unwrap();
+        let tarball = gzip.finish().unwrap();
+
+        let dir = create_work_dir(0).unwrap();
+        let root = unpack_tarball(&tarball, dir.path(), 1024 * 1024).unwrap();
+        assert_eq!(root, dir.path().join("demo-0.1.0"));
+
+        let dir = create_work_dir(1).unwrap();
+        let error = unpack_tarball(&tarball, dir.path(), 16 * 1024).unwrap_err();
+        assert!(format!("{:#}", error).contains("unpacks to more than 16384 bytes"));
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::llm_client::FlaggedPattern;
+use crate::taxonomy;
+
+/// Severities a finding class can be set to in `[severity]`, most severe first.
+pub const LEVELS: &[&str] = &["Critical", "High", "Medium", "Low", "Info"];
+
+/// `[severity]` value that drops a finding class from scoring and reports.
+pub const IGNORE: &str = "Ignore";
+
+/// The canonical spelling of a `[severity]` value (`"critical"` is
+/// `"Critical"`), or `None` if it is not one.
+pub fn canonical(value: &str) -> Option<&'static str> {
+    LEVELS
+        .iter()
+        .chain(std::iter::once(&IGNORE))
+        .find(|level| level.eq_ignore_ascii_case(value))
+        .copied()
+}
+
+/// Counts keyed by severity or risk level, most severe first; levels outside
+/// [`LEVELS`] (such as a dependency's `Clean`) follow in name order.
+pub fn by_level(counts: &BTreeMap<String, usize>) -> Vec<(&str, usize)> {
+    let mut counts: Vec<(&str, usize)> = counts
+        .iter()
+        .map(|(level, count)| (level.as_str(), *count))
+        .collect();
+    counts.sort_by_key(|(level, _)| {
+        LEVELS
+            .iter()
+            .position(|l| l == level)
+            .unwrap_or(LEVELS.len())
+    });
+    counts
+}
+
+/// Whether `severity` is `min` or more severe. Unknown levels never are.
+pub fn is_at_least(severity: &str, min: &str) -> bool {
+    let rank = |level: &str| LEVELS.iter().position(|l| l.eq_ignore_ascii_case(level));
+    matches!((rank(severity), rank(min)), (Some(s), Some(m)) if s <= m)
+}
+
+/// Contribution of a finding with this severity to a dependency's risk score.
+/// A single Critical finding makes the dependency Critical.
+pub fn weight(severity: &str) -> i32 {
+    match severity {
+        "Critical" => 80,
+        "High" => 30,
+        "Medium" => 15,
+        "Info" => 0,
+        _ => 5,
+    }
+}
+
+/// Applies `[severity]` overrides to code findings, which are keyed by their
+/// CWE (e.g. `CWE-78 = "Critical"`). Findings of ignored classes are removed.
+pub fn apply_to_patterns(overrides: &HashMap<String, String>, patterns: &mut Vec<FlaggedPattern>) {
+    taxonomy::classify_patterns(patterns);
+    if overrides.is_empty() {
+        return;
+    }
+    patterns.retain_mut(|pattern| {
+        let severity = pattern
+            .cwe
+            .as_ref()
+            .and_then(|cwe| overrides.get(cwe))
+            .and_then(|value| canonical(value));
+        match severity {
+            Some(IGNORE) => false,
+            Some(severity) => {
+                pattern.severity = severity.to_string();
+                true
+            }
+            None => true,
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_change_and_drop_classes() {
+        let pattern = |description: &str| FlaggedPattern {
+            line: 1,
+            severity: "Low".to_string(),
+            description: description.to_string(),
+            code_snippet: String::new(),
+            confidence: 1.0,
+            cwe: None,
+            owasp: None,
+            id: None,
+            triage: None,
+            blame: None,
+            rule: None,
+        };
+        let mut patterns = vec![
+            pattern("[static] Spawns an external process"),
+            pattern("[static] Reads an environment variable"),
+            pattern("[static] Recursively deletes a directory"),
+        ];
+        let overrides = HashMap::from([
+            ("CWE-78".to_string(), "critical".to_string()),
+            ("CWE-526".to_string(), "Ignore".to_string()),
+        ]);
+        apply_to_patterns(&overrides, &mut patterns);
+
+        let severities: Vec<&str> = patterns.iter().map(|p| p.severity.as_str()).collect();
+        assert_eq!(severities, vec!["Critical", "Low"]);
+        assert_eq!(canonical("info"), Some("Info"));
+        assert_eq!(canonical("Severe"), None);
+    }
+}
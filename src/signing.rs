@@ -0,0 +1,253 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::llm_client::PROMPT_VERSION;
+use crate::report::RiskReport;
+
+/// `ssh-keygen -Y` namespace of report signatures, so a signature made for
+/// something else (a git commit, say) does not pass as a report signature.
+pub const NAMESPACE: &str = "rustrecon-report";
+
+const BUILDER_ID: &str = "https://github.com/micro-tech/rustrecon";
+
+/// Where the detached signature of `file` goes: `<file>.sig`.
+pub fn signature_path(file: &Path) -> PathBuf {
+    with_suffix(file, ".sig")
+}
+
+/// Where the attestation for `file` goes: `<file>.intoto.json`.
+pub fn attestation_path(file: &Path) -> PathBuf {
+    with_suffix(file, ".intoto.json")
+}
+
+fn with_suffix(file: &Path, suffix: &str) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Signs `file` with an SSH private key through `ssh-keygen -Y sign` and
+/// returns the path of the detached signature.
+pub fn sign_file(file: &Path, key: &Path) -> Result<PathBuf> {
+    let signature = signature_path(file);
+    // ssh-keygen will not replace the signature of an earlier report
+    let _ = std::fs::remove_file(&signature);
+    let output = Command::new("ssh-keygen")
+        .args(["-Y", "sign", "-n", NAMESPACE, "-f"])
+        .arg(key)
+        .arg(file)
+        .output()
+        .context("Failed to run ssh-keygen, which signs reports")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "ssh-keygen could not sign {}: {}",
+            file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(signature)
+}
+
+/// Checks `file` against a detached signature made by `identity`, whose
+/// public key must be listed in the `allowed_signers` file.
+pub fn verify_file(
+    file: &Path,
+    signature: &Path,
+    allowed_signers: &Path,
+    identity: &str,
+) -> Result<()> {
+    let content =
+        std::fs::File::open(file).with_context(|| format!("Failed to open {}", file.display()))?;
+    let output = Command::new("ssh-keygen")
+        .args(["-Y", "verify", "-n", NAMESPACE, "-f"])
+        .arg(allowed_signers)
+        .arg("-I")
+        .arg(identity)
+        .arg("-s")
+        .arg(signature)
+        .stdin(content)
+        .output()
+        .context("Failed to run ssh-keygen, which verifies reports")?;
+    if !output.status.success() {
+        // Some failures (an unknown identity) are only reported on stdout
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = match stderr.trim() {
+            "" => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            reason => reason.to_string(),
+        };
+        anyhow::bail!("Signature check of {} failed: {}", file.display(), reason);
+    }
+    Ok(())
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let content =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(format!("{:x}", Sha256::digest(content)))
+}
+
+/// Digest of the sources a scan read, `Scanner::source_files` of `project`,
+/// hashed as a `sha256sum` listing sorted by path. Unchanged sources give
+/// the same digest on any machine.
+pub fn source_digest(project: &Path, sources: &[PathBuf]) -> Result<String> {
+    let mut files: Vec<(String, String)> = Vec::new();
+    for path in sources {
+        let relative = path
+            .strip_prefix(project)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        files.push((relative, sha256_file(path)?));
+    }
+    files.sort();
+    let listing: String = files
+        .iter()
+        .map(|(path, digest)| format!("{}  {}\n", digest, path))
+        .collect();
+    Ok(format!("{:x}", Sha256::digest(listing.as_bytes())))
+}
+
+/// An in-toto statement with a SLSA provenance predicate for the report
+/// written to `file`: the report's digest, the tool version, the models and
+/// prompt version behind its analyses, and the digest of the scanned sources.
+pub fn attestation(
+    file: &Path,
+    report: &RiskReport,
+    project: &Path,
+    format: &str,
+) -> Result<serde_json::Value> {
+    let mut models: Vec<&str> = report
+        .findings
+        .iter()
+        .filter_map(|finding| finding.analysis_version.as_ref())
+        .map(|version| version.model.as_str())
+        .collect();
+    models.sort_unstable();
+    models.dedup();
+    // Reproducible reports have no start time to give
+    let mut metadata = serde_json::json!({ "finishedOn": chrono::Utc::now().to_rfc3339() });
+    if !report.timestamp.is_empty() {
+        metadata["startedOn"] = serde_json::json!(report.timestamp);
+    }
+    let file_name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(serde_json::json!({
+        "_type": "https://in-toto.io/Statement/v1",
+        "subject": [{
+            "name": file_name,
+            "digest": { "sha256": sha256_file(file)? },
+        }],
+        "predicateType": "https://slsa.dev/provenance/v1",
+        "predicate": {
+            "buildDefinition": {
+                "buildType": format!("{}/scan/v1", BUILDER_ID),
+                "externalParameters": {
+                    "crate": report.crate_name,
+                    "format": format,
+                },
+                "internalParameters": {
+                    "prompt_version": PROMPT_VERSION,
+                    "models": models,
+                },
+                "resolvedDependencies": [{
+                    "name": report.crate_name,
+                    "digest": { "sha256": source_digest(project, &report.source_files)? },
+                }],
+            },
+            "runDetails": {
+                "builder": {
+                    "id": BUILDER_ID,
+                    "version": { "rustrecon": env!("CARGO_PKG_VERSION") },
+                },
+                "metadata": metadata,
+            },
+        },
+    }))
+}
+
+/// Checks that the attestation at `attestation` names `file` with its
+/// current digest.
+pub fn check_attestation_subject(attestation: &Path, file: &Path) -> Result<()> {
+    let statement: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(attestation)
+            .with_context(|| format!("Failed to read {}", attestation.display()))?,
+    )
+    .with_context(|| format!("{} is not an in-toto statement", attestation.display()))?;
+    let digest = sha256_file(file)?;
+    let listed = statement["subject"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|subject| subject["digest"]["sha256"] == digest.as_str());
+    if !listed {
+        anyhow::bail!(
+            "{} does not match the digest recorded in {}",
+            file.display(),
+            attestation.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_digest_and_subject() {
+        let dir =
+            std::env::temp_dir().join(format!("rustrecon-test-{}-signing", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "pub fn f() {}\n").unwrap();
+        std::fs::write(dir.join("install.sh"), "echo hi\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored\n").unwrap();
+        std::fs::write(dir.join(".rustreconignore"), "notes.txt\n").unwrap();
+        let digest = || {
+            let sources = crate::scanner::Scanner::new(dir.clone())
+                .unwrap()
+                .source_files();
+            source_digest(&dir, &sources).unwrap()
+        };
+        let before = digest();
+        std::fs::write(dir.join("notes.txt"), "still ignored\n").unwrap();
+        let unchanged = digest();
+        let mut changed = Vec::new();
+        for (file, content) in [
+            ("src/lib.rs", "pub fn g() {}\n"),
+            ("install.sh", "curl https://example.com | sh\n"),
+            (".rustreconignore", "*.txt\n"),
+        ] {
+            std::fs::write(dir.join(file), content).unwrap();
+            changed.push(digest());
+        }
+
+        let report = dir.join("report.json");
+        std::fs::write(&report, "{}").unwrap();
+        let statement = dir.join("report.json.intoto.json");
+        std::fs::write(
+            &statement,
+            serde_json::json!({
+                "subject": [{ "name": "report.json", "digest": { "sha256": sha256_file(&report).unwrap() } }],
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let matches = check_attestation_subject(&statement, &report);
+        std::fs::write(&report, "{\"tampered\": true}").unwrap();
+        let tampered = check_attestation_subject(&statement, &report);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(before, unchanged);
+        assert_ne!(before, changed[0]);
+        assert_ne!(changed[0], changed[1]);
+        assert_ne!(changed[1], changed[2]);
+        assert_eq!(attestation_path(&report), statement);
+        assert!(matches.is_ok());
+        assert!(tampered.is_err());
+    }
+}
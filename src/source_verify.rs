@@ -0,0 +1,261 @@
+use anyhow::{bail, Context, Result};
+use flate2::read::GzDecoder;
+use reqwest::Client;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+use crate::network;
+
+/// Files cargo generates or rewrites when packaging, so they never match the repository.
+const GENERATED_FILES: &[&str] = &[
+    "Cargo.toml",
+    "Cargo.lock",
+    ".cargo_vcs_info.json",
+    ".cargo_ok",
+];
+
+/// Result of comparing a published `.crate` with its repository.
+#[derive(Debug)]
+pub struct SourceDiff {
+    /// The commit or tag the tarball was compared against
+    pub revision: String,
+    pub compared_files: usize,
+    /// Files shipped to crates.io that the repository does not contain
+    pub only_in_tarball: Vec<String>,
+    /// Files present in both with different contents
+    pub modified: Vec<String>,
+}
+
+impl SourceDiff {
+    pub fn is_clean(&self) -> bool {
+        self.only_in_tarball.is_empty() && self.modified.is_empty()
+    }
+
+    /// Differences in code or build inputs, as opposed to docs or assets.
+    pub fn touches_code(&self) -> bool {
+        self.only_in_tarball
+            .iter()
+            .chain(&self.modified)
+            .any(|file| file.ends_with(".rs") || file == "Cargo.toml.orig")
+    }
+
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.modified.is_empty() {
+            parts.push(format!(
+                "{} modified ({})",
+                self.modified.len(),
+                preview(&self.modified)
+            ));
+        }
+        if !self.only_in_tarball.is_empty() {
+            parts.push(format!(
+                "{} not in the repository ({})",
+                self.only_in_tarball.len(),
+                preview(&self.only_in_tarball)
+            ));
+        }
+        format!(
+            "{} of {} files differ from the repository at {}",
+            parts.join(", "),
+            self.compared_files,
+            self.revision
+        )
+    }
+}
+
+fn preview(files: &[String]) -> String {
+    const SHOWN: usize = 5;
+    let mut shown = files
+        .iter()
+        .take(SHOWN)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if files.len() > SHOWN {
+        shown.push_str(&format!(", +{} more", files.len() - SHOWN));
+    }
+    shown
+}
+
+/// Downloads `name@version` from crates.io and the matching revision of
+/// `repository`, then compares them file by file. Needs `git` on the PATH.
+pub async fn verify_package(
+    client: &Client,
+    name: &str,
+    version: &str,
+    repository: &str,
+) -> Result<SourceDiff> {
+    let tarball = download_crate(client, name, version).await?;
+    let files = unpack_crate(&tarball, &format!("{}-{}", name, version))?;
+
+    // Cargo records the exact commit it packaged from, when the tree was clean
+    let vcs_info: Option<serde_json::Value> = files
+        .get(".cargo_vcs_info.json")
+        .and_then(|content| serde_json::from_slice(content).ok());
+    let path_in_vcs = vcs_info
+        .as_ref()
+        .and_then(|info| info["path_in_vcs"].as_str())
+        .unwrap_or("")
+        .to_string();
+    let revisions: Vec<String> = match vcs_info
+        .as_ref()
+        .and_then(|info| info["git"]["sha1"].as_str())
+    {
+        Some(sha1) => vec![sha1.to_string()],
+        None => vec![
+            format!("v{}", version),
+            version.to_string(),
+            format!("{}-v{}", name, version),
+            format!("{}-{}", name, version),
+        ],
+    };
+
+    let checkout = std::env::temp_dir().join(format!(
+        "rustrecon-verify-{}-{}-{}",
+        name,
+        version,
+        std::process::id()
+    ));
+    let result = async {
+        let revision = checkout_revision(&checkout, repository, &revisions).await?;
+        Ok(compare(&files, &checkout.join(&path_in_vcs), revision))
+    }
+    .await;
+    let _ = std::fs::remove_dir_all(&checkout);
+    result
+}
+
+async fn download_crate(client: &Client, name: &str, version: &str) -> Result<Vec<u8>> {
+    let url = format!(
+        "https://static.crates.io/crates/{}/{}-{}.crate",
+        name, name, version
+    );
+    let response = network::send(client.get(&url)).await?.error_for_status()?;
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// Reads every regular file of a `.crate` into memory, keyed by its path
+/// relative to the package root.
+fn unpack_crate(tarball: &[u8], root: &str) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(tarball));
+    let mut files = BTreeMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.to_path_buf();
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let mut content = Vec::new();
+        entry.read_to_end(&mut content)?;
+        files.insert(relative, content);
+    }
+    if files.is_empty() {
+        bail!("crate tarball is empty");
+    }
+    Ok(files)
+}
+
+/// Shallow-fetches the first revision that exists into `dir`.
+async fn checkout_revision(dir: &Path, repository: &str, revisions: &[String]) -> Result<String> {
+    network::check_git_url(repository)?;
+    run_git(&["init", "-q", &dir.to_string_lossy()], None).await?;
+    for revision in revisions {
+        if run_git(
+            &["fetch", "-q", "--depth", "1", repository, revision],
+            Some(dir),
+        )
+        .await
+        .is_ok()
+        {
+            run_git(&["checkout", "-q", "FETCH_HEAD"], Some(dir)).await?;
+            return Ok(revision.clone());
+        }
+    }
+    bail!("none of {} found in {}", revisions.join(", "), repository)
+}
+
+async fn run_git(args: &[&str], dir: Option<&Path>) -> Result<()> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .await
+        .context("Failed to run git; source verification requires git on the PATH")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn compare(files: &BTreeMap<String, Vec<u8>>, repo_root: &Path, revision: String) -> SourceDiff {
+    let mut diff = SourceDiff {
+        revision,
+        compared_files: 0,
+        only_in_tarball: Vec::new(),
+        modified: Vec::new(),
+    };
+
+    for (relative, content) in files {
+        // Cargo.toml is normalized on publish; the author's original is Cargo.toml.orig
+        let repo_file: PathBuf = match relative.as_str() {
+            "Cargo.toml.orig" => repo_root.join("Cargo.toml"),
+            file if GENERATED_FILES.contains(&file) => continue,
+            file => repo_root.join(file),
+        };
+        diff.compared_files += 1;
+        match std::fs::read(&repo_file) {
+            Ok(repo_content) if &repo_content == content => {}
+            Ok(_) => diff.modified.push(relative.clone()),
+            Err(_) => diff.only_in_tarball.push(relative.clone()),
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_tarball_with_checkout() {
+        let repo = std::env::temp_dir().join(format!("rustrecon-compare-{}", std::process::id()));
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        std::fs::write(repo.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        std::fs::write(repo.join("src/lib.rs"), "pub fn demo() {}\n").unwrap();
+
+        let mut files = BTreeMap::new();
+        files.insert("Cargo.toml".to_string(), b"# normalized by cargo".to_vec());
+        files.insert(
+            "Cargo.toml.orig".to_string(),
+            b"[package]\nname = \"demo\"\n".to_vec(),
+        );
+        files.insert(
+            "src/lib.rs".to_string(),
+            b"pub fn demo() { backdoor() }\n".to_vec(),
+        );
+        files.insert("src/extra.rs".to_string(), b"fn backdoor() {}\n".to_vec());
+
+        let diff = compare(&files, &repo, "v0.1.0".to_string());
+        std::fs::remove_dir_all(&repo).unwrap();
+
+        assert_eq!(diff.compared_files, 3);
+        assert_eq!(diff.modified, vec!["src/lib.rs".to_string()]);
+        assert_eq!(diff.only_in_tarball, vec!["src/extra.rs".to_string()]);
+        assert!(diff.touches_code());
+    }
+}
@@ -0,0 +1,267 @@
+use crate::llm_client::FlaggedPattern;
+
+/// A weakness class from the CWE list, with the OWASP Top 10 (2021) category
+/// it falls under where there is one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Category {
+    pub cwe: u32,
+    pub name: &'static str,
+    pub owasp: Option<&'static str>,
+}
+
+impl Category {
+    pub fn cwe_id(&self) -> String {
+        format!("CWE-{}", self.cwe)
+    }
+}
+
+const INJECTION: &str = "A03:2021-Injection";
+const INTEGRITY: &str = "A08:2021-Software and Data Integrity Failures";
+
+/// Keywords in a finding description, checked in order; the first match wins,
+/// so more specific entries come first.
+const MAPPINGS: &[(&[&str], Category)] = &[
+    (
+        &["analysis evasion", "anti-analysis", "sandbox evasion"],
+        Category {
+            cwe: 511,
+            name: "Logic/Time Bomb",
+            owasp: Some(INTEGRITY),
+        },
+    ),
+    (
+        &[
+            "hard-coded",
+            "hardcoded",
+            "embedded credential",
+            "private key",
+        ],
+        Category {
+            cwe: 798,
+            name: "Use of Hard-coded Credentials",
+            owasp: Some("A07:2021-Identification and Authentication Failures"),
+        },
+    ),
+    (
+        &[
+            "backdoor",
+            "malicious",
+            "obfuscat",
+            "precompiled binary",
+            "base64 blob",
+            "cryptomining",
+            "wallet address",
+            "reverse shell",
+            "persistence",
+            "windows defender",
+        ],
+        Category {
+            cwe: 506,
+            name: "Embedded Malicious Code",
+            owasp: Some(INTEGRITY),
+        },
+    ),
+    (
+        &["sql"],
+        Category {
+            cwe: 89,
+            name: "SQL Injection",
+            owasp: Some(INJECTION),
+        },
+    ),
+    (
+        &["external process", "command", "shell", "spawn"],
+        Category {
+            cwe: 78,
+            name: "OS Command Injection",
+            owasp: Some(INJECTION),
+        },
+    ),
+    (
+        &[
+            "dynamic code",
+            "code injection",
+            "loads a library",
+            "dlopen",
+        ],
+        Category {
+            cwe: 94,
+            name: "Code Injection",
+            owasp: Some(INJECTION),
+        },
+    ),
+    (
+        &["deserializ"],
+        Category {
+            cwe: 502,
+            name: "Deserialization of Untrusted Data",
+            owasp: Some(INTEGRITY),
+        },
+    ),
+    (
+        &["download", "fetches code", "remote code"],
+        Category {
+            cwe: 494,
+            name: "Download of Code Without Integrity Check",
+            owasp: Some(INTEGRITY),
+        },
+    ),
+    (
+        &[
+            "build script",
+            "git dependency",
+            "path dependency",
+            "[patch]",
+            "wildcard version",
+            "install script",
+            "editor task",
+            "wrapper program",
+            "another program",
+            "linker",
+        ],
+        Category {
+            cwe: 829,
+            name: "Inclusion of Functionality from Untrusted Control Sphere",
+            owasp: Some(INTEGRITY),
+        },
+    ),
+    (
+        &["path traversal", "directory traversal"],
+        Category {
+            cwe: 22,
+            name: "Path Traversal",
+            owasp: Some("A01:2021-Broken Access Control"),
+        },
+    ),
+    (
+        &[
+            "exfiltrat",
+            "stored credentials",
+            "sends the environment",
+            "tcp",
+            "udp",
+            "socket",
+            "network",
+            "http",
+        ],
+        Category {
+            cwe: 200,
+            name: "Exposure of Sensitive Information to an Unauthorized Actor",
+            owasp: Some("A01:2021-Broken Access Control"),
+        },
+    ),
+    (
+        &["environment variable"],
+        Category {
+            cwe: 526,
+            name: "Exposure of Sensitive Information Through Environmental Variables",
+            owasp: None,
+        },
+    ),
+    (
+        &["transmute"],
+        Category {
+            cwe: 843,
+            name: "Type Confusion",
+            owasp: None,
+        },
+    ),
+    (
+        &["assembly"],
+        Category {
+            cwe: 695,
+            name: "Use of Low-Level Functionality",
+            owasp: None,
+        },
+    ),
+    (
+        &["unsafe"],
+        Category {
+            cwe: 676,
+            name: "Use of Potentially Dangerous Function",
+            owasp: None,
+        },
+    ),
+];
+
+/// The category of a finding, judged from its description.
+pub fn classify(description: &str) -> Option<Category> {
+    let description = description.to_lowercase();
+    MAPPINGS
+        .iter()
+        .find(|(keywords, _)| keywords.iter().any(|k| description.contains(k)))
+        .map(|(_, category)| *category)
+}
+
+/// Fills in the CWE and OWASP fields of findings that do not have them yet.
+pub fn classify_patterns(patterns: &mut [FlaggedPattern]) {
+    for pattern in patterns.iter_mut().filter(|p| p.cwe.is_none()) {
+        if let Some(category) = classify(&pattern.description) {
+            pattern.cwe = Some(category.cwe_id());
+            pattern.owasp = category.owasp.map(str::to_string);
+        }
+    }
+}
+
+/// Name of a CWE identifier such as `CWE-78`, if it is one RustRecon maps to.
+pub fn cwe_name(cwe: &str) -> Option<&'static str> {
+    MAPPINGS
+        .iter()
+        .map(|(_, category)| category)
+        .find(|category| category.cwe_id() == cwe)
+        .map(|category| category.name)
+}
+
+/// `CWE-78 OS Command Injection (A03:2021-Injection)`, without the OWASP part
+/// for categories outside the Top 10; `None` for unclassified findings.
+pub fn label(pattern: &FlaggedPattern) -> Option<String> {
+    let cwe = pattern.cwe.as_ref()?;
+    let name = cwe_name(cwe).map_or(String::new(), |name| format!(" {}", name));
+    Some(match &pattern.owasp {
+        Some(owasp) => format!("{}{} ({})", cwe, name, owasp),
+        None => format!("{}{}", cwe, name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_static_and_llm_descriptions() {
+        let cwe = |description: &str| classify(description).map(|c| c.cwe);
+        assert_eq!(cwe("[static] Spawns an external process"), Some(78));
+        assert_eq!(cwe("[static] Hard-coded AWS access key ID"), Some(798));
+        assert_eq!(cwe("[static] Reads an environment variable"), Some(526));
+        assert_eq!(cwe("[static] unsafe block"), Some(676));
+        assert_eq!(
+            cwe("Sends the collected environment variables over a TCP socket"),
+            Some(200)
+        );
+        assert_eq!(cwe("Obfuscated payload decoded at runtime"), Some(506));
+        assert_eq!(
+            cwe("[static] Analysis evasion: behavior depends on the CI environment (CI)"),
+            Some(511)
+        );
+        assert_eq!(cwe("Uses a long function name"), None);
+
+        let mut patterns = vec![FlaggedPattern {
+            line: 3,
+            severity: "High".to_string(),
+            description: "Runs a shell command built from user input".to_string(),
+            code_snippet: "Command::new(\"sh\")".to_string(),
+            confidence: 0.8,
+            cwe: None,
+            owasp: None,
+            id: None,
+            triage: None,
+            blame: None,
+            rule: None,
+        }];
+        classify_patterns(&mut patterns);
+        assert_eq!(
+            label(&patterns[0]).as_deref(),
+            Some("CWE-78 OS Command Injection (A03:2021-Injection)")
+        );
+    }
+}
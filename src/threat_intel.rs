@@ -0,0 +1,221 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use tracing::{debug, info, warn};
+
+use crate::config::ThreatIntelConfig;
+use crate::database::{Database, SavedResponse};
+use crate::indicators::{self, IndicatorFlag, IndicatorKind, NetworkIndicator};
+use crate::network::{self, Revalidated};
+use crate::report::RiskReport;
+
+/// How long a downloaded feed is reused when `refresh_hours` is not set.
+pub const DEFAULT_REFRESH_HOURS: i64 = 24;
+
+/// Indicators listed by the configured threat-intel feeds.
+#[derive(Debug, Default)]
+pub struct ThreatFeeds {
+    /// Listed value, lowercased, and the feed that lists it
+    entries: HashMap<String, String>,
+}
+
+impl ThreatFeeds {
+    /// Reads every feed in `config`. URLs are downloaded and cached in the
+    /// database for `refresh_hours`; when a download fails, or the scan is
+    /// offline, the cached copy is used however old it is. A feed that cannot
+    /// be read at all is skipped with a warning.
+    pub async fn load(
+        config: &ThreatIntelConfig,
+        mut db: Option<&mut Database>,
+        offline: bool,
+    ) -> Self {
+        let max_age = config
+            .refresh_hours
+            .map_or(DEFAULT_REFRESH_HOURS, |hours| hours as i64);
+        let mut feeds = ThreatFeeds::default();
+        for source in &config.feeds {
+            let content = if is_url(source) {
+                fetch_cached(source, db.as_deref_mut(), max_age, offline).await
+            } else {
+                std::fs::read_to_string(source)
+                    .with_context(|| format!("Failed to read {}", source))
+            };
+            match content {
+                Ok(content) => feeds.add(source, &content),
+                Err(e) => warn!("⚠️  Skipping threat-intel feed {}: {:#}", source, e),
+            }
+        }
+        if !feeds.entries.is_empty() {
+            info!(
+                "Loaded {} threat-intel indicators from {} feeds",
+                feeds.entries.len(),
+                config.feeds.len()
+            );
+        }
+        feeds
+    }
+
+    /// Adds the entries of a plain-text feed: one domain, IP, URL or wallet
+    /// address per line, `#` comments, and hosts-file lines such as
+    /// `0.0.0.0 bad.example`.
+    pub fn add(&mut self, source: &str, content: &str) {
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let mut tokens = line.split_whitespace();
+            let Some(first) = tokens.next() else {
+                continue;
+            };
+            let entry = match tokens.next() {
+                Some(host) if matches!(first, "0.0.0.0" | "127.0.0.1" | "::") => host,
+                _ => first,
+            };
+            self.entries
+                .entry(normalize(entry))
+                .or_insert_with(|| source.to_string());
+        }
+    }
+
+    /// The feed listing `indicator`, by its value, its host or a parent
+    /// domain of the host. Wallet addresses are case-sensitive in base58, but
+    /// feeds are matched case-insensitively like everything else.
+    pub fn lookup(&self, indicator: &NetworkIndicator) -> Option<&str> {
+        if let Some(feed) = self.entries.get(&normalize(&indicator.value)) {
+            return Some(feed);
+        }
+        if indicator.kind == IndicatorKind::Wallet {
+            return None;
+        }
+        let host = indicators::host(indicator).to_lowercase();
+        let feed = indicators::domain_suffixes(&host).find_map(|domain| self.entries.get(domain));
+        feed.map(String::as_str)
+    }
+
+    /// Flags the indicators a feed lists; returns how many there are.
+    pub fn annotate(&self, indicators: &mut [NetworkIndicator]) -> usize {
+        let mut listed = 0;
+        for indicator in indicators.iter_mut() {
+            let Some(feed) = self.lookup(indicator) else {
+                continue;
+            };
+            if !indicator.flags.contains(&IndicatorFlag::ThreatIntel) {
+                indicator.flags.push(IndicatorFlag::ThreatIntel);
+            }
+            indicator.threat_feed = Some(feed.to_string());
+            listed += 1;
+        }
+        listed
+    }
+
+    /// Annotates the indicators of the crate and its dependencies; returns
+    /// how many a feed lists.
+    pub fn annotate_report(&self, report: &mut RiskReport) -> usize {
+        if self.entries.is_empty() {
+            return 0;
+        }
+        let mut listed = self.annotate(&mut report.network_indicators);
+        report.network_indicators =
+            indicators::merge(std::mem::take(&mut report.network_indicators));
+        for dependency in &mut report.dependency_findings {
+            listed += self.annotate(&mut dependency.network_indicators);
+        }
+        listed
+    }
+}
+
+fn is_url(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
+
+/// Feed entries and indicators compare lowercased, without a trailing slash.
+fn normalize(value: &str) -> String {
+    value.trim_end_matches('/').to_lowercase()
+}
+
+async fn fetch_cached(
+    url: &str,
+    db: Option<&mut Database>,
+    max_age_hours: i64,
+    offline: bool,
+) -> Result<String> {
+    let saved = db.as_deref().and_then(|db| {
+        db.load_threat_feed(url).unwrap_or_else(|e| {
+            debug!("Could not read the cached feed {}: {}", url, e);
+            None
+        })
+    });
+    match saved {
+        Some(saved) if offline || saved.is_fresh(max_age_hours) => Ok(saved.body),
+        _ if offline => bail!("not cached, and the scan is offline"),
+        saved => {
+            let (body, validators) = match fetch(url, saved.as_ref()).await {
+                Ok(Revalidated::Modified { body, validators }) => (body, validators),
+                Ok(Revalidated::NotModified) => {
+                    let saved = saved.context("304 without a saved copy")?;
+                    (saved.body, saved.validators)
+                }
+                Err(e) => match saved {
+                    Some(saved) => {
+                        warn!(
+                            "⚠️  Could not refresh threat-intel feed {} ({:#}); using the copy from {}",
+                            url,
+                            e,
+                            saved.fetched_at.format("%Y-%m-%d %H:%M UTC")
+                        );
+                        return Ok(saved.body);
+                    }
+                    None => return Err(e),
+                },
+            };
+            if let Some(db) = db {
+                if let Err(e) = db.save_threat_feed(url, &body, &validators) {
+                    warn!("⚠️  Could not cache threat-intel feed {}: {}", url, e);
+                }
+            }
+            Ok(body)
+        }
+    }
+}
+
+/// Downloads a feed, or confirms with a 304 that the saved copy is current.
+async fn fetch(url: &str, saved: Option<&SavedResponse>) -> Result<Revalidated> {
+    let client = network::client_builder()
+        .timeout(network::timeouts().request)
+        .user_agent(concat!("rustrecon/", env!("CARGO_PKG_VERSION")))
+        .build()?;
+    Ok(network::conditional_get(&client, url, saved.map(|saved| &saved.validators)).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feed_lookup() {
+        let mut feeds = ThreatFeeds::default();
+        feeds.add(
+            "feed.txt",
+            "# Known C2\n0.0.0.0 evil-c2.example.xyz\n203.0.113.7 # sinkholed\n\
+             https://drop.example.net/upload/\n44AFFq5kSiGBoZ4NMDwYtN18obc8AemS33DBLWs3H7otXft3XjrpDtQGv7SqSsaBYBb98uNbr2VBBEt7f2wfn3RVGQBEP3A\n",
+        );
+        let indicator = |value: &str, kind| NetworkIndicator {
+            value: value.to_string(),
+            kind,
+            flags: Vec::new(),
+            threat_feed: None,
+            locations: vec!["src/lib.rs:1".to_string()],
+        };
+        let mut indicators = vec![
+            indicator("https://api.evil-c2.example.xyz/beacon", IndicatorKind::Url),
+            indicator("203.0.113.7", IndicatorKind::Ip),
+            indicator("https://drop.example.net/upload", IndicatorKind::Url),
+            indicator("https://example.xyz/", IndicatorKind::Url),
+            indicator(
+                "44AFFq5kSiGBoZ4NMDwYtN18obc8AemS33DBLWs3H7otXft3XjrpDtQGv7SqSsaBYBb98uNbr2VBBEt7f2wfn3RVGQBEP3A",
+                IndicatorKind::Wallet,
+            ),
+        ];
+        assert_eq!(feeds.annotate(&mut indicators), 4);
+        assert_eq!(indicators[0].threat_feed.as_deref(), Some("feed.txt"));
+        assert_eq!(indicators[0].flags, vec![IndicatorFlag::ThreatIntel]);
+        assert!(indicators[3].threat_feed.is_none());
+    }
+}
@@ -0,0 +1,238 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::database::Database;
+use crate::llm_client::FlaggedPattern;
+use crate::report::RiskReport;
+
+/// Hex digits in a finding id.
+const ID_LENGTH: usize = 12;
+
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Where a finding stands in review. Findings start as `new`; a `fixed`
+/// finding that shows up again in a later scan goes back to `new`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TriageState {
+    New,
+    Confirmed,
+    FalsePositive,
+    Fixed,
+}
+
+impl TriageState {
+    pub const ALL: [TriageState; 4] = [
+        TriageState::New,
+        TriageState::Confirmed,
+        TriageState::FalsePositive,
+        TriageState::Fixed,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TriageState::New => "new",
+            TriageState::Confirmed => "confirmed",
+            TriageState::FalsePositive => "false-positive",
+            TriageState::Fixed => "fixed",
+        }
+    }
+}
+
+impl std::str::FromStr for TriageState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.to_ascii_lowercase().replace('_', "-");
+        TriageState::ALL
+            .into_iter()
+            .find(|state| state.label() == normalized)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown triage state '{}' (expected new, confirmed, false-positive or fixed)",
+                    s
+                )
+            })
+    }
+}
+
+/// A finding's triage state and the reviewer's note, as shown in reports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriageStatus {
+    pub state: TriageState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// A finding as kept in the findings database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FindingRecord {
+    pub id: String,
+    pub crate_name: String,
+    pub file_path: PathBuf,
+    pub line: usize,
+    pub severity: String,
+    pub description: String,
+    pub code_snippet: String,
+    #[serde(flatten)]
+    pub status: TriageStatus,
+    pub first_seen: String,
+    pub last_seen: String,
+    /// When the state or note last changed; `None` while untouched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// Reviewer who made that change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_by: Option<String>,
+}
+
+/// A reviewer's comment on a finding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    pub author: String,
+    pub created_at: String,
+    pub body: String,
+}
+
+/// A finding with its review comments, as exchanged in annotation bundles.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotatedFinding {
+    #[serde(flatten)]
+    pub finding: FindingRecord,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<Comment>,
+}
+
+/// File format shared by `triage export` and `triage import`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnnotationBundle {
+    pub format_version: u32,
+    pub reviewer: String,
+    pub exported_at: String,
+    pub findings: Vec<AnnotatedFinding>,
+}
+
+/// Outcome of merging another reviewer's bundle into the findings database.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MergeSummary {
+    pub added: usize,
+    /// Findings whose state and note were taken from the bundle
+    pub updated: usize,
+    /// Findings whose local state and note are as new or newer
+    pub kept_local: usize,
+    pub comments_added: usize,
+    /// Ids of findings the two reviewers put in different states; the later
+    /// decision was kept
+    pub conflicts: Vec<String>,
+}
+
+/// Name recorded with triage decisions and comments: `--reviewer`, else
+/// git's `user.name`, else the login name.
+pub fn reviewer_name(explicit: Option<&str>) -> String {
+    if let Some(name) = explicit.map(str::trim).filter(|name| !name.is_empty()) {
+        return name.to_string();
+    }
+    let git_name = std::process::Command::new("git")
+        .args(["config", "user.name"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|name| !name.is_empty());
+    git_name
+        .or_else(|| std::env::var("USER").ok())
+        .or_else(|| std::env::var("USERNAME").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Id of a finding, stable across scans while the flagged code stays the
+/// same: like baseline matching, it ignores the line number.
+pub fn finding_id(crate_name: &str, file: &Path, pattern: &FlaggedPattern) -> String {
+    let path = file
+        .strip_prefix(".")
+        .unwrap_or(file)
+        .to_string_lossy()
+        .replace('\\', "/");
+    let digest = Sha256::digest(
+        [
+            crate_name,
+            &path,
+            &pattern.description,
+            &pattern.code_snippet,
+        ]
+        .join("\0")
+        .as_bytes(),
+    );
+    format!("{:x}", digest)[..ID_LENGTH].to_string()
+}
+
+/// Records the report's findings in the findings database and tags each
+/// flagged pattern with its id and triage status. Patterns tagged by an
+/// earlier call are skipped, so this can run as findings are streamed.
+pub fn record_findings(db: &Database, report: &mut RiskReport) -> Result<()> {
+    let seen_at = chrono::Utc::now().to_rfc3339();
+    let crate_name = report.crate_name.clone();
+    for finding in &mut report.findings {
+        for pattern in &mut finding.flagged_patterns {
+            if pattern.id.is_some() {
+                continue;
+            }
+            let id = finding_id(&crate_name, &finding.file_path, pattern);
+            let status = db.record_finding(&FindingRecord {
+                id: id.clone(),
+                crate_name: crate_name.clone(),
+                file_path: finding.file_path.clone(),
+                line: pattern.line,
+                severity: pattern.severity.clone(),
+                description: pattern.description.clone(),
+                code_snippet: pattern.code_snippet.clone(),
+                status: TriageStatus {
+                    state: TriageState::New,
+                    note: None,
+                },
+                first_seen: seen_at.clone(),
+                last_seen: seen_at.clone(),
+                updated_at: None,
+                updated_by: None,
+            })?;
+            pattern.id = Some(id);
+            pattern.triage = Some(status);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finding_ids_and_states() {
+        let mut pattern = FlaggedPattern {
+            line: 10,
+            severity: "High".to_string(),
+            description: "Spawns a shell".to_string(),
+            code_snippet: "Command::new(\"sh\")".to_string(),
+            confidence: 0.9,
+            cwe: None,
+            owasp: None,
+            id: None,
+            triage: None,
+            blame: None,
+            rule: None,
+        };
+        let id = finding_id("demo", Path::new("./src/main.rs"), &pattern);
+        assert_eq!(id.len(), ID_LENGTH);
+        pattern.line = 42;
+        assert_eq!(finding_id("demo", Path::new("src/main.rs"), &pattern), id);
+        assert_ne!(finding_id("other", Path::new("src/main.rs"), &pattern), id);
+
+        assert_eq!(
+            "False_Positive".parse::<TriageState>(),
+            Ok(TriageState::FalsePositive)
+        );
+        assert!("wontfix".parse::<TriageState>().is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+
+/// How a candidate name imitates a popular crate name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TyposquatTechnique {
+    SeparatorConfusion, // serdejson vs serde_json
+    Homoglyph,          // t0kio vs tokio
+    CharacterSwap,      // toiko vs tokio
+    PaddedName,         // serde2, serde-utils2
+    KeyboardTypo,       // tokip vs tokio (adjacent keys)
+    EditDistance,       // sede vs serde
+}
+
+impl TyposquatTechnique {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TyposquatTechnique::SeparatorConfusion => "hyphen/underscore confusion",
+            TyposquatTechnique::Homoglyph => "look-alike character substitution",
+            TyposquatTechnique::CharacterSwap => "swapped characters",
+            TyposquatTechnique::PaddedName => "padded name",
+            TyposquatTechnique::KeyboardTypo => "adjacent-key typo",
+            TyposquatTechnique::EditDistance => "small spelling difference",
+        }
+    }
+
+    /// Lower is a stronger signal; used to pick the best match among popular names.
+    fn rank(&self) -> u8 {
+        match self {
+            TyposquatTechnique::Homoglyph => 0,
+            TyposquatTechnique::SeparatorConfusion => 1,
+            TyposquatTechnique::CharacterSwap => 2,
+            TyposquatTechnique::KeyboardTypo => 3,
+            TyposquatTechnique::PaddedName => 4,
+            TyposquatTechnique::EditDistance => 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TyposquatMatch {
+    pub popular_name: String,
+    pub technique: TyposquatTechnique,
+}
+
+const KEYBOARD_ROWS: [&str; 4] = ["1234567890-", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+/// crates.io treats `-` and `_` as the same name, so compare on a canonical form.
+fn normalize(name: &str) -> String {
+    name.to_ascii_lowercase().replace('_', "-")
+}
+
+fn strip_separators(name: &str) -> String {
+    name.chars().filter(|c| *c != '-').collect()
+}
+
+/// Maps visually confusable characters onto a single representative.
+fn homoglyph_skeleton(name: &str) -> String {
+    strip_separators(name)
+        .replace("rn", "m")
+        .replace("vv", "w")
+        .chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | 'i' | '|' => 'l',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            '8' => 'b',
+            _ => c,
+        })
+        .collect()
+}
+
+fn key_position(c: char) -> Option<(i32, i32)> {
+    KEYBOARD_ROWS.iter().enumerate().find_map(|(row, keys)| {
+        keys.chars()
+            .position(|k| k == c)
+            .map(|col| (row as i32, col as i32))
+    })
+}
+
+fn is_adjacent_key(a: char, b: char) -> bool {
+    match (key_position(a), key_position(b)) {
+        (Some((ra, ca)), Some((rb, cb))) => (ra - rb).abs() <= 1 && (ca - cb).abs() <= 1,
+        _ => false,
+    }
+}
+
+/// Damerau-Levenshtein distance in half-steps: an adjacent-key substitution or a
+/// transposition costs 1, every other edit costs 2.
+pub fn keyboard_distance(s1: &str, s2: &str) -> usize {
+    let a: Vec<char> = s1.chars().collect();
+    let b: Vec<char> = s2.chars().collect();
+    let mut matrix = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[0] = i * 2;
+    }
+    for (j, cell) in matrix[0].iter_mut().enumerate() {
+        *cell = j * 2;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = if a[i - 1] == b[j - 1] {
+                0
+            } else if is_adjacent_key(a[i - 1], b[j - 1]) {
+                1
+            } else {
+                2
+            };
+            let mut best = (matrix[i - 1][j] + 2)
+                .min(matrix[i][j - 1] + 2)
+                .min(matrix[i - 1][j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(matrix[i - 2][j - 2] + 1);
+            }
+            matrix[i][j] = best;
+        }
+    }
+
+    matrix[a.len()][b.len()]
+}
+
+fn is_single_swap(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len() != b.len() {
+        return false;
+    }
+    let diffs: Vec<usize> = (0..a.len()).filter(|&i| a[i] != b[i]).collect();
+    diffs.len() == 2
+        && diffs[1] == diffs[0] + 1
+        && a[diffs[0]] == b[diffs[1]]
+        && a[diffs[1]] == b[diffs[0]]
+}
+
+/// `serde2`, `serde-utils2`: the popular name followed by filler ending in digits.
+fn is_padded(candidate: &str, popular: &str) -> bool {
+    match candidate.strip_prefix(popular) {
+        Some(rest) if !rest.is_empty() => {
+            rest.ends_with(|c: char| c.is_ascii_digit())
+                && rest.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        }
+        _ => false,
+    }
+}
+
+/// Maximum keyboard distance (in half-steps) tolerated for a name of this length.
+fn distance_threshold(popular: &str) -> usize {
+    match popular.chars().count() {
+        0..=4 => 1, // short names collide too easily; only adjacent-key typos or swaps
+        5..=7 => 2,
+        _ => 3,
+    }
+}
+
+/// Checks whether `candidate` looks like an imitation of `popular`.
+pub fn detect(candidate: &str, popular: &str) -> Option<TyposquatTechnique> {
+    let candidate = normalize(candidate);
+    let popular = normalize(popular);
+
+    if candidate == popular {
+        return None;
+    }
+
+    if strip_separators(&candidate) == strip_separators(&popular) {
+        return Some(TyposquatTechnique::SeparatorConfusion);
+    }
+
+    if homoglyph_skeleton(&candidate) == homoglyph_skeleton(&popular) {
+        return Some(TyposquatTechnique::Homoglyph);
+    }
+
+    if is_single_swap(&candidate, &popular) {
+        return Some(TyposquatTechnique::CharacterSwap);
+    }
+
+    if is_padded(&candidate, &popular) {
+        return Some(TyposquatTechnique::PaddedName);
+    }
+
+    let distance = keyboard_distance(&candidate, &popular);
+    if distance <= distance_threshold(&popular) {
+        return Some(if distance % 2 == 1 {
+            TyposquatTechnique::KeyboardTypo
+        } else {
+            TyposquatTechnique::EditDistance
+        });
+    }
+
+    None
+}
+
+/// Finds the strongest imitation among `popular_names`, ignoring exact matches.
+pub fn find_best_match<'a, I>(candidate: &str, popular_names: I) -> Option<TyposquatMatch>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let normalized = normalize(candidate);
+    let mut names: Vec<&String> = popular_names.into_iter().collect();
+
+    // A popular crate is never a typosquat of another popular crate
+    if names.iter().any(|name| normalize(name) == normalized) {
+        return None;
+    }
+
+    names.sort();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            detect(candidate, name).map(|technique| TyposquatMatch {
+                popular_name: name.clone(),
+                technique,
+            })
+        })
+        .min_by_key(|m| m.technique.rank())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detection_techniques() {
+        assert_eq!(
+            detect("serdejson", "serde_json"),
+            Some(TyposquatTechnique::SeparatorConfusion)
+        );
+        assert_eq!(
+            detect("t0kio", "tokio"),
+            Some(TyposquatTechnique::Homoglyph)
+        );
+        assert_eq!(detect("c1ap", "clap"), Some(TyposquatTechnique::Homoglyph));
+        assert_eq!(
+            detect("toiko", "tokio"),
+            Some(TyposquatTechnique::CharacterSwap)
+        );
+        assert_eq!(
+            detect("serde-utils2", "serde"),
+            Some(TyposquatTechnique::PaddedName)
+        );
+        assert_eq!(
+            detect("tokip", "tokio"),
+            Some(TyposquatTechnique::KeyboardTypo)
+        );
+        assert_eq!(
+            detect("sede", "serde"),
+            Some(TyposquatTechnique::EditDistance)
+        );
+    }
+
+    #[test]
+    fn test_legitimate_names_not_flagged() {
+        assert_eq!(detect("serde-json", "serde_json"), None);
+        assert_eq!(detect("slab", "clap"), None);
+        assert_eq!(detect("serde_derive", "serde"), None);
+        assert_eq!(detect("tokio-util", "tokio"), None);
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+use std::path::Path;
+use tree_sitter::{Node, Parser, Tree};
+use walkdir::WalkDir;
+
+/// Geiger-style count of `unsafe` usage.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsafeMetrics {
+    pub blocks: usize,
+    pub functions: usize,
+    pub impls: usize,
+    pub traits: usize,
+}
+
+impl UnsafeMetrics {
+    pub fn from_tree(tree: &Tree) -> Self {
+        let mut metrics = UnsafeMetrics::default();
+        metrics.visit(tree.root_node());
+        metrics
+    }
+
+    /// Totals every `.rs` file under `dir`, skipping build output.
+    pub fn scan_dir(dir: &Path) -> Result<Self> {
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_rust::language())?;
+
+        let mut metrics = UnsafeMetrics::default();
+        for entry in WalkDir::new(dir)
+            .into_iter()
+            .filter_entry(|e| e.file_name() != "target")
+            .filter_map(|e| e.ok())
+        {
+            if !entry.file_type().is_file()
+                || entry.path().extension().is_none_or(|ext| ext != "rs")
+            {
+                continue;
+            }
+            let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            if let Some(tree) = parser.parse(&content, None) {
+                metrics += UnsafeMetrics::from_tree(&tree);
+            }
+        }
+        Ok(metrics)
+    }
+
+    pub fn total(&self) -> usize {
+        self.blocks + self.functions + self.impls + self.traits
+    }
+
+    /// Compact form for one-line reports, e.g. `12 (blocks 9, fns 2, impls 1, traits 0)`.
+    pub fn describe(&self) -> String {
+        format!(
+            "{} (blocks {}, fns {}, impls {}, traits {})",
+            self.total(),
+            self.blocks,
+            self.functions,
+            self.impls,
+            self.traits
+        )
+    }
+
+    fn visit(&mut self, node: Node) {
+        match node.kind() {
+            "unsafe_block" => self.blocks += 1,
+            "function_item" | "function_signature_item" => {
+                let modifiers = children(node)
+                    .into_iter()
+                    .find(|child| child.kind() == "function_modifiers");
+                if modifiers.is_some_and(has_unsafe_token) {
+                    self.functions += 1;
+                }
+            }
+            "impl_item" if has_unsafe_token(node) => self.impls += 1,
+            "trait_item" if has_unsafe_token(node) => self.traits += 1,
+            _ => {}
+        }
+
+        for child in children(node) {
+            self.visit(child);
+        }
+    }
+}
+
+impl AddAssign for UnsafeMetrics {
+    fn add_assign(&mut self, other: Self) {
+        self.blocks += other.blocks;
+        self.functions += other.functions;
+        self.impls += other.impls;
+        self.traits += other.traits;
+    }
+}
+
+fn children(node: Node) -> Vec<Node> {
+    let mut cursor = node.walk();
+    node.children(&mut cursor).collect()
+}
+
+fn has_unsafe_token(node: Node) -> bool {
+    children(node).iter().any(|child| child.kind() == "unsafe")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_tree_counts_unsafe_items() {
+        let source = r#"
+unsafe fn raw() {}
+pub unsafe extern "C" fn exported() {}
+fn safe() { unsafe { raw() }; unsafe { exported() } }
+unsafe impl Send for Wrapper {}
+impl Clone for Wrapper { fn clone(&self) -> Self { *self } }
+unsafe trait Marker {}
+"#;
+        let mut parser = Parser::new();
+        parser.set_language(tree_sitter_rust::language()).unwrap();
+        let tree = parser.parse(source, None).unwrap();
+
+        assert_eq!(
+            UnsafeMetrics::from_tree(&tree),
+            UnsafeMetrics {
+                blocks: 2,
+                functions: 2,
+                impls: 1,
+                traits: 1,
+            }
+        );
+    }
+}
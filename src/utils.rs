@@ -1,39 +1,245 @@
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 use tree_sitter::Tree;
 
-/// Traverses the Tree-sitter AST and extracts code chunks.
-/// This is a basic implementation and can be greatly refined.
-///
-/// For now, it extracts top-level functions and modules as chunks.
-pub fn chunk_code_for_llm(tree: &Tree, content: &str) -> Vec<String> {
-    let mut chunks = Vec::new();
-    let root_node = tree.root_node();
-    let source_bytes = content.as_bytes();
-
-    for child in root_node.children(&mut root_node.walk()) {
-        match child.kind() {
-            "function_item" | "mod_item" | "impl_item" | "struct_item" | "enum_item" => {
-                let start_byte = child.start_byte();
-                let end_byte = child.end_byte();
-                if let Ok(chunk) = std::str::from_utf8(&source_bytes[start_byte..end_byte]) {
-                    chunks.push(chunk.to_string());
-                }
+use crate::llm_client::{FlaggedPattern, LlmResponse};
+
+/// Lines of the previous chunk repeated at the start of the next one, so code
+/// near a boundary is seen with some context.
+const CHUNK_OVERLAP_LINES: usize = 5;
+
+/// A part of a source file that is sent to the LLM on its own.
+#[derive(Debug, Clone)]
+pub struct CodeChunk {
+    /// File line (1-based) of the first line of `text`
+    pub start_line: usize,
+    pub text: String,
+}
+
+/// Splits a file into chunks of at most `max_chars` for LLM analysis. Chunks
+/// end at item boundaries from the Tree-sitter AST: top-level items first, and
+/// inside items (impls, modules, functions) that are too large on their own.
+/// Together the chunks cover the whole file.
+pub fn chunk_code_for_llm(tree: &Tree, content: &str, max_chars: usize) -> Vec<CodeChunk> {
+    if content.len() <= max_chars {
+        return vec![CodeChunk {
+            start_line: 1,
+            text: content.to_string(),
+        }];
+    }
+
+    let mut boundaries = vec![0, content.len()];
+    collect_split_points(tree.root_node(), content, max_chars, &mut boundaries);
+    boundaries.sort_unstable();
+    boundaries.dedup();
+    pack_chunks(content, &boundaries, max_chars)
+}
+
+/// Packs the segments between consecutive `boundaries` greedily into chunks.
+fn pack_chunks(content: &str, boundaries: &[usize], max_chars: usize) -> Vec<CodeChunk> {
+    let mut ranges = Vec::new();
+    let (mut start, mut end) = (0, 0);
+    for &boundary in &boundaries[1..] {
+        if boundary - start > max_chars && end > start {
+            ranges.push((start, end));
+            start = end;
+        }
+        end = boundary;
+    }
+    ranges.push((start, end));
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let start = if start == 0 {
+                0
+            } else {
+                overlap_start(content, start)
+            };
+            CodeChunk {
+                start_line: content[..start].matches('\n').count() + 1,
+                text: content[start..end].to_string(),
             }
-            // You might want to handle other top-level items or expressions
-            _ => {
-                // Optionally, include smaller statements or expressions
-                // For a more sophisticated approach, this would involve recursive chunking
-                // or specific query-based extraction.
+        })
+        .collect()
+}
+
+/// Splits a file that has no syntax tree at line boundaries into chunks of
+/// at most `max_chars`, overlapping like `chunk_code_for_llm`.
+pub fn chunk_text_for_llm(content: &str, max_chars: usize) -> Vec<CodeChunk> {
+    let mut boundaries = vec![0];
+    boundaries.extend(content.match_indices('\n').map(|(i, _)| i + 1));
+    boundaries.push(content.len());
+    boundaries.dedup();
+    pack_chunks(content, &boundaries, max_chars)
+}
+
+fn collect_split_points(
+    node: tree_sitter::Node,
+    content: &str,
+    max_chars: usize,
+    boundaries: &mut Vec<usize>,
+) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        boundaries.push(line_start(content, child.start_byte()));
+        if child.end_byte() - child.start_byte() > max_chars {
+            if child.child_count() > 0 {
+                collect_split_points(child, content, max_chars, boundaries);
+            } else {
+                // A single huge token (e.g. an embedded blob): split it by lines
+                let text = &content[child.start_byte()..child.end_byte()];
+                boundaries.extend(
+                    text.match_indices('\n')
+                        .map(|(i, _)| child.start_byte() + i + 1),
+                );
             }
         }
     }
+}
+
+fn line_start(content: &str, byte: usize) -> usize {
+    content[..byte].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// Start of the line `CHUNK_OVERLAP_LINES` lines above `start`.
+fn overlap_start(content: &str, start: usize) -> usize {
+    let mut position = start;
+    for _ in 0..CHUNK_OVERLAP_LINES {
+        if position == 0 {
+            break;
+        }
+        position = line_start(content, position - 1);
+    }
+    position
+}
+
+/// Suffix added to a finding whose code snippet does not appear in the source.
+const UNVERIFIED_SNIPPET_NOTE: &str = " (snippet not found in source; line is approximate)";
+
+/// Combines the answers for the chunks of one file. Line numbers, which the
+/// model reports relative to its chunk, are mapped back to file lines (see
+/// `locate_pattern`), and findings reported twice because of chunk overlap are
+/// dropped.
+pub fn merge_chunk_responses(parts: Vec<(&CodeChunk, LlmResponse)>) -> LlmResponse {
+    if parts.len() == 1 {
+        let (chunk, mut response) = parts.into_iter().next().unwrap();
+        locate_patterns(&mut response.flagged_patterns, chunk);
+        return response;
+    }
 
-    if chunks.is_empty() && !content.is_empty() {
-        // If no specific items are found, treat the whole file as one chunk
-        chunks.push(content.to_string());
+    let mut analyses = Vec::new();
+    let mut flagged_patterns: Vec<FlaggedPattern> = Vec::new();
+    let mut versions = Vec::new();
+    for (chunk, mut response) in parts {
+        versions.push(response.version);
+        let end_line = chunk.start_line + chunk.text.lines().count().saturating_sub(1);
+        analyses.push(format!(
+            "Lines {}-{}: {}",
+            chunk.start_line, end_line, response.analysis
+        ));
+        locate_patterns(&mut response.flagged_patterns, chunk);
+        for pattern in response.flagged_patterns {
+            let duplicate = flagged_patterns
+                .iter()
+                .any(|p| p.line == pattern.line && p.description == pattern.description);
+            if !duplicate {
+                flagged_patterns.push(pattern);
+            }
+        }
+    }
+    LlmResponse {
+        analysis: analyses.join("\n\n"),
+        flagged_patterns,
+        // The oldest prompt among the chunks (cached chunks may predate the others)
+        version: versions
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .and_then(|versions| versions.into_iter().min_by_key(|v| v.prompt_version)),
     }
+}
 
-    chunks
+fn locate_patterns(patterns: &mut [FlaggedPattern], chunk: &CodeChunk) {
+    for pattern in patterns {
+        locate_pattern(pattern, chunk);
+    }
+}
+
+/// Sets the file line of a pattern whose `line` is relative to `chunk`. Model
+/// line numbers are often off, so the reported code snippet is looked up in
+/// the chunk and the occurrence nearest the reported line wins. When the
+/// snippet does not occur, the reported line is kept (within the chunk) and
+/// the description says so.
+fn locate_pattern(pattern: &mut FlaggedPattern, chunk: &CodeChunk) {
+    let chunk_lines: Vec<&str> = chunk.text.lines().collect();
+    let last_index = chunk_lines.len().saturating_sub(1);
+    let reported_index = pattern.line.saturating_sub(1).min(last_index);
+
+    let needle = snippet_needle(&pattern.code_snippet);
+    let found = needle.as_ref().and_then(|needle| {
+        chunk_lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| without_whitespace(line).contains(needle.as_str()))
+            .map(|(index, _)| index)
+            .min_by_key(|index| index.abs_diff(reported_index))
+    });
+
+    pattern.line = chunk.start_line + found.unwrap_or(reported_index);
+    if needle.is_some()
+        && found.is_none()
+        && !pattern.description.ends_with(UNVERIFIED_SNIPPET_NOTE)
+    {
+        pattern.description.push_str(UNVERIFIED_SNIPPET_NOTE);
+        // The cited code is not in the file, so the finding may be made up
+        pattern.confidence *= 0.5;
+    }
+    if let Some(index) = found {
+        let line = chunk_lines[index].trim_start();
+        if line.starts_with("//") || line.starts_with("/*") || line.starts_with('*') {
+            // The cited code only appears in a comment
+            pattern.confidence *= 0.3;
+        }
+    }
+}
+
+/// Adjusts the confidence of the LLM findings in a file: a static rule that
+/// flags the same line corroborates a finding, while code under `tests/`,
+/// `examples/` or `benches/` is not part of the built crate.
+pub fn adjust_confidence(
+    patterns: &mut [FlaggedPattern],
+    static_patterns: &[FlaggedPattern],
+    relative_path: &Path,
+) {
+    let non_shipping = relative_path.components().any(|c| {
+        matches!(
+            c.as_os_str().to_str(),
+            Some("tests" | "examples" | "benches")
+        )
+    });
+    for pattern in patterns {
+        if static_patterns.iter().any(|s| s.line == pattern.line) {
+            pattern.confidence = pattern.confidence.max(0.9);
+        }
+        if non_shipping {
+            pattern.confidence *= 0.7;
+        }
+    }
+}
+
+/// First line of a snippet as it would appear in the source, ignoring
+/// whitespace, markdown backticks and trailing ellipses. `None` when too short
+/// to identify a line.
+fn snippet_needle(snippet: &str) -> Option<String> {
+    let first_line = snippet
+        .lines()
+        .map(|line| line.trim().trim_matches('`').trim_end_matches("...").trim())
+        .find(|line| !line.is_empty())?;
+    let needle = without_whitespace(first_line);
+    (needle.len() >= 3).then_some(needle)
+}
+
+fn without_whitespace(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
 }
 
 /// Helper function to get the crate name from a given path.
@@ -45,3 +251,124 @@ pub fn get_crate_name_from_path(crate_path: &Path) -> String {
         .map(|s| s.to_string())
         .unwrap_or_else(|| "unknown_crate".to_string())
 }
+
+/// `path` relative to `root`, the scanned directory. Files outside it, such
+/// as a workspace's root manifest, get `..` steps; paths that cannot be
+/// resolved are kept as they are.
+pub fn relative_path(path: &Path, root: &Path) -> PathBuf {
+    if let Ok(relative) = path.strip_prefix(root) {
+        return relative.to_path_buf();
+    }
+    let (Ok(path), Ok(root)) = (path.canonicalize(), root.canonicalize()) else {
+        return path.to_path_buf();
+    };
+    let shared = path
+        .components()
+        .zip(root.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if shared == 0 {
+        // Another drive on Windows
+        return path;
+    }
+    root.components()
+        .skip(shared)
+        .map(|_| Component::ParentDir)
+        .chain(path.components().skip(shared))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_cover_file_at_item_boundaries() {
+        let function = |i: usize| format!("fn f{}() {{\n    let x = {};\n}}\n", i, i);
+        let content: String = (0..40).map(function).collect();
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(tree_sitter_rust::language()).unwrap();
+        let tree = parser.parse(&content, None).unwrap();
+
+        let chunks = chunk_code_for_llm(&tree, &content, 200);
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks[0].start_line, 1);
+        for pair in chunks.windows(2) {
+            let (previous, next) = (&pair[0], &pair[1]);
+            // Chunks overlap by a few lines and leave no gap
+            let previous_end = previous.start_line + previous.text.lines().count();
+            assert_eq!(next.start_line + CHUNK_OVERLAP_LINES, previous_end);
+            let first_line = content.lines().nth(next.start_line - 1).unwrap();
+            assert_eq!(next.text.lines().next(), Some(first_line));
+        }
+        assert!(content.ends_with(&chunks.last().unwrap().text));
+        assert!(chunks.iter().skip(1).all(|c| c
+            .text
+            .lines()
+            .nth(CHUNK_OVERLAP_LINES)
+            .unwrap()
+            .starts_with("fn ")));
+    }
+
+    #[test]
+    fn test_pattern_lines_follow_the_snippet() {
+        let chunk = CodeChunk {
+            start_line: 101,
+            text: "use std::env;\n\nfn token() -> String {\n    env::var(\"TOKEN\").unwrap()\n}\n"
+                .to_string(),
+        };
+        let pattern = |line: usize, snippet: &str| FlaggedPattern {
+            line,
+            severity: "Medium".to_string(),
+            description: "Reads a secret".to_string(),
+            code_snippet: snippet.to_string(),
+            confidence: 0.8,
+            cwe: None,
+            owasp: None,
+            id: None,
+            triage: None,
+            blame: None,
+            rule: None,
+        };
+
+        // The model's line is off by two; the snippet pins it to file line 104
+        let mut patterns = vec![
+            pattern(2, "`env::var( \"TOKEN\" ).unwrap()`"),
+            pattern(3, "std::process::Command::new(\"sh\")"),
+        ];
+        locate_patterns(&mut patterns, &chunk);
+        assert_eq!(patterns[0].line, 104);
+        assert_eq!(patterns[0].description, "Reads a secret");
+        assert_eq!(patterns[1].line, 103);
+        assert!(patterns[1].description.ends_with(UNVERIFIED_SNIPPET_NOTE));
+        assert_eq!(patterns[1].confidence, 0.4);
+
+        // A static rule on the same line corroborates; test code counts for less
+        let static_patterns = vec![pattern(104, "env::var(\"TOKEN\")")];
+        adjust_confidence(&mut patterns, &static_patterns, Path::new("src/lib.rs"));
+        assert_eq!(patterns[0].confidence, 0.9);
+        adjust_confidence(&mut patterns[1..], &[], Path::new("tests/fixtures.rs"));
+        assert!((patterns[1].confidence - 0.28).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(Path::new("./src/main.rs"), Path::new(".")),
+            Path::new("src/main.rs")
+        );
+        assert_eq!(
+            relative_path(Path::new("crates/foo/src/lib.rs"), Path::new("crates/foo")),
+            Path::new("src/lib.rs")
+        );
+
+        let workspace =
+            std::env::temp_dir().join(format!("rustrecon-test-{}-ws", std::process::id()));
+        let member = workspace.join("crates").join("foo");
+        std::fs::create_dir_all(&member).unwrap();
+        std::fs::write(workspace.join("Cargo.toml"), "[workspace]\n").unwrap();
+        let outside = relative_path(&workspace.join("Cargo.toml"), &member);
+        let _ = std::fs::remove_dir_all(&workspace);
+        assert_eq!(outside, Path::new("../../Cargo.toml"));
+    }
+}